@@ -0,0 +1,62 @@
+//! Configures the export names of the signal address.
+//!
+//! - `WASM_SIGNAL_EXPORT_NAME` renames the `WASM_SIGNAL_ADDR` export.
+//! - `WASM_SIGNAL_EXPORT_ALIASES` is a comma-separated list of additional
+//!   exports holding the same address, written to `$OUT_DIR/aliases.rs`.
+//!
+//! Both are read when the crate is built, so changing them requires a
+//! rebuild, which Cargo does on its own.
+
+use std::fmt::Write;
+
+const DEFAULT_NAME: &str = "WASM_SIGNAL_ADDR";
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=WASM_SIGNAL_EXPORT_NAME");
+    println!("cargo:rerun-if-env-changed=WASM_SIGNAL_EXPORT_ALIASES");
+
+    let name = std::env::var("WASM_SIGNAL_EXPORT_NAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from(DEFAULT_NAME));
+    check_symbol("WASM_SIGNAL_EXPORT_NAME", &name);
+    println!("cargo:rustc-env=WASM_SIGNAL_EXPORT_NAME={name}");
+
+    let aliases = std::env::var("WASM_SIGNAL_EXPORT_ALIASES").unwrap_or_default();
+    let mut seen = vec![name];
+    let mut out = String::new();
+    for (index, alias) in aliases
+        .split(',')
+        .map(str::trim)
+        .filter(|alias| !alias.is_empty())
+        .enumerate()
+    {
+        check_symbol("WASM_SIGNAL_EXPORT_ALIASES", alias);
+        if seen.iter().any(|name| name == alias) {
+            panic!("WASM_SIGNAL_EXPORT_ALIASES: `{alias}` is exported twice");
+        }
+        seen.push(String::from(alias));
+        writeln!(
+            out,
+            "#[export_name = {alias:?}]\n#[used]\nstatic ALIAS_{index}: &core::sync::atomic::AtomicU32 = &SIGNAL.word;"
+        )
+        .unwrap();
+    }
+
+    let path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("aliases.rs");
+    std::fs::write(path, out).unwrap();
+}
+
+/// Rejects names that are not valid symbols, so a typo fails the build
+/// rather than producing a module with a mangled or missing export.
+fn check_symbol(var: &str, name: &str) {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        panic!("{var}: `{name}` is not a valid symbol name");
+    }
+}
@@ -0,0 +1,191 @@
+/* Generated by wasm-signal-header. Do not edit. */
+
+#ifndef WASM_SIGNAL_HANDLER_H
+#define WASM_SIGNAL_HANDLER_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* ABI */
+
+/* The version of the host-shared layouts. */
+#define WASM_SIGNAL_ABI_VERSION 1u
+/* The signal word is a global. */
+#define WASM_SIGNAL_ABI_GLOBAL_STORAGE 1u
+/* The signal block is in a control memory. */
+#define WASM_SIGNAL_ABI_CONTROL_MEMORY 2u
+/* The shift of the control memory's index in the flags. */
+#define WASM_SIGNAL_ABI_MEMORY_INDEX_SHIFT 8u
+/* "WSIG", first word of the metadata block. */
+#define WASM_SIGNAL_METADATA_MAGIC 0x47495357u
+/* "WSBK", the word before the signal word. */
+#define WASM_SIGNAL_BLOCK_MAGIC 0x4b425357u
+/* Attached through the export. */
+#define WASM_SIGNAL_ATTACHED_EXPORT 1u
+/* Attached through the anchor. */
+#define WASM_SIGNAL_ATTACHED_ANCHOR 2u
+
+/* Signal codes */
+
+/* Requests termination. */
+#define WASM_SIGNAL_TERMINATE 1u
+/* Requests cooperative cancellation. */
+#define WASM_SIGNAL_CANCEL 2u
+/* A deadline has passed. */
+#define WASM_SIGNAL_DEADLINE 3u
+/* Requests a progress snapshot. */
+#define WASM_SIGNAL_CHECKPOINT 4u
+/* A command is waiting in the mailbox. */
+#define WASM_SIGNAL_COMMAND 5u
+/* The first code of the fatal range. */
+#define WASM_SIGNAL_FATAL_BASE 0xf0000000u
+
+/* Severity encoding */
+
+/* The bits the severity is shifted by. */
+#define WASM_SIGNAL_SEVERITY_SHIFT 28u
+/* The bits holding the code. */
+#define WASM_SIGNAL_CODE_MASK 0x0fffffffu
+/* Never propagates. */
+#define WASM_SIGNAL_SEVERITY_INFO 1u
+/* Never propagates. */
+#define WASM_SIGNAL_SEVERITY_WARN 2u
+/* The handler may decline it. */
+#define WASM_SIGNAL_SEVERITY_CANCEL 3u
+/* The handler cannot swallow it. */
+#define WASM_SIGNAL_SEVERITY_KILL 4u
+/* Bypasses the handler. */
+#define WASM_SIGNAL_SEVERITY_FATAL 15u
+
+/* Disposition flags */
+
+/* A handler is registered. */
+#define WASM_SIGNAL_DISPOSITION_HANDLER 1u
+/* Propagated signals panic. */
+#define WASM_SIGNAL_DISPOSITION_PANICS 2u
+/* Propagated signals trap. */
+#define WASM_SIGNAL_DISPOSITION_TRAPS 4u
+/* Propagated signals throw. */
+#define WASM_SIGNAL_DISPOSITION_THROWS 8u
+/* Latched mode is on. */
+#define WASM_SIGNAL_DISPOSITION_LATCH_MODE 16u
+/* A signal is latched. */
+#define WASM_SIGNAL_DISPOSITION_LATCHED 32u
+
+/* Lifecycle states */
+
+/* No request is running. */
+#define WASM_SIGNAL_STATE_IDLE 0u
+/* A request is running. */
+#define WASM_SIGNAL_STATE_RUNNING 1u
+/* A signal propagated; the request is winding down. */
+#define WASM_SIGNAL_STATE_CANCELLING 2u
+/* The shutdown hooks are running. */
+#define WASM_SIGNAL_STATE_CLEANING_UP 3u
+/* The shutdown hooks have run. */
+#define WASM_SIGNAL_STATE_TERMINATED 4u
+
+/* Event kinds */
+
+/* A check observed a signal. */
+#define WASM_SIGNAL_EVENT_SIGNAL_ACKNOWLEDGED 1u
+/* A checkpoint was written. */
+#define WASM_SIGNAL_EVENT_CHECKPOINT_WRITTEN 2u
+/* The stage changed. */
+#define WASM_SIGNAL_EVENT_STAGE_CHANGED 3u
+/* A code failed validation. */
+#define WASM_SIGNAL_EVENT_SIGNAL_REJECTED 4u
+/* The first application event kind. */
+#define WASM_SIGNAL_EVENT_USER_BASE 0x00000100u
+
+/* Export names of the host-shared slots. Each export is a global
+ * holding the address of its slot in linear memory. */
+
+#define WASM_SIGNAL_EXPORT_SIGNAL "WASM_SIGNAL_ADDR"
+#define WASM_SIGNAL_EXPORT_ACK "WASM_SIGNAL_ACK_ADDR"
+#define WASM_SIGNAL_EXPORT_HEARTBEAT "WASM_SIGNAL_HEARTBEAT_ADDR"
+#define WASM_SIGNAL_EXPORT_MAILBOX "WASM_SIGNAL_MAILBOX_ADDR"
+#define WASM_SIGNAL_EXPORT_EVENTS "WASM_SIGNAL_EVENTS_ADDR"
+#define WASM_SIGNAL_EXPORT_PROGRESS "WASM_SIGNAL_PROGRESS_ADDR"
+#define WASM_SIGNAL_EXPORT_SOURCE "WASM_SIGNAL_SOURCE_ADDR"
+#define WASM_SIGNAL_EXPORT_POLICY "WASM_SIGNAL_POLICY_ADDR"
+#define WASM_SIGNAL_EXPORT_QUEUE "WASM_SIGNAL_QUEUE_ADDR"
+#define WASM_SIGNAL_EXPORT_METADATA "WASM_SIGNAL_METADATA_ADDR"
+#define WASM_SIGNAL_EXPORT_DISPOSITION "WASM_SIGNAL_DISPOSITION_ADDR"
+#define WASM_SIGNAL_EXPORT_EPOCH "WASM_SIGNAL_EPOCH_ADDR"
+#define WASM_SIGNAL_EXPORT_STATE "WASM_SIGNAL_STATE_ADDR"
+
+/* The addresses of the host-shared slots in linear memory, one word
+ * per slot. Words are 32 bits wide in wasm32 modules. */
+typedef struct wasm_signal_layout_t {
+  /* The signal word, or 0 with global storage. */
+  uintptr_t signal;
+  /* The ack word. */
+  uintptr_t ack;
+  /* The heartbeat word. */
+  uintptr_t heartbeat;
+  /* The command mailbox. */
+  uintptr_t mailbox;
+  /* The event ring. */
+  uintptr_t events;
+  /* The progress slot. */
+  uintptr_t progress;
+  /* The source word. */
+  uintptr_t source;
+  /* The overwrite policy word. */
+  uintptr_t policy;
+  /* The signal queue. */
+  uintptr_t queue;
+  /* The export metadata block. */
+  uintptr_t metadata;
+  /* The disposition word. */
+  uintptr_t disposition;
+  /* The epoch word. */
+  uintptr_t epoch;
+  /* The lifecycle state word. */
+  uintptr_t state;
+} wasm_signal_layout_t;
+
+/* Checks for a pending signal. Returns the code of the signal to
+ * propagate, or 0 to continue. */
+uint32_t wasm_signal_check(void);
+
+/* Returns the code of the pending signal without consuming it, or 0. */
+uint32_t wasm_signal_peek(void);
+
+/* Raises a signal, dispatching it right away with `immediate`. Returns
+ * the code of the signal to propagate, or 0 to continue. */
+uint32_t wasm_signal_raise(uint32_t code, bool immediate);
+
+/* Masks signals until a matching wasm_signal_unmask(). Calls nest. */
+void wasm_signal_mask(void);
+
+/* Releases one wasm_signal_mask(), delivering any deferred signal once
+ * the outermost one is released. Returns the code of the signal to
+ * propagate, or 0 to continue. */
+uint32_t wasm_signal_unmask(void);
+
+/* Returns true if signals are currently masked. */
+bool wasm_signal_masked(void);
+
+/* Returns the disposition word, a set of WASM_SIGNAL_DISPOSITION_* flags. */
+uint32_t wasm_signal_disposition(void);
+
+/* Returns true if a signal handler, global or namespaced, is registered. */
+bool wasm_signal_has_handler(void);
+
+/* Returns the lifecycle state, one of the WASM_SIGNAL_STATE_* values. */
+uint32_t wasm_signal_state(void);
+
+/* Returns the addresses of every host-shared slot. */
+wasm_signal_layout_t wasm_signal_layout(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* WASM_SIGNAL_HANDLER_H */
@@ -0,0 +1,139 @@
+// Asyncify helper for wasm-signal-handler.
+//
+// With the crate's `asyncify` feature, `check_signal` calls the
+// `wasm_signal.suspend` import when a signal propagates. This helper
+// implements that import: it unwinds the guest with Binaryen's Asyncify, so
+// the export the host called returns straight away, and it can later rewind
+// into the saved stack.
+//
+// The module must be processed with
+//
+//   wasm-opt --asyncify --pass-arg=asyncify-imports@wasm_signal.suspend
+//
+// which also adds the `asyncify_*` exports used here.
+
+// Asyncify states, as returned by `asyncify_get_state`
+const NORMAL = 0;
+const UNWINDING = 1;
+const REWINDING = 2;
+
+/**
+ * Runs guest exports that can suspend on a signal.
+ *
+ * @example
+ * const signals = new SignalAsyncify();
+ * const { instance } = await WebAssembly.instantiate(bytes, {
+ *   ...imports,
+ *   ...signals.imports,
+ * });
+ * signals.attach(instance.exports);
+ *
+ * let result = signals.call("run", input);
+ * if (result.suspended) {
+ *   console.log(`suspended on signal ${result.signal}`);
+ *   // Later: continue as if the signal was dismissed, or pass
+ *   // `{ propagate: true }` to let the guest panic and clean up
+ *   result = signals.resume();
+ * }
+ */
+export class SignalAsyncify {
+  constructor() {
+    this.exports = null;
+    // The export call being run, so it can be re-entered on resume
+    this.pending = null;
+    this.data = 0;
+    this.signal = 0;
+    this.propagate = false;
+    this.imports = {
+      wasm_signal: { suspend: (code, data) => this.#suspend(code, data) },
+    };
+  }
+
+  /**
+   * Attaches the instance's exports, once it is instantiated.
+   *
+   * @param {WebAssembly.Exports} exports
+   */
+  attach(exports) {
+    if (typeof exports.asyncify_start_unwind !== "function") {
+      throw new Error("module is not Asyncify-instrumented; run wasm-opt --asyncify");
+    }
+    this.exports = exports;
+  }
+
+  /**
+   * Whether a call is suspended, waiting for `resume`.
+   *
+   * @returns {boolean}
+   */
+  get suspended() {
+    return this.pending !== null && this.exports.asyncify_get_state() === NORMAL;
+  }
+
+  /**
+   * Calls an export.
+   *
+   * @param {string} name - The export to call.
+   * @param {...*} args
+   * @returns {{ suspended: false, value: * } | { suspended: true, signal: number }}
+   */
+  call(name, ...args) {
+    if (this.pending !== null) {
+      throw new Error("a call is already suspended; resume it first");
+    }
+    this.pending = { name, args };
+    return this.#run();
+  }
+
+  /**
+   * Rewinds into a suspended call.
+   *
+   * By default the check returns as if the signal was dismissed. With
+   * `propagate`, the signal propagates instead, panicking in the guest so
+   * that destructors and shutdown hooks run.
+   *
+   * @param {object} [options]
+   * @param {boolean} [options.propagate]
+   * @returns {{ suspended: false, value: * } | { suspended: true, signal: number }}
+   */
+  resume({ propagate = false } = {}) {
+    if (!this.suspended) {
+      throw new Error("no call is suspended");
+    }
+    this.propagate = propagate;
+    this.exports.asyncify_start_rewind(this.data);
+    return this.#run();
+  }
+
+  #run() {
+    const { name, args } = this.pending;
+    let value;
+    try {
+      value = this.exports[name](...args);
+    } catch (err) {
+      this.pending = null;
+      throw err;
+    }
+    if (this.exports.asyncify_get_state() === UNWINDING) {
+      this.exports.asyncify_stop_unwind();
+      return { suspended: true, signal: this.signal };
+    }
+    this.pending = null;
+    return { suspended: false, value };
+  }
+
+  #suspend(code, data) {
+    if (this.exports.asyncify_get_state() === REWINDING) {
+      this.exports.asyncify_stop_rewind();
+      return this.propagate ? 0 : 1;
+    }
+    if (this.pending === null) {
+      // Not called through `call`, so there is nothing to unwind to
+      return 0;
+    }
+    this.signal = code;
+    this.data = data;
+    this.exports.asyncify_start_unwind(data);
+    return 0;
+  }
+}
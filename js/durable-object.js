@@ -0,0 +1,107 @@
+// Durable Objects helper for wasm-signal-handler.
+//
+// Long-running Durable Object work is killed once it exceeds the runtime's
+// limits. `SignalAlarm` schedules a Durable Object alarm some time before
+// that, and raises a soft-cancel signal in the guest when it fires, so the
+// guest can persist its progress and stop on its own terms.
+//
+// The alarm handler only runs while the guest is awaiting something, such
+// as storage or a fetch; purely CPU-bound guest code never lets it in. Guests
+// should check for signals after their awaits.
+
+import { getSignalAddress, wakeTasks, writeSignal } from "./wasm-signal-handler.js";
+
+export { WorkersLimit } from "./workers.js";
+
+// Must match `Signal::CANCEL` in the crate
+const CANCEL = 2;
+
+// Storage key recording when our alarm is due, to tell it apart from alarms
+// the object schedules for itself
+const ALARM_KEY = "__wasm_signal_alarm";
+
+/**
+ * Raises a signal in a guest instance from a Durable Object alarm.
+ *
+ * A Durable Object has a single alarm. Objects that schedule their own
+ * alarms should let `SignalAlarm` share it: call `alarm()` first from the
+ * object's `alarm()` method, and handle the alarm themselves if it returns
+ * `false`.
+ *
+ * @example
+ * export class Job {
+ *   constructor(state) {
+ *     this.signals = new SignalAlarm(state, instance.exports);
+ *   }
+ *
+ *   async fetch(request) {
+ *     await this.signals.arm(25_000);
+ *     try {
+ *       return await runGuestJob(request);
+ *     } finally {
+ *       await this.signals.disarm();
+ *     }
+ *   }
+ *
+ *   async alarm() {
+ *     await this.signals.alarm();
+ *   }
+ * }
+ */
+export class SignalAlarm {
+  /**
+   * @param {DurableObjectState} state - The object's state.
+   * @param {WebAssembly.Exports} exports - The guest instance's exports.
+   * @param {object} [options]
+   * @param {WebAssembly.Memory} [options.memory] - Defaults to
+   *   `exports.memory`.
+   * @param {number} [options.code] - Signal to raise; defaults to cancel.
+   */
+  constructor(state, exports, { memory = exports.memory, code = CANCEL } = {}) {
+    this.storage = state.storage;
+    this.exports = exports;
+    this.memory = memory;
+    this.code = code;
+  }
+
+  /**
+   * Schedules the signal `ms` milliseconds from now, replacing a previously
+   * armed one.
+   *
+   * @param {number} ms
+   */
+  async arm(ms) {
+    const at = Date.now() + ms;
+    await this.storage.put(ALARM_KEY, at);
+    await this.storage.setAlarm(at);
+  }
+
+  /**
+   * Cancels the scheduled signal, if any. An alarm the object scheduled for
+   * itself is left alone.
+   */
+  async disarm() {
+    const at = await this.storage.get(ALARM_KEY);
+    if (at === undefined) return;
+    await this.storage.delete(ALARM_KEY);
+    if ((await this.storage.getAlarm()) === at) {
+      await this.storage.deleteAlarm();
+    }
+  }
+
+  /**
+   * Handles an alarm: raises the signal in the guest if this alarm was
+   * scheduled by `arm`, and wakes guest tasks waiting on signal-aware
+   * channels.
+   *
+   * @returns {Promise<boolean>} `false` if the alarm was not ours.
+   */
+  async alarm() {
+    const at = await this.storage.get(ALARM_KEY);
+    if (at === undefined || Date.now() < at) return false;
+    await this.storage.delete(ALARM_KEY);
+    writeSignal(this.memory, getSignalAddress(this.exports, this.memory), this.code);
+    wakeTasks(this.exports);
+    return true;
+  }
+}
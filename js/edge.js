@@ -0,0 +1,112 @@
+// Edge runtime helpers for wasm-signal-handler.
+//
+// Vercel Edge Functions, Deno Deploy, Netlify Edge Functions and other
+// WinterCG-style runtimes all hand a request handler a `Request` whose
+// `signal` aborts when the client goes away, but they differ in how the
+// guest instance is loaded. Guests built with the `edge` feature register
+// their exports under the well-known `Symbol.for("wasm-signal-handler")`
+// property of `globalThis`, so the helpers here find them the same way on
+// every runtime.
+//
+// Guests that call `setup_edge` bind the request's abort themselves; these
+// helpers are for hosts and middleware that signal instances from outside.
+
+import { getSignalAddress, wakeTasks, writeSignal } from "./wasm-signal-handler.js";
+
+/**
+ * The `globalThis` property guests register under. Must match
+ * `EDGE_GLOBAL_KEY` in the crate.
+ */
+export const EDGE_KEY = Symbol.for("wasm-signal-handler");
+
+/** The code of `Signal::CANCEL`. */
+const CANCEL = 2;
+
+/**
+ * Returns the exports of every registered guest instance.
+ *
+ * @returns {WebAssembly.Exports[]}
+ */
+export function edgeInstances() {
+  const registry = globalThis[EDGE_KEY];
+  return Array.isArray(registry) ? registry : [];
+}
+
+/**
+ * Registers a guest instance for discovery, for guests that do not call
+ * `register_edge_instance` themselves.
+ *
+ * @param {WebAssembly.Exports} exports - The guest instance's exports.
+ * @returns {boolean} `false` if it was already registered.
+ */
+export function registerEdgeInstance(exports) {
+  if (!Array.isArray(globalThis[EDGE_KEY])) {
+    globalThis[EDGE_KEY] = [];
+  }
+  const registry = globalThis[EDGE_KEY];
+  if (registry.includes(exports)) return false;
+  registry.push(exports);
+  return true;
+}
+
+/**
+ * Writes a signal to guest instances and wakes their waiting tasks.
+ *
+ * @param {number} code - Signal to raise.
+ * @param {WebAssembly.Exports[]} [instances] - Defaults to every
+ *   registered instance.
+ */
+export function raiseEdgeSignal(code, instances = edgeInstances()) {
+  for (const exports of instances) {
+    writeSignal(exports.memory, getSignalAddress(exports), code);
+    wakeTasks(exports);
+  }
+}
+
+/**
+ * Raises a signal in guest instances when an `AbortSignal` aborts, or right
+ * away if it already has.
+ *
+ * @param {AbortSignal | undefined} abortSignal
+ * @param {object} [options]
+ * @param {number} [options.code] - Signal to raise; defaults to
+ *   `Signal::CANCEL`.
+ * @param {WebAssembly.Exports[]} [options.instances] - Defaults to every
+ *   instance registered when the signal aborts.
+ * @returns {() => void} Removes the listener.
+ */
+export function bridgeAbortSignal(abortSignal, { code = CANCEL, instances } = {}) {
+  if (!abortSignal) return () => {};
+  const raise = () => raiseEdgeSignal(code, instances);
+  if (abortSignal.aborted) {
+    raise();
+    return () => {};
+  }
+  abortSignal.addEventListener("abort", raise, { once: true });
+  return () => abortSignal.removeEventListener("abort", raise);
+}
+
+/**
+ * Sets up a guest instance for a request on any edge runtime: registers it
+ * and raises a signal in it if the request aborts.
+ *
+ * @example
+ * export default async function handler(request) {
+ *   const done = setupEdge(instance.exports, { request });
+ *   try {
+ *     return await handle(instance.exports, request);
+ *   } finally {
+ *     done();
+ *   }
+ * }
+ *
+ * @param {WebAssembly.Exports} exports - The guest instance's exports.
+ * @param {object} [options]
+ * @param {Request} [options.request] - The incoming request.
+ * @param {number} [options.code] - As for `bridgeAbortSignal`.
+ * @returns {() => void} Removes the abort listener.
+ */
+export function setupEdge(exports, { request, code } = {}) {
+  registerEdgeInstance(exports);
+  return bridgeAbortSignal(request?.signal, { code, instances: [exports] });
+}
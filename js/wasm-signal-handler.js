@@ -0,0 +1,845 @@
+// Host-side helpers for wasm-signal-handler.
+//
+// These functions implement the official JS write path for the signal word.
+// All accesses go through `Atomics` on a `Uint32Array` view, which is valid
+// for both shared (`SharedArrayBuffer`) and unshared memories. On shared
+// memories this guarantees the write is visible to guest threads with the
+// same sequentially consistent semantics the guest's atomics observe; plain
+// `DataView` writes give no such guarantee.
+//
+// Views are recreated on every call because `memory.buffer` is replaced
+// whenever the guest grows its memory.
+
+/**
+ * Severity levels carried in the top four bits of the signal word. Must match
+ * `Severity` in the crate.
+ *
+ * @readonly
+ * @enum {number}
+ */
+export const Severity = Object.freeze({
+  INFO: 1,
+  WARN: 2,
+  CANCEL: 3,
+  KILL: 4,
+  FATAL: 0xf,
+});
+
+const SEVERITY_SHIFT = 28;
+const CODE_MASK = (1 << SEVERITY_SHIFT) - 1;
+
+/**
+ * Encodes `code` with a `Severity` into a signal word.
+ *
+ * @param {number} code - Code; bits above the low 28 are discarded.
+ * @param {number} severity - A `Severity` value.
+ * @returns {number} Unsigned 32-bit signal word.
+ */
+export function encodeSignal(code, severity) {
+  return ((severity << SEVERITY_SHIFT) | (code & CODE_MASK)) >>> 0;
+}
+
+/**
+ * Splits a signal word into its code and severity. Words without an encoded
+ * severity decode as `{ code: word, severity: null }`.
+ *
+ * @param {number} word - Unsigned 32-bit signal word.
+ * @returns {{ code: number, severity: number | null }}
+ */
+export function decodeSignal(word) {
+  const severity = word >>> SEVERITY_SHIFT;
+  if (Object.values(Severity).includes(severity)) {
+    return { code: word & CODE_MASK, severity };
+  }
+  return { code: word >>> 0, severity: null };
+}
+
+/**
+ * What a sender does when a signal is already pending. Must match
+ * `OverwritePolicy` in the crate; the guest publishes the active policy.
+ *
+ * @readonly
+ * @enum {number}
+ */
+export const OverwritePolicy = Object.freeze({
+  OVERWRITE: 0,
+  KEEP_HIGHEST: 1,
+  ENQUEUE: 2,
+});
+
+// Must match the `abi` module in the crate
+const METADATA_MAGIC = 0x47495357; // "WSIG"
+const BLOCK_MAGIC = 0x4b425357; // "WSBK"
+const ABI_GLOBAL_STORAGE = 1 << 0;
+const ABI_CONTROL_MEMORY = 1 << 1;
+const ATTACHED_EXPORT = 1 << 0;
+const ATTACHED_ANCHOR = 1 << 1;
+
+// Must match `rank` in the crate's severity module
+function rankSignal(word) {
+  const severity = word >>> SEVERITY_SHIFT;
+  if (Object.values(Severity).includes(severity)) return severity;
+  return word === 1 ? Severity.KILL : Severity.CANCEL;
+}
+
+/**
+ * Resolves the linear-memory address of the signal word.
+ *
+ * The `WASM_SIGNAL_ADDR` export is a global holding the address of a
+ * pointer-sized static, which in turn holds the address of the signal word.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @param {string} [name] - The export name, if the module was built with
+ *   `WASM_SIGNAL_EXPORT_NAME` or aliases.
+ * @returns {number} Byte address of the signal word.
+ */
+export function getSignalAddress(exports, memory = exports.memory, name = "WASM_SIGNAL_ADDR") {
+  return resolveAddress(exports[name], memory);
+}
+
+/**
+ * Attaches to the guest's signal exports, and records how it did so for the
+ * guest's `verify_exports()`.
+ *
+ * The signal word is resolved through its export when present, and through
+ * the `__wasm_signal_anchor` function otherwise, in which case the guest
+ * reports the export as stripped. Throws if the metadata block is not found
+ * or does not start with the expected magic value.
+ *
+ * With a control memory, the returned address is in that memory (see
+ * `getControlMemory`).
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @param {string} [name] - The signal export name, if renamed.
+ * @returns {number | null} Byte address of the signal word, or `null` with
+ *   global storage.
+ */
+export function attachHost(exports, memory = exports.memory, name = "WASM_SIGNAL_ADDR") {
+  let metadataAddr;
+  if (exports.WASM_SIGNAL_METADATA_ADDR) {
+    metadataAddr = resolveAddress(exports.WASM_SIGNAL_METADATA_ADDR, memory);
+  } else if (typeof exports.__wasm_signal_anchor === "function") {
+    metadataAddr = exports.__wasm_signal_anchor() >>> 0;
+  } else {
+    throw new Error("module exports neither WASM_SIGNAL_METADATA_ADDR nor __wasm_signal_anchor");
+  }
+  const words = new Uint32Array(memory.buffer);
+  const base = metadataAddr >>> 2;
+  if (Atomics.load(words, base) !== METADATA_MAGIC) {
+    throw new Error(`no signal metadata at address ${metadataAddr}`);
+  }
+  const globalStorage = (words[base + 2] & ABI_GLOBAL_STORAGE) !== 0;
+  const controlMemory = (words[base + 2] & ABI_CONTROL_MEMORY) !== 0;
+  const exported = globalStorage
+    ? exports.WASM_SIGNAL_GLOBAL
+    : controlMemory
+      ? exports.WASM_SIGNAL_CONTROL_MEMORY
+      : exports[name];
+  Atomics.or(words, base + 4, exported ? ATTACHED_EXPORT : ATTACHED_ANCHOR);
+  if (globalStorage) return null;
+  return exported && !controlMemory ? resolveAddress(exported, memory) : words[base + 3];
+}
+
+/**
+ * Returns the memory holding the signal word of a module whose signal
+ * block was moved by `wasm-signal-control-memory`, or `null`.
+ *
+ * Pass it, with the address from `attachHost`, to the helpers that take the
+ * signal word's memory, such as `writeSignal`. The other slots stay in
+ * `exports.memory`.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @returns {WebAssembly.Memory | null}
+ */
+export function getControlMemory(exports) {
+  return exports.WASM_SIGNAL_CONTROL_MEMORY ?? null;
+}
+
+/**
+ * Resolves the addresses of the guest's status words: the ack word (code of
+ * the last signal observed by a check) and the heartbeat counter.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {{ ackAddr: number, heartbeatAddr: number }}
+ */
+export function getStatusAddresses(exports, memory = exports.memory) {
+  return {
+    ackAddr: resolveAddress(exports.WASM_SIGNAL_ACK_ADDR, memory),
+    heartbeatAddr: resolveAddress(exports.WASM_SIGNAL_HEARTBEAT_ADDR, memory),
+  };
+}
+
+/**
+ * Resolves the address of the guest's progress slot.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {number} Byte address of the progress slot.
+ */
+export function getProgressAddress(exports, memory = exports.memory) {
+  return resolveAddress(exports.WASM_SIGNAL_PROGRESS_ADDR, memory);
+}
+
+/**
+ * Resolves the addresses of the overwrite policy word and the signal queue.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {{ policyAddr: number, queueAddr: number }}
+ */
+export function getPolicyAddresses(exports, memory = exports.memory) {
+  return {
+    policyAddr: resolveAddress(exports.WASM_SIGNAL_POLICY_ADDR, memory),
+    queueAddr: resolveAddress(exports.WASM_SIGNAL_QUEUE_ADDR, memory),
+  };
+}
+
+/**
+ * Sends a signal following the guest's overwrite policy, so host and guest
+ * senders agree on what happens to a pending signal.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} signalAddr - Address from `getSignalAddress`.
+ * @param {{ policyAddr: number, queueAddr: number }} policyAddrs - From
+ *   `getPolicyAddresses`.
+ * @param {number} code - Unsigned 32-bit signal code.
+ * @returns {boolean} `false` if the signal was dropped, because a more severe
+ *   one is pending or the queue is full.
+ */
+export function sendSignal(memory, signalAddr, { policyAddr, queueAddr }, code) {
+  checkAlignment(signalAddr);
+  checkAlignment(policyAddr);
+  code >>>= 0;
+  if (code === 0) return true;
+  const view = new Uint32Array(memory.buffer);
+  const index = signalAddr >>> 2;
+  switch (Atomics.load(view, policyAddr >>> 2)) {
+    case OverwritePolicy.KEEP_HIGHEST: {
+      let pending = Atomics.load(view, index);
+      for (;;) {
+        if (pending !== 0 && rankSignal(pending) > rankSignal(code)) return false;
+        const previous = compareExchangeSealed(view, index, pending, code);
+        if (previous === pending) return wake(memory, signalAddr);
+        pending = previous;
+      }
+    }
+    case OverwritePolicy.ENQUEUE:
+      if (compareExchangeSealed(view, index, 0, code) === 0) return wake(memory, signalAddr);
+      return pushQueue(view, queueAddr, code);
+    default:
+      sealSignal(view, index, code);
+      Atomics.store(view, index, code);
+      return wake(memory, signalAddr);
+  }
+}
+
+// Mirrors `push` in the crate's policy module. Layout: capacity, head, tail,
+// then `capacity` slots.
+function pushQueue(view, queueAddr, code) {
+  checkAlignment(queueAddr);
+  const base = queueAddr >>> 2;
+  const capacity = Atomics.load(view, base);
+  let tail = Atomics.load(view, base + 2);
+  for (;;) {
+    const head = Atomics.load(view, base + 1);
+    if (((tail - head) >>> 0) >= capacity) return false;
+    const previous = Atomics.compareExchange(view, base + 2, tail, (tail + 1) >>> 0);
+    if (previous === tail) break;
+    tail = previous;
+  }
+  Atomics.store(view, base + 3 + (tail % capacity), code);
+  return true;
+}
+
+/**
+ * Resolves the address of the guest's command mailbox.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {number} Byte address of the mailbox.
+ */
+export function getMailboxAddress(exports, memory = exports.memory) {
+  return resolveAddress(exports.WASM_SIGNAL_MAILBOX_ADDR, memory);
+}
+
+// Must match `Signal::COMMAND` and `MAILBOX_ARGS` in the crate
+const COMMAND_SIGNAL = 5;
+const MAILBOX_ARGS = 4;
+
+/**
+ * Posts a command to the guest's mailbox and rings the doorbell by raising
+ * the command signal.
+ *
+ * The mailbox holds one command at a time: this returns `false` without
+ * writing anything while the previous command has not been taken. Without
+ * `policyAddrs`, the doorbell is only rung if no other signal is pending,
+ * since overwriting a pending cancel would lose it; the guest then finds the
+ * command on the next doorbell or `poll_command()`. With `policyAddrs`, the
+ * doorbell is sent like `sendSignal`.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} signalAddr - Address from `getSignalAddress`.
+ * @param {number} mailboxAddr - Address from `getMailboxAddress`.
+ * @param {number} code - Non-zero command code.
+ * @param {number[]} [args] - Up to four argument words; missing ones are `0`.
+ * @param {{ policyAddr: number, queueAddr: number }} [policyAddrs] - From
+ *   `getPolicyAddresses`.
+ * @returns {boolean} `false` if the mailbox is still full.
+ */
+export function sendCommand(memory, signalAddr, mailboxAddr, code, args = [], policyAddrs) {
+  checkAlignment(signalAddr);
+  checkAlignment(mailboxAddr);
+  code >>>= 0;
+  if (code === 0) throw new RangeError("command code must be non-zero");
+  if (args.length > MAILBOX_ARGS) throw new RangeError(`at most ${MAILBOX_ARGS} command arguments`);
+  const view = new Uint32Array(memory.buffer);
+  const base = mailboxAddr >>> 2;
+  if (Atomics.load(view, base) !== 0) return false;
+  for (let i = 0; i < MAILBOX_ARGS; i++) {
+    Atomics.store(view, base + 1 + i, (args[i] ?? 0) >>> 0);
+  }
+  // Publish the command only after its arguments
+  Atomics.store(view, base, code);
+  if (policyAddrs) {
+    sendSignal(memory, signalAddr, policyAddrs, COMMAND_SIGNAL);
+  } else if (compareExchangeSealed(view, signalAddr >>> 2, 0, COMMAND_SIGNAL) === 0) {
+    wake(memory, signalAddr);
+  }
+  return true;
+}
+
+/**
+ * Resolves the address of the source word, which tags the next signal with
+ * the subsystem that raised it.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {number} Byte address of the source word.
+ */
+export function getSourceAddress(exports, memory = exports.memory) {
+  return resolveAddress(exports.WASM_SIGNAL_SOURCE_ADDR, memory);
+}
+
+/**
+ * Tags the next signal with a source ID. Call before `writeSignal`; the
+ * guest consumes the tag when it observes the signal.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} sourceAddr - Address from `getSourceAddress`.
+ * @param {number} source - Host-defined unsigned 32-bit ID (`0` = untagged).
+ */
+export function writeSource(memory, sourceAddr, source) {
+  checkAlignment(sourceAddr);
+  Atomics.store(new Uint32Array(memory.buffer), sourceAddr >>> 2, source >>> 0);
+}
+
+/**
+ * Writes a signal code. `0` clears the signal.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} signalAddr - Address from `getSignalAddress`.
+ * @param {number} code - Unsigned 32-bit signal code.
+ */
+export function writeSignal(memory, signalAddr, code) {
+  checkAlignment(signalAddr);
+  const view = new Uint32Array(memory.buffer);
+  sealSignal(view, signalAddr >>> 2, code >>> 0);
+  Atomics.store(view, signalAddr >>> 2, code >>> 0);
+  wake(memory, signalAddr);
+}
+
+/**
+ * Reads the current signal code (`0` if no signal is pending).
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} signalAddr - Address from `getSignalAddress`.
+ * @returns {number}
+ */
+export function readSignal(memory, signalAddr) {
+  checkAlignment(signalAddr);
+  return Atomics.load(new Uint32Array(memory.buffer), signalAddr >>> 2);
+}
+
+/**
+ * Writes `code` only if the signal word currently holds `expected`.
+ *
+ * Use `expected = 0` to raise a signal without overwriting a pending one.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} signalAddr - Address from `getSignalAddress`.
+ * @param {number} expected - The code the word must hold.
+ * @param {number} code - Unsigned 32-bit signal code.
+ * @returns {number} The previous code; the write happened if it equals `expected`.
+ */
+export function compareExchangeSignal(memory, signalAddr, expected, code) {
+  checkAlignment(signalAddr);
+  return compareExchangeSealed(
+    new Uint32Array(memory.buffer),
+    signalAddr >>> 2,
+    expected >>> 0,
+    code >>> 0,
+  );
+}
+
+/**
+ * Clears any pending signal.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} signalAddr - Address from `getSignalAddress`.
+ */
+export function clearSignal(memory, signalAddr) {
+  writeSignal(memory, signalAddr, 0);
+}
+
+/**
+ * Reads the code of the last signal the guest acknowledged (`0` if none).
+ *
+ * Write `0` with `clearAck` before raising a signal to detect its ack.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} ackAddr - Address from `getStatusAddresses`.
+ * @returns {number}
+ */
+export function readAck(memory, ackAddr) {
+  checkAlignment(ackAddr);
+  return Atomics.load(new Uint32Array(memory.buffer), ackAddr >>> 2);
+}
+
+/**
+ * Clears the ack word.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} ackAddr - Address from `getStatusAddresses`.
+ */
+export function clearAck(memory, ackAddr) {
+  checkAlignment(ackAddr);
+  Atomics.store(new Uint32Array(memory.buffer), ackAddr >>> 2, 0);
+}
+
+/**
+ * Waits for the guest to acknowledge a signal, for escalating when it does
+ * not in time.
+ *
+ * Clear the ack word with `clearAck` before raising the signal. On a shared
+ * memory this waits with `Atomics.waitAsync`, which threaded guests wake
+ * when they acknowledge; otherwise it polls every `intervalMs`. Polling only
+ * sees the ack between guest calls, so a guest that runs to completion is
+ * seen when its call returns.
+ *
+ * @example
+ * clearAck(memory, ackAddr);
+ * writeSignal(memory, signalAddr, 2);
+ * if (!(await awaitAck(memory, ackAddr, 5_000))) {
+ *   writeSignal(memory, signalAddr, encodeSignal(1, Severity.FATAL));
+ * }
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} ackAddr - Address from `getStatusAddresses`.
+ * @param {number} timeoutMs - How long to wait for the ack.
+ * @param {number} [intervalMs] - Polling interval, without `Atomics.waitAsync`.
+ * @returns {Promise<number>} The acknowledged code, or `0` on timeout.
+ */
+export async function awaitAck(memory, ackAddr, timeoutMs, intervalMs = 5) {
+  checkAlignment(ackAddr);
+  const deadline = Date.now() + timeoutMs;
+  const canWait =
+    typeof Atomics.waitAsync === "function" &&
+    typeof SharedArrayBuffer === "function" &&
+    memory.buffer instanceof SharedArrayBuffer;
+  for (;;) {
+    // Re-created each time round, as the memory may have grown
+    const words = new Int32Array(memory.buffer);
+    const ack = Atomics.load(words, ackAddr >>> 2) >>> 0;
+    if (ack !== 0) {
+      return ack;
+    }
+    const remaining = deadline - Date.now();
+    if (remaining <= 0) {
+      return 0;
+    }
+    if (canWait) {
+      const result = Atomics.waitAsync(words, ackAddr >>> 2, 0, remaining);
+      if (result.async) {
+        await result.value;
+      }
+    } else {
+      await new Promise((resolve) => setTimeout(resolve, Math.min(intervalMs, remaining)));
+    }
+  }
+}
+
+/**
+ * Reads the guest's heartbeat counter. It wraps on overflow.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} heartbeatAddr - Address from `getStatusAddresses`.
+ * @returns {number}
+ */
+export function readHeartbeat(memory, heartbeatAddr) {
+  checkAlignment(heartbeatAddr);
+  return Atomics.load(new Uint32Array(memory.buffer), heartbeatAddr >>> 2);
+}
+
+/**
+ * Flags in the guest's disposition word. Exactly one of `PANICS`, `TRAPS`
+ * and `THROWS` is set, depending on how the guest was built.
+ */
+export const Disposition = Object.freeze({
+  HANDLER: 1 << 0,
+  PANICS: 1 << 1,
+  TRAPS: 1 << 2,
+  THROWS: 1 << 3,
+  LATCH_MODE: 1 << 4,
+  LATCHED: 1 << 5,
+});
+
+/**
+ * Resolves the address of the guest's disposition word.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {number} Byte address of the disposition word.
+ */
+export function getDispositionAddress(exports, memory = exports.memory) {
+  return resolveAddress(exports.WASM_SIGNAL_DISPOSITION_ADDR, memory);
+}
+
+/**
+ * Reads the guest's disposition, a combination of `Disposition` flags. A
+ * guest without `Disposition.HANDLER` ends the call on the next check
+ * after any signal, so hosts may prefer terminating it outright.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} dispositionAddr - Address from `getDispositionAddress`.
+ * @returns {number}
+ */
+export function readDisposition(memory, dispositionAddr) {
+  checkAlignment(dispositionAddr);
+  return Atomics.load(new Uint32Array(memory.buffer), dispositionAddr >>> 2);
+}
+
+/**
+ * Values of the guest's lifecycle state word, in the order a request moves
+ * through them.
+ */
+export const LifecycleState = Object.freeze({
+  IDLE: 0,
+  RUNNING: 1,
+  CANCELLING: 2,
+  CLEANING_UP: 3,
+  TERMINATED: 4,
+});
+
+/**
+ * Resolves the address of the guest's lifecycle state word.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {number} Byte address of the state word.
+ */
+export function getStateAddress(exports, memory = exports.memory) {
+  return resolveAddress(exports.WASM_SIGNAL_STATE_ADDR, memory);
+}
+
+/**
+ * Reads the guest's lifecycle state, one of the `LifecycleState` values. A
+ * guest still `RUNNING` after a cancel has not reached a check yet, while
+ * one `CLEANING_UP` is running its shutdown hooks and is worth waiting for.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} stateAddr - Address from `getStateAddress`.
+ * @returns {number}
+ */
+export function readState(memory, stateAddr) {
+  checkAlignment(stateAddr);
+  return Atomics.load(new Uint32Array(memory.buffer), stateAddr >>> 2);
+}
+
+/**
+ * Waits until the guest's lifecycle state reaches `state` or a later one,
+ * for up to `timeoutMs`.
+ *
+ * Like `awaitAck`, this waits with `Atomics.waitAsync` on a shared memory,
+ * as threaded guests wake waiters on every change, and otherwise polls
+ * every `intervalMs`.
+ *
+ * @example
+ * writeSignal(memory, signalAddr, 1);
+ * const reached = await awaitState(memory, stateAddr, LifecycleState.CANCELLING, 1_000);
+ * if (reached < LifecycleState.CANCELLING) {
+ *   // The guest never reached a check
+ *   worker.terminate();
+ * }
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} stateAddr - Address from `getStateAddress`.
+ * @param {number} state - A `LifecycleState` value.
+ * @param {number} timeoutMs - How long to wait.
+ * @param {number} [intervalMs] - Polling interval, without `Atomics.waitAsync`.
+ * @returns {Promise<number>} The state last read, earlier than `state` on
+ *   timeout.
+ */
+export async function awaitState(memory, stateAddr, state, timeoutMs, intervalMs = 5) {
+  checkAlignment(stateAddr);
+  const deadline = Date.now() + timeoutMs;
+  const canWait =
+    typeof Atomics.waitAsync === "function" &&
+    typeof SharedArrayBuffer === "function" &&
+    memory.buffer instanceof SharedArrayBuffer;
+  for (;;) {
+    // Re-created each time round, as the memory may have grown
+    const words = new Int32Array(memory.buffer);
+    const current = Atomics.load(words, stateAddr >>> 2);
+    const remaining = deadline - Date.now();
+    if (current >= state || remaining <= 0) {
+      return current;
+    }
+    if (canWait) {
+      const result = Atomics.waitAsync(words, stateAddr >>> 2, current, remaining);
+      if (result.async) {
+        await result.value;
+      }
+    } else {
+      await new Promise((resolve) => setTimeout(resolve, Math.min(intervalMs, remaining)));
+    }
+  }
+}
+
+/**
+ * Resolves the address of the guest's epoch word.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {number} Byte address of the epoch word.
+ */
+export function getEpochAddress(exports, memory = exports.memory) {
+  return resolveAddress(exports.WASM_SIGNAL_EPOCH_ADDR, memory);
+}
+
+/**
+ * Advances the guest's epoch by one, for guests waiting on an epoch
+ * deadline. It wraps on overflow.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} epochAddr - Address from `getEpochAddress`.
+ */
+export function incrementEpoch(memory, epochAddr) {
+  checkAlignment(epochAddr);
+  Atomics.add(new Uint32Array(memory.buffer), epochAddr >>> 2, 1);
+}
+
+/**
+ * Advances the epoch of every instance in `instances` every `periodMs`,
+ * so their epoch deadlines expire without further host involvement.
+ *
+ * @param {WebAssembly.Exports[]} instances
+ * @param {number} periodMs - The tick period, the deadlines' resolution.
+ * @returns {() => void} Stops the timer.
+ */
+export function startEpochTicker(instances, periodMs) {
+  const timer = setInterval(() => {
+    for (const exports of instances) {
+      incrementEpoch(exports.memory, getEpochAddress(exports));
+    }
+  }, periodMs);
+  return () => clearInterval(timer);
+}
+
+/**
+ * Reads the progress the guest published with `set_progress` and
+ * `set_stage`. A `total` of `0` means the guest did not report one.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} progressAddr - Address from `getProgressAddress`.
+ * @returns {{ done: number, total: number, stage: number }}
+ */
+export function readProgress(memory, progressAddr) {
+  checkAlignment(progressAddr);
+  const view = new Uint32Array(memory.buffer);
+  const base = progressAddr >>> 2;
+  // Read `done` first; the guest writes `total` before it
+  const done = Atomics.load(view, base);
+  return {
+    done,
+    total: Atomics.load(view, base + 1),
+    stage: Atomics.load(view, base + 2),
+  };
+}
+
+/**
+ * Kinds of the events the crate posts itself. Must match the `EVENT_*`
+ * constants in the crate; application kinds start at `USER_BASE`.
+ *
+ * @readonly
+ * @enum {number}
+ */
+export const EventKind = Object.freeze({
+  SIGNAL_ACKNOWLEDGED: 1,
+  CHECKPOINT_WRITTEN: 2,
+  STAGE_CHANGED: 3,
+  SIGNAL_REJECTED: 4,
+  USER_BASE: 0x100,
+});
+
+/**
+ * Resolves the address of the guest's event ring.
+ *
+ * @param {WebAssembly.Exports} exports - The instance exports.
+ * @param {WebAssembly.Memory} [memory] - Defaults to `exports.memory`.
+ * @returns {number} Byte address of the event ring.
+ */
+export function getEventsAddress(exports, memory = exports.memory) {
+  return resolveAddress(exports.WASM_SIGNAL_EVENTS_ADDR, memory);
+}
+
+/**
+ * Removes and returns the events the guest has posted, oldest first.
+ *
+ * Mirrors the ring layout of the crate's events module: capacity, head,
+ * tail, dropped count, then `capacity` slots of kind and value. Call from one
+ * place only; the ring has a single consumer.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} eventsAddr - Address from `getEventsAddress`.
+ * @returns {{ kind: number, value: number }[]}
+ */
+export function drainEvents(memory, eventsAddr) {
+  checkAlignment(eventsAddr);
+  const view = new Uint32Array(memory.buffer);
+  const base = eventsAddr >>> 2;
+  const capacity = Atomics.load(view, base);
+  const events = [];
+  for (;;) {
+    const head = Atomics.load(view, base + 1);
+    if (head === Atomics.load(view, base + 2)) break;
+    const slot = base + 4 + (head % capacity) * 2;
+    // A zero kind is reserved but not yet published
+    const kind = Atomics.exchange(view, slot, 0);
+    if (kind === 0) break;
+    events.push({ kind, value: Atomics.load(view, slot + 1) });
+    Atomics.store(view, base + 1, (head + 1) >>> 0);
+  }
+  return events;
+}
+
+/**
+ * Reads how many events the guest dropped because the ring was full. It
+ * wraps on overflow.
+ *
+ * @param {WebAssembly.Memory} memory
+ * @param {number} eventsAddr - Address from `getEventsAddress`.
+ * @returns {number}
+ */
+export function readDroppedEvents(memory, eventsAddr) {
+  checkAlignment(eventsAddr);
+  return Atomics.load(new Uint32Array(memory.buffer), (eventsAddr >>> 2) + 3);
+}
+
+/**
+ * Wakes guest tasks waiting on signal-aware channel receivers, so they see a
+ * signal just written with `writeSignal` or `sendSignal`.
+ *
+ * A no-op unless the guest was built with the `futures-channel` feature.
+ *
+ * @param {WebAssembly.Exports} exports
+ */
+export function wakeTasks(exports) {
+  exports.__wasm_signal_wake_tasks?.();
+}
+
+/**
+ * Returns the signal code carried by a caught exception, for guests built
+ * with the `throw` feature, which throw propagated signals with the
+ * exported `WASM_SIGNAL_TAG` tag.
+ *
+ * @param {WebAssembly.Exports} exports
+ * @param {*} err - The caught value.
+ * @returns {number | undefined} The signal code, or `undefined` if `err` is
+ *   not a signal exception.
+ */
+export function signalFromException(exports, err) {
+  const tag = exports.WASM_SIGNAL_TAG;
+  if (tag === undefined || !(err instanceof WebAssembly.Exception) || !err.is(tag)) {
+    return undefined;
+  }
+  return err.getArg(tag, 0) >>> 0;
+}
+
+/**
+ * Fetches the guest's diagnostics dump, for debugging stuck instances.
+ *
+ * Requires a guest built with the `diagnostics-json` feature, exporting
+ * `__wasm_signal_dump_diagnostics`, and a `malloc`/`free` pair to size the
+ * buffer with (wasm-bindgen's are used by default).
+ *
+ * @param {WebAssembly.Exports} exports
+ * @param {object} [options]
+ * @param {WebAssembly.Memory} [options.memory]
+ * @param {Function} [options.malloc]
+ * @param {Function} [options.free]
+ * @returns {object} The parsed dump.
+ */
+export function readDiagnostics(exports, {
+  memory = exports.memory,
+  malloc = exports.__wbindgen_malloc,
+  free = exports.__wbindgen_free,
+} = {}) {
+  const dump = exports.__wasm_signal_dump_diagnostics;
+  if (typeof dump !== "function") {
+    throw new Error("__wasm_signal_dump_diagnostics is not exported; build with the diagnostics-json feature");
+  }
+  // The sizing call keeps its dump for the next call, which writes it; if
+  // another caller took it in between, retry with the new length
+  let len = dump(0, 0);
+  for (;;) {
+    const size = len;
+    const ptr = malloc(size, 1);
+    try {
+      const needed = dump(ptr, size);
+      if (needed <= size) {
+        const bytes = new Uint8Array(memory.buffer, ptr, needed).slice();
+        return JSON.parse(new TextDecoder().decode(bytes));
+      }
+      len = needed;
+    } finally {
+      free(ptr, size, 1);
+    }
+  }
+}
+
+// With a block header before the signal word, sets the check word after it
+// for a write of `code`. Call before writing the code; clears need no check.
+function sealSignal(view, index, code) {
+  if (code !== 0 && index > 0 && Atomics.load(view, index - 1) === BLOCK_MAGIC) {
+    Atomics.store(view, index + 1, (code ^ BLOCK_MAGIC) >>> 0);
+  }
+}
+
+// Like `Atomics.compareExchange` on the signal word, keeping the check word
+// matching the code that ends up in it.
+function compareExchangeSealed(view, index, expected, code) {
+  sealSignal(view, index, code);
+  const previous = Atomics.compareExchange(view, index, expected, code);
+  if (previous !== expected) sealSignal(view, index, previous);
+  return previous;
+}
+
+function resolveAddress(global, memory) {
+  return Atomics.load(new Uint32Array(memory.buffer), global.value >>> 2);
+}
+
+// Wakes guest threads blocked in `wait_for_signal`. A no-op on unshared
+// memories. Returns `true` for the convenience of senders.
+function wake(memory, signalAddr) {
+  Atomics.notify(new Int32Array(memory.buffer), signalAddr >>> 2);
+  return true;
+}
+
+function checkAlignment(addr) {
+  if (addr % 4 !== 0) {
+    throw new RangeError(`signal address ${addr} is not 4-byte aligned`);
+  }
+}
@@ -0,0 +1,122 @@
+// Cloudflare Workers helpers for wasm-signal-handler.
+//
+// The Workers runtime hard-terminates a request that runs past its CPU or
+// wall clock limit, discarding whatever the guest had computed. A
+// `SignalBudget` arms a timer for shortly before the request's budget runs
+// out and raises a soft-cancel signal in the guest when it fires, so the
+// guest can stop and return a partial response instead.
+//
+// Timers only fire while the guest awaits something, such as a fetch or a
+// stream write; purely CPU-bound guest code never lets them in. Guests
+// should check for signals after their awaits.
+
+import { getSignalAddress, wakeTasks, writeSignal } from "./wasm-signal-handler.js";
+
+/**
+ * The signal words for the Workers runtime's limits. Must match
+ * `WorkersLimit` in the crate: a code in the `0xCF00` block, with the
+ * severity in the top four bits.
+ */
+export const WorkersLimit = Object.freeze({
+  CPU_LIMIT_APPROACHING: ((3 << 28) | 0xcf01) >>> 0,
+  WALL_CLOCK_LIMIT: ((4 << 28) | 0xcf02) >>> 0,
+  MEMORY_PRESSURE: ((2 << 28) | 0xcf03) >>> 0,
+  EVICTION: ((4 << 28) | 0xcf04) >>> 0,
+});
+
+/**
+ * Raises a signal in a guest instance shortly before a request's time
+ * budget runs out.
+ *
+ * @example
+ * export default {
+ *   async fetch(request) {
+ *     const budget = new SignalBudget(instance.exports, { budgetMs: 30_000 });
+ *     budget.start();
+ *     try {
+ *       return await instance.exports.handle(request);
+ *     } finally {
+ *       budget.stop();
+ *     }
+ *   },
+ * };
+ */
+export class SignalBudget {
+  /**
+   * @param {WebAssembly.Exports} exports - The guest instance's exports.
+   * @param {object} options
+   * @param {number} options.budgetMs - The request's time budget.
+   * @param {number} [options.marginMs] - How long before the budget runs out
+   *   to raise the signal; defaults to 10% of the budget.
+   * @param {number} [options.code] - Signal to raise; defaults to
+   *   `WorkersLimit.CPU_LIMIT_APPROACHING`, a soft cancel.
+   * @param {WebAssembly.Memory} [options.memory] - Defaults to
+   *   `exports.memory`.
+   */
+  constructor(
+    exports,
+    {
+      budgetMs,
+      marginMs = budgetMs / 10,
+      code = WorkersLimit.CPU_LIMIT_APPROACHING,
+      memory = exports.memory,
+    },
+  ) {
+    this.exports = exports;
+    this.memory = memory;
+    this.delayMs = Math.max(0, budgetMs - marginMs);
+    this.code = code;
+    this.timer = undefined;
+    this.fired = false;
+  }
+
+  /**
+   * Arms the timer, replacing a previously armed one. The budget counts
+   * from this call.
+   */
+  start() {
+    this.stop();
+    this.fired = false;
+    this.timer = setTimeout(() => this.fire(), this.delayMs);
+  }
+
+  /**
+   * Cancels the timer, if it has not fired.
+   */
+  stop() {
+    if (this.timer !== undefined) {
+      clearTimeout(this.timer);
+      this.timer = undefined;
+    }
+  }
+
+  /**
+   * Raises the signal right away and wakes guest tasks waiting on
+   * signal-aware channels. Called by the timer.
+   */
+  fire() {
+    this.timer = undefined;
+    this.fired = true;
+    writeSignal(this.memory, getSignalAddress(this.exports, this.memory), this.code);
+    wakeTasks(this.exports);
+  }
+}
+
+/**
+ * Runs `fn` under a `SignalBudget`, stopping the timer when it settles.
+ *
+ * @param {WebAssembly.Exports} exports - The guest instance's exports.
+ * @param {object} options - As for `SignalBudget`.
+ * @param {() => Promise<T>} fn
+ * @returns {Promise<T>}
+ * @template T
+ */
+export async function withSignalBudget(exports, options, fn) {
+  const budget = new SignalBudget(exports, options);
+  budget.start();
+  try {
+    return await fn();
+  } finally {
+    budget.stop();
+  }
+}
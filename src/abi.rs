@@ -0,0 +1,245 @@
+//! ABI metadata for host tooling.
+//!
+//! Compiled modules carry a custom section named [`ABI_SECTION`] that
+//! records which version of the host-shared layouts the guest was built
+//! with. The section is two little-endian `u32`s: [`ABI_VERSION`] and the
+//! `ABI_*` flags. Tools such as `wasm-signal-inspect` read it to tell
+//! whether a module matches the host helpers deployed with it.
+//!
+//! Each linked copy of the crate appends its own record, so a section longer
+//! than eight bytes means the crate was linked more than once, and the copies
+//! do not share a signal word.
+//!
+//! Custom sections are invisible to the guest at runtime, so the crate also
+//! keeps an [`ExportMetadata`] block in memory, exported as
+//! `WASM_SIGNAL_METADATA_ADDR` and returned by the exported
+//! `__wasm_signal_anchor` function. The anchor references every exported
+//! slot, which keeps them from being garbage-collected by `wasm-ld` or
+//! `wasm-opt` when nothing else in the module does, and gives hosts a way
+//! to find the signal word when a tool strips the globals anyway. Hosts mark
+//! the block when they attach, so [`verify_exports`] can report whether the
+//! signal export survived the toolchain.
+
+use core::fmt;
+use core::sync::atomic::AtomicU32;
+
+use crate::ACQUIRE;
+
+/// The version of the host-shared layouts: the exported slots, their
+/// double indirection and the signal codes the host helpers write. Bumped
+/// on incompatible changes.
+pub const ABI_VERSION: u32 = 1;
+
+/// The name of the custom section holding the ABI metadata.
+pub const ABI_SECTION: &str = "wasm_signal_abi";
+
+/// Flag set when the signal word is the `WASM_SIGNAL_GLOBAL` global rather
+/// than a word in memory (the `global-storage` feature).
+pub const ABI_GLOBAL_STORAGE: u32 = 1 << 0;
+
+/// Flag set when the signal block is in a control memory (the
+/// `control-memory` feature, once `wasm-signal-control-memory` has moved
+/// it). The memory's index is in the flags' bits from
+/// [`ABI_MEMORY_INDEX_SHIFT`], and the address of the signal word is its
+/// offset in that memory.
+pub const ABI_CONTROL_MEMORY: u32 = 1 << 1;
+
+/// The shift of the control memory's index in the `ABI_*` flags.
+pub const ABI_MEMORY_INDEX_SHIFT: u32 = 8;
+
+/// The crate's record in the ABI section.
+#[cfg(target_arch = "wasm32")]
+pub(crate) const fn record() -> [u8; 8] {
+    let version = ABI_VERSION.to_le_bytes();
+    let flags = if cfg!(feature = "global-storage") {
+        ABI_GLOBAL_STORAGE
+    } else {
+        0
+    }
+    .to_le_bytes();
+    [
+        version[0], version[1], version[2], version[3], flags[0], flags[1], flags[2], flags[3],
+    ]
+}
+
+// ============================================================================
+// Export Metadata
+// ============================================================================
+
+/// The first word of the [`ExportMetadata`] block, `"WSIG"` in little-endian
+/// byte order.
+pub const METADATA_MAGIC: u32 = u32::from_le_bytes(*b"WSIG");
+
+/// The word in memory before the signal word, `"WSBK"` in little-endian byte
+/// order. Host helpers that find it also write the check word after the
+/// signal word (see `storage`).
+pub const BLOCK_MAGIC: u32 = u32::from_le_bytes(*b"WSBK");
+
+/// Set by a host that resolved the signal word through its export.
+pub const ATTACHED_EXPORT: u32 = 1 << 0;
+
+/// Set by a host that had to resolve the signal word through
+/// `__wasm_signal_anchor`, because the export was missing.
+pub const ATTACHED_ANCHOR: u32 = 1 << 1;
+
+/// The metadata block shared with the host.
+///
+/// Five words: [`METADATA_MAGIC`], [`ABI_VERSION`], the `ABI_*` flags, the
+/// address of the signal word (`0` with global storage) and the `ATTACHED_*`
+/// flags written by the host. The layout is read by the host helpers and
+/// must not change.
+#[repr(C)]
+pub struct ExportMetadata {
+    magic: AtomicU32,
+    version: u32,
+    flags: u32,
+    signal: Option<&'static AtomicU32>,
+    attached: AtomicU32,
+}
+
+static METADATA: ExportMetadata = ExportMetadata {
+    magic: AtomicU32::new(METADATA_MAGIC),
+    version: ABI_VERSION,
+    flags: if cfg!(all(feature = "global-storage", target_arch = "wasm32")) {
+        ABI_GLOBAL_STORAGE
+    } else {
+        0
+    },
+    #[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+    signal: Some(&crate::SIGNAL.word),
+    #[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+    signal: None,
+    attached: AtomicU32::new(0),
+};
+
+/// The exported address of the metadata block.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_METADATA_ADDR: &ExportMetadata = &METADATA;
+
+/// Keeps every exported slot alive and returns the address of the metadata
+/// block.
+#[no_mangle]
+pub extern "C" fn __wasm_signal_anchor() -> *const ExportMetadata {
+    #[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+    core::hint::black_box(&crate::WASM_SIGNAL_ADDR);
+    core::hint::black_box((
+        &crate::disposition::WASM_SIGNAL_DISPOSITION_ADDR,
+        &crate::epoch::WASM_SIGNAL_EPOCH_ADDR,
+        &crate::events::WASM_SIGNAL_EVENTS_ADDR,
+        &crate::lifecycle::WASM_SIGNAL_STATE_ADDR,
+        &crate::mailbox::WASM_SIGNAL_MAILBOX_ADDR,
+        &crate::policy::WASM_SIGNAL_POLICY_ADDR,
+        &crate::policy::WASM_SIGNAL_QUEUE_ADDR,
+        &crate::progress::WASM_SIGNAL_PROGRESS_ADDR,
+        &crate::source::WASM_SIGNAL_SOURCE_ADDR,
+        &crate::status::WASM_SIGNAL_ACK_ADDR,
+        &crate::status::WASM_SIGNAL_HEARTBEAT_ADDR,
+        &WASM_SIGNAL_METADATA_ADDR,
+    ));
+    &METADATA
+}
+
+/// Why the exports cannot be relied on, as reported by [`verify_exports`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportError {
+    /// The metadata block was overwritten, most likely by a host writing to
+    /// an address resolved from a different build of the module.
+    Corrupted,
+    /// No host has attached, so nothing is known to be writing the signal
+    /// word. Either the host does not use the attach helper, or it could
+    /// find neither the exports nor the anchor.
+    NotAttached,
+    /// The host only found the signal word through the anchor: the signal
+    /// export was stripped by the toolchain, and hosts that look it up by
+    /// name cannot deliver signals.
+    Stripped,
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExportError::Corrupted => {
+                "the signal metadata block was overwritten; the host may be using a stale address"
+            }
+            ExportError::NotAttached => "no host has attached to the signal exports",
+            ExportError::Stripped => {
+                "the signal export was stripped from the module; \
+                 the host only found it through `__wasm_signal_anchor`"
+            }
+        })
+    }
+}
+
+impl core::error::Error for ExportError {}
+
+/// Checks that a host attached to the signal word through its export.
+///
+/// Call it once the host has run its attach helper (`attachHost` in
+/// `js/wasm-signal-handler.js`), for example from the first export the host
+/// calls, to fail loudly on a module whose exports did not survive
+/// `wasm-opt`, a bundler or a stripping step.
+///
+/// # Errors
+///
+/// Returns an [`ExportError`] describing what went wrong.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{verify_exports, ExportError};
+///
+/// // Natively, no host ever attaches
+/// assert_eq!(verify_exports(), Err(ExportError::NotAttached));
+/// ```
+pub fn verify_exports() -> Result<(), ExportError> {
+    if METADATA.magic.load(ACQUIRE) != METADATA_MAGIC {
+        return Err(ExportError::Corrupted);
+    }
+    let attached = METADATA.attached.load(ACQUIRE);
+    if attached & ATTACHED_EXPORT != 0 {
+        Ok(())
+    } else if attached & ATTACHED_ANCHOR != 0 {
+        Err(ExportError::Stripped)
+    } else {
+        Err(ExportError::NotAttached)
+    }
+}
+
+/// Returns the ABI version recorded in the metadata block, which differs
+/// from [`ABI_VERSION`] only if something overwrote it.
+pub(crate) fn metadata_version() -> u32 {
+    // SAFETY: A valid, aligned field of a static. Read volatile so that a
+    // host write to it is not assumed away.
+    unsafe { core::ptr::read_volatile(&METADATA.version) }
+}
+
+/// Marks the block as attached, as a host does.
+#[cfg(test)]
+pub(crate) fn attach(flags: u32) -> u32 {
+    METADATA.attached.swap(flags, crate::RELEASE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_exports() {
+        let previous = attach(0);
+        assert_eq!(verify_exports(), Err(ExportError::NotAttached));
+        attach(ATTACHED_ANCHOR);
+        assert_eq!(verify_exports(), Err(ExportError::Stripped));
+        attach(ATTACHED_ANCHOR | ATTACHED_EXPORT);
+        assert_eq!(verify_exports(), Ok(()));
+        attach(previous);
+
+        let metadata = __wasm_signal_anchor();
+        assert_eq!(metadata, WASM_SIGNAL_METADATA_ADDR as *const ExportMetadata);
+        #[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+        assert!(core::ptr::eq(
+            METADATA.signal.unwrap(),
+            crate::WASM_SIGNAL_ADDR
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+//! Aborting `futures` tasks on cancellation.
+//!
+//! Async code is often already structured around
+//! [`Abortable`](futures_util::future::Abortable). An [`abort_registration`]
+//! plugs such code into host cancellation: its handle is aborted as soon as
+//! a check propagates a signal, which wakes the abortable future and resolves
+//! it to `Err(Aborted)`.
+
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::sync::atomic::AtomicBool;
+
+use futures_util::future::{AbortHandle, AbortRegistration};
+
+use crate::{ACQUIRE, RELEASE};
+
+/// Handles of live registrations, guarded by [`LOCK`].
+struct Handles(UnsafeCell<Vec<AbortHandle>>);
+
+// SAFETY: The handles are only accessed under LOCK, and are `Send + Sync`
+unsafe impl Sync for Handles {}
+
+static HANDLES: Handles = Handles(UnsafeCell::new(Vec::new()));
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+fn with_handles<R>(f: impl FnOnce(&mut Vec<AbortHandle>) -> R) -> R {
+    while LOCK
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // SAFETY: LOCK grants exclusive access to the handles
+    let result = f(unsafe { &mut *HANDLES.0.get() });
+    LOCK.store(false, RELEASE);
+    result
+}
+
+/// Creates an [`AbortRegistration`] whose handle is aborted when a check
+/// propagates a signal.
+///
+/// Each registration is aborted at most once: after a propagated signal,
+/// create new registrations for new work. Signals the handler clears do not
+/// abort anything. [`reset_all`](crate::reset_all) drops live registrations
+/// without aborting them.
+///
+/// # Example
+///
+/// ```rust
+/// use futures_util::future::Abortable;
+/// use wasm_signal_handler::abort_registration;
+///
+/// async fn serve() {
+///     let work = async { /* ... */ };
+///     match Abortable::new(work, abort_registration()).await {
+///         Ok(()) => {}
+///         Err(_aborted) => { /* cancelled by the host */ }
+///     }
+/// }
+/// ```
+pub fn abort_registration() -> AbortRegistration {
+    let (handle, registration) = AbortHandle::new_pair();
+    with_handles(|handles| {
+        // Registrations whose future completed are only dropped here
+        handles.retain(|handle| !handle.is_aborted());
+        handles.push(handle);
+    });
+    registration
+}
+
+/// Aborts every live registration, after a check propagated a signal.
+pub(crate) fn abort_all() {
+    let handles = with_handles(core::mem::take);
+    // Abort outside the lock, since aborting wakes tasks
+    for handle in handles {
+        handle.abort();
+    }
+}
+
+/// Drops live registrations without aborting them.
+pub(crate) fn reset() {
+    drop(with_handles(core::mem::take));
+}
+
+/// The live registrations, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(Vec<AbortHandle>);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(with_handles(|handles| handles.clone()))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    let old = with_handles(|handles| core::mem::replace(handles, saved.0.clone()));
+    drop(old);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, try_check_signal, Signal};
+    use core::future::{pending, Future};
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use futures_util::future::{Abortable, Aborted};
+
+    #[test]
+    fn test_abort_on_propagated_signal() {
+        let _guard = lock();
+        clear_signal();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut task = pin!(Abortable::new(pending::<()>(), abort_registration()));
+        assert_eq!(task.as_mut().poll(&mut cx), Poll::Pending);
+
+        // A handled signal does not abort
+        crate::set_signal_handler(|_| Ok(()));
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(task.as_mut().poll(&mut cx), Poll::Pending);
+
+        clear_signal_handler();
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert_eq!(task.as_mut().poll(&mut cx), Poll::Ready(Err(Aborted)));
+    }
+}
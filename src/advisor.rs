@@ -0,0 +1,346 @@
+//! Advice on how often each call site checks for signals.
+//!
+//! Checks that run too often cost time in hot loops; checks that run too
+//! rarely delay cancellation. With the advisor enabled (see
+//! [`set_check_advisor`]), every check records its caller, and
+//! [`check_advice`] reports, per call site, how far apart its checks were,
+//! in checks made anywhere in between and, with a [clock](crate::now_ms), in
+//! time. Sites whose checks are closer together than the target interval get
+//! a recommended stride: check on every `stride`th iteration instead.
+//!
+//! While enabled, every check takes the slow path, so enable it for a
+//! profiling run rather than in production. Up to [`MAX_ADVISED_SITES`]
+//! call sites are tracked; checks from further sites are not recorded.
+
+use core::panic::Location;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicU64};
+
+use crate::{arm, disarm, now_ms, ACQUIRE, ACQ_REL, ARMED, ARMED_ADVISE, RELEASE};
+
+/// The maximum number of call sites the advisor tracks.
+pub const MAX_ADVISED_SITES: usize = 16;
+
+/// The default target interval between checks at one site, in microseconds.
+pub const DEFAULT_CHECK_TARGET_US: (u32, u32) = (100, 10_000);
+
+/// One call site's record.
+struct Site {
+    location: AtomicPtr<Location<'static>>,
+    checks: AtomicU32,
+    /// The advisor's check sequence number at the site's first and last
+    /// check.
+    first_seq: AtomicU32,
+    last_seq: AtomicU32,
+    /// Clock time of the site's first and last check, if there is a clock.
+    first_ms: AtomicU64,
+    last_ms: AtomicU64,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SITE: Site = Site {
+    location: AtomicPtr::new(null_mut()),
+    checks: AtomicU32::new(0),
+    first_seq: AtomicU32::new(0),
+    last_seq: AtomicU32::new(0),
+    first_ms: AtomicU64::new(0),
+    last_ms: AtomicU64::new(0),
+};
+
+static SITES: [Site; MAX_ADVISED_SITES] = [EMPTY_SITE; MAX_ADVISED_SITES];
+
+/// Checks recorded so far, across all sites.
+static SEQ: AtomicU32 = AtomicU32::new(0);
+
+static TARGET_MIN_US: AtomicU32 = AtomicU32::new(DEFAULT_CHECK_TARGET_US.0);
+static TARGET_MAX_US: AtomicU32 = AtomicU32::new(DEFAULT_CHECK_TARGET_US.1);
+
+/// How a call site's check rate compares to the target interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum CheckRate {
+    /// Checks are closer together than the target's lower bound.
+    TooOften,
+    /// Checks are within the target interval.
+    Ok,
+    /// Checks are further apart than the target's upper bound, so
+    /// cancellation waits on them.
+    TooRarely,
+    /// There is no clock, or too few checks to tell.
+    Unknown,
+}
+
+/// What the advisor observed at one call site, from [`check_advice`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CheckSiteAdvice {
+    /// The call site.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_location"))]
+    pub location: &'static Location<'static>,
+    /// Checks recorded at the site.
+    pub checks: u32,
+    /// The mean number of checks, at any site, from one check at this site
+    /// to the next. `1` means nothing else checked in between.
+    pub mean_interval_checks: u32,
+    /// The mean time between checks at the site, in microseconds, if there
+    /// is a clock.
+    pub mean_interval_us: Option<u64>,
+    /// How the interval compares to the target.
+    pub rate: CheckRate,
+    /// For sites that check too often, how many iterations to skip between
+    /// checks to reach the target's lower bound.
+    pub recommended_stride: Option<u32>,
+}
+
+#[cfg(feature = "serde")]
+fn serialize_location<S: serde::Serializer>(
+    location: &&'static Location<'static>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(location)
+}
+
+/// Enables or disables the advisor. Disabling it keeps what was recorded.
+pub fn set_check_advisor(enabled: bool) {
+    if enabled {
+        arm(ARMED_ADVISE);
+    } else {
+        disarm(ARMED_ADVISE);
+    }
+}
+
+/// Returns `true` if the advisor is enabled.
+pub fn check_advisor() -> bool {
+    ARMED.load(ACQUIRE) & ARMED_ADVISE != 0
+}
+
+/// Sets the target interval between checks at one call site, in
+/// microseconds. `max_us` is raised to `min_us` if it is lower.
+///
+/// The default, [`DEFAULT_CHECK_TARGET_US`], asks for checks between 0.1ms
+/// and 10ms apart.
+pub fn set_check_target_us(min_us: u32, max_us: u32) {
+    TARGET_MIN_US.store(min_us, RELEASE);
+    TARGET_MAX_US.store(max_us.max(min_us), RELEASE);
+}
+
+/// Forgets every recorded call site.
+pub fn reset_check_advice() {
+    for site in &SITES {
+        site.location.store(null_mut(), RELEASE);
+        site.checks.store(0, RELEASE);
+    }
+    SEQ.store(0, RELEASE);
+}
+
+/// Returns the advice for each recorded call site, in the order they were
+/// first seen.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{check_advice, set_check_advisor, try_check_signal, CheckRate};
+///
+/// set_check_advisor(true);
+/// for _ in 0..1000 {
+///     let _ = try_check_signal();
+/// }
+/// set_check_advisor(false);
+///
+/// for advice in check_advice() {
+///     if advice.rate == CheckRate::TooOften {
+///         println!("{}: check every {:?} iterations", advice.location, advice.recommended_stride);
+///     }
+/// }
+/// # wasm_signal_handler::reset_check_advice();
+/// ```
+pub fn check_advice() -> impl Iterator<Item = CheckSiteAdvice> {
+    SITES.iter().map_while(advise)
+}
+
+fn advise(site: &Site) -> Option<CheckSiteAdvice> {
+    // SAFETY: Only `&'static Location`s are stored in `location`
+    let location = unsafe { site.location.load(ACQUIRE).as_ref() }?;
+    let checks = site.checks.load(ACQUIRE);
+    let intervals = checks.saturating_sub(1);
+    let mean_interval_checks = site
+        .last_seq
+        .load(ACQUIRE)
+        .wrapping_sub(site.first_seq.load(ACQUIRE))
+        .checked_div(intervals)
+        .unwrap_or(0);
+    let mean_interval_us = match now_ms() {
+        Some(_) if intervals > 0 => {
+            let elapsed_ms = site
+                .last_ms
+                .load(ACQUIRE)
+                .saturating_sub(site.first_ms.load(ACQUIRE));
+            Some(elapsed_ms * 1000 / u64::from(intervals))
+        }
+        _ => None,
+    };
+
+    let (min_us, max_us) = (TARGET_MIN_US.load(ACQUIRE), TARGET_MAX_US.load(ACQUIRE));
+    let (rate, recommended_stride) = match mean_interval_us {
+        None => (CheckRate::Unknown, None),
+        Some(us) if us < u64::from(min_us) => {
+            // A clock too coarse to see any time pass gives a mean of 0
+            let stride = u64::from(min_us).div_ceil(us.max(1));
+            (
+                CheckRate::TooOften,
+                Some(u32::try_from(stride).unwrap_or(u32::MAX)),
+            )
+        }
+        Some(us) if us > u64::from(max_us) => (CheckRate::TooRarely, None),
+        Some(_) => (CheckRate::Ok, None),
+    };
+    Some(CheckSiteAdvice {
+        location,
+        checks,
+        mean_interval_checks,
+        mean_interval_us,
+        rate,
+        recommended_stride,
+    })
+}
+
+/// Returns up to `N` recorded call sites, most recently checked first.
+#[cfg(feature = "std")]
+pub(crate) fn last_check_sites<const N: usize>() -> [Option<&'static Location<'static>>; N] {
+    let seq = SEQ.load(ACQUIRE);
+    let mut last = [None; N];
+    let mut ages = [u32::MAX; N];
+    for site in &SITES {
+        // SAFETY: Only `&'static Location`s are stored in `location`
+        let Some(location) = (unsafe { site.location.load(ACQUIRE).as_ref() }) else {
+            break;
+        };
+        let age = seq.wrapping_sub(site.last_seq.load(ACQUIRE));
+        // Insertion into the few slots kept, youngest first
+        if let Some(i) = ages.iter().position(|&other| age < other) {
+            last[i..].rotate_right(1);
+            ages[i..].rotate_right(1);
+            last[i] = Some(location);
+            ages[i] = age;
+        }
+    }
+    last
+}
+
+/// Records a check made at `location`.
+pub(crate) fn record(location: &'static Location<'static>) {
+    let seq = SEQ.fetch_add(1, ACQ_REL);
+    let ptr = location as *const Location<'static> as *mut Location<'static>;
+    let now = now_ms().unwrap_or(0);
+    for site in &SITES {
+        let current = site.location.load(ACQUIRE);
+        let claimed = current == ptr
+            || (current.is_null()
+                && match site
+                    .location
+                    .compare_exchange(null_mut(), ptr, ACQ_REL, ACQUIRE)
+                {
+                    Ok(_) => {
+                        site.first_seq.store(seq, RELEASE);
+                        site.first_ms.store(now, RELEASE);
+                        true
+                    }
+                    Err(other) => other == ptr,
+                });
+        if claimed {
+            site.checks.fetch_add(1, ACQ_REL);
+            site.last_seq.store(seq, RELEASE);
+            site.last_ms.store(now, RELEASE);
+            return;
+        }
+    }
+}
+
+/// The target interval, saved by `ScopedSignalState`. The recorded sites
+/// are diagnostics and are left alone.
+#[cfg(feature = "testing")]
+pub(crate) struct Saved {
+    min_us: u32,
+    max_us: u32,
+}
+
+#[cfg(feature = "testing")]
+impl Default for Saved {
+    fn default() -> Saved {
+        Saved {
+            min_us: DEFAULT_CHECK_TARGET_US.0,
+            max_us: DEFAULT_CHECK_TARGET_US.1,
+        }
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        min_us: TARGET_MIN_US.load(ACQUIRE),
+        max_us: TARGET_MAX_US.load(ACQUIRE),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    set_check_target_us(saved.min_us, saved.max_us);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_clock, clear_signal, set_clock, try_check_signal};
+
+    static NOW: AtomicU64 = AtomicU64::new(0);
+
+    fn fake_clock() -> u64 {
+        NOW.load(ACQUIRE)
+    }
+
+    #[test]
+    fn test_check_advice() {
+        let _guard = lock();
+        clear_signal();
+        reset_check_advice();
+        let previous = set_clock(fake_clock);
+        set_check_advisor(true);
+
+        // One site checks every iteration, 10us apart; the other every
+        // 100th iteration, 1ms apart
+        for i in 0..1000u64 {
+            if i % 100 == 0 {
+                NOW.store(i / 100, RELEASE);
+            }
+            assert!(try_check_signal().is_ok());
+            if i % 100 == 99 {
+                assert!(try_check_signal().is_ok());
+            }
+        }
+        set_check_advisor(false);
+
+        let advice: [CheckSiteAdvice; 2] = {
+            let mut sites = check_advice();
+            [sites.next().unwrap(), sites.next().unwrap()]
+        };
+        assert_eq!(check_advice().count(), 2);
+        assert_eq!(advice[0].checks, 1000);
+        assert_eq!(advice[0].rate, CheckRate::TooOften);
+        assert_eq!(advice[0].recommended_stride, Some(12));
+        assert_eq!(advice[1].checks, 10);
+        assert_eq!(advice[1].mean_interval_checks, 101);
+        assert_eq!(advice[1].mean_interval_us, Some(1000));
+        assert_eq!(advice[1].rate, CheckRate::Ok);
+
+        set_check_target_us(2000, 5000);
+        assert_eq!(advise(&SITES[1]).unwrap().recommended_stride, Some(2));
+        set_check_target_us(DEFAULT_CHECK_TARGET_US.0, DEFAULT_CHECK_TARGET_US.1);
+        reset_check_advice();
+        match previous {
+            Some(clock) => set_clock(clock),
+            None => clear_clock(),
+        };
+    }
+}
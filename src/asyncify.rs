@@ -0,0 +1,71 @@
+//! Unwinding to the host with Binaryen's Asyncify.
+//!
+//! A propagated signal normally panics out of `check_signal`, which only
+//! returns control to the host once every frame has unwound, and not at all
+//! with `panic=abort`. With the `asyncify` feature, and the module processed
+//! by `wasm-opt --asyncify`, `check_signal` first calls the
+//! `wasm_signal.suspend` import instead. The host helper in
+//! `js/asyncify.js` starts an Asyncify unwind there, so the export the host
+//! called returns immediately, with the whole guest stack saved in
+//! [`ASYNCIFY_STACK_SIZE`] bytes of guest memory.
+//!
+//! The host may then rewind into the saved stack. It chooses whether the
+//! check returns normally, as if the signal had been dismissed, or goes on to
+//! panic, so that destructors and shutdown hooks run. It may also never
+//! rewind, and discard the instance.
+
+use core::cell::UnsafeCell;
+
+use crate::Signal;
+
+/// Bytes reserved for the stack Asyncify saves when unwinding. Deep or
+/// local-heavy call stacks that do not fit trap during the unwind.
+pub const ASYNCIFY_STACK_SIZE: usize = 64 * 1024;
+
+/// An Asyncify data buffer: the current and end addresses of the saved
+/// stack, followed by the stack itself.
+#[repr(C)]
+struct AsyncifyData {
+    current: u32,
+    end: u32,
+    stack: [u8; ASYNCIFY_STACK_SIZE],
+}
+
+struct Buffer(UnsafeCell<AsyncifyData>);
+
+// SAFETY: Only the thread suspending uses the buffer, and only one suspend
+// can be in progress per instance
+unsafe impl Sync for Buffer {}
+
+static DATA: Buffer = Buffer(UnsafeCell::new(AsyncifyData {
+    current: 0,
+    end: 0,
+    stack: [0; ASYNCIFY_STACK_SIZE],
+}));
+
+#[link(wasm_import_module = "wasm_signal")]
+extern "C" {
+    /// Unwinds to the host, passing the signal code and the data buffer.
+    /// Returns `1` once the host rewinds to resume execution, or `0` if it
+    /// rewinds to let the signal propagate.
+    #[link_name = "suspend"]
+    fn host_suspend(code: u32, data: *mut AsyncifyData) -> u32;
+}
+
+/// Suspends the guest for a propagated `signal`.
+///
+/// # Returns
+///
+/// Returns `true` if the host resumed execution, so the check should return
+/// normally.
+pub(crate) fn suspend(signal: Signal) -> bool {
+    let data = DATA.0.get();
+    // SAFETY: No unwind is in progress, so nothing else uses the buffer. When
+    // rewinding, Asyncify skips this write and resumes at the call below.
+    unsafe {
+        let stack = core::ptr::addr_of_mut!((*data).stack) as usize;
+        (*data).current = stack as u32;
+        (*data).end = (stack + ASYNCIFY_STACK_SIZE) as u32;
+        host_suspend(signal.code(), data) != 0
+    }
+}
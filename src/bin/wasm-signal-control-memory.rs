@@ -0,0 +1,327 @@
+//! Moves the signal block of a compiled module into a control memory.
+//!
+//! ```text
+//! wasm-signal-control-memory <input.wasm> -o <output.wasm>
+//! ```
+//!
+//! For modules built with the `control-memory` feature, whose checks reach
+//! the signal block only through the exported `__wasm_signal_control_load`
+//! and `__wasm_signal_control_store` functions. The tool adds a one-page
+//! memory holding the block, exported as `WASM_SIGNAL_CONTROL_MEMORY`, and
+//! rewrites both functions to access it, so the module needs the
+//! multi-memory proposal. The metadata block and the `wasm_signal_abi`
+//! section record the memory's index with `ABI_CONTROL_MEMORY`, and the
+//! signal word's offset in it replaces its address.
+//!
+//! `WASM_SIGNAL_ADDR` is no longer exported, as it points at the block's
+//! old place: hosts that were not updated fail to attach instead of writing
+//! a word no check reads.
+//!
+//! Run the tool after `wasm-bindgen` and before `wasm-opt`, which would
+//! otherwise inline the functions before they are rewritten, and pass
+//! `--enable-multimemory` to `wasm-opt`.
+
+use std::process::ExitCode;
+
+use walrus::ir::{LoadKind, MemArg, StoreKind, Value};
+use walrus::{
+    ConstExpr, DataKind, ExportItem, FunctionBuilder, FunctionId, FunctionKind, Module,
+    RawCustomSection, ValType,
+};
+use wasm_signal_handler::{
+    ABI_CONTROL_MEMORY, ABI_MEMORY_INDEX_SHIFT, ABI_SECTION, ABI_VERSION, BLOCK_MAGIC,
+    METADATA_MAGIC,
+};
+
+const LOAD_EXPORT: &str = "__wasm_signal_control_load";
+const STORE_EXPORT: &str = "__wasm_signal_control_store";
+const MEMORY_EXPORT: &str = "WASM_SIGNAL_CONTROL_MEMORY";
+const SIGNAL_EXPORT: &str = "WASM_SIGNAL_ADDR";
+
+/// The offset of the signal word in the block, after the magic value.
+const SIGNAL_OFFSET: u32 = 4;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let (Some(input), Some("-o" | "--output"), Some(output), None) = (
+        args.next(),
+        args.next().as_deref(),
+        args.next(),
+        args.next(),
+    ) else {
+        eprintln!("usage: wasm-signal-control-memory <input.wasm> -o <output.wasm>");
+        return ExitCode::from(2);
+    };
+    let mut module = match Module::from_file(&input) {
+        Ok(module) => module,
+        Err(err) => {
+            eprintln!("error: cannot load {input}: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    let index = match place(&mut module) {
+        Ok(index) => index,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = module.emit_wasm_file(&output) {
+        eprintln!("error: cannot write {output}: {err}");
+        return ExitCode::FAILURE;
+    }
+    println!("signal block moved to memory {index} (`{MEMORY_EXPORT}`)");
+    ExitCode::SUCCESS
+}
+
+/// Moves the signal block into a new memory.
+///
+/// # Returns
+///
+/// The index of the memory.
+fn place(module: &mut Module) -> Result<u32, String> {
+    let load = exported_func(module, LOAD_EXPORT, &[ValType::I32], &[ValType::I32])?;
+    let store = exported_func(module, STORE_EXPORT, &[ValType::I32, ValType::I32], &[])?;
+    if module
+        .exports
+        .iter()
+        .any(|export| export.name == MEMORY_EXPORT)
+    {
+        return Err(format!("the module already exports `{MEMORY_EXPORT}`"));
+    }
+
+    // Local memories follow the imported ones, in order
+    let index = module.memories.iter().count() as u32;
+    let flags = ABI_CONTROL_MEMORY | index << ABI_MEMORY_INDEX_SHIFT;
+    patch_metadata(module, flags)?;
+    if let Some(mut section) = module.customs.remove_raw(ABI_SECTION) {
+        if section.data.len() != 8 {
+            return Err(String::from(
+                "wasm-signal-handler is linked more than once; each copy has its own signal block",
+            ));
+        }
+        let previous = u32::from_le_bytes(section.data[4..8].try_into().unwrap());
+        section.data[4..8].copy_from_slice(&(previous | flags).to_le_bytes());
+        module.customs.add(RawCustomSection {
+            name: section.name,
+            data: section.data,
+        });
+    }
+
+    let memory = module.memories.add_local(false, false, 1, Some(1), None);
+    module.exports.add(MEMORY_EXPORT, memory);
+    let block = [BLOCK_MAGIC, 0, BLOCK_MAGIC]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    module.data.add(
+        DataKind::Active {
+            memory,
+            offset: ConstExpr::Value(Value::I32(0)),
+        },
+        block,
+    );
+
+    let arg = MemArg {
+        align: 4,
+        offset: 0,
+    };
+    let offset = module.locals.add(ValType::I32);
+    let mut builder = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[ValType::I32]);
+    builder
+        .func_body()
+        .local_get(offset)
+        .load(memory, LoadKind::I32 { atomic: false }, arg);
+    module.funcs.get_mut(load).kind = FunctionKind::Local(builder.local_func(vec![offset]));
+
+    let offset = module.locals.add(ValType::I32);
+    let value = module.locals.add(ValType::I32);
+    let mut builder = FunctionBuilder::new(&mut module.types, &[ValType::I32, ValType::I32], &[]);
+    builder
+        .func_body()
+        .local_get(offset)
+        .local_get(value)
+        .store(memory, StoreKind::I32 { atomic: false }, arg);
+    module.funcs.get_mut(store).kind = FunctionKind::Local(builder.local_func(vec![offset, value]));
+
+    let signal = module.exports.iter().find(|e| e.name == SIGNAL_EXPORT);
+    if let Some(id) = signal.map(|export| export.id()) {
+        module.exports.delete(id);
+    }
+    Ok(index)
+}
+
+/// Finds the local function exported as `name`, checking its type.
+fn exported_func(
+    module: &Module,
+    name: &str,
+    params: &[ValType],
+    results: &[ValType],
+) -> Result<FunctionId, String> {
+    let func = module
+        .exports
+        .iter()
+        .find_map(|export| match export.item {
+            ExportItem::Function(func) if export.name == name => Some(func),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            format!(
+                "`{name}` is not exported; is the module built with the `control-memory` feature?"
+            )
+        })?;
+    let func = module.funcs.get(func);
+    if !matches!(func.kind, FunctionKind::Local(_))
+        || module.types.params_results(func.ty()) != (params, results)
+    {
+        return Err(format!("`{name}` is not the function the crate exports"));
+    }
+    Ok(func.id())
+}
+
+/// Records `flags` and the signal word's offset in the metadata block.
+///
+/// The block is found in the data segments by its magic value and version,
+/// and must be found exactly once.
+fn patch_metadata(module: &mut Module, flags: u32) -> Result<(), String> {
+    let mut header = METADATA_MAGIC.to_le_bytes().to_vec();
+    header.extend(ABI_VERSION.to_le_bytes());
+    let mut found = Vec::new();
+    for data in module.data.iter() {
+        let mut start = 0;
+        while let Some(at) = data.value[start..]
+            .windows(header.len())
+            .position(|window| window == header)
+        {
+            found.push((data.id(), start + at));
+            start += at + 4;
+        }
+    }
+    let [(id, at)] = found[..] else {
+        return Err(format!(
+            "found {} metadata blocks for ABI version {ABI_VERSION}, expected one",
+            found.len()
+        ));
+    };
+    let block = module
+        .data
+        .get_mut(id)
+        .value
+        .get_mut(at + 8..at + 16)
+        .ok_or("the metadata block is truncated")?;
+    let previous = u32::from_le_bytes(block[..4].try_into().unwrap());
+    block[..4].copy_from_slice(&(previous | flags).to_le_bytes());
+    block[4..].copy_from_slice(&SIGNAL_OFFSET.to_le_bytes());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use walrus::ir::{Instr, Load};
+    use walrus::{ModuleConfig, ValType};
+
+    /// Adds a function exported as `name`, loading from or storing to the
+    /// first memory.
+    fn accessor(module: &mut Module, name: &str, params: &[ValType], results: &[ValType]) {
+        let memory = module.memories.iter().next().unwrap().id();
+        let args: Vec<_> = params.iter().map(|&ty| module.locals.add(ty)).collect();
+        let mut builder = FunctionBuilder::new(&mut module.types, params, results);
+        let mut body = builder.func_body();
+        for &arg in &args {
+            body.local_get(arg);
+        }
+        let arg = MemArg {
+            align: 4,
+            offset: 0x100,
+        };
+        if results.is_empty() {
+            body.store(memory, StoreKind::I32 { atomic: false }, arg);
+        } else {
+            body.load(memory, LoadKind::I32 { atomic: false }, arg);
+        }
+        let func = builder.finish(args, &mut module.funcs);
+        module.exports.add(name, func);
+    }
+
+    /// Builds a module with the block at `0x100` and the metadata at
+    /// `0x110`.
+    fn module() -> Module {
+        let mut module = Module::with_config(ModuleConfig::new());
+        let memory = module.memories.add_local(false, false, 1, None, None);
+        module.exports.add("memory", memory);
+        let words = [BLOCK_MAGIC, 0, BLOCK_MAGIC, 0];
+        let metadata = [METADATA_MAGIC, ABI_VERSION, 0, 0x104, 0];
+        let data = words
+            .iter()
+            .chain(&metadata)
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        module.data.add(
+            DataKind::Active {
+                memory,
+                offset: ConstExpr::Value(Value::I32(0x100)),
+            },
+            data,
+        );
+        let global = module.globals.add_local(
+            ValType::I32,
+            false,
+            false,
+            ConstExpr::Value(Value::I32(0x200)),
+        );
+        module.exports.add(SIGNAL_EXPORT, global);
+        accessor(&mut module, LOAD_EXPORT, &[ValType::I32], &[ValType::I32]);
+        accessor(
+            &mut module,
+            STORE_EXPORT,
+            &[ValType::I32, ValType::I32],
+            &[],
+        );
+        let mut abi = ABI_VERSION.to_le_bytes().to_vec();
+        abi.extend([0; 4]);
+        module.customs.add(RawCustomSection {
+            name: String::from(ABI_SECTION),
+            data: abi,
+        });
+        module
+    }
+
+    #[test]
+    fn test_place_control_memory() {
+        let mut module = module();
+        assert_eq!(place(&mut module), Ok(1));
+        let flags = ABI_CONTROL_MEMORY | 1 << ABI_MEMORY_INDEX_SHIFT;
+
+        let exported = |name: &str| module.exports.iter().find(|e| e.name == name);
+        let Some(ExportItem::Memory(memory)) = exported(MEMORY_EXPORT).map(|e| e.item) else {
+            panic!("`{MEMORY_EXPORT}` is not exported");
+        };
+        assert!(exported(SIGNAL_EXPORT).is_none());
+
+        let metadata = &module.data.iter().next().unwrap().value[0x18..0x20];
+        assert_eq!(metadata[..4], flags.to_le_bytes());
+        assert_eq!(metadata[4..], SIGNAL_OFFSET.to_le_bytes());
+        let section = module.customs.remove_raw(ABI_SECTION).unwrap();
+        assert_eq!(section.data[4..], flags.to_le_bytes());
+
+        let Some(ExportItem::Function(load)) = exported(LOAD_EXPORT).map(|e| e.item) else {
+            unreachable!()
+        };
+        let FunctionKind::Local(local) = &module.funcs.get(load).kind else {
+            unreachable!()
+        };
+        assert!(matches!(
+            local.block(local.entry_block()).instrs[1].0,
+            Instr::Load(Load { memory: m, arg, .. }) if m == memory && arg.offset == 0
+        ));
+
+        // The output is valid with multi-memory
+        let bytes = module.emit_wasm();
+        wasmparser::validate(&bytes).unwrap();
+
+        // Once moved, the accessors no longer match
+        let mut module = Module::from_buffer(&bytes).unwrap();
+        assert!(place(&mut module).unwrap_err().contains(MEMORY_EXPORT));
+    }
+}
@@ -0,0 +1,469 @@
+//! Generates the C header of the `capi` feature.
+//!
+//! ```text
+//! wasm-signal-header [--check] <header.h>
+//! ```
+//!
+//! Writes the declarations of the `extern "C"` functions, the slot export
+//! names, the constants of the protocol and the slot layout struct, all
+//! taken from the crate itself, so the header cannot drift from the
+//! library it is built with. With `--check`, compares the file instead and
+//! exits with status `1` if it is stale. The checked-in copy is
+//! `include/wasm_signal_handler.h`.
+
+use std::fmt::Write;
+use std::mem::offset_of;
+use std::process::ExitCode;
+
+use wasm_signal_handler::{
+    LifecycleState, Severity, Signal, SignalLayout, ABI_CONTROL_MEMORY, ABI_GLOBAL_STORAGE,
+    ABI_MEMORY_INDEX_SHIFT, ABI_VERSION, ATTACHED_ANCHOR, ATTACHED_EXPORT, BLOCK_MAGIC, CODE_MASK,
+    DISPOSITION_HANDLER, DISPOSITION_LATCHED, DISPOSITION_LATCH_MODE, DISPOSITION_PANICS,
+    DISPOSITION_THROWS, DISPOSITION_TRAPS, EVENT_CHECKPOINT_WRITTEN, EVENT_SIGNAL_ACKNOWLEDGED,
+    EVENT_SIGNAL_REJECTED, EVENT_STAGE_CHANGED, EVENT_USER_BASE, METADATA_MAGIC, SEVERITY_SHIFT,
+};
+
+/// A constant: its name without the `WASM_SIGNAL_` prefix, its value and
+/// its description.
+type Constant = (&'static str, u32, &'static str);
+
+/// The constants, by group.
+const CONSTANTS: &[(&str, &[Constant])] = &[
+    (
+        "ABI",
+        &[
+            (
+                "ABI_VERSION",
+                ABI_VERSION,
+                "The version of the host-shared layouts.",
+            ),
+            (
+                "ABI_GLOBAL_STORAGE",
+                ABI_GLOBAL_STORAGE,
+                "The signal word is a global.",
+            ),
+            (
+                "ABI_CONTROL_MEMORY",
+                ABI_CONTROL_MEMORY,
+                "The signal block is in a control memory.",
+            ),
+            (
+                "ABI_MEMORY_INDEX_SHIFT",
+                ABI_MEMORY_INDEX_SHIFT,
+                "The shift of the control memory's index in the flags.",
+            ),
+            (
+                "METADATA_MAGIC",
+                METADATA_MAGIC,
+                "\"WSIG\", first word of the metadata block.",
+            ),
+            (
+                "BLOCK_MAGIC",
+                BLOCK_MAGIC,
+                "\"WSBK\", the word before the signal word.",
+            ),
+            (
+                "ATTACHED_EXPORT",
+                ATTACHED_EXPORT,
+                "Attached through the export.",
+            ),
+            (
+                "ATTACHED_ANCHOR",
+                ATTACHED_ANCHOR,
+                "Attached through the anchor.",
+            ),
+        ],
+    ),
+    (
+        "Signal codes",
+        &[
+            (
+                "TERMINATE",
+                Signal::TERMINATE.code(),
+                "Requests termination.",
+            ),
+            (
+                "CANCEL",
+                Signal::CANCEL.code(),
+                "Requests cooperative cancellation.",
+            ),
+            (
+                "DEADLINE",
+                Signal::DEADLINE.code(),
+                "A deadline has passed.",
+            ),
+            (
+                "CHECKPOINT",
+                Signal::CHECKPOINT.code(),
+                "Requests a progress snapshot.",
+            ),
+            (
+                "COMMAND",
+                Signal::COMMAND.code(),
+                "A command is waiting in the mailbox.",
+            ),
+            (
+                "FATAL_BASE",
+                Signal::FATAL_BASE,
+                "The first code of the fatal range.",
+            ),
+        ],
+    ),
+    (
+        "Severity encoding",
+        &[
+            (
+                "SEVERITY_SHIFT",
+                SEVERITY_SHIFT,
+                "The bits the severity is shifted by.",
+            ),
+            ("CODE_MASK", CODE_MASK, "The bits holding the code."),
+            ("SEVERITY_INFO", Severity::Info as u32, "Never propagates."),
+            ("SEVERITY_WARN", Severity::Warn as u32, "Never propagates."),
+            (
+                "SEVERITY_CANCEL",
+                Severity::Cancel as u32,
+                "The handler may decline it.",
+            ),
+            (
+                "SEVERITY_KILL",
+                Severity::Kill as u32,
+                "The handler cannot swallow it.",
+            ),
+            (
+                "SEVERITY_FATAL",
+                Severity::Fatal as u32,
+                "Bypasses the handler.",
+            ),
+        ],
+    ),
+    (
+        "Disposition flags",
+        &[
+            (
+                "DISPOSITION_HANDLER",
+                DISPOSITION_HANDLER,
+                "A handler is registered.",
+            ),
+            (
+                "DISPOSITION_PANICS",
+                DISPOSITION_PANICS,
+                "Propagated signals panic.",
+            ),
+            (
+                "DISPOSITION_TRAPS",
+                DISPOSITION_TRAPS,
+                "Propagated signals trap.",
+            ),
+            (
+                "DISPOSITION_THROWS",
+                DISPOSITION_THROWS,
+                "Propagated signals throw.",
+            ),
+            (
+                "DISPOSITION_LATCH_MODE",
+                DISPOSITION_LATCH_MODE,
+                "Latched mode is on.",
+            ),
+            (
+                "DISPOSITION_LATCHED",
+                DISPOSITION_LATCHED,
+                "A signal is latched.",
+            ),
+        ],
+    ),
+    (
+        "Lifecycle states",
+        &[
+            (
+                "STATE_IDLE",
+                LifecycleState::Idle as u32,
+                "No request is running.",
+            ),
+            (
+                "STATE_RUNNING",
+                LifecycleState::Running as u32,
+                "A request is running.",
+            ),
+            (
+                "STATE_CANCELLING",
+                LifecycleState::Cancelling as u32,
+                "A signal propagated; the request is winding down.",
+            ),
+            (
+                "STATE_CLEANING_UP",
+                LifecycleState::CleaningUp as u32,
+                "The shutdown hooks are running.",
+            ),
+            (
+                "STATE_TERMINATED",
+                LifecycleState::Terminated as u32,
+                "The shutdown hooks have run.",
+            ),
+        ],
+    ),
+    (
+        "Event kinds",
+        &[
+            (
+                "EVENT_SIGNAL_ACKNOWLEDGED",
+                EVENT_SIGNAL_ACKNOWLEDGED,
+                "A check observed a signal.",
+            ),
+            (
+                "EVENT_CHECKPOINT_WRITTEN",
+                EVENT_CHECKPOINT_WRITTEN,
+                "A checkpoint was written.",
+            ),
+            (
+                "EVENT_STAGE_CHANGED",
+                EVENT_STAGE_CHANGED,
+                "The stage changed.",
+            ),
+            (
+                "EVENT_SIGNAL_REJECTED",
+                EVENT_SIGNAL_REJECTED,
+                "A code failed validation.",
+            ),
+            (
+                "EVENT_USER_BASE",
+                EVENT_USER_BASE,
+                "The first application event kind.",
+            ),
+        ],
+    ),
+];
+
+/// The exported slots: the name of the define, and of the export.
+const EXPORTS: &[(&str, &str)] = &[
+    ("SIGNAL", "WASM_SIGNAL_ADDR"),
+    ("ACK", "WASM_SIGNAL_ACK_ADDR"),
+    ("HEARTBEAT", "WASM_SIGNAL_HEARTBEAT_ADDR"),
+    ("MAILBOX", "WASM_SIGNAL_MAILBOX_ADDR"),
+    ("EVENTS", "WASM_SIGNAL_EVENTS_ADDR"),
+    ("PROGRESS", "WASM_SIGNAL_PROGRESS_ADDR"),
+    ("SOURCE", "WASM_SIGNAL_SOURCE_ADDR"),
+    ("POLICY", "WASM_SIGNAL_POLICY_ADDR"),
+    ("QUEUE", "WASM_SIGNAL_QUEUE_ADDR"),
+    ("METADATA", "WASM_SIGNAL_METADATA_ADDR"),
+    ("DISPOSITION", "WASM_SIGNAL_DISPOSITION_ADDR"),
+    ("EPOCH", "WASM_SIGNAL_EPOCH_ADDR"),
+    ("STATE", "WASM_SIGNAL_STATE_ADDR"),
+];
+
+/// The fields of [`SignalLayout`], with their offsets and descriptions.
+const FIELDS: &[(&str, usize, &str)] = &[
+    (
+        "signal",
+        offset_of!(SignalLayout, signal),
+        "The signal word, or 0 with global storage.",
+    ),
+    ("ack", offset_of!(SignalLayout, ack), "The ack word."),
+    (
+        "heartbeat",
+        offset_of!(SignalLayout, heartbeat),
+        "The heartbeat word.",
+    ),
+    (
+        "mailbox",
+        offset_of!(SignalLayout, mailbox),
+        "The command mailbox.",
+    ),
+    (
+        "events",
+        offset_of!(SignalLayout, events),
+        "The event ring.",
+    ),
+    (
+        "progress",
+        offset_of!(SignalLayout, progress),
+        "The progress slot.",
+    ),
+    (
+        "source",
+        offset_of!(SignalLayout, source),
+        "The source word.",
+    ),
+    (
+        "policy",
+        offset_of!(SignalLayout, policy),
+        "The overwrite policy word.",
+    ),
+    (
+        "queue",
+        offset_of!(SignalLayout, queue),
+        "The signal queue.",
+    ),
+    (
+        "metadata",
+        offset_of!(SignalLayout, metadata),
+        "The export metadata block.",
+    ),
+    (
+        "disposition",
+        offset_of!(SignalLayout, disposition),
+        "The disposition word.",
+    ),
+    ("epoch", offset_of!(SignalLayout, epoch), "The epoch word."),
+    (
+        "state",
+        offset_of!(SignalLayout, state),
+        "The lifecycle state word.",
+    ),
+];
+
+/// The `extern "C"` functions: the description and the declaration.
+const FUNCTIONS: &[(&str, &str)] = &[
+    (
+        "Checks for a pending signal. Returns the code of the signal to\n * propagate, or 0 to continue.",
+        "uint32_t wasm_signal_check(void);",
+    ),
+    (
+        "Returns the code of the pending signal without consuming it, or 0.",
+        "uint32_t wasm_signal_peek(void);",
+    ),
+    (
+        "Raises a signal, dispatching it right away with `immediate`. Returns\n * the code of the signal to propagate, or 0 to continue.",
+        "uint32_t wasm_signal_raise(uint32_t code, bool immediate);",
+    ),
+    (
+        "Masks signals until a matching wasm_signal_unmask(). Calls nest.",
+        "void wasm_signal_mask(void);",
+    ),
+    (
+        "Releases one wasm_signal_mask(), delivering any deferred signal once\n * the outermost one is released. Returns the code of the signal to\n * propagate, or 0 to continue.",
+        "uint32_t wasm_signal_unmask(void);",
+    ),
+    (
+        "Returns true if signals are currently masked.",
+        "bool wasm_signal_masked(void);",
+    ),
+    (
+        "Returns the disposition word, a set of WASM_SIGNAL_DISPOSITION_* flags.",
+        "uint32_t wasm_signal_disposition(void);",
+    ),
+    (
+        "Returns true if a signal handler, global or namespaced, is registered.",
+        "bool wasm_signal_has_handler(void);",
+    ),
+    (
+        "Returns the lifecycle state, one of the WASM_SIGNAL_STATE_* values.",
+        "uint32_t wasm_signal_state(void);",
+    ),
+    (
+        "Returns the addresses of every host-shared slot.",
+        "wasm_signal_layout_t wasm_signal_layout(void);",
+    ),
+];
+
+fn main() -> ExitCode {
+    let mut args = std::env::args_os().skip(1).peekable();
+    let check = args.next_if(|arg| arg == "--check").is_some();
+    let (Some(path), None) = (args.next(), args.next()) else {
+        eprintln!("usage: wasm-signal-header [--check] <header.h>");
+        return ExitCode::from(2);
+    };
+    let header = header();
+    if check {
+        return match std::fs::read_to_string(&path) {
+            Ok(current) if current == header => ExitCode::SUCCESS,
+            Ok(_) => {
+                eprintln!("{} is stale, regenerate it", path.to_string_lossy());
+                ExitCode::FAILURE
+            }
+            Err(err) => {
+                eprintln!("error: cannot read {}: {err}", path.to_string_lossy());
+                ExitCode::from(2)
+            }
+        };
+    }
+    if let Err(err) = std::fs::write(&path, header) {
+        eprintln!("error: cannot write {}: {err}", path.to_string_lossy());
+        return ExitCode::from(2);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Returns the header.
+fn header() -> String {
+    let mut out = String::new();
+    out.push_str(
+        "/* Generated by wasm-signal-header. Do not edit. */\n\
+         \n\
+         #ifndef WASM_SIGNAL_HANDLER_H\n\
+         #define WASM_SIGNAL_HANDLER_H\n\
+         \n\
+         #include <stdbool.h>\n\
+         #include <stdint.h>\n\
+         \n\
+         #ifdef __cplusplus\n\
+         extern \"C\" {\n\
+         #endif\n",
+    );
+
+    for (group, constants) in CONSTANTS {
+        writeln!(out, "\n/* {group} */\n").unwrap();
+        for (name, value, doc) in *constants {
+            writeln!(out, "/* {doc} */").unwrap();
+            if *value < 0x100 {
+                writeln!(out, "#define WASM_SIGNAL_{name} {value}u").unwrap();
+            } else {
+                writeln!(out, "#define WASM_SIGNAL_{name} {value:#010x}u").unwrap();
+            }
+        }
+    }
+
+    out.push_str(
+        "\n/* Export names of the host-shared slots. Each export is a global\n \
+         * holding the address of its slot in linear memory. */\n\n",
+    );
+    for (name, export) in EXPORTS {
+        writeln!(out, "#define WASM_SIGNAL_EXPORT_{name} \"{export}\"").unwrap();
+    }
+
+    out.push_str(
+        "\n/* The addresses of the host-shared slots in linear memory, one word\n \
+         * per slot. Words are 32 bits wide in wasm32 modules. */\n\
+         typedef struct wasm_signal_layout_t {\n",
+    );
+    for (name, _, doc) in FIELDS {
+        writeln!(out, "  /* {doc} */\n  uintptr_t {name};").unwrap();
+    }
+    out.push_str("} wasm_signal_layout_t;\n");
+
+    for (doc, declaration) in FUNCTIONS {
+        writeln!(out, "\n/* {doc} */\n{declaration}").unwrap();
+    }
+
+    out.push_str(
+        "\n#ifdef __cplusplus\n\
+         }\n\
+         #endif\n\
+         \n\
+         #endif /* WASM_SIGNAL_HANDLER_H */\n",
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::size_of;
+
+    #[test]
+    fn test_header_is_current() {
+        // Every field of the layout is listed, in order
+        assert_eq!(FIELDS.len() * size_of::<usize>(), size_of::<SignalLayout>());
+        for (index, (_, offset, _)) in FIELDS.iter().enumerate() {
+            assert_eq!(*offset, index * size_of::<usize>());
+        }
+        assert_eq!(EXPORTS.len(), FIELDS.len());
+
+        let current = include_str!("../../include/wasm_signal_handler.h");
+        assert!(
+            current == header(),
+            "include/wasm_signal_handler.h is stale, regenerate it with \
+             `cargo run --features capi --bin wasm-signal-header include/wasm_signal_handler.h`"
+        );
+    }
+}
@@ -0,0 +1,530 @@
+//! Checks that a compiled module is ready for host integration.
+//!
+//! ```text
+//! wasm-signal-inspect <module.wasm>
+//! ```
+//!
+//! Verifies that the exported slots exist, resolves the addresses they point
+//! at from the module's data segments, checks the ABI metadata section and
+//! prints a summary. Exits with status `1` if the host helpers would not
+//! work with the module, for example because `wasm-opt` or a bundler
+//! stripped an export. Run it on the module that is actually deployed.
+
+use std::fmt;
+use std::process::ExitCode;
+
+use wasm_signal_handler::{
+    EventRing, Mailbox, ProgressSlot, SignalQueue, ABI_CONTROL_MEMORY, ABI_GLOBAL_STORAGE,
+    ABI_MEMORY_INDEX_SHIFT, ABI_SECTION, ABI_VERSION,
+};
+use wasmparser::{
+    BinaryReaderError, ConstExpr, DataKind, ExternalKind, MemoryType, Operator, Parser, Payload,
+    TypeRef, ValType,
+};
+
+/// The exported slots, with the size of the value each one points at. The
+/// signal word is listed first.
+const SLOTS: &[(&str, usize)] = &[
+    ("WASM_SIGNAL_ADDR", 4),
+    ("WASM_SIGNAL_ACK_ADDR", 4),
+    ("WASM_SIGNAL_HEARTBEAT_ADDR", 4),
+    ("WASM_SIGNAL_SOURCE_ADDR", 4),
+    ("WASM_SIGNAL_POLICY_ADDR", 4),
+    ("WASM_SIGNAL_DISPOSITION_ADDR", 4),
+    ("WASM_SIGNAL_EPOCH_ADDR", 4),
+    ("WASM_SIGNAL_STATE_ADDR", 4),
+    ("WASM_SIGNAL_QUEUE_ADDR", size_of::<SignalQueue>()),
+    ("WASM_SIGNAL_PROGRESS_ADDR", size_of::<ProgressSlot>()),
+    ("WASM_SIGNAL_MAILBOX_ADDR", size_of::<Mailbox>()),
+    ("WASM_SIGNAL_EVENTS_ADDR", size_of::<EventRing>()),
+    // `ExportMetadata` holds a pointer, so its native size differs
+    ("WASM_SIGNAL_METADATA_ADDR", 20),
+];
+
+/// Exported functions that are only present with some features enabled.
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("__wasm_signal_wake_tasks", "futures-channel"),
+    ("__wasm_signal_dump_diagnostics", "diagnostics-json"),
+    ("__wasm_signal_control_load", "control-memory"),
+];
+
+fn main() -> ExitCode {
+    let mut args = std::env::args_os().skip(1);
+    let (Some(path), None) = (args.next(), args.next()) else {
+        eprintln!("usage: wasm-signal-inspect <module.wasm>");
+        return ExitCode::from(2);
+    };
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("error: cannot read {}: {err}", path.to_string_lossy());
+            return ExitCode::from(2);
+        }
+    };
+    let report = match inspect(&bytes) {
+        Ok(report) => report,
+        Err(err) => {
+            eprintln!("error: invalid module: {err}");
+            return ExitCode::from(2);
+        }
+    };
+    print!("{report}");
+    if report.problems.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// ============================================================================
+// Module Scan
+// ============================================================================
+
+/// The parts of a module the report is built from.
+#[derive(Default)]
+struct Module<'a> {
+    /// Types of the globals, imported ones first.
+    globals: Vec<(ValType, bool)>,
+    /// `i32.const` initializers of the globals, `None` for imported globals
+    /// and other initializers.
+    inits: Vec<Option<u32>>,
+    /// Exports by name, kind and index.
+    exports: Vec<(&'a str, ExternalKind, u32)>,
+    /// The first memory, and whether it is imported.
+    memory: Option<(MemoryType, bool)>,
+    /// Active segments of the first memory with a constant offset.
+    segments: Vec<(u32, &'a [u8])>,
+    /// Whether any segment is passive, or has a non-constant offset.
+    dynamic_data: bool,
+    /// Contents of the ABI sections, concatenated.
+    abi: Option<Vec<u8>>,
+}
+
+fn scan(bytes: &[u8]) -> Result<Module<'_>, BinaryReaderError> {
+    let mut module = Module::default();
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for import in reader.into_imports() {
+                    match import?.ty {
+                        TypeRef::Global(ty) => {
+                            module.globals.push((ty.content_type, ty.mutable));
+                            module.inits.push(None);
+                        }
+                        TypeRef::Memory(ty) if module.memory.is_none() => {
+                            module.memory = Some((ty, true));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for memory in reader {
+                    let memory = memory?;
+                    module.memory.get_or_insert((memory, false));
+                }
+            }
+            Payload::GlobalSection(reader) => {
+                for global in reader {
+                    let global = global?;
+                    module
+                        .globals
+                        .push((global.ty.content_type, global.ty.mutable));
+                    module.inits.push(const_i32(&global.init_expr));
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    module
+                        .exports
+                        .push((export.name, export.kind, export.index));
+                }
+            }
+            Payload::DataSection(reader) => {
+                for data in reader {
+                    let data = data?;
+                    match data.kind {
+                        DataKind::Active {
+                            memory_index: 0,
+                            offset_expr,
+                        } => match const_i32(&offset_expr) {
+                            Some(offset) => module.segments.push((offset, data.data)),
+                            None => module.dynamic_data = true,
+                        },
+                        DataKind::Active { .. } => {}
+                        DataKind::Passive => module.dynamic_data = true,
+                    }
+                }
+            }
+            Payload::CustomSection(reader) if reader.name() == ABI_SECTION => {
+                module
+                    .abi
+                    .get_or_insert_with(Vec::new)
+                    .extend_from_slice(reader.data());
+            }
+            _ => {}
+        }
+    }
+    Ok(module)
+}
+
+/// Evaluates a constant expression that is a single `i32.const`.
+fn const_i32(expr: &ConstExpr<'_>) -> Option<u32> {
+    let mut ops = expr.get_operators_reader();
+    match (ops.read().ok()?, ops.read().ok()?) {
+        (Operator::I32Const { value }, Operator::End) => Some(value as u32),
+        _ => None,
+    }
+}
+
+impl Module<'_> {
+    fn export(&self, name: &str) -> Option<(ExternalKind, u32)> {
+        self.exports
+            .iter()
+            .find(|&&(n, _, _)| n == name)
+            .map(|&(_, kind, index)| (kind, index))
+    }
+
+    /// Reads the little-endian word at `addr` from the data segments.
+    fn read_u32(&self, addr: u32) -> Option<u32> {
+        self.segments.iter().find_map(|&(offset, data)| {
+            let start = addr.checked_sub(offset)? as usize;
+            let word = data.get(start..start.checked_add(4)?)?;
+            Some(u32::from_le_bytes(word.try_into().ok()?))
+        })
+    }
+
+    fn resolve(&self, name: &str) -> Slot {
+        let Some((kind, index)) = self.export(name) else {
+            return Slot::Missing;
+        };
+        if kind != ExternalKind::Global {
+            return Slot::Invalid("not a global");
+        }
+        let Some(&Some(pointer)) = self.inits.get(index as usize) else {
+            return Slot::Invalid("not a constant global");
+        };
+        if pointer % 4 != 0 {
+            return Slot::Invalid("misaligned pointer");
+        }
+        match self.read_u32(pointer) {
+            Some(addr) if addr % 4 != 0 => Slot::Invalid("misaligned slot"),
+            Some(addr) => Slot::Resolved { pointer, addr },
+            None if self.dynamic_data => Slot::Unresolved { pointer },
+            None => Slot::Invalid("pointer outside the data segments"),
+        }
+    }
+}
+
+// ============================================================================
+// Report
+// ============================================================================
+
+/// Where an exported slot lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Missing,
+    Invalid(&'static str),
+    /// The pointer is in a segment only copied at instantiation, such as
+    /// the passive segments of modules with shared memory.
+    Unresolved {
+        pointer: u32,
+    },
+    Resolved {
+        pointer: u32,
+        addr: u32,
+    },
+}
+
+/// The ABI metadata found in the module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Abi {
+    Missing,
+    Malformed,
+    Found {
+        version: u32,
+        flags: u32,
+        copies: usize,
+    },
+}
+
+struct Report {
+    abi: Abi,
+    global_storage: bool,
+    /// The index of the control memory holding the signal block, if any.
+    control_memory: Option<u32>,
+    /// The memory's export name, its type, and whether it is imported.
+    memory: Option<(Option<String>, MemoryType, bool)>,
+    slots: Vec<(&'static str, usize, Slot)>,
+    functions: Vec<(&'static str, &'static str, bool)>,
+    problems: Vec<String>,
+    warnings: Vec<String>,
+}
+
+fn inspect(bytes: &[u8]) -> Result<Report, BinaryReaderError> {
+    let module = scan(bytes)?;
+    let mut problems = Vec::new();
+    let mut warnings = Vec::new();
+
+    let abi = match &module.abi {
+        None => Abi::Missing,
+        Some(data) if data.is_empty() || data.len() % 8 != 0 => Abi::Malformed,
+        Some(data) => Abi::Found {
+            version: u32::from_le_bytes(data[0..4].try_into().unwrap()),
+            flags: u32::from_le_bytes(data[4..8].try_into().unwrap()),
+            copies: data.len() / 8,
+        },
+    };
+    match abi {
+        Abi::Missing => warnings.push(format!(
+            "no `{ABI_SECTION}` section; custom sections may have been stripped"
+        )),
+        Abi::Malformed => problems.push(format!("malformed `{ABI_SECTION}` section")),
+        Abi::Found { version, .. } if version != ABI_VERSION => problems.push(format!(
+            "ABI version {version} does not match this tool's version {ABI_VERSION}"
+        )),
+        Abi::Found { copies, .. } if copies > 1 => problems.push(format!(
+            "wasm-signal-handler is linked {copies} times; each copy has its own signal word"
+        )),
+        Abi::Found { .. } => {}
+    }
+
+    let global_storage = match abi {
+        Abi::Found { flags, .. } => flags & ABI_GLOBAL_STORAGE != 0,
+        _ => module.export("WASM_SIGNAL_GLOBAL").is_some(),
+    };
+    if global_storage {
+        match module.export("WASM_SIGNAL_GLOBAL") {
+            Some((ExternalKind::Global, index))
+                if module.globals.get(index as usize) == Some(&(ValType::I32, true)) => {}
+            Some(_) => problems.push("`WASM_SIGNAL_GLOBAL` is not a mutable i32 global".into()),
+            None => problems.push("`WASM_SIGNAL_GLOBAL` is not exported".into()),
+        }
+    }
+
+    let control_memory = match abi {
+        Abi::Found { flags, .. } if flags & ABI_CONTROL_MEMORY != 0 => {
+            Some(flags >> ABI_MEMORY_INDEX_SHIFT & 0xFF)
+        }
+        _ => None,
+    };
+    if let Some(index) = control_memory {
+        match module.export("WASM_SIGNAL_CONTROL_MEMORY") {
+            Some((ExternalKind::Memory, exported)) if exported == index => {}
+            Some(_) => problems.push(format!(
+                "`WASM_SIGNAL_CONTROL_MEMORY` is not memory {index}, as the metadata records"
+            )),
+            None => problems.push("`WASM_SIGNAL_CONTROL_MEMORY` is not exported".into()),
+        }
+    }
+
+    let memory = module.memory.map(|(ty, imported)| {
+        let name = module
+            .exports
+            .iter()
+            .find(|&&(_, kind, index)| kind == ExternalKind::Memory && index == 0)
+            .map(|&(name, _, _)| String::from(name));
+        (name, ty, imported)
+    });
+    match &memory {
+        None => problems.push("the module has no memory".into()),
+        Some((None, _, false)) => problems.push("the memory is not exported".into()),
+        Some((Some(name), _, _)) if name != "memory" => warnings.push(format!(
+            "the memory is exported as `{name}`; pass it to the host helpers explicitly"
+        )),
+        _ => {}
+    }
+
+    let slots: Vec<_> = SLOTS
+        .iter()
+        // The signal word is a global, not a slot, with global storage, and
+        // in its own memory with a control memory
+        .filter(|&&(name, _)| {
+            !((global_storage || control_memory.is_some()) && name == "WASM_SIGNAL_ADDR")
+        })
+        .map(|&(name, size)| (name, size, module.resolve(name)))
+        .collect();
+    for &(name, _, slot) in &slots {
+        match slot {
+            Slot::Missing => problems.push(format!("`{name}` is not exported")),
+            Slot::Invalid(reason) => problems.push(format!("`{name}`: {reason}")),
+            Slot::Unresolved { .. } | Slot::Resolved { .. } => {}
+        }
+    }
+    if slots
+        .iter()
+        .any(|&(_, _, slot)| matches!(slot, Slot::Unresolved { .. }))
+    {
+        warnings.push("some slot addresses are only known at instantiation".into());
+    }
+
+    let functions = FUNCTIONS
+        .iter()
+        .map(|&(name, features)| {
+            let exported = matches!(module.export(name), Some((ExternalKind::Func, _)));
+            (name, features, exported)
+        })
+        .collect();
+
+    Ok(Report {
+        abi,
+        global_storage,
+        control_memory,
+        memory,
+        slots,
+        functions,
+        problems,
+        warnings,
+    })
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.abi {
+            Abi::Missing => writeln!(f, "ABI:            unknown (no metadata)")?,
+            Abi::Malformed => writeln!(f, "ABI:            malformed metadata")?,
+            Abi::Found { version, .. } => writeln!(f, "ABI:            version {version}")?,
+        }
+        let storage = match self.control_memory {
+            _ if self.global_storage => String::from("exported global `WASM_SIGNAL_GLOBAL`"),
+            Some(index) => format!("control memory {index} (`WASM_SIGNAL_CONTROL_MEMORY`)"),
+            None => String::from("word in linear memory"),
+        };
+        writeln!(f, "signal storage: {storage}")?;
+        if let Some((name, ty, imported)) = &self.memory {
+            let origin = match (name, imported) {
+                (Some(name), _) => format!("exported as `{name}`"),
+                (None, true) => String::from("imported"),
+                (None, false) => String::from("not exported"),
+            };
+            let shared = if ty.shared { "shared" } else { "not shared" };
+            writeln!(
+                f,
+                "memory:         {origin}, {} pages, {shared}",
+                ty.initial
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<28} {:>10} {:>10} {:>6}",
+            "slot", "pointer", "address", "bytes"
+        )?;
+        for &(name, size, slot) in &self.slots {
+            let (pointer, addr) = match slot {
+                Slot::Missing => (String::from("missing"), String::new()),
+                Slot::Invalid(_) => (String::from("invalid"), String::new()),
+                Slot::Unresolved { pointer } => (format!("{pointer:#010x}"), String::from("?")),
+                Slot::Resolved { pointer, addr } => {
+                    (format!("{pointer:#010x}"), format!("{addr:#010x}"))
+                }
+            };
+            writeln!(f, "{name:<28} {pointer:>10} {addr:>10} {size:>6}")?;
+        }
+
+        writeln!(f)?;
+        for &(name, features, exported) in &self.functions {
+            if exported {
+                writeln!(f, "{name:<32} exported")?;
+            } else {
+                writeln!(f, "{name:<32} not exported (needs {features})")?;
+            }
+        }
+
+        if !self.warnings.is_empty() || !self.problems.is_empty() {
+            writeln!(f)?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "warning: {warning}")?;
+        }
+        for problem in &self.problems {
+            writeln!(f, "error: {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(id: u8, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![id, body.len() as u8];
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    /// Builds a module exporting its memory and `WASM_SIGNAL_ADDR`, which
+    /// points at address 16, where the data segment places the slot address.
+    fn module(abi: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        // One memory of one page
+        bytes.extend(section(5, &[1, 0x00, 1]));
+        // One immutable i32 global: i32.const 16
+        bytes.extend(section(6, &[1, 0x7f, 0x00, 0x41, 16, 0x0b]));
+        let mut exports = vec![2, 6];
+        exports.extend_from_slice(b"memory");
+        exports.extend([0x02, 0]);
+        exports.push(16);
+        exports.extend_from_slice(b"WASM_SIGNAL_ADDR");
+        exports.extend([0x03, 0]);
+        bytes.extend(section(7, &exports));
+        // The slot lives at 0x100
+        bytes.extend(section(11, &[1, 0x00, 0x41, 16, 0x0b, 4, 0x00, 0x01, 0, 0]));
+        let mut custom = vec![ABI_SECTION.len() as u8];
+        custom.extend_from_slice(ABI_SECTION.as_bytes());
+        custom.extend_from_slice(abi);
+        bytes.extend(section(0, &custom));
+        bytes
+    }
+
+    #[test]
+    fn test_inspect_resolves_slots() {
+        let mut abi = ABI_VERSION.to_le_bytes().to_vec();
+        abi.extend([0; 4]);
+        let report = inspect(&module(&abi)).unwrap();
+        assert_eq!(
+            report.abi,
+            Abi::Found {
+                version: ABI_VERSION,
+                flags: 0,
+                copies: 1
+            }
+        );
+        assert!(!report.global_storage);
+        assert_eq!(
+            report.slots[0],
+            (
+                "WASM_SIGNAL_ADDR",
+                4,
+                Slot::Resolved {
+                    pointer: 16,
+                    addr: 0x100
+                }
+            )
+        );
+        // Every other slot is missing
+        assert_eq!(report.problems.len(), SLOTS.len() - 1);
+        assert!(report.warnings.is_empty());
+
+        // A second copy of the crate, with a different ABI
+        abi.extend((ABI_VERSION + 1).to_le_bytes());
+        abi.extend([0; 4]);
+        let report = inspect(&module(&abi)).unwrap();
+        assert!(matches!(report.abi, Abi::Found { copies: 2, .. }));
+        assert_eq!(report.problems.len(), SLOTS.len());
+
+        // A control memory replaces the signal slot
+        let mut abi = ABI_VERSION.to_le_bytes().to_vec();
+        abi.extend((ABI_CONTROL_MEMORY | 1 << ABI_MEMORY_INDEX_SHIFT).to_le_bytes());
+        let report = inspect(&module(&abi)).unwrap();
+        assert_eq!(report.control_memory, Some(1));
+        assert!(report
+            .slots
+            .iter()
+            .all(|&(name, ..)| name != "WASM_SIGNAL_ADDR"));
+        assert!(report.problems[0].contains("WASM_SIGNAL_CONTROL_MEMORY"));
+    }
+}
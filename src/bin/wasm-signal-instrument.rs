@@ -0,0 +1,377 @@
+//! Inserts signal checks into the loops of a compiled module.
+//!
+//! ```text
+//! wasm-signal-instrument [options] <input.wasm> -o <output.wasm>
+//!
+//!   --include <pattern>         instrument matching functions
+//!   --reachable-from <pattern>  instrument matching functions and every
+//!                               function they call, directly or not
+//!   --exclude <pattern>         never instrument matching functions
+//!   --dry-run                   list what would be instrumented
+//! ```
+//!
+//! For code where adding `check_signal()` calls to the source is not an
+//! option, such as third-party dependencies, this adds a call to the
+//! exported `__wasm_signal_check` at the top of every loop body of the
+//! selected functions, which every back edge passes through.
+//!
+//! Patterns match function names from the module's name section, so build
+//! with debug info or `-C strip=none` and run the tool before `wasm-opt`
+//! strips names. Rust symbols are matched demangled and without their hash,
+//! such as `my_crate::parse::tokenize`, and `*` matches any run of
+//! characters. Options can be repeated. Without `--include` or
+//! `--reachable-from`, every function is selected.
+//!
+//! Functions that `__wasm_signal_check` itself calls are never instrumented,
+//! as a check inside the check would recurse. Only direct calls are
+//! followed, for both this and `--reachable-from`.
+
+use std::collections::{HashMap, HashSet};
+use std::process::ExitCode;
+
+use walrus::ir::{Call, Instr, InstrLocId, InstrSeqId, Loop, ReturnCall, Visitor};
+use walrus::{ExportItem, FunctionId, FunctionKind, Module};
+
+const CHECK_EXPORT: &str = "__wasm_signal_check";
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {err}");
+            eprintln!(
+                "usage: wasm-signal-instrument [--include <pattern>] [--reachable-from <pattern>] \
+                 [--exclude <pattern>] [--dry-run] <input.wasm> -o <output.wasm>"
+            );
+            return ExitCode::from(2);
+        }
+    };
+    let mut module = match Module::from_file(&options.input) {
+        Ok(module) => module,
+        Err(err) => {
+            eprintln!("error: cannot load {}: {err}", options.input);
+            return ExitCode::from(2);
+        }
+    };
+    let plan = match plan(&module, &options.selection) {
+        Ok(plan) => plan,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut loops = 0;
+    for &(func, ref seqs) in &plan.targets {
+        if options.dry_run {
+            println!("{} ({} loops)", display_name(&module, func), seqs.len());
+        }
+        loops += seqs.len();
+    }
+    if !options.dry_run {
+        apply(&mut module, &plan);
+        let Some(output) = &options.output else {
+            eprintln!("error: no output file; pass -o <output.wasm>");
+            return ExitCode::from(2);
+        };
+        if let Err(err) = module.emit_wasm_file(output) {
+            eprintln!("error: cannot write {output}: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+    println!(
+        "{loops} loops in {} functions{}",
+        plan.targets.len(),
+        if options.dry_run { "" } else { " instrumented" }
+    );
+    ExitCode::SUCCESS
+}
+
+// ============================================================================
+// Options
+// ============================================================================
+
+/// Which functions to instrument.
+#[derive(Debug, Default)]
+struct Selection {
+    include: Vec<String>,
+    reachable_from: Vec<String>,
+    exclude: Vec<String>,
+}
+
+struct Options {
+    input: String,
+    output: Option<String>,
+    dry_run: bool,
+    selection: Selection,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut input = None;
+        let mut output = None;
+        let mut dry_run = false;
+        let mut selection = Selection::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "-o" | "--output" => output = Some(value()?),
+                "--include" => selection.include.push(value()?),
+                "--reachable-from" => selection.reachable_from.push(value()?),
+                "--exclude" => selection.exclude.push(value()?),
+                "--dry-run" => dry_run = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+                _ if input.is_none() => input = Some(arg),
+                _ => return Err(format!("unexpected argument {arg}")),
+            }
+        }
+        Ok(Options {
+            input: input.ok_or("no input file")?,
+            output,
+            dry_run,
+            selection,
+        })
+    }
+}
+
+/// Matches `name` against a pattern where `*` matches any run of
+/// characters.
+fn glob(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*`: the whole name must match
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Demangles a Rust symbol, without its hash. Other names are returned
+/// unchanged.
+fn demangle(name: &str) -> String {
+    format!("{:#}", rustc_demangle::demangle(name))
+}
+
+fn matches_any(patterns: &[String], name: Option<&str>) -> bool {
+    let Some(name) = name else {
+        return false;
+    };
+    let demangled = demangle(name);
+    patterns
+        .iter()
+        .any(|pattern| glob(pattern, name) || glob(pattern, &demangled))
+}
+
+fn display_name(module: &Module, func: FunctionId) -> String {
+    match &module.funcs.get(func).name {
+        Some(name) => demangle(name),
+        None => format!("function {}", func.index()),
+    }
+}
+
+// ============================================================================
+// Instrumentation
+// ============================================================================
+
+/// What one function calls, and the loops it contains.
+#[derive(Default)]
+struct Scan {
+    callees: Vec<FunctionId>,
+    loops: Vec<InstrSeqId>,
+}
+
+impl<'instr> Visitor<'instr> for Scan {
+    fn visit_call(&mut self, instr: &Call) {
+        self.callees.push(instr.func);
+    }
+
+    fn visit_return_call(&mut self, instr: &ReturnCall) {
+        self.callees.push(instr.func);
+    }
+
+    fn visit_loop(&mut self, instr: &Loop) {
+        self.loops.push(instr.seq);
+    }
+}
+
+/// The check function and the loops to insert calls to it into.
+struct Plan {
+    check: FunctionId,
+    targets: Vec<(FunctionId, Vec<InstrSeqId>)>,
+}
+
+fn plan(module: &Module, selection: &Selection) -> Result<Plan, String> {
+    let check = module
+        .exports
+        .iter()
+        .find_map(|export| match export.item {
+            ExportItem::Function(func) if export.name == CHECK_EXPORT => Some(func),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            format!(
+                "`{CHECK_EXPORT}` is not exported; is the module built with wasm-signal-handler?"
+            )
+        })?;
+
+    let scans: HashMap<FunctionId, Scan> = module
+        .funcs
+        .iter_local()
+        .map(|(id, func)| {
+            let mut scan = Scan::default();
+            walrus::ir::dfs_in_order(&mut scan, func, func.entry_block());
+            (id, scan)
+        })
+        .collect();
+    let reachable = |roots: &mut dyn Iterator<Item = FunctionId>| {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<FunctionId> = roots.collect();
+        while let Some(func) = stack.pop() {
+            if seen.insert(func) {
+                if let Some(scan) = scans.get(&func) {
+                    stack.extend(scan.callees.iter().copied());
+                }
+            }
+        }
+        seen
+    };
+
+    let name = |func: FunctionId| module.funcs.get(func).name.as_deref();
+    let forbidden = reachable(&mut core::iter::once(check));
+    let everything = selection.include.is_empty() && selection.reachable_from.is_empty();
+    let mut selected: HashSet<FunctionId> = reachable(
+        &mut scans
+            .keys()
+            .copied()
+            .filter(|&func| matches_any(&selection.reachable_from, name(func))),
+    );
+    selected.extend(
+        scans
+            .keys()
+            .copied()
+            .filter(|&func| everything || matches_any(&selection.include, name(func))),
+    );
+
+    let mut targets: Vec<_> = selected
+        .into_iter()
+        .filter(|func| !forbidden.contains(func))
+        .filter(|&func| !matches_any(&selection.exclude, name(func)))
+        .filter_map(|func| {
+            let loops = &scans.get(&func)?.loops;
+            (!loops.is_empty()).then(|| (func, loops.clone()))
+        })
+        .collect();
+    targets.sort_by_key(|&(func, _)| func.index());
+    Ok(Plan { check, targets })
+}
+
+fn apply(module: &mut Module, plan: &Plan) {
+    for (func, seqs) in &plan.targets {
+        let FunctionKind::Local(local) = &mut module.funcs.get_mut(*func).kind else {
+            continue;
+        };
+        for &seq in seqs {
+            local.block_mut(seq).instrs.insert(
+                0,
+                (
+                    Instr::Call(Call { func: plan.check }),
+                    InstrLocId::default(),
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use walrus::{FunctionBuilder, ModuleConfig};
+
+    /// Adds a function with one loop calling `callees`.
+    fn looping(module: &mut Module, name: &str, callees: &[FunctionId]) -> FunctionId {
+        let mut builder = FunctionBuilder::new(&mut module.types, &[], &[]);
+        builder.name(String::from(name));
+        builder.func_body().loop_(None, |body| {
+            for &callee in callees {
+                body.call(callee);
+            }
+        });
+        builder.finish(vec![], &mut module.funcs)
+    }
+
+    fn starts_with_check(module: &Module, func: FunctionId, check: FunctionId) -> bool {
+        let FunctionKind::Local(local) = &module.funcs.get(func).kind else {
+            unreachable!()
+        };
+        let mut scan = Scan::default();
+        walrus::ir::dfs_in_order(&mut scan, local, local.entry_block());
+        matches!(
+            local.block(scan.loops[0]).instrs.first(),
+            Some((Instr::Call(Call { func }), _)) if *func == check
+        )
+    }
+
+    #[test]
+    fn test_instrument_selected_loops() {
+        let mut module = Module::with_config(ModuleConfig::new());
+        let handler = looping(&mut module, "handler", &[]);
+        let check = looping(&mut module, CHECK_EXPORT, &[handler]);
+        module.exports.add(CHECK_EXPORT, check);
+        let leaf = looping(&mut module, "_ZN3dep4leaf17h0123456789abcdefE", &[]);
+        let root = looping(&mut module, "app::run", &[leaf, handler]);
+        let other = looping(&mut module, "app::other", &[]);
+
+        let selection = Selection {
+            reachable_from: vec![String::from("app::run")],
+            ..Selection::default()
+        };
+        let plan = plan(&module, &selection).unwrap();
+        apply(&mut module, &plan);
+        assert!(starts_with_check(&module, root, check));
+        assert!(starts_with_check(&module, leaf, check));
+        assert!(!starts_with_check(&module, other, check));
+        // The check's own callees are left alone
+        assert!(!starts_with_check(&module, handler, check));
+
+        let selection = Selection {
+            include: vec![String::from("dep::*")],
+            exclude: vec![String::from("*::leaf")],
+            ..Selection::default()
+        };
+        assert!(plan_names(&module, &selection).is_empty());
+        assert_eq!(
+            plan_names(&module, &Selection::default()),
+            ["dep::leaf", "app::run", "app::other"]
+        );
+    }
+
+    fn plan_names(module: &Module, selection: &Selection) -> Vec<String> {
+        plan(module, selection)
+            .unwrap()
+            .targets
+            .iter()
+            .map(|&(func, _)| display_name(module, func))
+            .collect()
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(glob("app::*", "app::run"));
+        assert!(glob("*::run", "app::run"));
+        assert!(glob("a*p*n", "app::run"));
+        assert!(glob("app::run", "app::run"));
+        assert!(!glob("app", "app::run"));
+        assert!(!glob("*x*", "app::run"));
+        assert!(!glob("ab*ba", "aba"));
+    }
+}
@@ -0,0 +1,156 @@
+//! The C API.
+//!
+//! C and C++ code linked into the same module, hand-written wat and other
+//! LLVM languages, as well as C hosts embedding the module, cannot call the
+//! Rust API. With the `capi` feature, the crate exports `extern "C"`
+//! functions to check, peek at and raise signals, to mask them around
+//! critical sections, to query how they are handled and the lifecycle
+//! state, and to find the host-shared slots. Their names and signatures are
+//! stable: functions may be added, but existing ones only change with the
+//! [ABI version](crate::ABI_VERSION).
+//!
+//! `include/wasm_signal_handler.h` declares them, along with the slot export
+//! names, the signal codes and constants of the protocol and the
+//! [`SignalLayout`] struct. The header is generated from
+//! the crate by the `wasm-signal-header` tool, whose tests fail if the
+//! checked-in copy is stale.
+
+use core::mem::forget;
+
+use crate::{
+    current_state, disposition, mask, peek_signal, raise_signal, signal_layout, signals_masked,
+    try_check_signal, SignalLayout, SignalMaskGuard, DISPOSITION_HANDLER,
+};
+
+/// Checks for a pending signal, as [`try_check_signal`] does.
+///
+/// # Returns
+///
+/// The code of the signal to propagate, or `0` to continue.
+#[no_mangle]
+pub extern "C" fn wasm_signal_check() -> u32 {
+    match try_check_signal() {
+        Ok(()) => 0,
+        Err(signal) => signal.code(),
+    }
+}
+
+/// Returns the code of the pending signal without consuming it, as
+/// [`peek_signal`] does, or `0`.
+#[no_mangle]
+pub extern "C" fn wasm_signal_peek() -> u32 {
+    peek_signal().map_or(0, |signal| signal.code())
+}
+
+/// Raises `code`, as [`raise_signal`] does.
+///
+/// # Returns
+///
+/// With `immediate`, the code of the signal to propagate, or `0` to
+/// continue; otherwise `0`.
+#[no_mangle]
+pub extern "C" fn wasm_signal_raise(code: u32, immediate: bool) -> u32 {
+    match raise_signal(code, immediate) {
+        Ok(()) => 0,
+        Err(signal) => signal.code(),
+    }
+}
+
+/// Masks signals until a matching [`wasm_signal_unmask`], as a
+/// [`SignalMaskGuard`] does. Calls nest.
+#[no_mangle]
+pub extern "C" fn wasm_signal_mask() {
+    forget(SignalMaskGuard::new());
+}
+
+/// Releases one [`wasm_signal_mask`], delivering any deferred signal once
+/// the outermost one is released, as [`SignalMaskGuard::unmask`] does.
+///
+/// # Returns
+///
+/// The code of the deferred signal to propagate, or `0` to continue.
+#[no_mangle]
+pub extern "C" fn wasm_signal_unmask() -> u32 {
+    match mask::unmask_forgotten() {
+        Ok(()) => 0,
+        Err(signal) => signal.code(),
+    }
+}
+
+/// Returns `true` if signals are currently masked, as [`signals_masked`]
+/// does.
+#[no_mangle]
+pub extern "C" fn wasm_signal_masked() -> bool {
+    signals_masked()
+}
+
+/// Returns the disposition word, as [`disposition`] does: how a signal
+/// would be handled if a check observed it now.
+#[no_mangle]
+pub extern "C" fn wasm_signal_disposition() -> u32 {
+    disposition()
+}
+
+/// Returns `true` if a signal handler, global or namespaced, is registered.
+#[no_mangle]
+pub extern "C" fn wasm_signal_has_handler() -> bool {
+    disposition() & DISPOSITION_HANDLER != 0
+}
+
+/// Returns the lifecycle state, as [`current_state`] does.
+#[no_mangle]
+pub extern "C" fn wasm_signal_state() -> u32 {
+    current_state() as u32
+}
+
+/// Returns the addresses of every host-shared slot, as [`signal_layout`]
+/// does.
+#[no_mangle]
+pub extern "C" fn wasm_signal_layout() -> SignalLayout {
+    signal_layout()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, send_signal, set_signal_handler, Signal};
+
+    #[test]
+    fn test_capi() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        assert_eq!(wasm_signal_check(), 0);
+        assert_eq!(wasm_signal_raise(Signal::CANCEL.code(), false), 0);
+        assert_eq!(wasm_signal_check(), Signal::CANCEL.code());
+        assert_eq!(wasm_signal_check(), 0);
+        assert_eq!(
+            wasm_signal_raise(Signal::TERMINATE.code(), true),
+            Signal::TERMINATE.code()
+        );
+        assert_eq!(wasm_signal_raise(0, true), 0);
+        assert_eq!(wasm_signal_layout(), signal_layout());
+        assert_eq!(wasm_signal_state(), current_state() as u32);
+
+        // Masked signals are deferred to the outermost unmask
+        wasm_signal_mask();
+        wasm_signal_mask();
+        assert!(wasm_signal_masked());
+        send_signal(Signal::CANCEL.code());
+        assert_eq!(wasm_signal_peek(), Signal::CANCEL.code());
+        assert_eq!(wasm_signal_check(), 0);
+        assert_eq!(wasm_signal_unmask(), 0);
+        assert_eq!(wasm_signal_unmask(), Signal::CANCEL.code());
+        assert!(!wasm_signal_masked());
+        assert_eq!(wasm_signal_peek(), 0);
+        assert_eq!(wasm_signal_unmask(), 0);
+
+        assert!(!wasm_signal_has_handler());
+        set_signal_handler(|_| Ok(()));
+        assert!(wasm_signal_has_handler());
+        assert_ne!(wasm_signal_disposition() & DISPOSITION_HANDLER, 0);
+        clear_signal_handler();
+    }
+}
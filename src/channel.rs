@@ -0,0 +1,288 @@
+//! Signal-aware `futures` channel receivers.
+//!
+//! Actor-style guests spend most of their time waiting on a channel. A
+//! receiver wrapped in [`SignalReceiver`] or [`SignalOneshot`] resolves to
+//! `Err(Signal)` instead of waiting forever once the host asks the guest to
+//! stop.
+//!
+//! A waiting receiver cannot see the signal word change, so it is woken
+//! instead:
+//!
+//! - when guest code raises a signal ([`set_signal`](crate::set_signal),
+//!   [`raise_signal`](crate::raise_signal), [`send_signal`](crate::send_signal)
+//!   and friends),
+//! - when a check anywhere propagates a signal, and
+//! - when the host calls the exported `__wasm_signal_wake_tasks` after writing
+//!   the signal word (see `wakeTasks` in the host helpers).
+//!
+//! Once a check has propagated a signal, every receiver reports it, until
+//! [`clear_signal`](crate::clear_signal) or [`reset_all`](crate::reset_all).
+//! The signal word itself is cleared by the check that observed it, so this
+//! is what lets all of an actor's receivers learn about the same
+//! cancellation.
+
+use core::cell::UnsafeCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, AtomicU32};
+use core::task::{Context, Poll, Waker};
+use std::vec::Vec;
+
+use futures_channel::{mpsc, oneshot};
+use futures_core::Stream;
+
+use crate::{try_check_signal, Signal, ACQUIRE, RELEASE};
+
+/// The signal propagated since the last reset, or `0`.
+static PROPAGATED: AtomicU32 = AtomicU32::new(0);
+
+/// Wakers of pending receives, guarded by [`LOCK`].
+struct Wakers(UnsafeCell<Vec<Waker>>);
+
+// SAFETY: The wakers are only accessed under LOCK, and are `Send + Sync`
+unsafe impl Sync for Wakers {}
+
+static WAKERS: Wakers = Wakers(UnsafeCell::new(Vec::new()));
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+fn with_wakers<R>(f: impl FnOnce(&mut Vec<Waker>) -> R) -> R {
+    while LOCK
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // SAFETY: LOCK grants exclusive access to the wakers
+    let result = f(unsafe { &mut *WAKERS.0.get() });
+    LOCK.store(false, RELEASE);
+    result
+}
+
+/// Wakes every pending receive, so it checks for a signal.
+pub(crate) fn wake_all() {
+    let wakers = with_wakers(core::mem::take);
+    // Wake outside the lock, since waking may poll inline
+    for waker in wakers {
+        waker.wake();
+    }
+}
+
+/// Records a signal propagated by a check and wakes every pending receive.
+pub(crate) fn propagated(signal: Signal) {
+    PROPAGATED.store(signal.code(), RELEASE);
+    wake_all();
+}
+
+/// Forgets a propagated signal.
+pub(crate) fn forget() {
+    PROPAGATED.store(0, RELEASE);
+}
+
+/// Forgets a propagated signal and drops registered wakers.
+pub(crate) fn reset() {
+    forget();
+    drop(with_wakers(core::mem::take));
+}
+
+/// The propagated signal, saved by `ScopedSignalState`.
+///
+/// Wakers are left alone; spurious wakes are harmless.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(u32);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(PROPAGATED.load(ACQUIRE))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    PROPAGATED.store(saved.0, RELEASE);
+}
+
+/// Wakes tasks waiting on a [`SignalReceiver`] or [`SignalOneshot`], for
+/// hosts to call after writing the signal word.
+#[no_mangle]
+pub extern "C" fn __wasm_signal_wake_tasks() {
+    wake_all();
+}
+
+/// Polls `inner` unless a signal is pending or was propagated.
+pub(crate) fn poll_signal_aware<T>(
+    cx: &mut Context<'_>,
+    inner: impl FnOnce(&mut Context<'_>) -> Poll<T>,
+) -> Poll<Result<T, Signal>> {
+    let check = || match Signal::new(PROPAGATED.load(ACQUIRE)) {
+        Some(signal) => Err(signal),
+        None => try_check_signal(),
+    };
+    check()?;
+    if let Poll::Ready(value) = inner(cx) {
+        return Poll::Ready(Ok(value));
+    }
+    with_wakers(|wakers| {
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+    });
+    // Check again, in case a signal was raised before the waker was registered
+    check()?;
+    Poll::Pending
+}
+
+/// An [`mpsc::Receiver`] whose receives end on cancellation.
+///
+/// # Example
+///
+/// ```rust
+/// use futures_channel::mpsc;
+/// use wasm_signal_handler::{Signal, SignalReceiver};
+///
+/// async fn actor(rx: mpsc::Receiver<u32>) -> Result<(), Signal> {
+///     let mut rx = SignalReceiver::new(rx);
+///     while let Some(message) = rx.recv().await? {
+///         let _ = message;
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SignalReceiver<T> {
+    inner: mpsc::Receiver<T>,
+}
+
+impl<T> SignalReceiver<T> {
+    /// Wraps `inner`.
+    pub fn new(inner: mpsc::Receiver<T>) -> SignalReceiver<T> {
+        SignalReceiver { inner }
+    }
+
+    /// Receives the next message.
+    ///
+    /// Resolves to `Ok(None)` once all senders are dropped and the channel
+    /// is drained, and to `Err(Signal)` on cancellation.
+    pub fn recv(&mut self) -> Recv<'_, T> {
+        Recv { receiver: self }
+    }
+
+    /// Returns the wrapped receiver.
+    pub fn into_inner(self) -> mpsc::Receiver<T> {
+        self.inner
+    }
+}
+
+impl<T> From<mpsc::Receiver<T>> for SignalReceiver<T> {
+    fn from(inner: mpsc::Receiver<T>) -> SignalReceiver<T> {
+        SignalReceiver::new(inner)
+    }
+}
+
+/// The future returned by [`SignalReceiver::recv`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Recv<'a, T> {
+    receiver: &'a mut SignalReceiver<T>,
+}
+
+impl<T> Future for Recv<'_, T> {
+    type Output = Result<Option<T>, Signal>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.receiver.inner;
+        poll_signal_aware(cx, |cx| Pin::new(inner).poll_next(cx))
+    }
+}
+
+/// A [`oneshot::Receiver`] that resolves to `Err(Signal)` on cancellation.
+///
+/// Awaiting it resolves to `Ok(Err(Canceled))` if the sender is dropped.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct SignalOneshot<T> {
+    inner: oneshot::Receiver<T>,
+}
+
+impl<T> SignalOneshot<T> {
+    /// Wraps `inner`.
+    pub fn new(inner: oneshot::Receiver<T>) -> SignalOneshot<T> {
+        SignalOneshot { inner }
+    }
+
+    /// Returns the wrapped receiver.
+    pub fn into_inner(self) -> oneshot::Receiver<T> {
+        self.inner
+    }
+}
+
+impl<T> From<oneshot::Receiver<T>> for SignalOneshot<T> {
+    fn from(inner: oneshot::Receiver<T>) -> SignalOneshot<T> {
+        SignalOneshot::new(inner)
+    }
+}
+
+impl<T> Future for SignalOneshot<T> {
+    type Output = Result<Result<T, oneshot::Canceled>, Signal>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let inner = &mut self.inner;
+        poll_signal_aware(cx, |cx| Pin::new(inner).poll(cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, reset_all, set_signal};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    struct CountWakes(AtomicUsize);
+
+    impl Wake for CountWakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_receivers_end_on_cancellation() {
+        let _guard = lock();
+        reset_all();
+        clear_signal_handler();
+        let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let (mut tx, rx) = mpsc::channel(1);
+        let mut rx = SignalReceiver::new(rx);
+        tx.try_send(1).unwrap();
+        assert_eq!(
+            Pin::new(&mut rx.recv()).poll(&mut cx),
+            Poll::Ready(Ok(Some(1)))
+        );
+        assert_eq!(Pin::new(&mut rx.recv()).poll(&mut cx), Poll::Pending);
+
+        let (_oneshot_tx, oneshot_rx) = oneshot::channel::<u32>();
+        let mut oneshot_rx = SignalOneshot::new(oneshot_rx);
+        assert_eq!(Pin::new(&mut oneshot_rx).poll(&mut cx), Poll::Pending);
+
+        // Raising wakes the task; the first receive to poll takes the signal
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            Pin::new(&mut rx.recv()).poll(&mut cx),
+            Poll::Ready(Err(Signal::CANCEL))
+        );
+        assert_eq!(
+            Pin::new(&mut oneshot_rx).poll(&mut cx),
+            Poll::Ready(Err(Signal::CANCEL))
+        );
+
+        reset_all();
+        clear_signal();
+        assert_eq!(Pin::new(&mut rx.recv()).poll(&mut cx), Poll::Pending);
+    }
+}
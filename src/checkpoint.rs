@@ -0,0 +1,204 @@
+//! Checkpoint signals with resumable snapshots.
+//!
+//! Hosts that migrate or evict instances can raise [`Signal::CHECKPOINT`] to
+//! ask the guest to persist its progress without being stopped. Once a
+//! snapshotter is registered with [`set_checkpointer`], a check that observes
+//! the signal writes a snapshot of the registered [`Checkpointable`] to the
+//! [`CheckpointSink`] and then continues (the check returns `Ok(())`). Without
+//! a snapshotter, the signal goes to the signal handler like any other.
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::AtomicBool;
+
+use crate::{ACQUIRE, RELEASE};
+
+/// State that can write a snapshot of itself for later resumption.
+///
+/// # Example
+///
+/// ```rust
+/// use core::fmt::Write;
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use wasm_signal_handler::Checkpointable;
+///
+/// struct Progress {
+///     next_row: AtomicU32,
+/// }
+///
+/// impl Checkpointable for Progress {
+///     fn snapshot(&self, w: &mut impl Write) {
+///         let _ = write!(w, "next_row={}", self.next_row.load(Ordering::Relaxed));
+///     }
+/// }
+/// ```
+pub trait Checkpointable {
+    /// Writes a snapshot of the current state to `w`.
+    ///
+    /// Snapshots are taken from inside a signal check, so implementations
+    /// must not check signals themselves.
+    fn snapshot(&self, w: &mut impl Write);
+}
+
+/// Receives snapshot output, possibly in several chunks per snapshot.
+pub type CheckpointSink = fn(&str);
+
+/// A registered snapshotter, with the target's type erased.
+#[derive(Clone, Copy)]
+struct Checkpointer {
+    snapshot: fn(*const (), &mut dyn Write),
+    target: *const (),
+    sink: CheckpointSink,
+}
+
+/// The registered snapshotter, guarded by [`LOCK`].
+///
+/// A snapshotter is a data pointer and a function pointer that must be read
+/// together, so unlike the handler slots it cannot live in a single atomic.
+struct Slot(UnsafeCell<Option<Checkpointer>>);
+
+// SAFETY: The slot is only accessed under LOCK, and targets are `Sync`
+unsafe impl Sync for Slot {}
+
+static SLOT: Slot = Slot(UnsafeCell::new(None));
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+fn with_slot<R>(f: impl FnOnce(&mut Option<Checkpointer>) -> R) -> R {
+    while LOCK
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // SAFETY: LOCK grants exclusive access to the slot
+    let result = f(unsafe { &mut *SLOT.0.get() });
+    LOCK.store(false, RELEASE);
+    result
+}
+
+fn snapshot_erased<T: Checkpointable>(target: *const (), mut w: &mut dyn Write) {
+    // SAFETY: `target` was created from a `&'static T` in `set_checkpointer`
+    let target = unsafe { &*(target as *const T) };
+    target.snapshot(&mut w);
+}
+
+/// Forwards snapshot output to a sink.
+struct SinkWriter(CheckpointSink);
+
+impl Write for SinkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (self.0)(s);
+        Ok(())
+    }
+}
+
+/// Registers `target` to be snapshotted into `sink` on [`Signal::CHECKPOINT`],
+/// replacing any previous registration.
+///
+/// [`Signal::CHECKPOINT`]: crate::Signal::CHECKPOINT
+///
+/// # Returns
+///
+/// Returns `true` if a snapshotter was already registered.
+pub fn set_checkpointer<T: Checkpointable + Sync>(
+    target: &'static T,
+    sink: CheckpointSink,
+) -> bool {
+    let checkpointer = Checkpointer {
+        snapshot: snapshot_erased::<T>,
+        target: target as *const T as *const (),
+        sink,
+    };
+    with_slot(|slot| slot.replace(checkpointer).is_some())
+}
+
+/// Removes the registered snapshotter, so checkpoint signals go to the signal
+/// handler again.
+///
+/// # Returns
+///
+/// Returns `true` if a snapshotter was registered.
+pub fn clear_checkpointer() -> bool {
+    with_slot(|slot| slot.take().is_some())
+}
+
+/// Returns `true` if a snapshotter is registered.
+pub fn has_checkpointer() -> bool {
+    with_slot(|slot| slot.is_some())
+}
+
+/// Takes a snapshot with the registered snapshotter, if any.
+///
+/// # Returns
+///
+/// Returns `true` if a snapshot was written.
+pub(crate) fn take_snapshot() -> bool {
+    // Copy the registration out so the snapshot runs without holding the lock
+    let Some(checkpointer) = with_slot(|slot| *slot) else {
+        return false;
+    };
+    let mut writer = SinkWriter(checkpointer.sink);
+    (checkpointer.snapshot)(checkpointer.target, &mut writer);
+    crate::events::post_event(crate::events::EVENT_CHECKPOINT_WRITTEN, 0);
+    true
+}
+
+/// The registered snapshotter, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(Option<Checkpointer>);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(with_slot(|slot| *slot))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    with_slot(|slot| *slot = saved.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, try_check_signal, Signal};
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    extern crate std;
+    use std::string::String;
+    use std::sync::Mutex;
+
+    struct Progress(AtomicU32);
+
+    impl Checkpointable for Progress {
+        fn snapshot(&self, w: &mut impl Write) {
+            let _ = write!(w, "row={}", self.0.load(Ordering::SeqCst));
+        }
+    }
+
+    static PROGRESS: Progress = Progress(AtomicU32::new(7));
+    static OUTPUT: Mutex<String> = Mutex::new(String::new());
+
+    fn sink(s: &str) {
+        OUTPUT.lock().unwrap().push_str(s);
+    }
+
+    #[test]
+    fn test_checkpoint_snapshots_and_continues() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        OUTPUT.lock().unwrap().clear();
+
+        assert!(!set_checkpointer(&PROGRESS, sink));
+        set_signal(Signal::CHECKPOINT.code());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(OUTPUT.lock().unwrap().as_str(), "row=7");
+
+        // Without a snapshotter the signal propagates
+        assert!(clear_checkpointer());
+        set_signal(Signal::CHECKPOINT.code());
+        assert_eq!(try_check_signal(), Err(Signal::CHECKPOINT));
+    }
+}
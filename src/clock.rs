@@ -0,0 +1,142 @@
+//! Pluggable monotonic clock.
+//!
+//! Time-based features (such as deadlines) read the current time through
+//! [`now_ms`], which uses the registered [`Clock`] or a platform default:
+//!
+//! - On wasm32 in a JavaScript host, `performance.now()` via wasm-bindgen
+//! - On other targets (including WASI) with the `std` feature,
+//!   [`std::time::Instant`]
+//! - Otherwise, no clock (time-based features stay inactive)
+//!
+//! Note that some runtimes deliberately coarsen timers. In Cloudflare Workers,
+//! `performance.now()` only advances across I/O, so CPU-bound code observes a
+//! frozen clock; register a custom clock if a better source is available.
+
+use core::ptr::null_mut;
+use core::sync::atomic::AtomicPtr;
+
+use crate::{ACQUIRE, ACQ_REL};
+
+/// A monotonic clock returning the current time in milliseconds.
+///
+/// The epoch is arbitrary, but must not change while the clock is registered.
+pub type Clock = fn() -> u64;
+
+/// Storage for the registered clock.
+///
+/// Stored as a raw pointer for the same reasons as the signal handler.
+static CLOCK: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Registers a clock, replacing the platform default.
+///
+/// # Returns
+///
+/// Returns the previously registered clock, if any.
+pub fn set_clock(clock: Clock) -> Option<Clock> {
+    let old_ptr = CLOCK.swap(clock as *mut (), ACQ_REL);
+
+    if old_ptr.is_null() {
+        None
+    } else {
+        // SAFETY: We only store valid Clock function pointers in CLOCK
+        Some(unsafe { core::mem::transmute::<*mut (), Clock>(old_ptr) })
+    }
+}
+
+/// Clears the registered clock, restoring the platform default.
+///
+/// # Returns
+///
+/// Returns the previously registered clock, if any.
+pub fn clear_clock() -> Option<Clock> {
+    let old_ptr = CLOCK.swap(null_mut(), ACQ_REL);
+
+    if old_ptr.is_null() {
+        None
+    } else {
+        // SAFETY: We only store valid Clock function pointers in CLOCK
+        Some(unsafe { core::mem::transmute::<*mut (), Clock>(old_ptr) })
+    }
+}
+
+/// Gets the clock in use: the registered clock, or the platform default.
+///
+/// Returns `None` if no clock is registered and the platform has no default.
+pub fn get_clock() -> Option<Clock> {
+    let ptr = CLOCK.load(ACQUIRE);
+
+    if ptr.is_null() {
+        default_clock()
+    } else {
+        // SAFETY: We only store valid Clock function pointers in CLOCK
+        Some(unsafe { core::mem::transmute::<*mut (), Clock>(ptr) })
+    }
+}
+
+/// Reads the current time in milliseconds, if a clock is available.
+#[inline]
+pub fn now_ms() -> Option<u64> {
+    get_clock().map(|clock| clock())
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn default_clock() -> Option<Clock> {
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = performance, js_name = now)]
+        fn performance_now() -> f64;
+    }
+
+    fn performance_clock() -> u64 {
+        performance_now() as u64
+    }
+
+    Some(performance_clock)
+}
+
+#[cfg(all(
+    not(all(target_arch = "wasm32", target_os = "unknown")),
+    feature = "std"
+))]
+fn default_clock() -> Option<Clock> {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    fn instant_clock() -> u64 {
+        START.get_or_init(Instant::now).elapsed().as_millis() as u64
+    }
+
+    Some(instant_clock)
+}
+
+#[cfg(all(
+    not(all(target_arch = "wasm32", target_os = "unknown")),
+    not(feature = "std")
+))]
+fn default_clock() -> Option<Clock> {
+    None
+}
+
+/// The registered clock, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(Option<Clock>);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    let ptr = CLOCK.load(ACQUIRE);
+    // SAFETY: We only store valid Clock function pointers in CLOCK
+    Saved((!ptr.is_null()).then(|| unsafe { core::mem::transmute::<*mut (), Clock>(ptr) }))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    match saved.0 {
+        Some(clock) => set_clock(clock),
+        None => clear_clock(),
+    };
+}
@@ -0,0 +1,85 @@
+//! Signal conventions for Cloudflare Workers.
+//!
+//! The Workers runtime warns guests about its limits with the signal words
+//! defined here, mirrored by `WorkersLimit` in `js/workers.js`. Each
+//! word is a code in the `0xCF00` block with an [encoded severity](crate::Severity)
+//! that decides how a check treats it: memory pressure is advisory, an
+//! approaching CPU limit is a cancel the handler may decline, and a wall
+//! clock limit or eviction always propagates.
+//!
+//! Guests that only know the generic signals can treat a limit as its
+//! [`SignalKind`](crate::SignalKind), through the `From` conversion.
+
+use crate::platform::platform_signals;
+
+platform_signals! {
+    /// A limit the Workers runtime warns the guest about.
+    pub enum WorkersLimit ("limit", "limits") {
+        /// The request is about to exceed its CPU time limit.
+        CpuLimitApproaching = 0xCF01, Cancel, Cancel, "cpu-limit-approaching";
+        /// The request exceeded its wall clock limit.
+        WallClockLimit = 0xCF02, Kill, Terminate, "wall-clock-limit";
+        /// The isolate is close to its memory limit.
+        MemoryPressure = 0xCF03, Warn, Custom, "memory-pressure";
+        /// The isolate or Durable Object is about to be evicted.
+        Eviction = 0xCF04, Kill, Terminate, "eviction";
+    }
+
+    /// The generic kind a guest should treat the limit as: an approaching CPU
+    /// limit is a cancel, a wall clock limit or eviction a termination, and
+    /// memory pressure has no generic equivalent.
+    kind;
+
+    pub fn register_workers_names;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{register_signal_name, unregister_signal_name, Signal, SignalKind};
+
+    #[test]
+    fn test_workers_limits() {
+        let _guard = lock();
+        for limit in WorkersLimit::ALL {
+            assert_eq!(WorkersLimit::from_code(limit.code()), Some(limit));
+            assert_eq!(WorkersLimit::try_from(limit.signal()), Ok(limit));
+            assert_eq!(limit.signal().severity(), Some(limit.severity()));
+            assert_eq!(limit.signal().kind(), SignalKind::Custom);
+        }
+        assert_eq!(
+            SignalKind::from(WorkersLimit::CpuLimitApproaching),
+            SignalKind::Cancel
+        );
+        assert_eq!(WorkersLimit::try_from(Signal::CANCEL), Err(Signal::CANCEL));
+
+        assert!(register_workers_names());
+        assert_eq!(WorkersLimit::Eviction.signal().name(), Some("eviction"));
+        for limit in WorkersLimit::ALL {
+            unregister_signal_name(limit.base_code());
+        }
+    }
+
+    #[test]
+    fn test_register_names_attempts_every_name() {
+        let _guard = lock();
+        // Fill the table, with the last limit already named
+        assert!(register_signal_name(
+            WorkersLimit::Eviction.base_code(),
+            "old"
+        ));
+        let fillers = (0x7000..)
+            .take_while(|&code| register_signal_name(code, "filler"))
+            .count() as u32;
+
+        assert!(!register_workers_names());
+        assert_eq!(WorkersLimit::Eviction.signal().name(), Some("eviction"));
+        assert_eq!(WorkersLimit::WallClockLimit.signal().name(), None);
+
+        for code in 0x7000..0x7000 + fillers {
+            unregister_signal_name(code);
+        }
+        unregister_signal_name(WorkersLimit::Eviction.base_code());
+    }
+}
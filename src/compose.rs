@@ -0,0 +1,294 @@
+//! Building one signal handler from smaller pieces.
+//!
+//! Only one handler is registered at a time, so applications with several
+//! concerns (logging, ignoring some codes, translating others) used to write
+//! the dispatch by hand. The combinators [`filtered`], [`chained`] and
+//! [`mapped`] compose [`ComposableHandler`]s instead.
+//!
+//! The combinators are `const fn`s over plain function pointers, so a
+//! composed handler can live in a `static` and be registered through a
+//! non-capturing closure:
+//!
+//! ```rust
+//! use wasm_signal_handler::{
+//!     chained, filtered, set_signal_handler, Chained, ComposableHandler, Filtered, Signal,
+//!     SignalHandler, SignalMask,
+//! };
+//!
+//! fn ignore(_signal: Signal) -> Result<(), Signal> {
+//!     Ok(())
+//! }
+//!
+//! // Swallow cancels, propagate everything else
+//! static HANDLER: Chained<Filtered<SignalHandler>, SignalHandler> = chained(
+//!     filtered(SignalMask::NONE.with(Signal::CANCEL), ignore as SignalHandler),
+//!     Err as SignalHandler,
+//! );
+//!
+//! set_signal_handler(|signal| HANDLER.handle(signal));
+//! ```
+//!
+//! With the `alloc` feature, boxed handlers compose too, and
+//! [`set_boxed_signal_handler`] registers handlers that capture state.
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, sync::Arc};
+#[cfg(feature = "alloc")]
+use core::cell::UnsafeCell;
+#[cfg(feature = "alloc")]
+use core::sync::atomic::AtomicBool;
+
+#[cfg(feature = "alloc")]
+use crate::{set_signal_handler, ACQUIRE, RELEASE};
+use crate::{Signal, SignalHandler, SignalMask};
+
+/// A signal handler that can be composed.
+///
+/// Implemented by [`SignalHandler`] function pointers, the combinator types
+/// and, with the `alloc` feature, boxed and shared handlers.
+pub trait ComposableHandler {
+    /// Handles `signal`, with the same contract as a [`SignalHandler`].
+    fn handle(&self, signal: Signal) -> Result<(), Signal>;
+}
+
+impl ComposableHandler for SignalHandler {
+    fn handle(&self, signal: Signal) -> Result<(), Signal> {
+        self(signal)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<H: ComposableHandler + ?Sized> ComposableHandler for Box<H> {
+    fn handle(&self, signal: Signal) -> Result<(), Signal> {
+        (**self).handle(signal)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<H: ComposableHandler + ?Sized> ComposableHandler for Arc<H> {
+    fn handle(&self, signal: Signal) -> Result<(), Signal> {
+        (**self).handle(signal)
+    }
+}
+
+/// A handler that only sees some codes; see [`filtered`].
+#[derive(Debug, Clone, Copy)]
+pub struct Filtered<H> {
+    mask: SignalMask,
+    inner: H,
+}
+
+/// Passes signals in `mask` to `inner`, and propagates the rest unhandled.
+///
+/// Matching follows [`SignalMask::contains`], so fatal codes always match.
+pub const fn filtered<H>(mask: SignalMask, inner: H) -> Filtered<H> {
+    Filtered { mask, inner }
+}
+
+impl<H: ComposableHandler> ComposableHandler for Filtered<H> {
+    fn handle(&self, signal: Signal) -> Result<(), Signal> {
+        if self.mask.contains(signal) {
+            self.inner.handle(signal)
+        } else {
+            Err(signal)
+        }
+    }
+}
+
+/// Two handlers tried in turn; see [`chained`].
+#[derive(Debug, Clone, Copy)]
+pub struct Chained<A, B> {
+    first: A,
+    second: B,
+}
+
+/// Passes signals to `first`, and whatever it propagates to `second`.
+///
+/// `second` sees the signal `first` returned, which may differ from the one
+/// it was given.
+pub const fn chained<A, B>(first: A, second: B) -> Chained<A, B> {
+    Chained { first, second }
+}
+
+impl<A: ComposableHandler, B: ComposableHandler> ComposableHandler for Chained<A, B> {
+    fn handle(&self, signal: Signal) -> Result<(), Signal> {
+        match self.first.handle(signal) {
+            Ok(()) => Ok(()),
+            Err(signal) => self.second.handle(signal),
+        }
+    }
+}
+
+/// A handler that sees translated signals; see [`mapped`].
+#[derive(Debug, Clone, Copy)]
+pub struct Mapped<H> {
+    map: fn(Signal) -> Signal,
+    inner: H,
+}
+
+/// Passes `map(signal)` to `inner`.
+///
+/// The translation only affects what `inner` sees and propagates; the
+/// observed signal is still the one acknowledged to the host.
+pub const fn mapped<H>(map: fn(Signal) -> Signal, inner: H) -> Mapped<H> {
+    Mapped { map, inner }
+}
+
+impl<H: ComposableHandler> ComposableHandler for Mapped<H> {
+    fn handle(&self, signal: Signal) -> Result<(), Signal> {
+        self.inner.handle((self.map)(signal))
+    }
+}
+
+/// A registered boxed handler.
+#[cfg(feature = "alloc")]
+type Boxed = Arc<dyn ComposableHandler + Send + Sync>;
+
+/// The handler registered by [`set_boxed_signal_handler`], guarded by
+/// [`LOCK`].
+///
+/// A trait object is two words, so it cannot live in a single atomic like
+/// the function pointer slots.
+#[cfg(feature = "alloc")]
+struct Slot(UnsafeCell<Option<Boxed>>);
+
+// SAFETY: The slot is only accessed under LOCK, and handlers are `Send + Sync`
+#[cfg(feature = "alloc")]
+unsafe impl Sync for Slot {}
+
+#[cfg(feature = "alloc")]
+static SLOT: Slot = Slot(UnsafeCell::new(None));
+#[cfg(feature = "alloc")]
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "alloc")]
+fn with_slot<R>(f: impl FnOnce(&mut Option<Boxed>) -> R) -> R {
+    while LOCK
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // SAFETY: LOCK grants exclusive access to the slot
+    let result = f(unsafe { &mut *SLOT.0.get() });
+    LOCK.store(false, RELEASE);
+    result
+}
+
+/// Calls the boxed handler; registered in the handler slot in its place.
+#[cfg(feature = "alloc")]
+fn call_boxed(signal: Signal) -> Result<(), Signal> {
+    // Clone it out so the handler runs without holding the lock
+    match with_slot(|slot| slot.clone()) {
+        Some(handler) => handler.handle(signal),
+        None => Err(signal),
+    }
+}
+
+/// Registers a handler that may carry state, such as a composition built
+/// at runtime.
+///
+/// The handler slot then holds a trampoline to `handler`, which is what
+/// [`get_signal_handler`](crate::get_signal_handler) returns. `handler` is
+/// kept alive until the next call to this function, even if another handler
+/// replaces the trampoline meanwhile.
+///
+/// # Returns
+///
+/// Returns the previously registered handler, if any.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{chained, set_boxed_signal_handler, Signal, SignalHandler};
+///
+/// struct Log(&'static str);
+///
+/// impl wasm_signal_handler::ComposableHandler for Log {
+///     fn handle(&self, signal: Signal) -> Result<(), Signal> {
+///         // log::warn!("{}: {}", self.0, signal);
+///         Err(signal)
+///     }
+/// }
+///
+/// set_boxed_signal_handler(Box::new(chained(Log("request 42"), Err as SignalHandler)));
+/// ```
+#[cfg(feature = "alloc")]
+pub fn set_boxed_signal_handler(
+    handler: Box<dyn ComposableHandler + Send + Sync>,
+) -> Option<SignalHandler> {
+    let old = with_slot(|slot| slot.replace(Arc::from(handler)));
+    let previous = set_signal_handler(call_boxed);
+    // Drop the old handler outside the lock
+    drop(old);
+    previous
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal_handler, set_signal, set_signal_handler, try_check_signal};
+
+    fn ignore(_signal: Signal) -> Result<(), Signal> {
+        Ok(())
+    }
+
+    fn escalate(signal: Signal) -> Signal {
+        if signal == Signal::CANCEL {
+            Signal::TERMINATE
+        } else {
+            signal
+        }
+    }
+
+    static HANDLER: Chained<Filtered<SignalHandler>, Mapped<SignalHandler>> = chained(
+        filtered(
+            SignalMask::NONE.with(Signal::CHECKPOINT),
+            ignore as SignalHandler,
+        ),
+        mapped(escalate, Err as SignalHandler),
+    );
+
+    #[test]
+    fn test_combinators() {
+        let _guard = lock();
+        set_signal_handler(|signal| HANDLER.handle(signal));
+
+        set_signal(Signal::CHECKPOINT.code());
+        assert!(try_check_signal().is_ok());
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        set_signal(7);
+        assert_eq!(try_check_signal().map_err(Signal::code), Err(7));
+
+        clear_signal_handler();
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_boxed_handler() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        struct Count(&'static AtomicU32);
+
+        impl ComposableHandler for Count {
+            fn handle(&self, signal: Signal) -> Result<(), Signal> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Err(signal)
+            }
+        }
+
+        static SEEN: AtomicU32 = AtomicU32::new(0);
+
+        let _guard = lock();
+        let handler = chained(Box::new(Count(&SEEN)), ignore as SignalHandler);
+        set_boxed_signal_handler(Box::new(handler));
+
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(SEEN.load(Ordering::SeqCst), 1);
+
+        clear_signal_handler();
+    }
+}
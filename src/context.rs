@@ -0,0 +1,196 @@
+//! Checks against a snapshot of the configuration.
+//!
+//! [`try_check_signal`](crate::try_check_signal) reads the configuration on
+//! every check: the [`ARMED`] flags on the fast path, and the handler, the
+//! [signal mask](crate::set_signal_mask) and
+//! [latch mode](crate::set_latch_mode) on the slow one. A [`SignalContext`]
+//! reads them once, when it is created, so its
+//! [`check`](SignalContext::check) only loads the signal word. For loops
+//! doing hundreds of millions of checks, that is one load less per check.
+//!
+//! The price is that configuration changed after the snapshot, such as a new
+//! handler, a signal mask or a [deadline](crate::set_deadline), is not seen
+//! until [`refresh`](SignalContext::refresh) takes a new one. Keep a context for
+//! the duration of a loop rather than for the life of the program.
+
+use core::sync::atomic::Ordering;
+
+use crate::{
+    check_armed, get_signal_handler, latch_mode, propagate, signal_mask, storage, CheckConfig,
+    Signal, SignalHandler, SignalMask, ACQUIRE, ARMED,
+};
+
+/// A snapshot of the signal configuration, for checks that only load the
+/// signal word.
+///
+/// It holds the registered handler, the signal mask, latch mode and the
+/// guest-side work (deadlines, masks, counting and the like) in force when it
+/// was created. With no such work, a check with no signal pending costs one
+/// relaxed load and a branch, and a pending signal goes straight to the
+/// snapshotted handler. With any, every check takes the slow path, as
+/// [`try_check_signal`](crate::try_check_signal) would, still with the
+/// snapshotted configuration.
+///
+/// A handler replaced after the snapshot may still be called by the context,
+/// even after [`synchronize_signal_handler`](crate::synchronize_signal_handler)
+/// returns.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{Signal, SignalContext};
+///
+/// fn sum(values: &[u64]) -> Result<u64, Signal> {
+///     let context = SignalContext::new();
+///     let mut total = 0;
+///     for value in values {
+///         context.check()?;
+///         total += value;
+///     }
+///     Ok(total)
+/// }
+/// # assert_eq!(sum(&[1, 2, 3]), Ok(6));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SignalContext {
+    handler: Option<SignalHandler>,
+    armed: u32,
+    mask: SignalMask,
+    latch_mode: bool,
+}
+
+impl SignalContext {
+    /// Snapshots the current configuration.
+    pub fn new() -> SignalContext {
+        SignalContext {
+            handler: get_signal_handler(),
+            armed: ARMED.load(ACQUIRE),
+            mask: signal_mask(),
+            latch_mode: latch_mode(),
+        }
+    }
+
+    /// Replaces the snapshot with the current configuration.
+    pub fn refresh(&mut self) {
+        *self = SignalContext::new();
+    }
+
+    /// Returns the handler in the snapshot.
+    pub fn handler(&self) -> Option<SignalHandler> {
+        self.handler
+    }
+
+    /// Returns `true` if checks with no signal pending stay on the fast
+    /// path, because no guest-side work was armed at the snapshot.
+    pub fn is_fast(&self) -> bool {
+        self.armed == 0
+    }
+
+    /// Checks for an active signal, like
+    /// [`try_check_signal`](crate::try_check_signal) with the snapshotted
+    /// configuration.
+    #[inline(always)]
+    #[track_caller]
+    pub fn check(&self) -> Result<(), Signal> {
+        let code = storage::load(Ordering::Relaxed);
+        if code == 0 && self.armed == 0 {
+            return Ok(());
+        }
+        core::hint::cold_path();
+        self.slow_check(code)
+    }
+
+    #[cold]
+    #[inline(never)]
+    #[track_caller]
+    fn slow_check(&self, code: u32) -> Result<(), Signal> {
+        if code != 0 {
+            storage::validate(code);
+        }
+        propagate(check_armed(code, *self))
+    }
+}
+
+impl CheckConfig for SignalContext {
+    #[inline(always)]
+    fn armed(self) -> u32 {
+        self.armed
+    }
+
+    #[inline(always)]
+    fn handler(self) -> Option<SignalHandler> {
+        self.handler
+    }
+
+    #[inline(always)]
+    fn signal_mask(self) -> SignalMask {
+        self.mask
+    }
+
+    #[inline(always)]
+    fn latch_mode(self) -> bool {
+        self.latch_mode
+    }
+}
+
+impl Default for SignalContext {
+    fn default() -> SignalContext {
+        SignalContext::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_deadline, clear_signal, clear_signal_handler, latched_signal, set_deadline,
+        set_latch_mode, set_signal, set_signal_handler, set_signal_mask, SignalMask,
+    };
+    use core::time::Duration;
+
+    fn handled(_signal: Signal) -> Result<(), Signal> {
+        Ok(())
+    }
+
+    #[test]
+    fn test_signal_context() {
+        let _guard = lock();
+        clear_signal();
+        set_signal_handler(handled);
+        let mut context = SignalContext::new();
+        assert_eq!(context.is_fast(), crate::ARMED_INITIAL == 0);
+        assert_eq!(context.check(), Ok(()));
+
+        // The snapshotted handler still runs after it is replaced
+        clear_signal_handler();
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(context.check(), Ok(()));
+        assert!(crate::peek_signal().is_none());
+
+        // So do the snapshotted signal mask and latch mode
+        set_signal_handler(handled);
+        context.refresh();
+        clear_signal_handler();
+        set_signal_mask(SignalMask::NONE);
+        set_latch_mode(true);
+        set_signal(Signal::TERMINATE.code());
+        assert_eq!(context.check(), Ok(()));
+        assert!(latched_signal().is_none());
+        set_latch_mode(false);
+        set_signal_mask(SignalMask::ALL);
+
+        context.refresh();
+        assert!(context.handler().is_none());
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(context.check(), Err(Signal::CANCEL));
+
+        // Armed work takes the full slow path
+        if set_deadline(Duration::from_secs(60)).is_ok() {
+            context.refresh();
+            assert!(!context.is_fast());
+            assert_eq!(context.check(), Ok(()));
+            clear_deadline();
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Guest-side deadlines enforced by signal checks.
+//!
+//! [`set_deadline`] arms a self-imposed time limit. Every subsequent
+//! [`try_check_signal`](crate::try_check_signal) compares the [clock](crate::now_ms)
+//! against it and, once it has passed, synthesizes a [`Signal::DEADLINE`]
+//! that goes through the same handler and propagation path as host signals.
+//! The deadline is one-shot: it is cleared when the signal is synthesized.
+
+use core::sync::atomic::AtomicU64;
+use core::time::Duration;
+
+use crate::{arm, disarm, now_ms, Signal, ACQUIRE, ARMED_DEADLINE, RELEASE};
+
+/// The deadline in clock milliseconds, or `0` if none is set.
+static DEADLINE_AT: AtomicU64 = AtomicU64::new(0);
+
+/// Error returned by [`set_deadline`] when no clock is available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoClock;
+
+impl core::fmt::Display for NoClock {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("no clock available; register one with set_clock")
+    }
+}
+
+impl core::error::Error for NoClock {}
+
+/// Sets a deadline `timeout` from now, replacing any existing deadline.
+///
+/// # Errors
+///
+/// Returns [`NoClock`] if no clock is registered and the platform has no
+/// default clock.
+///
+/// # Example
+///
+/// ```rust
+/// use core::time::Duration;
+/// use wasm_signal_handler::{set_deadline, try_check_signal, Signal};
+///
+/// fn render() -> Result<(), Signal> {
+///     set_deadline(Duration::from_millis(50)).ok();
+///     for _ in 0..1000 {
+///         try_check_signal()?; // Err(Signal::DEADLINE) after 50ms
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn set_deadline(timeout: Duration) -> Result<(), NoClock> {
+    let now = now_ms().ok_or(NoClock)?;
+    let millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+    // Never store 0, which means "no deadline"
+    let at = now.saturating_add(millis).max(1);
+    DEADLINE_AT.store(at, RELEASE);
+    arm(ARMED_DEADLINE);
+    Ok(())
+}
+
+/// Clears the deadline, if any.
+pub fn clear_deadline() {
+    disarm(ARMED_DEADLINE);
+    DEADLINE_AT.store(0, RELEASE);
+}
+
+/// Returns the time remaining until the deadline.
+///
+/// Returns `None` if no deadline is set or no clock is available, and
+/// `Some(Duration::ZERO)` if the deadline has passed but not been observed
+/// by a check yet.
+pub fn deadline_remaining() -> Option<Duration> {
+    let at = DEADLINE_AT.load(ACQUIRE);
+    if at == 0 {
+        return None;
+    }
+    let now = now_ms()?;
+    Some(Duration::from_millis(at.saturating_sub(now)))
+}
+
+/// Called from the check slow path while a deadline is armed.
+///
+/// Returns the synthesized signal once the deadline has passed, clearing it.
+pub(crate) fn poll() -> Option<Signal> {
+    let at = DEADLINE_AT.load(ACQUIRE);
+    if at == 0 || now_ms()? < at {
+        return None;
+    }
+    clear_deadline();
+    Some(Signal::DEADLINE)
+}
+
+/// The deadline, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(u64);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(DEADLINE_AT.load(ACQUIRE))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    DEADLINE_AT.store(saved.0, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_clock, clear_signal, clear_signal_handler, set_clock, try_check_signal};
+    use core::sync::atomic::Ordering;
+
+    static NOW: AtomicU64 = AtomicU64::new(1_000);
+
+    fn fake_clock() -> u64 {
+        NOW.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_deadline_synthesizes_signal() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        set_clock(fake_clock);
+
+        set_deadline(Duration::from_millis(10)).unwrap();
+        assert_eq!(deadline_remaining(), Some(Duration::from_millis(10)));
+
+        NOW.fetch_add(9, Ordering::SeqCst);
+        assert!(try_check_signal().is_ok());
+
+        NOW.fetch_add(1, Ordering::SeqCst);
+        assert_eq!(try_check_signal(), Err(Signal::DEADLINE));
+
+        // One-shot: cleared once observed
+        assert!(deadline_remaining().is_none());
+        assert!(try_check_signal().is_ok());
+
+        clear_clock();
+    }
+
+    #[test]
+    fn test_host_signal_takes_precedence() {
+        let _guard = lock();
+        clear_signal_handler();
+        set_clock(fake_clock);
+
+        set_deadline(Duration::ZERO).unwrap();
+        crate::set_signal(Signal::TERMINATE.code());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        assert_eq!(try_check_signal(), Err(Signal::DEADLINE));
+
+        clear_clock();
+    }
+
+    #[test]
+    fn test_clear_deadline() {
+        let _guard = lock();
+        clear_signal();
+        set_clock(fake_clock);
+
+        set_deadline(Duration::ZERO).unwrap();
+        clear_deadline();
+        assert!(try_check_signal().is_ok());
+
+        clear_clock();
+    }
+}
@@ -0,0 +1,415 @@
+//! A snapshot of the signal subsystem, for error reports and debugging.
+//!
+//! When an isolate stops responding to cancellation, the useful questions
+//! are all about guest-side state: is a signal pending, is a handler
+//! installed, are signals masked or latched, is the guest still making
+//! progress? [`diagnostics`] answers them in one [`Diagnostics`] value that
+//! applications can attach to their own error reports.
+//!
+//! With the `diagnostics-json` feature, [`diagnostics_json`] renders the same
+//! snapshot as JSON, and the exported `__wasm_signal_dump_diagnostics` lets
+//! the host fetch it without any guest cooperation beyond being callable.
+//! With `std`, [`metrics_prometheus`] renders its counters for a metrics
+//! endpoint.
+
+use core::time::Duration;
+
+use crate::{
+    checks_since_last_signal, deadline_remaining, drop_ignored, get_grace_period,
+    get_panic_formatter, get_signal_handler, grace_remaining, has_checkpointer, heartbeat_count,
+    last_ack, latch_mode, latched_signal, listeners, overwrite_policy, peek_signal, queued,
+    recheck_depth, shutdown_hooks_ran, signal_history, signal_mask, signals_masked, GracePeriod,
+    OverwritePolicy, Signal, SignalMask, SIGNAL_HISTORY_LEN,
+};
+
+/// A snapshot of the signal subsystem, returned by [`diagnostics`].
+///
+/// The fields are read one at a time, so a snapshot taken while another
+/// thread raises or handles signals may mix state from before and after.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Diagnostics {
+    /// The pending signal.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "signal_info::serialize"))]
+    pub current_signal: Option<Signal>,
+    /// The last signal acknowledged by a check.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "signal_info::serialize"))]
+    pub last_ack: Option<Signal>,
+    /// The last signals acknowledged by checks, most recent first, padded
+    /// with `None` (see [`signal_history`]).
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "signal_info::serialize_history")
+    )]
+    pub signal_history: [Option<Signal>; SIGNAL_HISTORY_LEN],
+    /// Whether a signal handler is registered.
+    pub handler_installed: bool,
+    /// Whether a panic formatter is registered.
+    pub panic_formatter_installed: bool,
+    /// The number of registered listeners.
+    pub listeners: usize,
+    /// Whether a checkpointer is registered.
+    pub checkpointer_installed: bool,
+    /// Whether signals are masked by a [`SignalMaskGuard`](crate::SignalMaskGuard).
+    pub masked: bool,
+    /// The codes checks respond to (see [`set_signal_mask`](crate::set_signal_mask)).
+    pub mask: SignalMask,
+    /// Whether ignored codes are dropped.
+    pub drop_ignored: bool,
+    /// Whether latch mode is enabled.
+    pub latch_mode: bool,
+    /// The latched signal.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "signal_info::serialize"))]
+    pub latched: Option<Signal>,
+    /// The overwrite policy.
+    pub overwrite_policy: OverwritePolicy,
+    /// The number of signals queued behind the pending one.
+    pub pending_queue_len: usize,
+    /// Checks since the last acknowledged signal, if
+    /// [counting](crate::set_count_checks) is enabled.
+    pub checks_since_last_signal: Option<u32>,
+    /// The post-handler recheck depth.
+    pub recheck_depth: u32,
+    /// Time left until the deadline.
+    pub deadline_remaining: Option<Duration>,
+    /// The configured grace period.
+    pub grace_period: Option<GracePeriod>,
+    /// What is left of an open grace window.
+    pub grace_remaining: Option<GracePeriod>,
+    /// Whether the shutdown hooks have run.
+    pub shutdown_hooks_ran: bool,
+    /// The heartbeat counter.
+    pub heartbeat: u32,
+}
+
+/// Takes a snapshot of the signal subsystem.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::diagnostics;
+///
+/// let snapshot = diagnostics();
+/// if let Some(signal) = snapshot.current_signal {
+///     // Include it in the error report
+///     let _ = (signal, snapshot.handler_installed);
+/// }
+/// ```
+pub fn diagnostics() -> Diagnostics {
+    let mut history = [None; SIGNAL_HISTORY_LEN];
+    for (slot, signal) in history.iter_mut().zip(signal_history()) {
+        *slot = Some(signal);
+    }
+    Diagnostics {
+        current_signal: peek_signal(),
+        last_ack: last_ack(),
+        signal_history: history,
+        handler_installed: get_signal_handler().is_some(),
+        panic_formatter_installed: get_panic_formatter().is_some(),
+        listeners: listeners::count(),
+        checkpointer_installed: has_checkpointer(),
+        masked: signals_masked(),
+        mask: signal_mask(),
+        drop_ignored: drop_ignored(),
+        latch_mode: latch_mode(),
+        latched: latched_signal(),
+        overwrite_policy: overwrite_policy(),
+        pending_queue_len: queued(),
+        checks_since_last_signal: checks_since_last_signal(),
+        recheck_depth: recheck_depth(),
+        deadline_remaining: deadline_remaining(),
+        grace_period: get_grace_period(),
+        grace_remaining: grace_remaining(),
+        shutdown_hooks_ran: shutdown_hooks_ran(),
+        heartbeat: heartbeat_count(),
+    }
+}
+
+/// Serializes signals in [`Diagnostics`] with their decoded fields, which
+/// are more useful to a human reading a dump than the bare code.
+#[cfg(feature = "serde")]
+mod signal_info {
+    use crate::{Severity, Signal, SignalKind};
+
+    #[derive(serde::Serialize)]
+    struct SignalInfo {
+        code: u32,
+        kind: SignalKind,
+        name: Option<&'static str>,
+        severity: Option<Severity>,
+        source: Option<u32>,
+    }
+
+    pub(super) fn serialize<S: serde::Serializer>(
+        signal: &Option<Signal>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let info = signal.map(|signal| SignalInfo {
+            code: signal.code(),
+            kind: signal.kind(),
+            name: signal.name(),
+            severity: signal.severity(),
+            source: signal.source(),
+        });
+        serde::Serialize::serialize(&info, serializer)
+    }
+
+    /// Serializes the acknowledged history as a list, without the padding.
+    pub(super) fn serialize_history<S: serde::Serializer>(
+        history: &[Option<Signal>; crate::SIGNAL_HISTORY_LEN],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        struct Entry(Signal);
+
+        impl serde::Serialize for Entry {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize(&Some(self.0), serializer)
+            }
+        }
+
+        serializer.collect_seq(history.iter().map_while(|signal| signal.map(Entry)))
+    }
+}
+
+/// Renders a [`diagnostics`] snapshot as JSON.
+///
+/// Signals are rendered as objects with their code, kind, name, severity and
+/// source. The exact shape is meant for humans and may gain fields between
+/// releases.
+///
+/// # Example
+///
+/// ```rust
+/// let json = wasm_signal_handler::diagnostics_json();
+/// assert!(json.starts_with('{'));
+/// ```
+#[cfg(feature = "diagnostics-json")]
+pub fn diagnostics_json() -> std::string::String {
+    // Serializing plain data into a string cannot fail
+    serde_json::to_string(&diagnostics()).unwrap_or_default()
+}
+
+/// The dump rendered by a call whose buffer was too small, kept for the next
+/// call.
+#[cfg(feature = "diagnostics-json")]
+static DUMP: std::sync::Mutex<Option<std::string::String>> = std::sync::Mutex::new(None);
+
+/// Writes [`diagnostics_json`] into the guest buffer at `ptr`, for hosts.
+///
+/// Returns the length of the JSON in bytes. If it exceeds `len`, nothing is
+/// written and the rendered dump is kept, so hosts call it with `len` `0`
+/// first to size their buffer, and the next call writes the snapshot that
+/// was sized rather than a newer one. If the length returned is still larger
+/// than `len` (another caller took the kept dump), retry with the new length.
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes of `len` bytes.
+#[cfg(feature = "diagnostics-json")]
+#[no_mangle]
+pub unsafe extern "C" fn __wasm_signal_dump_diagnostics(ptr: *mut u8, len: usize) -> usize {
+    let mut kept = DUMP
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let json = kept.take().unwrap_or_else(diagnostics_json);
+    let needed = json.len();
+    if needed <= len {
+        // SAFETY: The caller guarantees `ptr` is valid for `len` bytes
+        unsafe { core::ptr::copy_nonoverlapping(json.as_ptr(), ptr, needed) };
+    } else {
+        *kept = Some(json);
+    }
+    needed
+}
+
+/// Appends the counters and gauges of a [`diagnostics`] snapshot to `out`,
+/// in the Prometheus text exposition format.
+///
+/// Metric names are prefixed with `wasm_signal_`. Signals are reported by
+/// code, with `0` for none. `wasm_signal_checks_since_last_signal` is only
+/// present while [check counting](crate::set_count_checks) is enabled, and
+/// `wasm_signal_deadline_remaining_seconds` only while a deadline is set.
+///
+/// # Example
+///
+/// ```rust
+/// let mut body = String::new();
+/// wasm_signal_handler::metrics_prometheus(&mut body);
+/// assert!(body.contains("# TYPE wasm_signal_heartbeat_total counter"));
+/// ```
+#[cfg(feature = "std")]
+pub fn metrics_prometheus(out: &mut std::string::String) {
+    let snapshot = diagnostics();
+    let code = |signal: Option<Signal>| signal.map_or(0, Signal::code);
+
+    let mut metric = |name: &str, kind: &str, help: &str, value: &dyn core::fmt::Display| {
+        use core::fmt::Write;
+        // Writing to a String cannot fail
+        let _ = write!(
+            out,
+            "# HELP wasm_signal_{name} {help}\n\
+             # TYPE wasm_signal_{name} {kind}\n\
+             wasm_signal_{name} {value}\n"
+        );
+    };
+    metric(
+        "heartbeat_total",
+        "counter",
+        "Heartbeats signalled by the guest.",
+        &snapshot.heartbeat,
+    );
+    if let Some(checks) = snapshot.checks_since_last_signal {
+        metric(
+            "checks_since_last_signal",
+            "gauge",
+            "Signal checks since the last acknowledged signal.",
+            &checks,
+        );
+    }
+    metric(
+        "pending",
+        "gauge",
+        "Code of the pending signal, or 0.",
+        &code(snapshot.current_signal),
+    );
+    metric(
+        "last_ack",
+        "gauge",
+        "Code of the last acknowledged signal, or 0.",
+        &code(snapshot.last_ack),
+    );
+    metric(
+        "latched",
+        "gauge",
+        "Code of the latched signal, or 0.",
+        &code(snapshot.latched),
+    );
+    metric(
+        "queue_length",
+        "gauge",
+        "Signals queued behind the pending one.",
+        &snapshot.pending_queue_len,
+    );
+    metric(
+        "masked",
+        "gauge",
+        "Whether signals are masked.",
+        &u8::from(snapshot.masked),
+    );
+    metric(
+        "handler_installed",
+        "gauge",
+        "Whether a signal handler is registered.",
+        &u8::from(snapshot.handler_installed),
+    );
+    metric(
+        "grace_open",
+        "gauge",
+        "Whether a grace window is open.",
+        &u8::from(snapshot.grace_remaining.is_some()),
+    );
+    if let Some(remaining) = snapshot.deadline_remaining {
+        metric(
+            "deadline_remaining_seconds",
+            "gauge",
+            "Time left until the deadline.",
+            &remaining.as_secs_f64(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        check_signal, clear_signal, clear_signal_handler, set_count_checks, set_signal,
+        set_signal_handler,
+    };
+
+    #[test]
+    fn test_diagnostics() {
+        let _guard = lock();
+        clear_signal();
+        set_signal_handler(|_| Ok(()));
+        set_count_checks(true);
+
+        check_signal();
+        check_signal();
+        set_signal(Signal::CANCEL.code());
+        let snapshot = diagnostics();
+        assert_eq!(snapshot.current_signal, Some(Signal::CANCEL));
+        assert!(snapshot.handler_installed);
+        assert_eq!(snapshot.checks_since_last_signal, Some(2));
+
+        check_signal();
+        let snapshot = diagnostics();
+        assert_eq!(snapshot.current_signal, None);
+        assert_eq!(snapshot.last_ack, Some(Signal::CANCEL));
+        assert_eq!(snapshot.signal_history[0], Some(Signal::CANCEL));
+        assert_eq!(snapshot.checks_since_last_signal, Some(0));
+
+        set_count_checks(false);
+        assert_eq!(diagnostics().checks_since_last_signal, None);
+        clear_signal_handler();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_metrics_prometheus() {
+        let _guard = lock();
+        clear_signal();
+        set_signal(Signal::TERMINATE.code());
+
+        let mut body = std::string::String::from("# other metrics\n");
+        metrics_prometheus(&mut body);
+        assert!(body.starts_with("# other metrics\n# HELP wasm_signal_heartbeat_total "));
+        assert!(body.contains("\n# TYPE wasm_signal_pending gauge\nwasm_signal_pending 1\n"));
+        assert!(!body.contains("checks_since_last_signal"));
+        assert!(body.ends_with('\n'));
+
+        clear_signal();
+    }
+
+    #[cfg(feature = "diagnostics-json")]
+    #[test]
+    fn test_diagnostics_json() {
+        let _guard = lock();
+        clear_signal();
+        set_signal_handler(Err);
+        set_signal(Signal::CANCEL.code());
+
+        let dump: serde_json::Value = serde_json::from_str(&diagnostics_json()).unwrap();
+        assert_eq!(dump["current_signal"]["code"], 2);
+        assert_eq!(dump["current_signal"]["kind"], "cancel");
+        assert_eq!(dump["handler_installed"], true);
+        assert_eq!(
+            dump["overwrite_policy"],
+            serde_json::json!(overwrite_policy())
+        );
+        let history = dump["signal_history"].as_array().unwrap();
+        assert_eq!(history.len(), signal_history().count());
+
+        let json = diagnostics_json();
+        // SAFETY: Nothing is written with a length of 0
+        let needed = unsafe { __wasm_signal_dump_diagnostics(core::ptr::null_mut(), 0) };
+        assert_eq!(needed, json.len());
+
+        // The snapshot that was sized is written, even if the state changed
+        clear_signal();
+        let mut buf = std::vec![0u8; needed];
+        // SAFETY: `buf` has room for `buf.len()` bytes
+        let written = unsafe { __wasm_signal_dump_diagnostics(buf.as_mut_ptr(), buf.len()) };
+        assert_eq!(&buf[..written], json.as_bytes());
+
+        // Then a fresh one
+        // SAFETY: `buf` still has room for `buf.len()` bytes, and a longer
+        // dump would be kept rather than written
+        let written = unsafe { __wasm_signal_dump_diagnostics(buf.as_mut_ptr(), buf.len()) };
+        assert_ne!(&buf[..written], json.as_bytes());
+
+        clear_signal();
+        clear_signal_handler();
+    }
+}
@@ -0,0 +1,124 @@
+//! The guest's disposition towards signals, published for the host.
+//!
+//! Before sending a soft signal, a host may want to know whether anything
+//! will honor it: without a handler, a cancel propagates out of the next
+//! check and ends the call, and a guest that traps on it is better
+//! terminated straight away. The disposition word answers that. It holds
+//! the `DISPOSITION_*` flags and is exported, like the status words, as
+//! `WASM_SIGNAL_DISPOSITION_ADDR`.
+//!
+//! How `check_signal` ends execution is fixed at build time, so exactly one
+//! of [`DISPOSITION_PANICS`], [`DISPOSITION_TRAPS`] and
+//! [`DISPOSITION_THROWS`] is always set. The other flags are kept up to date
+//! as handlers are registered and latch mode changes.
+
+use core::sync::atomic::AtomicU32;
+
+use crate::{get_signal_handler, latch_mode, latched_signal, namespaced, ACQUIRE, RELEASE};
+
+/// Set while a signal handler, global or namespaced, is registered.
+pub const DISPOSITION_HANDLER: u32 = 1 << 0;
+
+/// Set when a signal propagated out of `check_signal` panics.
+pub const DISPOSITION_PANICS: u32 = 1 << 1;
+
+/// Set when a signal propagated out of `check_signal` traps (the `trap`
+/// feature on wasm32).
+pub const DISPOSITION_TRAPS: u32 = 1 << 2;
+
+/// Set when a signal propagated out of `check_signal` throws a wasm
+/// exception (the `throw` feature with exception handling).
+pub const DISPOSITION_THROWS: u32 = 1 << 3;
+
+/// Set while latched mode is enabled (see
+/// [`set_latch_mode`](crate::set_latch_mode)).
+pub const DISPOSITION_LATCH_MODE: u32 = 1 << 4;
+
+/// Set while a signal is latched, so every check fails until the guest
+/// calls [`unlatch`](crate::unlatch).
+pub const DISPOSITION_LATCHED: u32 = 1 << 5;
+
+/// How this build ends execution for a propagated signal.
+const MODE: u32 = if cfg!(all(
+    feature = "throw",
+    target_arch = "wasm32",
+    target_feature = "exception-handling"
+)) {
+    DISPOSITION_THROWS
+} else if cfg!(all(feature = "trap", target_arch = "wasm32")) {
+    DISPOSITION_TRAPS
+} else {
+    DISPOSITION_PANICS
+};
+
+static DISPOSITION: AtomicU32 = AtomicU32::new(MODE);
+
+/// The exported address of the disposition word.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_DISPOSITION_ADDR: &AtomicU32 = &DISPOSITION;
+
+/// Returns the `DISPOSITION_*` flags currently published to the host.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{disposition, set_signal_handler, DISPOSITION_HANDLER};
+///
+/// set_signal_handler(|_| Ok(()));
+/// assert_ne!(disposition() & DISPOSITION_HANDLER, 0);
+/// # wasm_signal_handler::clear_signal_handler();
+/// ```
+pub fn disposition() -> u32 {
+    DISPOSITION.load(ACQUIRE)
+}
+
+/// Recomputes the disposition word after a handler or latch change.
+pub(crate) fn refresh() {
+    let mut flags = MODE;
+    if get_signal_handler().is_some() || namespaced::any() {
+        flags |= DISPOSITION_HANDLER;
+    }
+    if latch_mode() {
+        flags |= DISPOSITION_LATCH_MODE;
+    }
+    if latched_signal().is_some() {
+        flags |= DISPOSITION_LATCHED;
+    }
+    DISPOSITION.store(flags, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, set_latch_mode, set_signal, set_signal_handler,
+        try_check_signal, unlatch, Signal,
+    };
+
+    #[test]
+    fn test_disposition_follows_configuration() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        assert_eq!(disposition(), DISPOSITION_PANICS);
+
+        set_signal_handler(|_| Ok(()));
+        set_latch_mode(true);
+        assert_eq!(
+            disposition(),
+            DISPOSITION_PANICS | DISPOSITION_HANDLER | DISPOSITION_LATCH_MODE
+        );
+
+        set_signal(Signal::TERMINATE.code());
+        assert!(try_check_signal().is_err());
+        assert_ne!(disposition() & DISPOSITION_LATCHED, 0);
+
+        unlatch();
+        set_latch_mode(false);
+        clear_signal_handler();
+        clear_signal();
+        assert_eq!(disposition(), DISPOSITION_PANICS);
+    }
+}
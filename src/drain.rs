@@ -0,0 +1,148 @@
+//! Batch dispatch of pending signals.
+
+use crate::{
+    admit, drop_ignored, handle_signal, latched_signal, signals_masked, storage, Signal, SignalSet,
+    ACQUIRE,
+};
+
+/// Upper bound on dispatches per [`drain_signals`] call, so a handler that
+/// keeps raising signals cannot livelock the caller.
+pub const MAX_DRAIN: u32 = 32;
+
+/// The outcome of [`drain_signals`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DrainSummary {
+    /// Signals passed to the handler (or, without one, propagated directly).
+    pub dispatched: u32,
+    /// The codes dispatched.
+    pub signals: SignalSet,
+    /// Signals the handler cleared by returning `Ok(())`.
+    pub handled: u32,
+    /// The first signal that was propagated, if any.
+    pub propagated: Option<Signal>,
+    /// `true` if draining stopped at [`MAX_DRAIN`] with a signal still
+    /// pending.
+    pub truncated: bool,
+}
+
+impl DrainSummary {
+    /// Returns `Err` with the first propagated signal, if any.
+    pub fn into_result(self) -> Result<(), Signal> {
+        match self.propagated {
+            Some(signal) => Err(signal),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Dispatches every pending signal through the handler in one call.
+///
+/// Entry points use this to flush accumulated signals before starting a new
+/// unit of work. Unlike [`try_check_signal`](crate::try_check_signal), it
+/// does not stop at the first signal: signals raised by the handler itself
+/// are dispatched too, up to [`MAX_DRAIN`]. Guest-side work such as
+/// deadlines is left to regular checks.
+///
+/// While signals are masked nothing is dispatched, and draining stops at a
+/// code excluded by the [signal mask](crate::set_signal_mask). A latched
+/// signal is reported as propagated.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{drain_signals, Signal};
+///
+/// fn start_job() -> Result<(), Signal> {
+///     drain_signals().into_result()?;
+///     // run the job
+///     Ok(())
+/// }
+/// ```
+pub fn drain_signals() -> DrainSummary {
+    let mut summary = DrainSummary::default();
+    if signals_masked() {
+        return summary;
+    }
+
+    while let Some(signal) = Signal::new(storage::load(ACQUIRE)) {
+        if summary.dispatched == MAX_DRAIN {
+            summary.truncated = true;
+            break;
+        }
+        if !admit(signal) {
+            if drop_ignored() {
+                continue;
+            }
+            // Left pending for code that understands it
+            break;
+        }
+        summary.dispatched += 1;
+        summary.signals.insert(signal);
+        match handle_signal(signal) {
+            Ok(()) => summary.handled += 1,
+            Err(signal) => {
+                summary.propagated.get_or_insert(signal);
+            }
+        }
+    }
+
+    if summary.propagated.is_none() {
+        summary.propagated = latched_signal();
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, set_signal_handler};
+
+    #[test]
+    fn test_drain_dispatches_handler_raised_signals() {
+        let _guard = lock();
+        clear_signal();
+        set_signal_handler(|signal| match signal {
+            // Cancelling turns into a terminate request, which propagates
+            Signal::CANCEL => {
+                set_signal(Signal::TERMINATE.code());
+                Ok(())
+            }
+            _ => Err(signal),
+        });
+
+        assert_eq!(drain_signals(), DrainSummary::default());
+
+        set_signal(Signal::CANCEL.code());
+        let summary = drain_signals();
+        assert_eq!(summary.dispatched, 2);
+        assert_eq!(summary.handled, 1);
+        assert_eq!(
+            summary.signals,
+            SignalSet::from_iter([Signal::CANCEL, Signal::TERMINATE])
+        );
+        assert_eq!(summary.into_result(), Err(Signal::TERMINATE));
+        assert!(crate::peek_signal().is_none());
+
+        clear_signal_handler();
+    }
+
+    #[test]
+    fn test_drain_is_bounded() {
+        let _guard = lock();
+        clear_signal();
+        set_signal_handler(|signal| {
+            set_signal(signal.code());
+            Ok(())
+        });
+
+        set_signal(7);
+        let summary = drain_signals();
+        assert_eq!(summary.dispatched, MAX_DRAIN);
+        assert!(summary.truncated);
+
+        clear_signal_handler();
+        clear_signal();
+    }
+}
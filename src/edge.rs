@@ -0,0 +1,173 @@
+//! Running the same guest on WinterCG-style edge runtimes.
+//!
+//! Vercel Edge Functions, Deno Deploy, Netlify Edge Functions and similar
+//! runtimes share the WinterCG common API, but none of them writes the
+//! signal word for the guest. The `edge` feature gives them all the same two
+//! hooks, so one binary behaves the same on each:
+//!
+//! - Discovery. [`register_edge_instance`] adds the instance's raw exports
+//!   to an array under the well-known `Symbol.for(EDGE_GLOBAL_KEY)` property
+//!   of `globalThis`. Host glue such as `js/edge.js`, or any middleware,
+//!   finds every instance to signal there without being handed it.
+//! - Request abort. [`bind_abort_signal`] listens for `abort` on a request's
+//!   `AbortSignal`, which these runtimes fire when the client disconnects,
+//!   and sends a signal for it.
+//!
+//! [`setup_edge`] does both, and is the one call a request handler needs.
+//!
+//! Event listeners run between tasks, so as in the [page lifecycle
+//! bindings](crate::bind_page_lifecycle), the guest sees an abort once it
+//! next awaits.
+
+use js_sys::{Array, Function, Reflect, Symbol};
+use wasm_bindgen::prelude::*;
+
+use crate::{send_signal, Signal};
+
+/// The key of the `globalThis` property instances register under, as
+/// `Symbol.for(EDGE_GLOBAL_KEY)`.
+pub const EDGE_GLOBAL_KEY: &str = "wasm-signal-handler";
+
+#[wasm_bindgen]
+extern "C" {
+    type EventTarget;
+
+    #[wasm_bindgen(method, js_name = addEventListener)]
+    fn add_event_listener(this: &EventTarget, kind: &str, listener: &Function);
+
+    #[wasm_bindgen(method, js_name = removeEventListener)]
+    fn remove_event_listener(this: &EventTarget, kind: &str, listener: &Function);
+}
+
+/// Registers the instance for discovery under [`EDGE_GLOBAL_KEY`].
+///
+/// # Returns
+///
+/// Returns `false` if the instance was already registered, or the registry
+/// could not be created.
+pub fn register_edge_instance() -> bool {
+    let global = js_sys::global();
+    let key = Symbol::for_(EDGE_GLOBAL_KEY);
+    let registry = match Reflect::get(&global, &key) {
+        Ok(registry) if Array::is_array(&registry) => registry.unchecked_into::<Array>(),
+        _ => {
+            let registry = Array::new();
+            if !Reflect::set(&global, &key, &registry).unwrap_or(false) {
+                return false;
+            }
+            registry
+        }
+    };
+    let exports = wasm_bindgen::exports();
+    if registry.includes(&exports, 0) {
+        return false;
+    }
+    registry.push(&exports);
+    true
+}
+
+/// An `abort` listener, from [`bind_abort_signal`]. Dropping it removes the
+/// listener; use [`forget`](AbortBinding::forget) to keep it for the life of
+/// the request.
+#[must_use = "dropping the binding removes the listener"]
+pub struct AbortBinding {
+    target: Option<EventTarget>,
+    /// Taken when the listener is kept for good.
+    closure: Option<Closure<dyn FnMut()>>,
+}
+
+impl AbortBinding {
+    /// Keeps the listener bound for the life of the `AbortSignal`.
+    pub fn forget(mut self) {
+        if let Some(closure) = self.closure.take() {
+            closure.forget();
+        }
+    }
+
+    /// Returns `true` if a listener is bound, rather than the `AbortSignal`
+    /// having been missing or already aborted.
+    pub fn is_bound(&self) -> bool {
+        self.closure.is_some()
+    }
+}
+
+impl Drop for AbortBinding {
+    fn drop(&mut self) {
+        if let (Some(target), Some(closure)) = (&self.target, &self.closure) {
+            target.remove_event_listener("abort", closure.as_ref().unchecked_ref());
+        }
+    }
+}
+
+impl core::fmt::Debug for AbortBinding {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AbortBinding")
+            .field("bound", &self.is_bound())
+            .finish()
+    }
+}
+
+/// Sends `code` when `signal`, an `AbortSignal`, aborts.
+///
+/// An already aborted `signal` sends `code` right away. Anything that is not
+/// an `AbortSignal`, such as `undefined` where a runtime's `Request` has no
+/// `signal`, is left unbound.
+pub fn bind_abort_signal(signal: &JsValue, code: Signal) -> AbortBinding {
+    let unbound = AbortBinding {
+        target: None,
+        closure: None,
+    };
+    if !signal.is_object() {
+        return unbound;
+    }
+    if Reflect::get(signal, &JsValue::from_str("aborted")).is_ok_and(|aborted| aborted.is_truthy())
+    {
+        send_signal(code.code());
+        return unbound;
+    }
+    if !Reflect::get(signal, &JsValue::from_str("addEventListener"))
+        .is_ok_and(|add| add.is_function())
+    {
+        return unbound;
+    }
+    let target: EventTarget = signal.clone().unchecked_into();
+    let closure = Closure::<dyn FnMut()>::new(move || {
+        send_signal(code.code());
+    });
+    target.add_event_listener("abort", closure.as_ref().unchecked_ref());
+    AbortBinding {
+        target: Some(target),
+        closure: Some(closure),
+    }
+}
+
+/// Sets up a request on any WinterCG-style runtime: registers the instance
+/// for discovery and sends [`Signal::CANCEL`] if `request`'s `signal`
+/// aborts.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_bindgen::prelude::*;
+/// use wasm_signal_handler::{setup_edge, try_check_signal};
+///
+/// #[wasm_bindgen]
+/// pub fn handle(request: JsValue, body: String) -> Result<usize, JsValue> {
+///     let _abort = setup_edge(&request);
+///     let mut words = 0;
+///     for line in body.lines() {
+///         try_check_signal().map_err(|signal| JsValue::from(signal.code()))?;
+///         words += line.split_whitespace().count();
+///     }
+///     Ok(words)
+/// }
+/// ```
+pub fn setup_edge(request: &JsValue) -> AbortBinding {
+    register_edge_instance();
+    let signal = if request.is_object() {
+        Reflect::get(request, &JsValue::from_str("signal")).unwrap_or(JsValue::UNDEFINED)
+    } else {
+        JsValue::UNDEFINED
+    };
+    bind_abort_signal(&signal, Signal::CANCEL)
+}
@@ -0,0 +1,136 @@
+//! Deadlines measured in host epochs.
+//!
+//! Some hosts cannot target a write at one instance when it should stop,
+//! but can bump a counter in every instance on a timer, as Wasmtime does
+//! with its engine epoch. The epoch word is exported as
+//! `WASM_SIGNAL_EPOCH_ADDR`, for the host to increment at whatever period
+//! it likes. [`set_epoch_deadline`] arms a deadline a number of epochs
+//! ahead, and every subsequent check compares the two and, once the
+//! deadline is reached, synthesizes a [`Signal::DEADLINE`], exactly like a
+//! [clock deadline](crate::set_deadline). It is one-shot too.
+//!
+//! The guest needs no clock, and the host no knowledge of what the guest is
+//! doing: the deadline's resolution is the host's tick period.
+
+use core::sync::atomic::AtomicU32;
+
+use crate::{arm, disarm, Signal, ACQUIRE, ARMED, ARMED_EPOCH, RELEASE};
+
+/// The epoch word, incremented by the host. It wraps on overflow.
+static EPOCH: AtomicU32 = AtomicU32::new(0);
+
+/// The epoch the deadline is reached at, while [`ARMED_EPOCH`] is set.
+static EPOCH_DEADLINE: AtomicU32 = AtomicU32::new(0);
+
+/// The exported address of the epoch word.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_EPOCH_ADDR: &AtomicU32 = &EPOCH;
+
+/// Returns the current epoch.
+pub fn epoch() -> u32 {
+    EPOCH.load(ACQUIRE)
+}
+
+/// Sets a deadline `epochs` host ticks from now, replacing any existing
+/// epoch deadline.
+///
+/// A deadline of `0` epochs is reached at the next check.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{set_epoch_deadline, try_check_signal, Signal};
+///
+/// fn render() -> Result<(), Signal> {
+///     // With a host ticking every 10ms, about 50ms
+///     set_epoch_deadline(5);
+///     for _ in 0..1000 {
+///         try_check_signal()?; // Err(Signal::DEADLINE) after 5 ticks
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn set_epoch_deadline(epochs: u32) {
+    EPOCH_DEADLINE.store(epoch().wrapping_add(epochs), RELEASE);
+    arm(ARMED_EPOCH);
+}
+
+/// Clears the epoch deadline, if any.
+pub fn clear_epoch_deadline() {
+    disarm(ARMED_EPOCH);
+}
+
+/// Returns the number of epochs until the epoch deadline.
+///
+/// Returns `None` if no epoch deadline is set, and `Some(0)` if it has been
+/// reached but not observed by a check yet.
+pub fn epoch_deadline_remaining() -> Option<u32> {
+    if ARMED.load(ACQUIRE) & ARMED_EPOCH == 0 {
+        return None;
+    }
+    let left = EPOCH_DEADLINE.load(ACQUIRE).wrapping_sub(epoch());
+    // Past the deadline, the difference wraps to the upper half
+    Some(if left > u32::MAX / 2 { 0 } else { left })
+}
+
+/// Called from the check slow path while an epoch deadline is armed.
+///
+/// Returns the synthesized signal once the deadline is reached, clearing it.
+pub(crate) fn poll() -> Option<Signal> {
+    if epoch_deadline_remaining()? != 0 {
+        return None;
+    }
+    clear_epoch_deadline();
+    Some(Signal::DEADLINE)
+}
+
+/// The epoch deadline, saved by `ScopedSignalState`. Whether it is armed is
+/// saved with the other armed flags.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(u32);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(EPOCH_DEADLINE.load(ACQUIRE))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    EPOCH_DEADLINE.store(saved.0, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, try_check_signal, ACQ_REL};
+
+    #[test]
+    fn test_epoch_deadline() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        // Deadlines are measured across the wrap
+        EPOCH.store(u32::MAX - 1, RELEASE);
+
+        set_epoch_deadline(3);
+        assert_eq!(epoch_deadline_remaining(), Some(3));
+        EPOCH.fetch_add(2, ACQ_REL);
+        assert!(try_check_signal().is_ok());
+        assert_eq!(epoch_deadline_remaining(), Some(1));
+
+        EPOCH.fetch_add(2, ACQ_REL);
+        assert_eq!(epoch_deadline_remaining(), Some(0));
+        assert_eq!(try_check_signal(), Err(Signal::DEADLINE));
+
+        // One-shot: cleared once observed
+        assert_eq!(epoch_deadline_remaining(), None);
+        assert!(try_check_signal().is_ok());
+
+        set_epoch_deadline(0);
+        clear_epoch_deadline();
+        assert!(try_check_signal().is_ok());
+    }
+}
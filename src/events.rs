@@ -0,0 +1,221 @@
+//! Guest-to-host events.
+//!
+//! The signal word only flows from host to guest. To see how the guest
+//! reacted, the host drains an exported ring buffer of small events (at
+//! `WASM_SIGNAL_EVENTS_ADDR`). The crate posts events for signals acknowledged
+//! by a check, codes rejected by validation, checkpoints written and stage
+//! changes, and applications post their own with [`post_event`].
+//!
+//! The ring is bounded. When the host falls behind, new events are dropped
+//! and counted, rather than overwriting events the host has not read.
+
+use core::sync::atomic::AtomicU32;
+
+use crate::{ACQUIRE, ACQ_REL, RELEASE};
+
+/// Number of events the ring holds before dropping new ones.
+pub const EVENT_CAPACITY: usize = 32;
+
+/// A check observed a signal; the value is its code.
+pub const EVENT_SIGNAL_ACKNOWLEDGED: u32 = 1;
+
+/// A check left a code pending because it failed validation (see
+/// [`set_signal_validator`](crate::set_signal_validator)); the value is the
+/// code. Posted once per distinct rejected code.
+pub const EVENT_SIGNAL_REJECTED: u32 = 4;
+
+/// A checkpoint snapshot was written; the value is `0`.
+pub const EVENT_CHECKPOINT_WRITTEN: u32 = 2;
+
+/// The stage changed with [`set_stage`](crate::set_stage); the value is the
+/// new stage code.
+pub const EVENT_STAGE_CHANGED: u32 = 3;
+
+/// The first event kind for applications. Kinds below it are reserved for
+/// the crate.
+pub const EVENT_USER_BASE: u32 = 0x100;
+
+/// A bounded multi-producer, single-consumer ring shared with the host.
+///
+/// Producers reserve a slot by advancing `tail`, write the value, then
+/// publish the kind. The host consumes from `head`, clearing a slot's kind
+/// before advancing past it. A slot whose kind is still `0` has been reserved
+/// but not yet published.
+///
+/// The layout is read by the host helpers and must not change.
+#[repr(C)]
+pub struct EventRing {
+    capacity: u32,
+    head: AtomicU32,
+    tail: AtomicU32,
+    dropped: AtomicU32,
+    slots: [[AtomicU32; 2]; EVENT_CAPACITY],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: [AtomicU32; 2] = [AtomicU32::new(0), AtomicU32::new(0)];
+
+static RING: EventRing = EventRing {
+    capacity: EVENT_CAPACITY as u32,
+    head: AtomicU32::new(0),
+    tail: AtomicU32::new(0),
+    dropped: AtomicU32::new(0),
+    slots: [EMPTY_SLOT; EVENT_CAPACITY],
+};
+
+/// The exported address of the event ring.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_EVENTS_ADDR: &EventRing = &RING;
+
+/// Posts an event for the host to drain.
+///
+/// `kind` must be non-zero; applications use kinds from [`EVENT_USER_BASE`]
+/// on. The meaning of `value` depends on the kind.
+///
+/// # Returns
+///
+/// Returns `false` if `kind` is `0`, or if the ring is full and the event was
+/// dropped.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{post_event, EVENT_USER_BASE};
+///
+/// const CACHE_FLUSHED: u32 = EVENT_USER_BASE;
+///
+/// post_event(CACHE_FLUSHED, 128);
+/// ```
+pub fn post_event(kind: u32, value: u32) -> bool {
+    if kind == 0 {
+        return false;
+    }
+    let mut tail = RING.tail.load(ACQUIRE);
+    loop {
+        if tail.wrapping_sub(RING.head.load(ACQUIRE)) as usize >= EVENT_CAPACITY {
+            RING.dropped.fetch_add(1, ACQ_REL);
+            return false;
+        }
+        match RING
+            .tail
+            .compare_exchange_weak(tail, tail.wrapping_add(1), ACQ_REL, ACQUIRE)
+        {
+            Ok(_) => break,
+            Err(current) => tail = current,
+        }
+    }
+    let slot = &RING.slots[tail as usize % EVENT_CAPACITY];
+    slot[1].store(value, RELEASE);
+    slot[0].store(kind, RELEASE);
+    true
+}
+
+/// Returns the number of events the host has not drained yet.
+pub fn pending_events() -> usize {
+    let head = RING.head.load(ACQUIRE);
+    RING.tail.load(ACQUIRE).wrapping_sub(head) as usize
+}
+
+/// Returns the number of events dropped because the ring was full. It wraps
+/// on overflow.
+pub fn dropped_events() -> u32 {
+    RING.dropped.load(ACQUIRE)
+}
+
+/// The ring, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+pub(crate) struct Saved {
+    head: u32,
+    tail: u32,
+    dropped: u32,
+    slots: [[u32; 2]; EVENT_CAPACITY],
+}
+
+#[cfg(feature = "testing")]
+impl Default for Saved {
+    fn default() -> Saved {
+        Saved {
+            head: 0,
+            tail: 0,
+            dropped: 0,
+            slots: [[0; 2]; EVENT_CAPACITY],
+        }
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        head: RING.head.load(ACQUIRE),
+        tail: RING.tail.load(ACQUIRE),
+        dropped: RING.dropped.load(ACQUIRE),
+        slots: core::array::from_fn(|i| {
+            [
+                RING.slots[i][0].load(ACQUIRE),
+                RING.slots[i][1].load(ACQUIRE),
+            ]
+        }),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    for (slot, saved) in RING.slots.iter().zip(&saved.slots) {
+        slot[1].store(saved[1], RELEASE);
+        slot[0].store(saved[0], RELEASE);
+    }
+    RING.dropped.store(saved.dropped, RELEASE);
+    RING.head.store(saved.head, RELEASE);
+    RING.tail.store(saved.tail, RELEASE);
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, try_check_signal, Signal};
+
+    /// Drains one event, as the host does.
+    pub(crate) fn drain() -> Option<(u32, u32)> {
+        let head = RING.head.load(ACQUIRE);
+        if head == RING.tail.load(ACQUIRE) {
+            return None;
+        }
+        let slot = &RING.slots[head as usize % EVENT_CAPACITY];
+        let kind = slot[0].swap(0, ACQ_REL);
+        if kind == 0 {
+            return None;
+        }
+        let value = slot[1].load(ACQUIRE);
+        RING.head.store(head.wrapping_add(1), RELEASE);
+        Some((kind, value))
+    }
+
+    #[test]
+    fn test_events_posted_and_dropped() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        while drain().is_some() {}
+
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_err());
+        assert!(post_event(EVENT_USER_BASE, 7));
+        assert!(!post_event(0, 7));
+        assert_eq!(pending_events(), 2);
+        assert_eq!(drain(), Some((EVENT_SIGNAL_ACKNOWLEDGED, 2)));
+        assert_eq!(drain(), Some((EVENT_USER_BASE, 7)));
+        assert_eq!(drain(), None);
+
+        // A full ring drops new events instead of overwriting old ones
+        let dropped = dropped_events();
+        for i in 0..EVENT_CAPACITY as u32 {
+            assert!(post_event(EVENT_USER_BASE, i));
+        }
+        assert!(!post_event(EVENT_USER_BASE, 99));
+        assert_eq!(dropped_events(), dropped + 1);
+        assert_eq!(drain(), Some((EVENT_USER_BASE, 0)));
+        while drain().is_some() {}
+    }
+}
@@ -0,0 +1,173 @@
+//! Delivering signals through Extism plugin vars.
+//!
+//! Extism hosts cannot write plugin memory, but they can set the plugin's
+//! vars. With the `extism` feature, the host raises a signal by setting the
+//! [`EXTISM_SIGNAL_VAR`] var to the code, as four little-endian bytes.
+//! Checks read it through the [host poll](crate::set_poll_stride) machinery
+//! (the feature implies `host-poll`), in place of the `env.wasm_signal_poll`
+//! import: the var is removed and its code sent as a signal. Each poll also
+//! writes the code of the last acknowledged signal to [`EXTISM_ACK_VAR`], so
+//! the host can tell when the plugin has seen it.
+//!
+//! The vars are read with the Extism kernel functions in the
+//! `extism:host/env` import module, which every Extism host provides.
+
+use core::sync::atomic::AtomicU32;
+
+use crate::{last_ack, ACQ_REL};
+
+/// The var a host sets to raise a signal.
+pub const EXTISM_SIGNAL_VAR: &str = "wasm-signal";
+
+/// The var the plugin sets to the last acknowledged signal.
+pub const EXTISM_ACK_VAR: &str = "wasm-signal-ack";
+
+/// The ack last written to [`EXTISM_ACK_VAR`], or `0`.
+static PUBLISHED_ACK: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(target_arch = "wasm32")]
+mod kernel {
+    #[link(wasm_import_module = "extism:host/env")]
+    extern "C" {
+        #[link_name = "alloc"]
+        fn extism_alloc(length: u64) -> u64;
+        #[link_name = "free"]
+        fn extism_free(offset: u64);
+        #[link_name = "length"]
+        fn extism_length(offset: u64) -> u64;
+        #[link_name = "load_u8"]
+        fn extism_load_u8(offset: u64) -> u8;
+        #[link_name = "store_u8"]
+        fn extism_store_u8(offset: u64, value: u8);
+        #[link_name = "var_get"]
+        fn extism_var_get(key: u64) -> u64;
+        #[link_name = "var_set"]
+        fn extism_var_set(key: u64, value: u64);
+    }
+
+    /// Copies `bytes` into a new block of Extism memory.
+    fn store(bytes: &[u8]) -> u64 {
+        // SAFETY: The kernel functions only touch Extism's own memory, and
+        // every store is within the block just allocated
+        unsafe {
+            let offset = extism_alloc(bytes.len() as u64);
+            for (i, &byte) in bytes.iter().enumerate() {
+                extism_store_u8(offset + i as u64, byte);
+            }
+            offset
+        }
+    }
+
+    /// Returns the var `key` as a code, or `0` if it is unset or not four
+    /// bytes long.
+    pub(super) fn get(key: &str) -> u32 {
+        let key = store(key.as_bytes());
+        // SAFETY: As in `store`; loads are within the value's length
+        unsafe {
+            let value = extism_var_get(key);
+            extism_free(key);
+            if value == 0 {
+                return 0;
+            }
+            let mut bytes = [0; 4];
+            if extism_length(value) == 4 {
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    *byte = extism_load_u8(value + i as u64);
+                }
+            }
+            extism_free(value);
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Sets the var `key` to `code`, or removes it if `code` is `0`.
+    pub(super) fn set(key: &str, code: u32) {
+        let key = store(key.as_bytes());
+        let value = if code == 0 {
+            0
+        } else {
+            store(&code.to_le_bytes())
+        };
+        // SAFETY: Both blocks were allocated above
+        unsafe {
+            extism_var_set(key, value);
+            extism_free(key);
+            if value != 0 {
+                extism_free(value);
+            }
+        }
+    }
+}
+
+/// Outside wasm32 there is no Extism host; tests use a stand-in for the two
+/// vars.
+#[cfg(not(target_arch = "wasm32"))]
+mod kernel {
+    #[cfg(test)]
+    pub(super) static TEST_VARS: [core::sync::atomic::AtomicU32; 2] =
+        [const { core::sync::atomic::AtomicU32::new(0) }; 2];
+
+    #[cfg(test)]
+    fn var(key: &str) -> &'static core::sync::atomic::AtomicU32 {
+        &TEST_VARS[usize::from(key == super::EXTISM_ACK_VAR)]
+    }
+
+    pub(super) fn get(key: &str) -> u32 {
+        #[cfg(test)]
+        return var(key).load(crate::ACQUIRE);
+        #[cfg(not(test))]
+        {
+            let _ = key;
+            0
+        }
+    }
+
+    pub(super) fn set(key: &str, code: u32) {
+        #[cfg(test)]
+        var(key).store(code, crate::RELEASE);
+        let _ = (key, code);
+    }
+}
+
+/// Takes the signal the host set, publishing the last ack first.
+///
+/// # Returns
+///
+/// The code the host set, or `0` for none.
+pub(crate) fn take_signal() -> u32 {
+    let ack = last_ack().map_or(0, |signal| signal.code());
+    if PUBLISHED_ACK.swap(ack, ACQ_REL) != ack {
+        kernel::set(EXTISM_ACK_VAR, ack);
+    }
+    let code = kernel::get(EXTISM_SIGNAL_VAR);
+    if code != 0 {
+        kernel::set(EXTISM_SIGNAL_VAR, 0);
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, poll_host, try_check_signal, Signal, ACQUIRE, RELEASE,
+    };
+
+    #[test]
+    fn test_extism_vars() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        let [signal_var, ack_var] = &kernel::TEST_VARS;
+
+        signal_var.store(Signal::CANCEL.code(), RELEASE);
+        assert!(poll_host());
+        assert_eq!(signal_var.load(ACQUIRE), 0);
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+
+        // The ack is published by the next poll
+        assert!(!poll_host());
+        assert_eq!(ack_var.load(ACQUIRE), Signal::CANCEL.code());
+    }
+}
@@ -0,0 +1,147 @@
+//! Signal conventions for Fastly Compute.
+//!
+//! Compute runs each request in a fresh instance, with limits on CPU time,
+//! wall clock time and heap, and an edge rate limiter that can penalize a
+//! client mid-request. The signal words here, in the `0xFA00` block, are the
+//! conventions for a host or middleware to warn the guest about those, each
+//! with an [encoded severity](crate::Severity) like the
+//! [Workers limits](crate::WorkersLimit).
+//!
+//! Rather than being terminated with nothing returned, a cancelled request
+//! should answer with a synthetic response. [`handle_fastly_request`] sets
+//! up the request's signal state, runs the handler, and maps a propagated
+//! signal to the response built by the caller, with the status code
+//! [`cancellation_status`] suggests.
+
+use crate::platform::platform_signals;
+use crate::{RequestScope, Signal};
+
+platform_signals! {
+    /// A limit Fastly Compute warns the guest about.
+    pub enum FastlyLimit ("limit", "limits") {
+        /// The request is about to exceed its CPU time limit.
+        CpuLimitApproaching = 0xFA01, Cancel, Cancel, "cpu-limit-approaching";
+        /// The request exceeded its wall clock limit.
+        WallClockLimit = 0xFA02, Kill, Terminate, "wall-clock-limit";
+        /// The instance is close to its heap limit.
+        MemoryPressure = 0xFA03, Warn, Custom, "memory-pressure";
+        /// The edge rate limiter penalized the client.
+        RateLimited = 0xFA04, Cancel, Cancel, "rate-limited";
+    }
+
+    /// The generic kind a guest should treat the limit as: an approaching CPU
+    /// limit or a rate limit is a cancel, a wall clock limit a termination, and
+    /// memory pressure has no generic equivalent.
+    kind;
+
+    pub fn register_fastly_names;
+}
+
+impl FastlyLimit {
+    /// Returns the HTTP status of the synthetic response for a request
+    /// cancelled by the limit: `429` when rate limited, `504` past the wall
+    /// clock limit, and `503` otherwise.
+    pub const fn status(self) -> u16 {
+        match self {
+            FastlyLimit::RateLimited => 429,
+            FastlyLimit::WallClockLimit => 504,
+            FastlyLimit::CpuLimitApproaching | FastlyLimit::MemoryPressure => 503,
+        }
+    }
+}
+
+/// Returns the HTTP status of the synthetic response for a request
+/// cancelled by `signal`.
+///
+/// Limits use [`FastlyLimit::status`], [`Signal::DEADLINE`] maps to `504`,
+/// and any other signal to `503`.
+pub fn cancellation_status(signal: Signal) -> u16 {
+    match FastlyLimit::try_from(signal) {
+        Ok(limit) => limit.status(),
+        Err(Signal::DEADLINE) => 504,
+        Err(_) => 503,
+    }
+}
+
+/// Runs a Compute request handler, answering with a synthetic response if
+/// a signal cancels it.
+///
+/// The request runs in a [`RequestScope`] with the limits' names
+/// registered, so it starts with clean signal state even where an instance
+/// serves several requests. If `handler` returns a propagated signal,
+/// `cancelled` builds the response from it and the status
+/// [`cancellation_status`] suggests.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{handle_fastly_request, try_check_signal, Signal};
+///
+/// struct Response {
+///     status: u16,
+///     body: String,
+/// }
+///
+/// fn render(path: &str) -> Result<Response, Signal> {
+///     try_check_signal()?;
+///     Ok(Response { status: 200, body: path.to_uppercase() })
+/// }
+///
+/// // In `#[fastly::main]`, with `fastly::Response` in place of `Response`
+/// let response = handle_fastly_request(
+///     || render("/index"),
+///     |signal, status| Response {
+///         status,
+///         body: format!("cancelled by signal {}", signal.code()),
+///     },
+/// );
+/// assert_eq!(response.status, 200);
+/// ```
+pub fn handle_fastly_request<R>(
+    handler: impl FnOnce() -> Result<R, Signal>,
+    cancelled: impl FnOnce(Signal, u16) -> R,
+) -> R {
+    let _scope = RequestScope::new();
+    register_fastly_names();
+    match handler() {
+        Ok(response) => response,
+        Err(signal) => cancelled(signal, cancellation_status(signal)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{peek_signal, set_signal, try_check_signal, unregister_signal_name};
+
+    #[test]
+    fn test_fastly_request() {
+        let _guard = lock();
+        for limit in FastlyLimit::ALL {
+            assert_eq!(FastlyLimit::from_code(limit.code()), Some(limit));
+            assert_eq!(FastlyLimit::try_from(limit.signal()), Ok(limit));
+            assert_eq!(limit.signal().severity(), Some(limit.severity()));
+        }
+        assert_eq!(FastlyLimit::try_from(Signal::CANCEL), Err(Signal::CANCEL));
+
+        let response = handle_fastly_request(
+            || {
+                set_signal(FastlyLimit::RateLimited.code());
+                try_check_signal()?;
+                Ok((200, None))
+            },
+            |signal, status| (status, Signal::name(&signal)),
+        );
+        assert_eq!(response, (429, Some("rate-limited")));
+        assert!(peek_signal().is_none());
+        assert_eq!(cancellation_status(Signal::DEADLINE), 504);
+        assert_eq!(cancellation_status(Signal::TERMINATE), 503);
+
+        let response = handle_fastly_request(|| Ok(200), |_, status| status);
+        assert_eq!(response, 200);
+        for limit in FastlyLimit::ALL {
+            unregister_signal_name(limit.base_code());
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Filtering the codes an application responds to.
+//!
+//! Code that only understands some signals can declare them with
+//! [`set_signal_mask`]. Checks then ignore any other code instead of
+//! consuming it and turning it into an error the caller cannot interpret. By
+//! default an ignored code stays pending in the signal word (where code that
+//! does understand it, or a wider mask, can pick it up later); with
+//! [`set_drop_ignored`] it is cleared instead.
+//!
+//! This is unrelated to [`SignalMaskGuard`](crate::SignalMaskGuard), which
+//! defers every signal for the duration of a critical section.
+//! [Fatal](Signal::is_fatal) signals always pass the mask, and
+//! [`Signal::DEADLINE`] is never filtered since the guest raises it itself.
+
+use core::sync::atomic::{AtomicBool, AtomicU64};
+
+use crate::{arm, disarm, storage, Signal, ACQUIRE, ACQ_REL, ARMED_FILTER, RELEASE};
+
+/// A set of signal codes.
+///
+/// Codes `1` to `62` are tracked individually; all higher codes share a
+/// single "other" entry. Signals are matched by their
+/// [base code](Signal::base_code), whatever their severity. Fatal codes are
+/// always contained.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{Signal, SignalMask};
+///
+/// let mask = SignalMask::NONE.with(Signal::TERMINATE).with(Signal::CANCEL);
+/// assert!(mask.contains(Signal::CANCEL));
+/// assert!(!mask.contains(Signal::new(42).unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SignalMask(u64);
+
+impl SignalMask {
+    /// Contains every code (the default).
+    pub const ALL: SignalMask = SignalMask(u64::MAX);
+
+    /// Contains only the fatal codes.
+    pub const NONE: SignalMask = SignalMask(0);
+
+    /// The entry shared by codes above `62`.
+    const OTHER: u64 = 1 << 63;
+
+    const fn bit(code: u32) -> u64 {
+        if code < 63 {
+            1 << code
+        } else {
+            SignalMask::OTHER
+        }
+    }
+
+    /// Returns the mask with `signal` added.
+    pub const fn with(self, signal: Signal) -> SignalMask {
+        SignalMask(self.0 | SignalMask::bit(signal.base_code()))
+    }
+
+    /// Returns the mask with `signal` removed.
+    pub const fn without(self, signal: Signal) -> SignalMask {
+        SignalMask(self.0 & !SignalMask::bit(signal.base_code()))
+    }
+
+    /// Returns the mask with every code above `62` added.
+    pub const fn with_others(self) -> SignalMask {
+        SignalMask(self.0 | SignalMask::OTHER)
+    }
+
+    /// Returns `true` if `signal` passes the mask.
+    pub const fn contains(self, signal: Signal) -> bool {
+        signal.is_fatal() || self.0 & SignalMask::bit(signal.base_code()) != 0
+    }
+
+    /// The mask's entries, laid out as in a [`SignalSet`](crate::SignalSet).
+    pub(crate) const fn bits(self) -> u64 {
+        self.0
+    }
+
+    pub(crate) const fn from_bits(bits: u64) -> SignalMask {
+        SignalMask(bits)
+    }
+}
+
+impl Default for SignalMask {
+    fn default() -> SignalMask {
+        SignalMask::ALL
+    }
+}
+
+static MASK: AtomicU64 = AtomicU64::new(u64::MAX);
+static DROP_IGNORED: AtomicBool = AtomicBool::new(false);
+
+/// Sets which codes checks respond to, from a [`SignalMask`] or a
+/// [`SignalSet`](crate::SignalSet).
+///
+/// # Returns
+///
+/// Returns the previous mask.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{set_signal_mask, Signal, SignalMask};
+///
+/// // Leave host-specific codes for the runtime layer
+/// set_signal_mask(SignalMask::NONE.with(Signal::TERMINATE).with(Signal::CANCEL));
+/// ```
+pub fn set_signal_mask(mask: impl Into<SignalMask>) -> SignalMask {
+    let mask = mask.into();
+    let previous = SignalMask(MASK.swap(mask.0, ACQ_REL));
+    if mask == SignalMask::ALL {
+        disarm(ARMED_FILTER);
+    } else {
+        arm(ARMED_FILTER);
+    }
+    previous
+}
+
+/// Gets the current signal mask.
+pub fn signal_mask() -> SignalMask {
+    SignalMask(MASK.load(ACQUIRE))
+}
+
+/// Sets whether ignored codes are cleared from the signal word (`true`) or
+/// left pending (`false`, the default).
+pub fn set_drop_ignored(drop: bool) {
+    DROP_IGNORED.store(drop, RELEASE);
+}
+
+/// Returns `true` if ignored codes are cleared.
+pub fn drop_ignored() -> bool {
+    DROP_IGNORED.load(ACQUIRE)
+}
+
+/// Returns `true` if a check with the signal mask `mask` should handle
+/// `signal`, dropping it from the signal word otherwise if so configured.
+pub(crate) fn admit(mask: SignalMask, signal: Signal) -> bool {
+    if mask.contains(signal) {
+        return true;
+    }
+    if DROP_IGNORED.load(ACQUIRE) {
+        let _ = storage::compare_exchange(signal.code(), 0, ACQ_REL, ACQUIRE);
+    }
+    false
+}
+
+/// The mask and drop setting, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+pub(crate) struct Saved {
+    mask: SignalMask,
+    drop_ignored: bool,
+}
+
+#[cfg(feature = "testing")]
+impl Default for Saved {
+    fn default() -> Saved {
+        Saved {
+            mask: SignalMask::ALL,
+            drop_ignored: false,
+        }
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        mask: signal_mask(),
+        drop_ignored: drop_ignored(),
+    }
+}
+
+/// Restores the mask. `ARMED` is restored separately by the caller.
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    MASK.store(saved.mask.0, RELEASE);
+    DROP_IGNORED.store(saved.drop_ignored, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, drain_signals, peek_signal, set_signal,
+        try_check_signal,
+    };
+
+    #[test]
+    fn test_mask_contains() {
+        let mask = SignalMask::NONE.with(Signal::CANCEL);
+        assert!(mask.contains(Signal::CANCEL));
+        assert!(!mask.without(Signal::CANCEL).contains(Signal::CANCEL));
+        assert!(!mask.contains(Signal::new(1000).unwrap()));
+        assert!(mask.with_others().contains(Signal::new(1000).unwrap()));
+        assert!(SignalMask::NONE.contains(Signal::new(Signal::FATAL_BASE).unwrap()));
+    }
+
+    #[test]
+    fn test_ignored_codes_stay_pending_or_drop() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        let previous = set_signal_mask(SignalMask::NONE.with(Signal::TERMINATE));
+        assert_eq!(previous, SignalMask::ALL);
+
+        set_signal(42);
+        assert!(try_check_signal().is_ok());
+        assert!(drain_signals().into_result().is_ok());
+        assert_eq!(peek_signal(), Signal::new(42));
+
+        set_drop_ignored(true);
+        assert!(try_check_signal().is_ok());
+        assert!(peek_signal().is_none());
+
+        set_signal(Signal::TERMINATE.code());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+
+        set_drop_ignored(false);
+        set_signal_mask(SignalMask::ALL);
+    }
+}
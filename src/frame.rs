@@ -0,0 +1,204 @@
+//! Per-frame time budgets for render and game loops.
+//!
+//! A loop driven by `requestAnimationFrame` has two reasons to stop work
+//! early: the frame is out of time, or the host wants the guest to stop.
+//! [`FrameBudget`] answers both with one [`should_stop`](FrameBudget::should_stop)
+//! call, and keeps count of the frames that ran over.
+
+use crate::{now_ms, try_check_signal, Signal};
+
+/// Budget overrun statistics, from [`FrameBudget::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FrameStats {
+    /// Frames finished so far.
+    pub frames: u32,
+    /// Finished frames that took longer than their budget.
+    pub overruns: u32,
+    /// The largest overrun of a finished frame, in milliseconds.
+    pub worst_overrun_ms: u64,
+    /// The sum of all overruns, in milliseconds.
+    pub total_overrun_ms: u64,
+}
+
+/// Tracks one frame's time budget along with host cancellation.
+///
+/// Frame timing uses the [clock](crate::now_ms). Without a clock, frames
+/// never run out of time and only signals stop them.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{FrameBudget, Signal};
+///
+/// fn on_animation_frame(budget: &mut FrameBudget, work: &mut Vec<u32>) -> Result<(), Signal> {
+///     budget.begin_frame(12);
+///     while let Some(item) = work.pop() {
+///         let _ = item; // update(item)
+///         if budget.should_stop() {
+///             break;
+///         }
+///     }
+///     budget.end_frame();
+///     match budget.signal() {
+///         Some(signal) => Err(signal),
+///         None => Ok(()),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FrameBudget {
+    /// Clock time the open frame began at, if a frame is open.
+    start: Option<u64>,
+    budget_ms: u64,
+    signal: Option<Signal>,
+    stats: FrameStats,
+}
+
+impl FrameBudget {
+    /// Creates a budget with no open frame.
+    pub const fn new() -> FrameBudget {
+        FrameBudget {
+            start: None,
+            budget_ms: 0,
+            signal: None,
+            stats: FrameStats {
+                frames: 0,
+                overruns: 0,
+                worst_overrun_ms: 0,
+                total_overrun_ms: 0,
+            },
+        }
+    }
+
+    /// Starts a frame with `budget_ms` milliseconds to spend.
+    ///
+    /// Finishes the previous frame first if [`end_frame`](Self::end_frame)
+    /// was not called. A signal observed in an earlier frame is kept, so a
+    /// cancelled loop keeps stopping.
+    pub fn begin_frame(&mut self, budget_ms: u32) {
+        self.end_frame();
+        // Without a clock, elapsed time stays at zero
+        self.start = Some(now_ms().unwrap_or(0));
+        self.budget_ms = budget_ms.into();
+    }
+
+    /// Returns `true` if the frame should stop: its budget is spent, or a
+    /// check propagated a signal.
+    ///
+    /// Runs [`try_check_signal`], so signals go through the handler as
+    /// usual; the propagated one is available from [`signal`](Self::signal).
+    /// Outside a frame, only signals stop.
+    pub fn should_stop(&mut self) -> bool {
+        if self.signal.is_some() {
+            return true;
+        }
+        if let Err(signal) = try_check_signal() {
+            self.signal = Some(signal);
+            return true;
+        }
+        self.remaining_ms() == Some(0)
+    }
+
+    /// Finishes the open frame, recording an overrun if it went over budget.
+    ///
+    /// Does nothing outside a frame.
+    pub fn end_frame(&mut self) {
+        let Some(elapsed) = self.elapsed_ms() else {
+            return;
+        };
+        self.start = None;
+        let stats = &mut self.stats;
+        stats.frames = stats.frames.saturating_add(1);
+        let overrun = elapsed.saturating_sub(self.budget_ms);
+        if overrun > 0 {
+            stats.overruns = stats.overruns.saturating_add(1);
+            stats.worst_overrun_ms = stats.worst_overrun_ms.max(overrun);
+            stats.total_overrun_ms = stats.total_overrun_ms.saturating_add(overrun);
+        }
+    }
+
+    /// Milliseconds spent in the open frame, or `None` outside a frame.
+    pub fn elapsed_ms(&self) -> Option<u64> {
+        let start = self.start?;
+        Some(now_ms().map_or(0, |now| now.saturating_sub(start)))
+    }
+
+    /// Milliseconds left in the open frame's budget, or `None` outside a
+    /// frame.
+    pub fn remaining_ms(&self) -> Option<u64> {
+        Some(self.budget_ms.saturating_sub(self.elapsed_ms()?))
+    }
+
+    /// The signal that stopped a frame, if any.
+    pub fn signal(&self) -> Option<Signal> {
+        self.signal
+    }
+
+    /// Forgets the observed signal, for loops that recover from it.
+    pub fn clear_signal(&mut self) -> Option<Signal> {
+        self.signal.take()
+    }
+
+    /// Returns overrun statistics for the finished frames.
+    pub fn stats(&self) -> FrameStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_clock, clear_signal, clear_signal_handler, set_clock, set_signal};
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    static NOW: AtomicU64 = AtomicU64::new(1_000);
+
+    fn fake_clock() -> u64 {
+        NOW.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_frame_budget() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        set_clock(fake_clock);
+
+        let mut budget = FrameBudget::new();
+        assert!(!budget.should_stop());
+        assert_eq!(budget.remaining_ms(), None);
+
+        budget.begin_frame(10);
+        NOW.fetch_add(9, Ordering::SeqCst);
+        assert!(!budget.should_stop());
+        NOW.fetch_add(1, Ordering::SeqCst);
+        assert!(budget.should_stop());
+
+        // Ends the late frame and starts the next one
+        NOW.fetch_add(5, Ordering::SeqCst);
+        budget.begin_frame(10);
+        budget.end_frame();
+        assert_eq!(
+            budget.stats(),
+            FrameStats {
+                frames: 2,
+                overruns: 1,
+                worst_overrun_ms: 5,
+                total_overrun_ms: 5,
+            }
+        );
+
+        // A propagated signal stops every frame until cleared
+        budget.begin_frame(10);
+        set_signal(Signal::CANCEL.code());
+        assert!(budget.should_stop());
+        budget.begin_frame(10);
+        assert!(budget.should_stop());
+        assert_eq!(budget.clear_signal(), Some(Signal::CANCEL));
+        assert!(!budget.should_stop());
+
+        clear_clock();
+    }
+}
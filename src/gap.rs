@@ -0,0 +1,291 @@
+//! Warning about long gaps between checks.
+//!
+//! Cancellation waits for the next check, so a stretch of code that runs
+//! for seconds without one makes the guest unresponsive for that long. With
+//! a threshold set (see [`set_check_gap_warning`]), every check reads the
+//! [clock](crate::now_ms) and compares it with the previous check's. The
+//! first gap above the threshold is reported once, naming the call sites on
+//! either side of it, and the warning then disarms itself so checks return
+//! to the fast path.
+//!
+//! Time the guest spends idle, such as awaiting I/O or between requests,
+//! counts towards the gap. [`reset_all`](crate::reset_all) restarts the
+//! measurement, and [`restart_check_gap`] does so on its own, for example
+//! after an await.
+
+use core::fmt;
+use core::panic::Location;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64};
+
+use crate::{arm, disarm, now_ms, ACQUIRE, ACQ_REL, ARMED_GAP, RELEASE};
+
+/// A gap between checks longer than the threshold, passed to the
+/// [`CheckGapWarning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CheckGap {
+    /// Time between the two checks, in milliseconds.
+    pub gap_ms: u64,
+    /// The threshold the gap exceeded, in milliseconds.
+    pub threshold_ms: u64,
+    /// The call site of the check before the gap.
+    pub previous: &'static Location<'static>,
+    /// The call site of the check after the gap.
+    pub location: &'static Location<'static>,
+}
+
+impl fmt::Display for CheckGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no signal check for {}ms (threshold {}ms), from {} to {}",
+            self.gap_ms, self.threshold_ms, self.previous, self.location
+        )
+    }
+}
+
+/// Receives the first gap between checks above the threshold.
+///
+/// It runs inside the check that ended the gap, so it should return quickly.
+pub type CheckGapWarning = fn(&CheckGap);
+
+static THRESHOLD_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Stored as a raw pointer for the same reasons as the signal handler.
+static WARNING: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Clock time of the previous check, plus one, or `0` if unknown.
+static LAST_MS: AtomicU64 = AtomicU64::new(0);
+static LAST_SITE: AtomicPtr<Location<'static>> = AtomicPtr::new(null_mut());
+
+/// Set once the warning has been given.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Warns through `warning` the first time two checks are more than
+/// `threshold_ms` apart.
+///
+/// Replaces a warning set before and arms it again, even if it had already
+/// fired. Without a clock, nothing is measured.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{set_check_gap_warning, CheckGap};
+///
+/// fn report(gap: &CheckGap) {
+///     // Forward to the application's logger
+///     let _ = gap.to_string();
+/// }
+///
+/// set_check_gap_warning(50, report);
+/// # wasm_signal_handler::clear_check_gap_warning();
+/// ```
+pub fn set_check_gap_warning(threshold_ms: u64, warning: CheckGapWarning) {
+    THRESHOLD_MS.store(threshold_ms, RELEASE);
+    WARNING.store(warning as *mut (), RELEASE);
+    restart_check_gap();
+    WARNED.store(false, RELEASE);
+    arm(ARMED_GAP);
+}
+
+/// Stops measuring gaps between checks.
+pub fn clear_check_gap_warning() {
+    disarm(ARMED_GAP);
+    WARNING.store(null_mut(), RELEASE);
+}
+
+/// Starts measuring the next gap from the next check, forgetting the
+/// previous one.
+pub fn restart_check_gap() {
+    LAST_MS.store(0, RELEASE);
+}
+
+/// Writes the gap to `console.warn` in JavaScript hosts, or to stderr
+/// elsewhere. A ready-made [`CheckGapWarning`].
+#[cfg(feature = "std")]
+pub fn log_check_gap(gap: &CheckGap) {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        use wasm_bindgen::prelude::*;
+
+        #[wasm_bindgen]
+        extern "C" {
+            #[wasm_bindgen(js_namespace = console, js_name = warn)]
+            fn console_warn(message: &str);
+        }
+        console_warn(&std::format!("wasm-signal-handler: {gap}"));
+    }
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    std::eprintln!("wasm-signal-handler: {gap}");
+}
+
+/// Measures the gap since the previous check, from a check at `location`.
+pub(crate) fn on_check(location: &'static Location<'static>) {
+    let Some(now) = now_ms() else {
+        return;
+    };
+    let last = LAST_MS.swap(now.saturating_add(1), ACQ_REL);
+    let previous = LAST_SITE.swap((location as *const Location<'static>).cast_mut(), ACQ_REL);
+    let Some(last) = last.checked_sub(1) else {
+        return;
+    };
+    let gap_ms = now.saturating_sub(last);
+    let threshold_ms = THRESHOLD_MS.load(ACQUIRE);
+    if gap_ms <= threshold_ms || previous.is_null() || WARNED.swap(true, ACQ_REL) {
+        return;
+    }
+    disarm(ARMED_GAP);
+    let warning = WARNING.load(ACQUIRE);
+    if warning.is_null() {
+        return;
+    }
+    // SAFETY: We only store valid CheckGapWarning function pointers in
+    // WARNING, and only Location references in LAST_SITE
+    let (warning, previous) = unsafe {
+        (
+            core::mem::transmute::<*mut (), CheckGapWarning>(warning),
+            &*previous,
+        )
+    };
+    warning(&CheckGap {
+        gap_ms,
+        threshold_ms,
+        previous,
+        location,
+    });
+}
+
+/// The threshold, the warning and the measurement in progress, saved by
+/// `ScopedSignalState`. Whether the warning is armed is saved with the other
+/// armed flags.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    threshold_ms: u64,
+    warning: Option<CheckGapWarning>,
+    last_ms: u64,
+    last_site: Option<&'static Location<'static>>,
+    warned: bool,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    let warning = WARNING.load(ACQUIRE);
+    // SAFETY: We only store valid CheckGapWarning function pointers in
+    // WARNING, and only Location references in LAST_SITE
+    let (warning, last_site) = unsafe {
+        (
+            (!warning.is_null()).then(|| core::mem::transmute::<*mut (), CheckGapWarning>(warning)),
+            LAST_SITE.load(ACQUIRE).as_ref(),
+        )
+    };
+    Saved {
+        threshold_ms: THRESHOLD_MS.load(ACQUIRE),
+        warning,
+        last_ms: LAST_MS.load(ACQUIRE),
+        last_site,
+        warned: WARNED.load(ACQUIRE),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    THRESHOLD_MS.store(saved.threshold_ms, RELEASE);
+    WARNING.store(
+        saved
+            .warning
+            .map_or(null_mut(), |warning| warning as *mut ()),
+        RELEASE,
+    );
+    LAST_MS.store(saved.last_ms, RELEASE);
+    LAST_SITE.store(
+        saved.last_site.map_or(null_mut(), |site| {
+            (site as *const Location<'static>).cast_mut()
+        }),
+        RELEASE,
+    );
+    WARNED.store(saved.warned, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_clock, clear_signal, set_clock, try_check_signal};
+    use core::sync::atomic::AtomicU32;
+    use core::sync::atomic::Ordering::SeqCst;
+
+    static NOW: AtomicU64 = AtomicU64::new(1_000);
+    static WARNINGS: AtomicU32 = AtomicU32::new(0);
+    static LAST_GAP: AtomicU64 = AtomicU64::new(0);
+    static LAST_LINE: AtomicU32 = AtomicU32::new(0);
+
+    fn fake_clock() -> u64 {
+        NOW.load(SeqCst)
+    }
+
+    fn record(gap: &CheckGap) {
+        WARNINGS.fetch_add(1, SeqCst);
+        LAST_GAP.store(gap.gap_ms, SeqCst);
+        LAST_LINE.store(gap.location.line(), SeqCst);
+        assert_eq!(gap.previous.file(), file!());
+    }
+
+    #[test]
+    fn test_check_gap_warning() {
+        let _guard = lock();
+        clear_signal();
+        let previous = set_clock(fake_clock);
+        WARNINGS.store(0, SeqCst);
+        set_check_gap_warning(50, record);
+
+        let _ = try_check_signal();
+        NOW.fetch_add(50, SeqCst);
+        let _ = try_check_signal();
+        assert_eq!(WARNINGS.load(SeqCst), 0);
+
+        // Idle time before a restart does not count
+        NOW.fetch_add(500, SeqCst);
+        restart_check_gap();
+        let _ = try_check_signal();
+        assert_eq!(WARNINGS.load(SeqCst), 0);
+
+        NOW.fetch_add(80, SeqCst);
+        let line = line!() + 1;
+        let _ = try_check_signal();
+        assert_eq!(WARNINGS.load(SeqCst), 1);
+        assert_eq!(LAST_GAP.load(SeqCst), 80);
+        assert_eq!(LAST_LINE.load(SeqCst), line);
+
+        // Only once, until set again
+        NOW.fetch_add(80, SeqCst);
+        let _ = try_check_signal();
+        let _ = try_check_signal();
+        NOW.fetch_add(80, SeqCst);
+        let _ = try_check_signal();
+        assert_eq!(WARNINGS.load(SeqCst), 1);
+
+        clear_check_gap_warning();
+        match previous {
+            Some(clock) => set_clock(clock),
+            None => clear_clock(),
+        };
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_scoped_state_restores_gap_warning() {
+        let _guard = lock();
+        clear_check_gap_warning();
+        let threshold_ms = THRESHOLD_MS.load(SeqCst);
+
+        {
+            let _state = crate::testing::ScopedSignalState::new();
+            set_check_gap_warning(threshold_ms + 10, record);
+        }
+
+        assert!(WARNING.load(SeqCst).is_null());
+        assert_eq!(THRESHOLD_MS.load(SeqCst), threshold_ms);
+        assert_eq!(crate::ARMED.load(SeqCst) & ARMED_GAP, 0);
+    }
+}
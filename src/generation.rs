@@ -0,0 +1,175 @@
+//! Handler generations and quiescence.
+//!
+//! In threaded builds a check on one thread can load the handler just before
+//! another thread replaces or clears it, and go on to call the old handler
+//! after the replacement returned. That is harmless for most handlers, but
+//! not for one that borrows state its owner tears down right after removing
+//! it.
+//!
+//! Dispatches therefore register as readers of the current epoch before
+//! loading the handler, and [`synchronize_signal_handler`] waits for the
+//! readers of every earlier epoch to finish, in the manner of RCU. Each
+//! registration also bumps a [generation](handler_generation) counter, so
+//! code can tell whether the handler changed under it.
+//!
+//! The protocol uses sequentially consistent orderings throughout, including
+//! with the `relaxed-fast` feature: it only runs when a signal is dispatched,
+//! never on the fast path.
+
+use core::sync::atomic::Ordering::{Acquire, Release, SeqCst};
+use core::sync::atomic::{AtomicBool, AtomicU32};
+
+use crate::{ACQUIRE, ACQ_REL};
+
+/// Bumped on every handler registration or removal.
+static GENERATION: AtomicU32 = AtomicU32::new(0);
+
+/// Flipped by [`synchronize_signal_handler`]; readers count against its
+/// parity.
+static EPOCH: AtomicU32 = AtomicU32::new(0);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_READERS: AtomicU32 = AtomicU32::new(0);
+
+/// Dispatches in progress, by the parity of the epoch they started in.
+static READERS: [AtomicU32; 2] = [NO_READERS; 2];
+
+/// Serializes [`synchronize_signal_handler`] calls, as concurrent epoch
+/// flips could otherwise skip a parity.
+static SYNCHRONIZING: AtomicBool = AtomicBool::new(false);
+
+/// Returns the handler generation, which changes whenever a handler is
+/// registered or cleared.
+///
+/// A handler that compares it against the value taken when it was
+/// registered can tell whether it has since been replaced.
+pub fn handler_generation() -> u32 {
+    GENERATION.load(ACQUIRE)
+}
+
+/// Records a handler registration or removal.
+pub(crate) fn bump() {
+    GENERATION.fetch_add(1, ACQ_REL);
+}
+
+/// A dispatch in progress, counted until dropped (including by unwinding
+/// out of the handler).
+pub(crate) struct Reader(usize);
+
+/// Registers a dispatch in the current epoch. Load the handler after this
+/// returns.
+pub(crate) fn enter() -> Reader {
+    loop {
+        let epoch = EPOCH.load(SeqCst);
+        let parity = (epoch & 1) as usize;
+        READERS[parity].fetch_add(1, SeqCst);
+        // Counted against the epoch that was current when the count became
+        // visible, or not at all
+        if EPOCH.load(SeqCst) == epoch {
+            return Reader(parity);
+        }
+        READERS[parity].fetch_sub(1, SeqCst);
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        READERS[self.0].fetch_sub(1, SeqCst);
+    }
+}
+
+/// Waits until every dispatch that may have loaded a previous handler has
+/// returned.
+///
+/// After [`set_signal_handler`](crate::set_signal_handler) or
+/// [`clear_signal_handler`](crate::clear_signal_handler) returns, checks on
+/// other threads that had already loaded the old handler may still be
+/// running it. Once this returns, none are, and state the old handler uses
+/// can be released.
+///
+/// Must not be called from a signal handler, which would wait for itself.
+///
+/// # Panics
+///
+/// On wasm32 without threads, panics if called from a signal handler
+/// rather than waiting forever.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{clear_signal_handler, synchronize_signal_handler};
+///
+/// clear_signal_handler();
+/// synchronize_signal_handler();
+/// // No check is still running the old handler
+/// ```
+pub fn synchronize_signal_handler() {
+    while SYNCHRONIZING
+        .compare_exchange_weak(false, true, Acquire, SeqCst)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // Two flips: the first moves new dispatches to the other parity, the
+    // second drains dispatches that registered against it just before
+    for _ in 0..2 {
+        let parity = (EPOCH.fetch_add(1, SeqCst) & 1) as usize;
+        // Without threads, the only reader can be the caller's own handler
+        #[cfg(all(target_arch = "wasm32", not(target_feature = "atomics")))]
+        if READERS[parity].load(SeqCst) != 0 {
+            SYNCHRONIZING.store(false, Release);
+            panic!("synchronize_signal_handler called from a signal handler");
+        }
+        while READERS[parity].load(SeqCst) != 0 {
+            #[cfg(feature = "std")]
+            std::thread::yield_now();
+            #[cfg(not(feature = "std"))]
+            core::hint::spin_loop();
+        }
+    }
+    SYNCHRONIZING.store(false, Release);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, set_signal, set_signal_handler, try_check_signal,
+        Signal,
+    };
+
+    extern crate std;
+    use std::thread;
+    use std::time::Duration;
+
+    static ENTERED: AtomicBool = AtomicBool::new(false);
+    static RETURNED: AtomicBool = AtomicBool::new(false);
+
+    fn slow(_signal: Signal) -> Result<(), Signal> {
+        ENTERED.store(true, SeqCst);
+        thread::sleep(Duration::from_millis(50));
+        RETURNED.store(true, SeqCst);
+        Ok(())
+    }
+
+    #[test]
+    fn test_synchronize_waits_for_old_handler() {
+        let _guard = lock();
+        clear_signal();
+        let before = handler_generation();
+        set_signal_handler(slow);
+        assert_eq!(handler_generation(), before.wrapping_add(1));
+
+        set_signal(Signal::CANCEL.code());
+        let checker = thread::spawn(try_check_signal);
+        while !ENTERED.load(SeqCst) {
+            thread::yield_now();
+        }
+        clear_signal_handler();
+        synchronize_signal_handler();
+        assert!(RETURNED.load(SeqCst));
+        assert!(checker.join().unwrap().is_ok());
+        assert_eq!(handler_generation(), before.wrapping_add(2));
+    }
+}
@@ -0,0 +1,256 @@
+//! Grace-period escalation after a soft cancel.
+//!
+//! When a [`Signal::CANCEL`], with or without an encoded severity, is observed
+//! by a check and a grace period is configured, a grace window opens. The
+//! application is expected to wind down and call [`end_grace`] once it has
+//! returned; if the window runs out first, the next check escalates to the
+//! fatal path with [`Signal::TERMINATE`] (run the shutdown hooks, then panic
+//! or trap), even from [`try_check_signal`](crate::try_check_signal).
+//!
+//! Handlers run after the window opens, so they can consult
+//! [`grace_remaining`] to decide whether to propagate the cancel or let the
+//! current unit of work finish.
+
+use core::sync::atomic::{AtomicU32, AtomicU64};
+use core::time::Duration;
+
+use crate::{
+    arm, disarm, now_ms, NoClock, Signal, SignalKind, ACQUIRE, ACQ_REL, ARMED_GRACE, RELEASE,
+};
+
+/// The length of a grace window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum GracePeriod {
+    /// Escalate on the check after this many further checks.
+    Checks(u32),
+    /// Escalate on the first check after this much time, per the
+    /// [clock](crate::now_ms).
+    Time(Duration),
+}
+
+const KIND_NONE: u32 = 0;
+const KIND_CHECKS: u32 = 1;
+const KIND_TIME: u32 = 2;
+
+/// The configured grace period: its kind and amount (checks or milliseconds).
+static CONFIG_KIND: AtomicU32 = AtomicU32::new(KIND_NONE);
+static CONFIG_AMOUNT: AtomicU64 = AtomicU64::new(0);
+
+/// The open window: its kind and what is left (checks, or the clock time at
+/// which it closes). The window is open while `ARMED_GRACE` is set.
+static WINDOW_KIND: AtomicU32 = AtomicU32::new(KIND_NONE);
+static WINDOW_LEFT: AtomicU64 = AtomicU64::new(0);
+
+/// Configures the grace period applied to subsequent soft cancels.
+///
+/// A window that is already open keeps its original length.
+///
+/// # Errors
+///
+/// Returns [`NoClock`] for [`GracePeriod::Time`] if no clock is available.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{set_grace_period, GracePeriod};
+///
+/// // Give the application 10,000 more checks to return after a cancel
+/// set_grace_period(GracePeriod::Checks(10_000)).unwrap();
+/// ```
+pub fn set_grace_period(period: GracePeriod) -> Result<(), NoClock> {
+    let (kind, amount) = match period {
+        GracePeriod::Checks(checks) => (KIND_CHECKS, u64::from(checks)),
+        GracePeriod::Time(duration) => {
+            now_ms().ok_or(NoClock)?;
+            let millis = u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+            (KIND_TIME, millis)
+        }
+    };
+    CONFIG_AMOUNT.store(amount, RELEASE);
+    CONFIG_KIND.store(kind, RELEASE);
+    Ok(())
+}
+
+/// Removes the grace period configuration. An open window is not affected.
+pub fn clear_grace_period() {
+    CONFIG_KIND.store(KIND_NONE, RELEASE);
+}
+
+/// Gets the configured grace period, if any.
+pub fn get_grace_period() -> Option<GracePeriod> {
+    let amount = CONFIG_AMOUNT.load(ACQUIRE);
+    match CONFIG_KIND.load(ACQUIRE) {
+        KIND_CHECKS => Some(GracePeriod::Checks(amount as u32)),
+        KIND_TIME => Some(GracePeriod::Time(Duration::from_millis(amount))),
+        _ => None,
+    }
+}
+
+/// Returns what is left of the open grace window, or `None` if no window is
+/// open.
+///
+/// The remainder is reported in the unit the window was configured with.
+pub fn grace_remaining() -> Option<GracePeriod> {
+    if crate::ARMED.load(ACQUIRE) & ARMED_GRACE == 0 {
+        return None;
+    }
+    let left = WINDOW_LEFT.load(ACQUIRE);
+    match WINDOW_KIND.load(ACQUIRE) {
+        KIND_CHECKS => Some(GracePeriod::Checks(left as u32)),
+        KIND_TIME => {
+            let now = now_ms()?;
+            Some(GracePeriod::Time(Duration::from_millis(
+                left.saturating_sub(now),
+            )))
+        }
+        _ => None,
+    }
+}
+
+/// Closes the open grace window, if any.
+///
+/// Call this once the application has returned from the cancelled work.
+pub fn end_grace() {
+    disarm(ARMED_GRACE);
+    WINDOW_KIND.store(KIND_NONE, RELEASE);
+}
+
+/// Opens a grace window for an observed soft cancel, unless one is already
+/// open or no grace period is configured.
+pub(crate) fn start(signal: Signal) {
+    if signal.kind() != SignalKind::Cancel || crate::ARMED.load(ACQUIRE) & ARMED_GRACE != 0 {
+        return;
+    }
+    let kind = CONFIG_KIND.load(ACQUIRE);
+    let amount = CONFIG_AMOUNT.load(ACQUIRE);
+    let left = match kind {
+        KIND_CHECKS => amount,
+        KIND_TIME => match now_ms() {
+            Some(now) => now.saturating_add(amount),
+            None => return,
+        },
+        _ => return,
+    };
+    WINDOW_LEFT.store(left, RELEASE);
+    WINDOW_KIND.store(kind, RELEASE);
+    arm(ARMED_GRACE);
+}
+
+/// Called from the check slow path while a window is open.
+///
+/// Returns `true` if the window has run out and the check must escalate.
+pub(crate) fn expired() -> bool {
+    match WINDOW_KIND.load(ACQUIRE) {
+        KIND_CHECKS => WINDOW_LEFT
+            .try_update(ACQ_REL, ACQUIRE, |left| left.checked_sub(1))
+            .is_err(),
+        KIND_TIME => match now_ms() {
+            Some(now) => now >= WINDOW_LEFT.load(ACQUIRE),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// The configuration and open window, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    config_kind: u32,
+    config_amount: u64,
+    window_kind: u32,
+    window_left: u64,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        config_kind: CONFIG_KIND.load(ACQUIRE),
+        config_amount: CONFIG_AMOUNT.load(ACQUIRE),
+        window_kind: WINDOW_KIND.load(ACQUIRE),
+        window_left: WINDOW_LEFT.load(ACQUIRE),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    CONFIG_KIND.store(saved.config_kind, RELEASE);
+    CONFIG_AMOUNT.store(saved.config_amount, RELEASE);
+    WINDOW_KIND.store(saved.window_kind, RELEASE);
+    WINDOW_LEFT.store(saved.window_left, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_shutdown_hooks, clear_signal, clear_signal_handler, encode_signal,
+        rearm_shutdown_hooks, set_signal, try_check_signal, Severity,
+    };
+
+    extern crate std;
+
+    #[test]
+    fn test_escalates_after_checks() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        clear_shutdown_hooks();
+        set_grace_period(GracePeriod::Checks(2)).unwrap();
+
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert_eq!(grace_remaining(), Some(GracePeriod::Checks(2)));
+
+        assert!(try_check_signal().is_ok());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(grace_remaining(), Some(GracePeriod::Checks(0)));
+        assert!(std::panic::catch_unwind(try_check_signal).is_err());
+
+        // Escalation closes the window
+        assert!(grace_remaining().is_none());
+        assert!(try_check_signal().is_ok());
+
+        clear_grace_period();
+        rearm_shutdown_hooks();
+    }
+
+    #[test]
+    fn test_end_grace_prevents_escalation() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        set_grace_period(GracePeriod::Checks(0)).unwrap();
+
+        // Only soft cancels open a window
+        set_signal(Signal::TERMINATE.code());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        assert!(grace_remaining().is_none());
+
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        end_grace();
+        assert!(try_check_signal().is_ok());
+
+        clear_grace_period();
+    }
+
+    #[test]
+    fn test_severity_encoded_cancel_opens_window() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        set_grace_period(GracePeriod::Checks(1)).unwrap();
+
+        let word = encode_signal(Signal::CANCEL.code(), Severity::Cancel);
+        set_signal(word);
+        assert_eq!(try_check_signal().map_err(|s| s.code()), Err(word));
+        assert_eq!(grace_remaining(), Some(GracePeriod::Checks(1)));
+
+        end_grace();
+        clear_grace_period();
+    }
+}
@@ -0,0 +1,245 @@
+//! Startup self-verification.
+//!
+//! Most ways to miswire the signal subsystem fail silently: a stripped
+//! export, a host writing through a stale address or a missing clock all
+//! just mean that signals never arrive. [`health_check`] verifies the wiring
+//! in one call, so integrators can assert it in staging, or at startup.
+
+use core::fmt;
+
+use crate::{get_clock, get_signal_handler, verify_exports, ExportError, ABI_VERSION};
+
+/// The state of a healthy signal subsystem, returned by [`health_check`].
+///
+/// Conditions that are only wrong for some applications, such as running
+/// without a handler, are reported here rather than as errors; use the
+/// `expect_*` methods to turn them into errors where they matter.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{health_check, HealthError};
+///
+/// fn start() -> Result<(), HealthError> {
+///     health_check()?.expect_host()?.expect_handler()?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HealthReport {
+    /// The address of the signal word, or `None` with global storage.
+    pub signal_addr: Option<usize>,
+    /// Whether a host attached to the exports (see
+    /// [`verify_exports`](crate::verify_exports)).
+    pub host_attached: bool,
+    /// Whether a signal handler is registered.
+    pub handler_installed: bool,
+    /// Whether a clock is available, as deadlines and frame budgets need.
+    pub clock_available: bool,
+    /// The ABI version the guest was built with.
+    pub abi_version: u32,
+}
+
+impl HealthReport {
+    /// Requires that a host attached to the exports.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HealthError::NoHost`] otherwise.
+    pub fn expect_host(self) -> Result<HealthReport, HealthError> {
+        if self.host_attached {
+            Ok(self)
+        } else {
+            Err(HealthError::NoHost)
+        }
+    }
+
+    /// Requires that a signal handler is registered.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HealthError::NoHandler`] otherwise.
+    pub fn expect_handler(self) -> Result<HealthReport, HealthError> {
+        if self.handler_installed {
+            Ok(self)
+        } else {
+            Err(HealthError::NoHandler)
+        }
+    }
+
+    /// Requires that a clock is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`HealthError::NoClock`] otherwise.
+    pub fn expect_clock(self) -> Result<HealthReport, HealthError> {
+        if self.clock_available {
+            Ok(self)
+        } else {
+            Err(HealthError::NoClock)
+        }
+    }
+}
+
+/// A wiring problem found by [`health_check`] or a [`HealthReport`]
+/// expectation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HealthError {
+    /// The exports were stripped or their metadata overwritten.
+    Exports(ExportError),
+    /// The signal word is outside linear memory.
+    OutOfBounds {
+        /// The address of the signal word.
+        addr: usize,
+        /// The size of linear memory in bytes.
+        memory: usize,
+    },
+    /// The signal word is not 4-byte aligned.
+    Misaligned(usize),
+    /// The magic value before the signal word was overwritten.
+    BlockCorrupted,
+    /// The metadata block records a different ABI version than the guest
+    /// was built with.
+    AbiMismatch {
+        /// The version found in the metadata block.
+        found: u32,
+        /// The version the guest was built with.
+        expected: u32,
+    },
+    /// No host attached to the exports.
+    NoHost,
+    /// No signal handler is registered.
+    NoHandler,
+    /// No clock is available.
+    NoClock,
+}
+
+impl fmt::Display for HealthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthError::Exports(err) => fmt::Display::fmt(err, f),
+            HealthError::OutOfBounds { addr, memory } => write!(
+                f,
+                "the signal word at {addr:#x} is outside linear memory ({memory} bytes)"
+            ),
+            HealthError::Misaligned(addr) => {
+                write!(f, "the signal word at {addr:#x} is not 4-byte aligned")
+            }
+            HealthError::BlockCorrupted => {
+                f.write_str("the magic value before the signal word was overwritten")
+            }
+            HealthError::AbiMismatch { found, expected } => write!(
+                f,
+                "the metadata block records ABI version {found}, expected {expected}"
+            ),
+            HealthError::NoHost => f.write_str("no host has attached to the signal exports"),
+            HealthError::NoHandler => f.write_str("no signal handler is registered"),
+            HealthError::NoClock => f.write_str("no clock available; register one with set_clock"),
+        }
+    }
+}
+
+impl core::error::Error for HealthError {}
+
+impl From<ExportError> for HealthError {
+    fn from(err: ExportError) -> HealthError {
+        HealthError::Exports(err)
+    }
+}
+
+/// Verifies the signal subsystem's wiring.
+///
+/// Checks that the metadata block and the header of the signal word are
+/// intact, that the metadata records the guest's ABI version, that the
+/// signal word is aligned and inside linear memory, and that the signal
+/// export was not stripped. A host that has not attached yet is not an
+/// error; see [`HealthReport::expect_host`].
+///
+/// # Errors
+///
+/// Returns the first [`HealthError`] found.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::health_check;
+///
+/// let report = health_check().unwrap();
+/// assert!(report.signal_addr.is_some());
+/// ```
+pub fn health_check() -> Result<HealthReport, HealthError> {
+    let host_attached = match verify_exports() {
+        Ok(()) => true,
+        Err(ExportError::NotAttached) => false,
+        Err(err) => return Err(err.into()),
+    };
+    let found = crate::abi::metadata_version();
+    if found != ABI_VERSION {
+        return Err(HealthError::AbiMismatch {
+            found,
+            expected: ABI_VERSION,
+        });
+    }
+
+    #[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+    let signal_addr = {
+        if !crate::storage::block_intact() {
+            return Err(HealthError::BlockCorrupted);
+        }
+        let addr = crate::signal_addr();
+        if !addr.is_multiple_of(4) {
+            return Err(HealthError::Misaligned(addr));
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let memory = core::arch::wasm32::memory_size(0) * 65536;
+            if addr + 4 > memory {
+                return Err(HealthError::OutOfBounds { addr, memory });
+            }
+        }
+        Some(addr)
+    };
+    #[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+    let signal_addr = None;
+
+    Ok(HealthReport {
+        signal_addr,
+        host_attached,
+        handler_installed: get_signal_handler().is_some(),
+        clock_available: get_clock().is_some(),
+        abi_version: ABI_VERSION,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal_handler, set_signal_handler, ATTACHED_EXPORT};
+
+    #[test]
+    fn test_health_check() {
+        let _guard = lock();
+        clear_signal_handler();
+        let previous = crate::abi::attach(0);
+
+        let report = health_check().unwrap();
+        assert!(!report.host_attached);
+        assert_eq!(report.abi_version, ABI_VERSION);
+        assert_eq!(report.expect_host(), Err(HealthError::NoHost));
+        assert_eq!(report.expect_handler(), Err(HealthError::NoHandler));
+
+        crate::abi::attach(ATTACHED_EXPORT);
+        set_signal_handler(|_| Ok(()));
+        let report = health_check().unwrap();
+        assert!(report
+            .expect_host()
+            .and_then(HealthReport::expect_handler)
+            .is_ok());
+
+        clear_signal_handler();
+        crate::abi::attach(previous);
+    }
+}
@@ -0,0 +1,180 @@
+//! Check counts for every call site.
+//!
+//! The [advisor](crate::set_check_advisor) tracks a handful of sites in
+//! detail. For auditing cancellation coverage across a whole program, the
+//! heat map instead counts checks at every site, without a limit: the sites
+//! at the top of [`check_heatmap`] dominate execution, and code that appears
+//! nowhere in it never checked while the heat map was enabled.
+//!
+//! While enabled, every check takes the slow path and a lock, so enable it
+//! for a profiling run rather than in production.
+
+use core::panic::Location;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use std::vec::Vec;
+
+use crate::{arm, disarm, ACQUIRE, ARMED, ARMED_HEATMAP};
+
+/// Check counts by call site, from [`check_heatmap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CheckHeat {
+    /// The call site.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_location"))]
+    pub location: &'static Location<'static>,
+    /// Checks made at the site.
+    pub checks: u64,
+}
+
+#[cfg(feature = "serde")]
+fn serialize_location<S: serde::Serializer>(
+    location: &&'static Location<'static>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(location)
+}
+
+type Counts = BTreeMap<&'static Location<'static>, u64>;
+
+static HEATMAP: Mutex<Counts> = Mutex::new(BTreeMap::new());
+
+/// Runs `f` on the counts under the lock.
+///
+/// The map stays valid if `f` panics, so a poisoned lock is taken over
+/// rather than failing every later check.
+fn with_counts<R>(f: impl FnOnce(&mut Counts) -> R) -> R {
+    f(&mut HEATMAP.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Enables or disables the heat map. Disabling it keeps the counts.
+pub fn set_check_heatmap(enabled: bool) {
+    if enabled {
+        arm(ARMED_HEATMAP);
+    } else {
+        disarm(ARMED_HEATMAP);
+    }
+}
+
+/// Returns `true` if the heat map is enabled.
+pub fn check_heatmap_enabled() -> bool {
+    ARMED.load(ACQUIRE) & ARMED_HEATMAP != 0
+}
+
+/// Returns the check count of every call site that checked while the heat
+/// map was enabled, most checks first. Sites with equal counts are ordered
+/// by file, line and column.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{check_heatmap, set_check_heatmap, try_check_signal};
+///
+/// set_check_heatmap(true);
+/// for _ in 0..1000 {
+///     let _ = try_check_signal();
+/// }
+/// set_check_heatmap(false);
+///
+/// for heat in check_heatmap() {
+///     println!("{:>10} {}", heat.checks, heat.location);
+/// }
+/// # wasm_signal_handler::reset_check_heatmap();
+/// ```
+pub fn check_heatmap() -> Vec<CheckHeat> {
+    let mut heatmap: Vec<CheckHeat> = with_counts(|counts| {
+        counts
+            .iter()
+            .map(|(&location, &checks)| CheckHeat { location, checks })
+            .collect()
+    });
+    // Stable, so ties keep the map's location order
+    heatmap.sort_by_key(|heat| core::cmp::Reverse(heat.checks));
+    heatmap
+}
+
+/// Forgets every count.
+pub fn reset_check_heatmap() {
+    with_counts(Counts::clear);
+}
+
+/// Counts a check made at `location`.
+pub(crate) fn record(location: &'static Location<'static>) {
+    with_counts(|counts| *counts.entry(location).or_default() += 1);
+}
+
+/// The counts, saved by `ScopedSignalState`. Whether the heat map is
+/// enabled is saved with the other armed flags.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(Counts);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(with_counts(|counts| counts.clone()))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    with_counts(|counts| counts.clone_from(&saved.0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, try_check_signal};
+
+    #[test]
+    fn test_check_heatmap() {
+        let _guard = lock();
+        clear_signal();
+        reset_check_heatmap();
+        set_check_heatmap(true);
+        assert!(check_heatmap_enabled());
+
+        for i in 0..10 {
+            let _ = try_check_signal();
+            if i % 5 == 0 {
+                let _ = try_check_signal();
+            }
+        }
+        set_check_heatmap(false);
+        let _ = try_check_signal();
+
+        let heatmap = check_heatmap();
+        assert_eq!(
+            heatmap.iter().map(|heat| heat.checks).collect::<Vec<_>>(),
+            [10, 2]
+        );
+        assert!(heatmap.iter().all(|heat| heat.location.file() == file!()));
+        assert!(heatmap[0].location.line() < heatmap[1].location.line());
+        reset_check_heatmap();
+        assert!(check_heatmap().is_empty());
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_scoped_state_restores_heatmap() {
+        let _guard = lock();
+        clear_signal();
+        reset_check_heatmap();
+        set_check_heatmap(true);
+        let _ = try_check_signal();
+        set_check_heatmap(false);
+
+        {
+            let _state = crate::testing::ScopedSignalState::new();
+            assert!(check_heatmap().is_empty());
+            set_check_heatmap(true);
+            let _ = try_check_signal();
+            let _ = try_check_signal();
+        }
+
+        assert!(!check_heatmap_enabled());
+        let heatmap = check_heatmap();
+        assert_eq!(heatmap.len(), 1);
+        assert_eq!(heatmap[0].checks, 1);
+        reset_check_heatmap();
+    }
+}
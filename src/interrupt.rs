@@ -0,0 +1,174 @@
+//! Named interrupt points.
+//!
+//! [`interrupt_point!`] is a check with a name. With the `log` or `tracing`
+//! feature, every execution is recorded along with the call site: reaching
+//! the point at trace level, and cancellation there at debug level. Placed
+//! between the stages of a pipeline, the records show which stage a
+//! cancellation actually landed in. Without either feature it is a plain
+//! [`try_check_signal`](crate::try_check_signal).
+
+#[cfg(any(feature = "log", feature = "tracing"))]
+use core::panic::Location;
+
+use crate::Signal;
+
+/// The `log` target and `tracing` target of the records.
+#[cfg(any(feature = "log", feature = "tracing"))]
+const TARGET: &str = "wasm_signal";
+
+/// Records the result of the check at an interrupt point, returning it.
+#[doc(hidden)]
+#[inline(always)]
+#[track_caller]
+pub fn __interrupt_point(name: &'static str, result: Result<(), Signal>) -> Result<(), Signal> {
+    #[cfg(any(feature = "log", feature = "tracing"))]
+    record(name, Location::caller(), result);
+    let _ = name;
+    result
+}
+
+#[cfg(any(feature = "log", feature = "tracing"))]
+#[inline(never)]
+fn record(name: &'static str, location: &'static Location<'static>, result: Result<(), Signal>) {
+    #[cfg(feature = "log")]
+    match result {
+        Ok(()) => log_record(
+            log::Level::Trace,
+            location,
+            format_args!("interrupt point {name} reached"),
+        ),
+        Err(signal) => log_record(
+            log::Level::Debug,
+            location,
+            format_args!(
+                "interrupt point {name} cancelled by signal {}",
+                signal.code()
+            ),
+        ),
+    }
+    #[cfg(feature = "tracing")]
+    match result {
+        Ok(()) => tracing::trace!(
+            target: TARGET,
+            point = name,
+            file = location.file(),
+            line = location.line(),
+            "interrupt point reached"
+        ),
+        Err(signal) => tracing::debug!(
+            target: TARGET,
+            point = name,
+            file = location.file(),
+            line = location.line(),
+            signal = signal.code(),
+            "interrupt point cancelled"
+        ),
+    }
+}
+
+/// Logs a record attributed to the interrupt point's call site rather than
+/// to this module.
+#[cfg(feature = "log")]
+fn log_record(level: log::Level, location: &'static Location<'static>, args: core::fmt::Arguments) {
+    if level > log::max_level() {
+        return;
+    }
+    log::logger().log(
+        &log::Record::builder()
+            .args(args)
+            .level(level)
+            .target(TARGET)
+            .file_static(Some(location.file()))
+            .line(Some(location.line()))
+            .build(),
+    );
+}
+
+/// [`try_check_signal`](crate::try_check_signal) at a named point,
+/// recorded with the `log` or `tracing` feature.
+///
+/// Reaching the point is recorded at trace level and cancellation at debug
+/// level, under the `wasm_signal` target, with the name and the file and
+/// line of the call site.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{interrupt_point, Signal};
+///
+/// fn handle(body: &str) -> Result<usize, Signal> {
+///     interrupt_point!("parse")?;
+///     let words = body.split_whitespace().count();
+///     interrupt_point!("render")?;
+///     Ok(words)
+/// }
+/// # assert_eq!(handle("a b"), Ok(2));
+/// ```
+#[macro_export]
+macro_rules! interrupt_point {
+    ($name:expr) => {
+        $crate::__interrupt_point($name, $crate::try_check_signal())
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal};
+
+    #[cfg(feature = "log")]
+    mod capture {
+        use core::sync::atomic::{AtomicU32, Ordering::SeqCst};
+
+        pub static RECORDS: AtomicU32 = AtomicU32::new(0);
+        pub static LAST_LINE: AtomicU32 = AtomicU32::new(0);
+        pub static LAST_LEVEL: AtomicU32 = AtomicU32::new(0);
+
+        /// Counts the records for interrupt points.
+        pub struct Logger;
+
+        impl log::Log for Logger {
+            fn enabled(&self, _metadata: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                if record.target() == super::TARGET && record.file() == Some(file!()) {
+                    RECORDS.fetch_add(1, SeqCst);
+                    LAST_LINE.store(record.line().unwrap_or(0), SeqCst);
+                    LAST_LEVEL.store(record.level() as u32, SeqCst);
+                }
+            }
+
+            fn flush(&self) {}
+        }
+    }
+
+    #[test]
+    fn test_interrupt_point() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        #[cfg(feature = "log")]
+        {
+            let _ = log::set_logger(&capture::Logger);
+            log::set_max_level(log::LevelFilter::Trace);
+        }
+
+        assert_eq!(interrupt_point!("parse"), Ok(()));
+        set_signal(Signal::CANCEL.code());
+        let line = line!() + 1;
+        assert_eq!(interrupt_point!("render"), Err(Signal::CANCEL));
+
+        #[cfg(feature = "log")]
+        {
+            use core::sync::atomic::Ordering::SeqCst;
+
+            assert_eq!(capture::RECORDS.load(SeqCst), 2);
+            assert_eq!(capture::LAST_LINE.load(SeqCst), line);
+            assert_eq!(capture::LAST_LEVEL.load(SeqCst), log::Level::Debug as u32);
+        }
+        let _ = line;
+    }
+}
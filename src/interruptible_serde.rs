@@ -0,0 +1,749 @@
+//! Checking for signals during serde (de)serialization.
+//!
+//! Parsing a multi-megabyte JSON body is one long call with no place for a
+//! check, so a cancelled request only notices once the whole body is parsed.
+//! [`InterruptibleDeserializer`] and [`InterruptibleSerializer`] wrap any
+//! serde deserializer or serializer, and check for a signal every `n`
+//! sequence elements, map entries and struct fields, at any depth. A
+//! propagated signal fails the operation with the format's own error,
+//! built with `Error::custom`, and is recorded in the shared
+//! [`SerdeChecks`] so the caller can tell it from a parse error.
+//! [`deserialize_interruptible`] and [`serialize_interruptible`] do both in
+//! one call, returning an [`InterruptibleError`].
+
+use core::cell::Cell;
+use core::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+use crate::{try_check_signal, Signal};
+
+/// The check stride shared by the wrappers of one (de)serialization, and
+/// the signal that interrupted it.
+#[derive(Debug)]
+pub struct SerdeChecks {
+    every: u32,
+    left: Cell<u32>,
+    signal: Cell<Option<Signal>>,
+}
+
+impl SerdeChecks {
+    /// Checks every `every` elements, entries and fields. `0` is treated as
+    /// `1`. The first element is checked.
+    pub const fn new(every: u32) -> SerdeChecks {
+        SerdeChecks {
+            every: if every == 0 { 1 } else { every },
+            left: Cell::new(0),
+            signal: Cell::new(None),
+        }
+    }
+
+    /// Returns the signal that interrupted the operation, if any.
+    pub fn signal(&self) -> Option<Signal> {
+        self.signal.get()
+    }
+
+    /// Counts an element, checking if the stride is complete.
+    fn tick(&self) -> Result<(), Signal> {
+        match self.left.get() {
+            0 => {
+                self.left.set(self.every - 1);
+                try_check_signal().inspect_err(|&signal| self.signal.set(Some(signal)))
+            }
+            left => {
+                self.left.set(left - 1);
+                Ok(())
+            }
+        }
+    }
+
+    fn tick_de<E: de::Error>(&self) -> Result<(), E> {
+        self.tick().map_err(|signal| E::custom(Interrupted(signal)))
+    }
+
+    fn tick_ser<E: ser::Error>(&self) -> Result<(), E> {
+        self.tick().map_err(|signal| E::custom(Interrupted(signal)))
+    }
+}
+
+/// The message of the format error for an interruption.
+struct Interrupted(Signal);
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "interrupted by signal {}", self.0.code())
+    }
+}
+
+/// A failed interruptible (de)serialization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterruptibleError<E> {
+    /// A check propagated a signal.
+    Signal(Signal),
+    /// The format failed.
+    Inner(E),
+}
+
+impl<E: fmt::Display> fmt::Display for InterruptibleError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterruptibleError::Signal(signal) => Interrupted(*signal).fmt(f),
+            InterruptibleError::Inner(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: core::error::Error + 'static> core::error::Error for InterruptibleError<E> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            InterruptibleError::Signal(signal) => Some(signal),
+            InterruptibleError::Inner(error) => Some(error),
+        }
+    }
+}
+
+impl<E> InterruptibleError<E> {
+    fn new(checks: &SerdeChecks, error: E) -> InterruptibleError<E> {
+        match checks.signal() {
+            Some(signal) => InterruptibleError::Signal(signal),
+            None => InterruptibleError::Inner(error),
+        }
+    }
+}
+
+/// Deserializes a `T` from `deserializer`, checking for a signal every
+/// `every` elements, entries and fields.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{deserialize_interruptible, InterruptibleError};
+///
+/// let mut json = serde_json::Deserializer::from_str("[1, 2, 3]");
+/// let numbers: Vec<u32> = deserialize_interruptible(&mut json, 256)?;
+/// # assert_eq!(numbers, [1, 2, 3]);
+/// # Ok::<(), InterruptibleError<serde_json::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns [`InterruptibleError::Signal`] if a check propagated a signal,
+/// and [`InterruptibleError::Inner`] if the format failed.
+pub fn deserialize_interruptible<'de, T, D>(
+    deserializer: D,
+    every: u32,
+) -> Result<T, InterruptibleError<D::Error>>
+where
+    T: de::Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    let checks = SerdeChecks::new(every);
+    T::deserialize(InterruptibleDeserializer::new(deserializer, &checks))
+        .map_err(|error| InterruptibleError::new(&checks, error))
+}
+
+/// Serializes `value` with `serializer`, checking for a signal every `every`
+/// elements, entries and fields.
+///
+/// # Errors
+///
+/// Returns [`InterruptibleError::Signal`] if a check propagated a signal,
+/// and [`InterruptibleError::Inner`] if the format failed.
+pub fn serialize_interruptible<T, S>(
+    value: &T,
+    serializer: S,
+    every: u32,
+) -> Result<S::Ok, InterruptibleError<S::Error>>
+where
+    T: ?Sized + Serialize,
+    S: Serializer,
+{
+    let checks = SerdeChecks::new(every);
+    value
+        .serialize(InterruptibleSerializer::new(serializer, &checks))
+        .map_err(|error| InterruptibleError::new(&checks, error))
+}
+
+// ============================================================================
+// Deserializer
+// ============================================================================
+
+/// A [`Deserializer`] checking for signals as it goes.
+#[derive(Debug)]
+pub struct InterruptibleDeserializer<'a, D> {
+    inner: D,
+    checks: &'a SerdeChecks,
+}
+
+impl<'a, D> InterruptibleDeserializer<'a, D> {
+    /// Wraps `inner`, checking on the stride of `checks`.
+    pub fn new(inner: D, checks: &'a SerdeChecks) -> InterruptibleDeserializer<'a, D> {
+        InterruptibleDeserializer { inner, checks }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, D::Error> {
+                self.inner.$method($($arg,)* Wrap::new(visitor, self.checks))
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for InterruptibleDeserializer<'_, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any();
+        deserialize_bool();
+        deserialize_i8();
+        deserialize_i16();
+        deserialize_i32();
+        deserialize_i64();
+        deserialize_i128();
+        deserialize_u8();
+        deserialize_u16();
+        deserialize_u32();
+        deserialize_u64();
+        deserialize_u128();
+        deserialize_f32();
+        deserialize_f64();
+        deserialize_char();
+        deserialize_str();
+        deserialize_string();
+        deserialize_bytes();
+        deserialize_byte_buf();
+        deserialize_option();
+        deserialize_unit();
+        deserialize_unit_struct(name: &'static str);
+        deserialize_newtype_struct(name: &'static str);
+        deserialize_seq();
+        deserialize_tuple(len: usize);
+        deserialize_tuple_struct(name: &'static str, len: usize);
+        deserialize_map();
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]);
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]);
+        deserialize_identifier();
+        deserialize_ignored_any();
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Wraps the visitors, seeds and accessors handed to and from the inner
+/// deserializer, so nested values are checked too.
+struct Wrap<'a, T> {
+    inner: T,
+    checks: &'a SerdeChecks,
+}
+
+impl<'a, T> Wrap<'a, T> {
+    fn new(inner: T, checks: &'a SerdeChecks) -> Wrap<'a, T> {
+        Wrap { inner, checks }
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method<E: de::Error>(self, value: $ty) -> Result<V::Value, E> {
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for Wrap<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit! {
+        visit_bool(bool);
+        visit_i8(i8);
+        visit_i16(i16);
+        visit_i32(i32);
+        visit_i64(i64);
+        visit_i128(i128);
+        visit_u8(u8);
+        visit_u16(u16);
+        visit_u32(u32);
+        visit_u64(u64);
+        visit_u128(u128);
+        visit_f32(f32);
+        visit_f64(f64);
+        visit_char(char);
+        visit_str(&str);
+        visit_borrowed_str(&'de str);
+        visit_bytes(&[u8]);
+        visit_borrowed_bytes(&'de [u8]);
+    }
+
+    #[cfg(feature = "alloc")]
+    fn visit_string<E: de::Error>(self, value: alloc::string::String) -> Result<V::Value, E> {
+        self.inner.visit_string(value)
+    }
+
+    #[cfg(feature = "alloc")]
+    fn visit_byte_buf<E: de::Error>(self, value: alloc::vec::Vec<u8>) -> Result<V::Value, E> {
+        self.inner.visit_byte_buf(value)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        self.inner
+            .visit_some(InterruptibleDeserializer::new(deserializer, self.checks))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<V::Value, D::Error> {
+        self.inner
+            .visit_newtype_struct(InterruptibleDeserializer::new(deserializer, self.checks))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_seq(Wrap::new(seq, self.checks))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_map(Wrap::new(map, self.checks))
+    }
+
+    fn visit_enum<A: de::EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_enum(Wrap::new(data, self.checks))
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Wrap<'_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<S::Value, D::Error> {
+        self.inner
+            .deserialize(InterruptibleDeserializer::new(deserializer, self.checks))
+    }
+}
+
+impl<'de, A: de::SeqAccess<'de>> de::SeqAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, A::Error> {
+        self.checks.tick_de()?;
+        self.inner.next_element_seed(Wrap::new(seed, self.checks))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        self.checks.tick_de()?;
+        self.inner.next_key_seed(Wrap::new(seed, self.checks))
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, A::Error> {
+        self.inner.next_value_seed(Wrap::new(seed, self.checks))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, 'de, A: de::EnumAccess<'de>> de::EnumAccess<'de> for Wrap<'a, A> {
+    type Error = A::Error;
+    type Variant = Wrap<'a, A::Variant>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, Self::Variant), A::Error> {
+        let checks = self.checks;
+        let (value, variant) = self.inner.variant_seed(Wrap::new(seed, checks))?;
+        Ok((value, Wrap::new(variant, checks)))
+    }
+}
+
+impl<'de, A: de::VariantAccess<'de>> de::VariantAccess<'de> for Wrap<'_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        self.inner
+            .newtype_variant_seed(Wrap::new(seed, self.checks))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        self.inner
+            .tuple_variant(len, Wrap::new(visitor, self.checks))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        self.inner
+            .struct_variant(fields, Wrap::new(visitor, self.checks))
+    }
+}
+
+// ============================================================================
+// Serializer
+// ============================================================================
+
+/// A [`Serializer`] checking for signals as it goes.
+#[derive(Debug)]
+pub struct InterruptibleSerializer<'a, S> {
+    inner: S,
+    checks: &'a SerdeChecks,
+}
+
+impl<'a, S> InterruptibleSerializer<'a, S> {
+    /// Wraps `inner`, checking on the stride of `checks`.
+    pub fn new(inner: S, checks: &'a SerdeChecks) -> InterruptibleSerializer<'a, S> {
+        InterruptibleSerializer { inner, checks }
+    }
+}
+
+/// A value serialized through an [`InterruptibleSerializer`].
+struct Checked<'a, T: ?Sized> {
+    value: &'a T,
+    checks: &'a SerdeChecks,
+}
+
+impl<T: ?Sized + Serialize> Serialize for Checked<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value
+            .serialize(InterruptibleSerializer::new(serializer, self.checks))
+    }
+}
+
+macro_rules! forward_serialize {
+    ($($method:ident($ty:ty);)*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<S::Ok, S::Error> {
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+impl<'a, S: Serializer> Serializer for InterruptibleSerializer<'a, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = InterruptibleCompound<'a, S::SerializeSeq>;
+    type SerializeTuple = InterruptibleCompound<'a, S::SerializeTuple>;
+    type SerializeTupleStruct = InterruptibleCompound<'a, S::SerializeTupleStruct>;
+    type SerializeTupleVariant = InterruptibleCompound<'a, S::SerializeTupleVariant>;
+    type SerializeMap = InterruptibleCompound<'a, S::SerializeMap>;
+    type SerializeStruct = InterruptibleCompound<'a, S::SerializeStruct>;
+    type SerializeStructVariant = InterruptibleCompound<'a, S::SerializeStructVariant>;
+
+    forward_serialize! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_i128(i128);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_u128(u128);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_unit_struct(&'static str);
+    }
+
+    fn serialize_none(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<S::Ok, S::Error> {
+        let checks = self.checks;
+        self.inner.serialize_some(&Checked { value, checks })
+    }
+
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner
+            .serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        let checks = self.checks;
+        self.inner
+            .serialize_newtype_struct(name, &Checked { value, checks })
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        let checks = self.checks;
+        self.inner.serialize_newtype_variant(
+            name,
+            variant_index,
+            variant,
+            &Checked { value, checks },
+        )
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        let checks = self.checks;
+        let inner = self.inner.serialize_seq(len)?;
+        Ok(InterruptibleCompound { inner, checks })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        let checks = self.checks;
+        let inner = self.inner.serialize_tuple(len)?;
+        Ok(InterruptibleCompound { inner, checks })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        let checks = self.checks;
+        let inner = self.inner.serialize_tuple_struct(name, len)?;
+        Ok(InterruptibleCompound { inner, checks })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        let checks = self.checks;
+        let inner = self
+            .inner
+            .serialize_tuple_variant(name, variant_index, variant, len)?;
+        Ok(InterruptibleCompound { inner, checks })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        let checks = self.checks;
+        let inner = self.inner.serialize_map(len)?;
+        Ok(InterruptibleCompound { inner, checks })
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        let checks = self.checks;
+        let inner = self.inner.serialize_struct(name, len)?;
+        Ok(InterruptibleCompound { inner, checks })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        let checks = self.checks;
+        let inner = self
+            .inner
+            .serialize_struct_variant(name, variant_index, variant, len)?;
+        Ok(InterruptibleCompound { inner, checks })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// The sequence, tuple, map and struct serializers of an
+/// [`InterruptibleSerializer`], checking before each element, entry or
+/// field.
+#[derive(Debug)]
+pub struct InterruptibleCompound<'a, C> {
+    inner: C,
+    checks: &'a SerdeChecks,
+}
+
+macro_rules! impl_compound_elements {
+    ($($trait:ident::$method:ident;)*) => {
+        $(
+            impl<C: ser::$trait> ser::$trait for InterruptibleCompound<'_, C> {
+                type Ok = C::Ok;
+                type Error = C::Error;
+
+                fn $method<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), C::Error> {
+                    self.checks.tick_ser()?;
+                    let checks = self.checks;
+                    self.inner.$method(&Checked { value, checks })
+                }
+
+                fn end(self) -> Result<C::Ok, C::Error> {
+                    self.inner.end()
+                }
+            }
+        )*
+    };
+}
+
+impl_compound_elements! {
+    SerializeSeq::serialize_element;
+    SerializeTuple::serialize_element;
+    SerializeTupleStruct::serialize_field;
+    SerializeTupleVariant::serialize_field;
+}
+
+impl<C: ser::SerializeMap> ser::SerializeMap for InterruptibleCompound<'_, C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), C::Error> {
+        self.checks.tick_ser()?;
+        let checks = self.checks;
+        self.inner.serialize_key(&Checked { value: key, checks })
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), C::Error> {
+        let checks = self.checks;
+        self.inner.serialize_value(&Checked { value, checks })
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+macro_rules! impl_compound_fields {
+    ($($trait:ident;)*) => {
+        $(
+            impl<C: ser::$trait> ser::$trait for InterruptibleCompound<'_, C> {
+                type Ok = C::Ok;
+                type Error = C::Error;
+
+                fn serialize_field<T: ?Sized + Serialize>(
+                    &mut self,
+                    key: &'static str,
+                    value: &T,
+                ) -> Result<(), C::Error> {
+                    self.checks.tick_ser()?;
+                    let checks = self.checks;
+                    self.inner.serialize_field(key, &Checked { value, checks })
+                }
+
+                fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+                    self.inner.skip_field(key)
+                }
+
+                fn end(self) -> Result<C::Ok, C::Error> {
+                    self.inner.end()
+                }
+            }
+        )*
+    };
+}
+
+impl_compound_fields! {
+    SerializeStruct;
+    SerializeStructVariant;
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal};
+    use alloc::collections::BTreeMap;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_interruptible_serde() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        let json = "[{\"a\": [1, 2]}, {\"b\": [3]}]";
+        let parsed: Vec<BTreeMap<&str, Vec<u32>>> =
+            deserialize_interruptible(&mut serde_json::Deserializer::from_str(json), 2).unwrap();
+        assert_eq!(parsed[1]["b"], [3]);
+        let mut output = Vec::new();
+        serialize_interruptible(&parsed, &mut serde_json::Serializer::new(&mut output), 2).unwrap();
+        assert_eq!(output, b"[{\"a\":[1,2]},{\"b\":[3]}]");
+
+        // A pending signal interrupts at the first check
+        set_signal(Signal::CANCEL.code());
+        let result: Result<Vec<u32>, _> =
+            deserialize_interruptible(&mut serde_json::Deserializer::from_str("[1]"), 2);
+        assert_eq!(
+            result.map_err(|error| matches!(error, InterruptibleError::Signal(Signal::CANCEL))),
+            Err(true)
+        );
+        set_signal(Signal::CANCEL.code());
+        let result =
+            serialize_interruptible(&parsed, &mut serde_json::Serializer::new(Vec::new()), 2);
+        assert!(matches!(
+            result,
+            Err(InterruptibleError::Signal(Signal::CANCEL))
+        ));
+
+        // A malformed body is the format's error
+        let result: Result<Vec<u32>, _> =
+            deserialize_interruptible(&mut serde_json::Deserializer::from_str("[1,"), 2);
+        assert!(matches!(result, Err(InterruptibleError::Inner(_))));
+    }
+}
@@ -0,0 +1,48 @@
+//! Periodic checks on interval timers.
+//!
+//! Async applications whose own code rarely checks for signals still want
+//! cancellation noticed within a bounded time. [`poll_signal_every`] checks
+//! on every tick of a `gloo-timers` interval, so racing it against the
+//! application's work bounds how long a host signal goes unnoticed.
+//!
+//! wasm-bindgen requires `UnwindSafe` callbacks with `panic=unwind`, which
+//! gloo-timers' are not, so this feature only builds for wasm32 with
+//! `panic=abort`.
+
+use core::future::poll_fn;
+use core::pin::Pin;
+
+use futures_core::Stream;
+use gloo_timers::future::IntervalStream;
+
+use crate::{try_check_signal, Signal};
+
+/// Checks for a signal every `ms` milliseconds, resolving to the first one a
+/// check propagates.
+///
+/// The first check runs immediately, then one on every tick of a
+/// `gloo-timers` [`IntervalStream`]. Signals the handler clears do not
+/// resolve the future. Race it against the application's work, and drop it
+/// to stop checking.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{poll_signal_every, Signal};
+///
+/// // Spawned alongside the application's own tasks
+/// async fn watchdog() {
+///     let signal: Signal = poll_signal_every(100).await;
+///     let _ = signal; // stop the application's tasks
+/// }
+/// ```
+pub async fn poll_signal_every(ms: u32) -> Signal {
+    let mut ticks = IntervalStream::new(ms);
+    loop {
+        if let Err(signal) = try_check_signal() {
+            return signal;
+        }
+        // The interval never ends
+        poll_fn(|cx| Pin::new(&mut ticks).poll_next(cx)).await;
+    }
+}
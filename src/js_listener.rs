@@ -0,0 +1,114 @@
+//! JavaScript callbacks notified of observed signals.
+//!
+//! Supervision code on the JavaScript side of the same isolate, such as
+//! timers, UI or stream plumbing, would otherwise have to poll guest memory
+//! to learn that the guest saw a signal. [`add_js_signal_listener`]
+//! registers a JavaScript function that is called with the code whenever a
+//! check observes one, as a [listener](crate::add_signal_listener) is.
+//!
+//! JavaScript values belong to the thread that created them, so callbacks
+//! are kept per thread and only see the signals observed by checks on the
+//! thread that registered them.
+
+use std::cell::RefCell;
+use std::vec::Vec;
+
+use js_sys::Function;
+use wasm_bindgen::JsValue;
+
+use crate::{add_signal_listener, listeners, Signal};
+
+std::thread_local! {
+    static CALLBACKS: RefCell<Vec<Function>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The listener that forwards to this thread's callbacks.
+fn notify(signal: Signal) {
+    // Cloned out, so callbacks can register or remove callbacks
+    let callbacks = CALLBACKS.with(|callbacks| callbacks.borrow().clone());
+    let code = JsValue::from(signal.code());
+    for callback in callbacks {
+        // A throwing callback must not unwind through the check
+        let _ = callback.call1(&JsValue::UNDEFINED, &code);
+    }
+}
+
+/// Registers a JavaScript function to be called with the code of every
+/// signal a check on this thread observes.
+///
+/// The callback runs inside the check, before the handler, and cannot
+/// change the outcome; exceptions it throws are ignored. It must not call
+/// back into guest code that checks for signals. A callback registered
+/// twice is called twice.
+///
+/// All callbacks share one listener slot, which
+/// [`clear_signal_listeners`](crate::clear_signal_listeners) also clears;
+/// adding a callback afterwards takes the slot again.
+///
+/// # Returns
+///
+/// Returns `false` if the listener slots are all taken by other listeners.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_bindgen::prelude::*;
+/// use wasm_signal_handler::add_js_signal_listener;
+///
+/// // Called from JavaScript with `(code) => ...`
+/// #[wasm_bindgen]
+/// pub fn on_guest_signal(callback: js_sys::Function) -> bool {
+///     add_js_signal_listener(&callback)
+/// }
+/// ```
+///
+/// A Rust closure can be passed as a [`Closure`](wasm_bindgen::closure::Closure)
+/// with `closure.as_ref().unchecked_ref()`, kept alive for as long as it is
+/// registered.
+pub fn add_js_signal_listener(callback: &Function) -> bool {
+    if !listeners::contains(notify) && !add_signal_listener(notify) {
+        return false;
+    }
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().push(callback.clone()));
+    true
+}
+
+/// Removes one registration of `callback`, compared by identity.
+///
+/// # Returns
+///
+/// Returns `true` if the callback was registered on this thread.
+pub fn remove_js_signal_listener(callback: &Function) -> bool {
+    CALLBACKS.with(|callbacks| {
+        let mut callbacks = callbacks.borrow_mut();
+        let Some(index) = callbacks.iter().position(|other| other == callback) else {
+            return false;
+        };
+        callbacks.remove(index);
+        true
+    })
+}
+
+/// Removes every callback registered on this thread.
+pub fn clear_js_signal_listeners() {
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().clear());
+    // Other threads may still have callbacks
+    #[cfg(not(target_feature = "atomics"))]
+    crate::remove_signal_listener(notify);
+}
+
+/// This thread's callbacks, saved by `ScopedSignalState`. The listener slot
+/// they share is saved with the other listeners.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(Vec<Function>);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(CALLBACKS.with(|callbacks| callbacks.borrow().clone()))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    CALLBACKS.with(|callbacks| callbacks.borrow_mut().clone_from(&saved.0));
+}
@@ -0,0 +1,117 @@
+//! Latched handling of termination requests.
+//!
+//! In latched mode, once a check observes [`Signal::TERMINATE`], a signal of
+//! [kill severity](Severity::Kill) or a [fatal](Signal::is_fatal) signal, the
+//! signal is latched: that check and every later one fail with it, even if
+//! the handler returns `Ok(())` or someone calls
+//! [`clear_signal`](crate::clear_signal), until [`unlatch`] is called. This
+//! stops buggy handlers and libraries from accidentally swallowing a
+//! termination request.
+
+use core::sync::atomic::{AtomicBool, AtomicU32};
+
+use crate::{arm, disarm, disposition, Severity, Signal, ACQUIRE, ACQ_REL, ARMED_LATCH, RELEASE};
+
+/// Whether latched mode is enabled.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The latched signal code, or `0` if nothing is latched.
+static LATCHED: AtomicU32 = AtomicU32::new(0);
+
+/// Enables or disables latched mode.
+///
+/// Disabling latched mode does not release a signal that is already latched;
+/// use [`unlatch`] for that.
+pub fn set_latch_mode(enabled: bool) {
+    ENABLED.store(enabled, RELEASE);
+    disposition::refresh();
+}
+
+/// Returns `true` if latched mode is enabled.
+pub fn latch_mode() -> bool {
+    ENABLED.load(ACQUIRE)
+}
+
+/// Returns the latched signal, if any.
+pub fn latched_signal() -> Option<Signal> {
+    Signal::new(LATCHED.load(ACQUIRE))
+}
+
+/// Releases the latched signal so checks succeed again.
+///
+/// # Returns
+///
+/// Returns the signal that was latched, if any.
+pub fn unlatch() -> Option<Signal> {
+    disarm(ARMED_LATCH);
+    let latched = Signal::new(LATCHED.swap(0, ACQ_REL));
+    disposition::refresh();
+    latched
+}
+
+/// Latches `signal` if latched mode is `enabled` and it requests
+/// termination, has [kill severity](Severity::Kill) or is fatal.
+pub(crate) fn observe(signal: Signal, enabled: bool) {
+    let terminates = signal == Signal::TERMINATE || signal.severity() >= Some(Severity::Kill);
+    if terminates && enabled {
+        LATCHED.store(signal.code(), RELEASE);
+        arm(ARMED_LATCH);
+        disposition::refresh();
+    }
+}
+
+/// Latch mode and the latched signal, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    enabled: bool,
+    latched: u32,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        enabled: ENABLED.load(ACQUIRE),
+        latched: LATCHED.load(ACQUIRE),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    ENABLED.store(saved.enabled, RELEASE);
+    LATCHED.store(saved.latched, RELEASE);
+    disposition::refresh();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, set_signal, set_signal_handler, try_check_signal,
+    };
+
+    #[test]
+    fn test_latch_survives_swallowing_handler() {
+        let _guard = lock();
+        clear_signal();
+        set_latch_mode(true);
+        set_signal_handler(|_| Ok(()));
+
+        // Soft signals are not latched
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_ok());
+        assert!(latched_signal().is_none());
+
+        set_signal(Signal::TERMINATE.code());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        clear_signal();
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+
+        assert_eq!(unlatch(), Some(Signal::TERMINATE));
+        assert!(try_check_signal().is_ok());
+
+        set_latch_mode(false);
+        clear_signal_handler();
+    }
+}
@@ -0,0 +1,138 @@
+//! Addresses of the host-shared slots.
+//!
+//! Every slot the host reads or writes is exported as a global holding its
+//! address (`WASM_SIGNAL_ADDR`, `WASM_SIGNAL_ACK_ADDR`, ...). Guest code that
+//! hands those addresses to its own glue, such as a `wasm-bindgen` import
+//! that writes a signal, can use these accessors instead of casting the
+//! exported references by hand, or take them all at once as a
+//! [`SignalLayout`].
+
+use crate::{
+    WASM_SIGNAL_ACK_ADDR, WASM_SIGNAL_DISPOSITION_ADDR, WASM_SIGNAL_EPOCH_ADDR,
+    WASM_SIGNAL_EVENTS_ADDR, WASM_SIGNAL_HEARTBEAT_ADDR, WASM_SIGNAL_MAILBOX_ADDR,
+    WASM_SIGNAL_METADATA_ADDR, WASM_SIGNAL_POLICY_ADDR, WASM_SIGNAL_PROGRESS_ADDR,
+    WASM_SIGNAL_QUEUE_ADDR, WASM_SIGNAL_SOURCE_ADDR, WASM_SIGNAL_STATE_ADDR,
+};
+
+/// The addresses of the host-shared slots in linear memory.
+///
+/// One word per slot, in a fixed order, so the descriptor can be passed to
+/// host glue as a single pointer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignalLayout {
+    /// The signal word, or `0` with global storage (see [`signal_addr`]).
+    pub signal: usize,
+    /// The ack word.
+    pub ack: usize,
+    /// The heartbeat word.
+    pub heartbeat: usize,
+    /// The command mailbox, holding the command code and its arguments.
+    pub mailbox: usize,
+    /// The event ring.
+    pub events: usize,
+    /// The progress slot.
+    pub progress: usize,
+    /// The source word.
+    pub source: usize,
+    /// The overwrite policy word.
+    pub policy: usize,
+    /// The signal queue.
+    pub queue: usize,
+    /// The export metadata block.
+    pub metadata: usize,
+    /// The disposition word.
+    pub disposition: usize,
+    /// The epoch word.
+    pub epoch: usize,
+    /// The lifecycle state word.
+    pub state: usize,
+}
+
+/// Returns the address of the signal word.
+///
+/// With the `global-storage` feature on wasm32 the signal is a global rather
+/// than a word in memory, and this returns `0`, as the metadata block does.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{signal_addr, WASM_SIGNAL_ADDR};
+///
+/// assert_eq!(signal_addr(), WASM_SIGNAL_ADDR as *const _ as usize);
+/// ```
+pub fn signal_addr() -> usize {
+    #[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+    return crate::WASM_SIGNAL_ADDR as *const _ as usize;
+    #[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+    0
+}
+
+/// Returns the address of the ack word.
+pub fn ack_addr() -> usize {
+    WASM_SIGNAL_ACK_ADDR as *const _ as usize
+}
+
+/// Returns the address of the heartbeat word.
+pub fn heartbeat_addr() -> usize {
+    WASM_SIGNAL_HEARTBEAT_ADDR as *const _ as usize
+}
+
+/// Returns the address of the command mailbox, where hosts write the
+/// command code and its argument words.
+pub fn mailbox_addr() -> usize {
+    WASM_SIGNAL_MAILBOX_ADDR as *const _ as usize
+}
+
+/// Returns the addresses of every host-shared slot.
+pub fn signal_layout() -> SignalLayout {
+    SignalLayout {
+        signal: signal_addr(),
+        ack: ack_addr(),
+        heartbeat: heartbeat_addr(),
+        mailbox: mailbox_addr(),
+        events: WASM_SIGNAL_EVENTS_ADDR as *const _ as usize,
+        progress: WASM_SIGNAL_PROGRESS_ADDR as *const _ as usize,
+        source: WASM_SIGNAL_SOURCE_ADDR as *const _ as usize,
+        policy: WASM_SIGNAL_POLICY_ADDR as *const _ as usize,
+        queue: WASM_SIGNAL_QUEUE_ADDR as *const _ as usize,
+        metadata: WASM_SIGNAL_METADATA_ADDR as *const _ as usize,
+        disposition: WASM_SIGNAL_DISPOSITION_ADDR as *const _ as usize,
+        epoch: WASM_SIGNAL_EPOCH_ADDR as *const _ as usize,
+        state: WASM_SIGNAL_STATE_ADDR as *const _ as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_layout() {
+        let layout = signal_layout();
+        assert_eq!(layout.signal, signal_addr());
+        assert_eq!(layout.ack, WASM_SIGNAL_ACK_ADDR as *const _ as usize);
+        assert_eq!(layout.mailbox, mailbox_addr());
+
+        let addrs = [
+            layout.signal,
+            layout.ack,
+            layout.heartbeat,
+            layout.mailbox,
+            layout.events,
+            layout.progress,
+            layout.source,
+            layout.policy,
+            layout.queue,
+            layout.metadata,
+            layout.disposition,
+            layout.epoch,
+            layout.state,
+        ];
+        for (i, addr) in addrs.iter().enumerate() {
+            assert_ne!(*addr, 0);
+            assert!(addr.is_multiple_of(4));
+            assert!(!addrs[i + 1..].contains(addr));
+        }
+    }
+}
@@ -41,31 +41,925 @@
 //! ```
 
 #![no_std]
+#![cfg_attr(
+    all(
+        any(feature = "global-storage", feature = "throw"),
+        target_arch = "wasm32"
+    ),
+    feature(asm_experimental_arch)
+)]
+#![cfg_attr(
+    all(target_arch = "wasm32", target_feature = "atomics"),
+    feature(stdarch_wasm_atomic_wait)
+)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
 
+// Each wasi-threads thread is a separate instance with its own globals, so a
+// host write to the global would only reach one of them
+#[cfg(all(
+    feature = "global-storage",
+    target_os = "wasi",
+    target_feature = "atomics"
+))]
+compile_error!(
+    "the `global-storage` feature is not supported on wasm32-wasip1-threads, where globals are per thread"
+);
+
+// The signal block cannot be both a global and in a control memory, and the
+// control memory is not shared between threads
+#[cfg(all(
+    feature = "control-memory",
+    any(feature = "global-storage", target_feature = "atomics"),
+    target_arch = "wasm32"
+))]
+compile_error!(
+    "the `control-memory` feature is supported neither with `global-storage` nor with the `atomics` target feature"
+);
+
 use core::fmt;
+use core::num::NonZeroU32;
+use core::panic::Location;
 use core::ptr::null_mut;
-use core::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+mod abi;
+#[cfg(feature = "futures-util")]
+mod abort;
+mod advisor;
+#[cfg(all(feature = "asyncify", target_arch = "wasm32"))]
+mod asyncify;
+#[cfg(feature = "capi")]
+mod capi;
+#[cfg(feature = "futures-channel")]
+mod channel;
+mod checkpoint;
+mod clock;
+mod cloudflare;
+mod compose;
+mod context;
+mod deadline;
+mod diagnostics;
+mod disposition;
+mod drain;
+#[cfg(feature = "edge")]
+mod edge;
+mod epoch;
+mod events;
+#[cfg(feature = "extism")]
+mod extism;
+#[cfg(feature = "fastly")]
+mod fastly;
+mod filter;
+mod frame;
+mod gap;
+mod generation;
+mod grace;
+mod health;
+#[cfg(feature = "std")]
+mod heatmap;
+mod interrupt;
+#[cfg(feature = "serde")]
+mod interruptible_serde;
+#[cfg(feature = "gloo")]
+mod interval;
+#[cfg(feature = "js-listener")]
+mod js_listener;
+mod latch;
+mod layout;
+mod lifecycle;
+mod listeners;
+#[cfg(feature = "lunatic")]
+mod lunatic;
+mod mailbox;
+mod mask;
+mod names;
+mod namespaced;
+#[cfg(feature = "otel")]
+mod otel;
+#[cfg(feature = "std")]
+mod panic_hook;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod platform;
+mod policy;
+#[cfg(feature = "host-poll")]
+mod poll;
+mod progress;
+mod retry;
+mod scope;
+#[cfg(feature = "sentry")]
+mod sentry;
+mod set;
+mod severity;
+mod shopify;
+mod shutdown;
+#[cfg(feature = "alloc")]
+mod signal_scope;
+mod slice;
+mod source;
+mod status;
+mod storage;
+#[cfg(feature = "futures-core")]
+mod stream;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod thin;
+#[cfg(all(
+    feature = "throw",
+    target_arch = "wasm32",
+    target_feature = "exception-handling"
+))]
+mod throw;
+#[cfg(feature = "wasm-bindgen-futures")]
+mod timeout;
+#[cfg(feature = "tokio-util")]
+mod token;
+#[cfg(feature = "ui")]
+mod ui;
+mod validator;
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+mod wait;
+#[cfg(feature = "wasi-http")]
+mod wasi_http;
+#[cfg(feature = "wasmcloud")]
+mod wasmcloud;
+#[cfg(feature = "web")]
+mod web;
+#[cfg(feature = "wasm-bindgen-futures")]
+mod yielding;
+
+pub use abi::{
+    verify_exports, ExportError, ExportMetadata, ABI_CONTROL_MEMORY, ABI_GLOBAL_STORAGE,
+    ABI_MEMORY_INDEX_SHIFT, ABI_SECTION, ABI_VERSION, ATTACHED_ANCHOR, ATTACHED_EXPORT,
+    BLOCK_MAGIC, METADATA_MAGIC, WASM_SIGNAL_METADATA_ADDR,
+};
+#[cfg(feature = "futures-util")]
+pub use abort::abort_registration;
+pub use advisor::{
+    check_advice, check_advisor, reset_check_advice, set_check_advisor, set_check_target_us,
+    CheckRate, CheckSiteAdvice, DEFAULT_CHECK_TARGET_US, MAX_ADVISED_SITES,
+};
+#[cfg(all(feature = "asyncify", target_arch = "wasm32"))]
+pub use asyncify::ASYNCIFY_STACK_SIZE;
+#[cfg(feature = "capi")]
+pub use capi::{wasm_signal_check, wasm_signal_layout, wasm_signal_raise};
+#[cfg(feature = "futures-channel")]
+pub use channel::{__wasm_signal_wake_tasks, Recv, SignalOneshot, SignalReceiver};
+pub use checkpoint::{
+    clear_checkpointer, has_checkpointer, set_checkpointer, CheckpointSink, Checkpointable,
+};
+pub use clock::{clear_clock, get_clock, now_ms, set_clock, Clock};
+pub use cloudflare::{register_workers_names, WorkersLimit};
+#[cfg(feature = "alloc")]
+pub use compose::set_boxed_signal_handler;
+pub use compose::{chained, filtered, mapped, Chained, ComposableHandler, Filtered, Mapped};
+pub use context::SignalContext;
+pub use deadline::{clear_deadline, deadline_remaining, set_deadline, NoClock};
+#[cfg(feature = "std")]
+pub use diagnostics::metrics_prometheus;
+#[cfg(feature = "diagnostics-json")]
+pub use diagnostics::{__wasm_signal_dump_diagnostics, diagnostics_json};
+pub use diagnostics::{diagnostics, Diagnostics};
+pub use disposition::{
+    disposition, DISPOSITION_HANDLER, DISPOSITION_LATCHED, DISPOSITION_LATCH_MODE,
+    DISPOSITION_PANICS, DISPOSITION_THROWS, DISPOSITION_TRAPS, WASM_SIGNAL_DISPOSITION_ADDR,
+};
+pub use drain::{drain_signals, DrainSummary, MAX_DRAIN};
+#[cfg(feature = "edge")]
+pub use edge::{
+    bind_abort_signal, register_edge_instance, setup_edge, AbortBinding, EDGE_GLOBAL_KEY,
+};
+pub use epoch::{
+    clear_epoch_deadline, epoch, epoch_deadline_remaining, set_epoch_deadline,
+    WASM_SIGNAL_EPOCH_ADDR,
+};
+pub use events::{
+    dropped_events, pending_events, post_event, EventRing, EVENT_CAPACITY,
+    EVENT_CHECKPOINT_WRITTEN, EVENT_SIGNAL_ACKNOWLEDGED, EVENT_SIGNAL_REJECTED,
+    EVENT_STAGE_CHANGED, EVENT_USER_BASE, WASM_SIGNAL_EVENTS_ADDR,
+};
+#[cfg(feature = "extism")]
+pub use extism::{EXTISM_ACK_VAR, EXTISM_SIGNAL_VAR};
+#[cfg(feature = "fastly")]
+pub use fastly::{cancellation_status, handle_fastly_request, register_fastly_names, FastlyLimit};
+pub use filter::{drop_ignored, set_drop_ignored, set_signal_mask, signal_mask, SignalMask};
+pub use frame::{FrameBudget, FrameStats};
+#[cfg(feature = "std")]
+pub use gap::log_check_gap;
+pub use gap::{
+    clear_check_gap_warning, restart_check_gap, set_check_gap_warning, CheckGap, CheckGapWarning,
+};
+pub use generation::{handler_generation, synchronize_signal_handler};
+pub use grace::{
+    clear_grace_period, end_grace, get_grace_period, grace_remaining, set_grace_period, GracePeriod,
+};
+pub use health::{health_check, HealthError, HealthReport};
+#[cfg(feature = "std")]
+pub use heatmap::{
+    check_heatmap, check_heatmap_enabled, reset_check_heatmap, set_check_heatmap, CheckHeat,
+};
+#[doc(hidden)]
+pub use interrupt::__interrupt_point;
+#[cfg(feature = "serde")]
+pub use interruptible_serde::{
+    deserialize_interruptible, serialize_interruptible, InterruptibleCompound,
+    InterruptibleDeserializer, InterruptibleError, InterruptibleSerializer, SerdeChecks,
+};
+#[cfg(feature = "gloo")]
+pub use interval::poll_signal_every;
+#[cfg(feature = "js-listener")]
+pub use js_listener::{
+    add_js_signal_listener, clear_js_signal_listeners, remove_js_signal_listener,
+};
+pub use latch::{latch_mode, latched_signal, set_latch_mode, unlatch};
+pub use layout::{
+    ack_addr, heartbeat_addr, mailbox_addr, signal_addr, signal_layout, SignalLayout,
+};
+pub use lifecycle::{current_state, LifecycleState, WASM_SIGNAL_STATE_ADDR};
+pub use listeners::{
+    add_signal_listener, clear_signal_listeners, remove_signal_listener, SignalListener,
+    MAX_SIGNAL_LISTENERS,
+};
+#[cfg(feature = "lunatic")]
+pub use lunatic::{link_lunatic_process, send_lunatic_signal, LUNATIC_SIGNAL_TAG};
+pub use mailbox::{
+    clear_command_handler, poll_command, set_command_handler, Command, CommandHandler, Mailbox,
+    MAILBOX_ARGS, MAX_COMMAND_HANDLERS, WASM_SIGNAL_MAILBOX_ADDR,
+};
+pub use mask::{signals_masked, without_signals, SignalMaskGuard};
+pub use names::{register_signal_name, unregister_signal_name, MAX_SIGNAL_NAMES};
+pub use namespaced::{
+    namespaced_handler, register_namespaced_handler, unregister_namespaced_handler,
+    NamespacedHandlersFull, MAX_NAMESPACED_HANDLERS,
+};
+#[cfg(feature = "otel")]
+pub use otel::OTEL_EVENT_NAME;
+#[cfg(feature = "std")]
+pub use panic_hook::{panic_hook, set_panic_hook, wrap_panic_hook, write_signal_report};
+#[cfg(feature = "rayon")]
+pub use parallel::{ParInterruptible, SignalParallelIteratorExt, SignalParallelRefExt};
+pub use policy::{
+    overwrite_policy, queued, send_signal, set_overwrite_policy, OverwritePolicy, SignalQueue,
+    QUEUE_CAPACITY, WASM_SIGNAL_POLICY_ADDR, WASM_SIGNAL_QUEUE_ADDR,
+};
+#[cfg(feature = "host-poll")]
+pub use poll::{poll_host, poll_stride, set_poll_stride, DEFAULT_POLL_STRIDE};
+pub use progress::{
+    progress, set_progress, set_stage, stage, ProgressSlot, WASM_SIGNAL_PROGRESS_ADDR,
+};
+#[cfg(feature = "wasm-bindgen-futures")]
+pub use retry::retry_interruptible_async;
+pub use retry::{retry_interruptible, RetryError, RetryPolicy};
+pub use scope::{reset_all, RequestScope};
+#[cfg(feature = "sentry")]
+pub use sentry::{sentry_signal_context, wrap_sentry_panic_hook};
+pub use set::{SignalSet, SignalSetIter};
+pub use severity::{decode_signal, encode_signal, Severity, CODE_MASK, SEVERITY_SHIFT};
+pub use shopify::{
+    register_shopify_names, ShopifyLimit, ShopifyOutputBudget, SHOPIFY_CHECK_STRIDE,
+    SHOPIFY_INSTRUCTION_LIMIT, SHOPIFY_OUTPUT_LIMIT, SHOPIFY_OUTPUT_PRESSURE_PERCENT,
+};
+pub use shutdown::{
+    clear_shutdown_hooks, rearm_shutdown_hooks, register_shutdown_hook, run_shutdown_hooks,
+    shutdown_hooks_ran, ShutdownHook, ShutdownHooksFull, MAX_SHUTDOWN_HOOKS,
+};
+#[cfg(feature = "alloc")]
+pub use signal_scope::{ScopeJoin, SignalScope};
+pub use slice::SignalSliceExt;
+pub use source::{set_signal_source, WASM_SIGNAL_SOURCE_ADDR};
+pub use status::{
+    checks_since_last_signal, heartbeat, heartbeat_count, last_ack, set_count_checks,
+    signal_history, SIGNAL_HISTORY_LEN, WASM_SIGNAL_ACK_ADDR, WASM_SIGNAL_HEARTBEAT_ADDR,
+};
+#[cfg(feature = "futures-core")]
+pub use stream::{Interruptible, SignalStreamExt};
+#[doc(hidden)]
+pub use thin::__check_due;
+#[cfg(feature = "wasm-bindgen-futures")]
+pub use timeout::{with_timeout, WithTimeout};
+#[cfg(feature = "tokio-util")]
+pub use token::bridge_to_token;
+#[cfg(feature = "ui")]
+pub use ui::{route_changed, use_cancellable_task, ViewTask, ViewTaskGuard};
+pub use validator::{
+    clear_permitted_codes, clear_signal_validator, get_signal_validator, last_rejected,
+    set_permitted_codes, set_signal_validator, PermittedCodes, SignalValidator,
+};
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+pub use wait::wait_for_signal;
+#[cfg(feature = "wasi-http")]
+pub use wasi_http::handle_wasi_http;
+#[cfg(feature = "wasmcloud")]
+pub use wasmcloud::{
+    clear_wasmcloud_poll, notify_lifecycle, register_wasmcloud_names, set_wasmcloud_poll,
+    wasmcloud_status, WasmcloudLifecycle, WasmcloudPoll, WasmcloudStatus,
+};
+#[cfg(feature = "web")]
+pub use web::{bind_page_lifecycle, PageLifecycle, PageLifecycleCodes};
+#[cfg(feature = "wasm-bindgen-futures")]
+pub use yielding::{for_each_yielding, signal_aware_yield};
 
 // ============================================================================
 // Signal Type
 // ============================================================================
 
-/// Represents a signal value.
+/// Represents an active signal.
 ///
-/// A signal value of `0` means no signal. Any non-zero value represents
-/// an active signal, where the value itself is the signal code.
+/// A signal word of `0` means no signal, so a `Signal` always carries a
+/// non-zero code. This invariant is enforced by the type: `Signal` wraps a
+/// [`NonZeroU32`], which also lets `Option<Signal>` and `Result<(), Signal>`
+/// occupy a single `u32`.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Signal(pub u32);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Signal(NonZeroU32);
+
+impl Signal {
+    /// Requests termination of the current work (code `1`).
+    pub const TERMINATE: Signal = Signal(NonZeroU32::MIN);
+
+    /// Requests cooperative (soft) cancellation (code `2`).
+    pub const CANCEL: Signal = Signal(NonZeroU32::new(2).unwrap());
+
+    /// A deadline set with [`set_deadline`] has passed (code `3`).
+    ///
+    /// Synthesized by the guest rather than written by the host.
+    pub const DEADLINE: Signal = Signal(NonZeroU32::new(3).unwrap());
+
+    /// Requests a snapshot of progress without stopping (code `4`).
+    ///
+    /// See [`set_checkpointer`].
+    pub const CHECKPOINT: Signal = Signal(NonZeroU32::new(4).unwrap());
+
+    /// A command is waiting in the mailbox (code `5`).
+    ///
+    /// See [`set_command_handler`].
+    pub const COMMAND: Signal = Signal(NonZeroU32::new(5).unwrap());
+
+    /// The first code of the fatal range (`0xF000_0000..=0xFFFF_FFFF`).
+    ///
+    /// Fatal signals always propagate: they bypass the signal handler, the
+    /// signal mask and the checkpointer, so hosts have a kill path that guest
+    /// code cannot swallow. [`check_signal`] panics (or traps) on them.
+    pub const FATAL_BASE: u32 = 0xF000_0000;
+
+    /// Creates a signal from a raw code.
+    ///
+    /// Returns `None` if `code` is `0`, which means "no signal".
+    #[inline]
+    pub const fn new(code: u32) -> Option<Signal> {
+        match NonZeroU32::new(code) {
+            Some(code) => Some(Signal(code)),
+            None => None,
+        }
+    }
+
+    /// Creates a signal from a non-zero code.
+    #[inline]
+    pub const fn from_non_zero(code: NonZeroU32) -> Signal {
+        Signal(code)
+    }
+
+    /// Returns the raw signal code.
+    #[inline]
+    pub const fn code(self) -> u32 {
+        self.0.get()
+    }
+
+    /// Returns the signal code as a [`NonZeroU32`].
+    #[inline]
+    pub const fn as_non_zero(self) -> NonZeroU32 {
+        self.0
+    }
+
+    /// Returns `true` if the code is in the fatal range (see
+    /// [`Signal::FATAL_BASE`]).
+    #[inline]
+    pub const fn is_fatal(self) -> bool {
+        self.code() >= Signal::FATAL_BASE
+    }
+
+    /// Returns the code without any [encoded severity](Severity).
+    #[inline]
+    pub const fn base_code(self) -> u32 {
+        decode_signal(self.code()).0
+    }
+
+    /// Returns the severity encoded in the signal word, if any.
+    ///
+    /// Fatal codes report [`Severity::Fatal`].
+    #[inline]
+    pub const fn severity(self) -> Option<Severity> {
+        Severity::from_word(self.code())
+    }
+
+    /// Returns the ID of the subsystem that raised this signal, if the host
+    /// tagged it.
+    ///
+    /// The tag is the value of the source word when a check last observed
+    /// this code, so it is meaningful inside the handler and afterwards until
+    /// another signal is observed. See [`set_signal_source`].
+    #[inline]
+    pub fn source(&self) -> Option<u32> {
+        source::source_of(*self)
+    }
+
+    /// Returns the kind of this signal, derived from its code.
+    #[inline]
+    pub fn kind(&self) -> SignalKind {
+        SignalKind::from_code(self.code())
+    }
+
+    /// Returns the name of this signal.
+    ///
+    /// This is the name registered with [`register_signal_name`] for the
+    /// signal word or, failing that, its [base code](Signal::base_code), or
+    /// else the [kind](SignalKind::as_str) of a well-known code. Other codes
+    /// have no name.
+    pub fn name(&self) -> Option<&'static str> {
+        let registered = names::lookup(self.code()).or_else(|| names::lookup(self.base_code()));
+        registered.or(match self.kind() {
+            SignalKind::Custom => None,
+            kind => Some(kind.as_str()),
+        })
+    }
+}
+
+impl From<NonZeroU32> for Signal {
+    #[inline]
+    fn from(code: NonZeroU32) -> Signal {
+        Signal(code)
+    }
+}
+
+impl From<Signal> for u32 {
+    #[inline]
+    fn from(signal: Signal) -> u32 {
+        signal.code()
+    }
+}
 
 impl fmt::Display for Signal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Signal({})", self.0)
+        match self.name() {
+            Some(name) => write!(f, "Signal({}, {})", self.code(), name),
+            None => write!(f, "Signal({})", self.code()),
+        }
+    }
+}
+
+impl core::error::Error for Signal {}
+
+/// The kind of a signal, classifying its code.
+///
+/// Codes without a well-known meaning are reported as [`SignalKind::Custom`];
+/// their interpretation is left to the host runtime and the application.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[non_exhaustive]
+pub enum SignalKind {
+    /// Termination was requested ([`Signal::TERMINATE`]).
+    Terminate,
+    /// Cooperative cancellation was requested ([`Signal::CANCEL`]).
+    Cancel,
+    /// A guest deadline passed ([`Signal::DEADLINE`]).
+    Deadline,
+    /// A snapshot was requested ([`Signal::CHECKPOINT`]).
+    Checkpoint,
+    /// A command is waiting in the mailbox ([`Signal::COMMAND`]).
+    Command,
+    /// A code in the fatal range, which bypasses handlers (see
+    /// [`Signal::FATAL_BASE`]).
+    Fatal,
+    /// Any other host-defined code.
+    Custom,
+}
+
+impl SignalKind {
+    /// Classifies a raw signal code.
+    ///
+    /// An [encoded severity](Severity) other than fatal is ignored, so a
+    /// cancel sent with any severity is still [`SignalKind::Cancel`].
+    pub const fn from_code(code: u32) -> SignalKind {
+        if code >= Signal::FATAL_BASE {
+            return SignalKind::Fatal;
+        }
+        let code = decode_signal(code).0;
+        if code == Signal::TERMINATE.code() {
+            SignalKind::Terminate
+        } else if code == Signal::CANCEL.code() {
+            SignalKind::Cancel
+        } else if code == Signal::DEADLINE.code() {
+            SignalKind::Deadline
+        } else if code == Signal::CHECKPOINT.code() {
+            SignalKind::Checkpoint
+        } else if code == Signal::COMMAND.code() {
+            SignalKind::Command
+        } else {
+            SignalKind::Custom
+        }
+    }
+
+    /// Returns a short, stable name for this kind.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            SignalKind::Terminate => "terminate",
+            SignalKind::Cancel => "cancel",
+            SignalKind::Deadline => "deadline",
+            SignalKind::Checkpoint => "checkpoint",
+            SignalKind::Command => "command",
+            SignalKind::Fatal => "fatal",
+            SignalKind::Custom => "custom",
+        }
+    }
+}
+
+impl fmt::Display for SignalKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
     }
 }
 
+// ============================================================================
+// Signal Error
+// ============================================================================
+
+/// A rich error describing a propagated signal.
+///
+/// In addition to the signal itself, a `SignalError` records the location
+/// where it was created and an optional static message. Converting a
+/// [`Signal`] with `?` captures the location of the `?` operator, so
+/// functions returning `Result<_, SignalError>` (or an error type wrapping
+/// it) report where the interruption was propagated from.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{try_check_signal, SignalError};
+///
+/// fn step() -> Result<(), SignalError> {
+///     try_check_signal()?; // Records this line as the call-site
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalError {
+    signal: Signal,
+    message: Option<&'static str>,
+    location: &'static Location<'static>,
+}
+
+impl SignalError {
+    /// Creates an error for `signal`, recording the caller's location.
+    #[track_caller]
+    #[inline]
+    pub fn new(signal: Signal) -> SignalError {
+        SignalError {
+            signal,
+            message: None,
+            location: Location::caller(),
+        }
+    }
+
+    /// Attaches a static message describing the interrupted operation.
+    #[inline]
+    pub fn with_message(mut self, message: &'static str) -> SignalError {
+        self.message = Some(message);
+        self
+    }
+
+    /// Returns the propagated signal.
+    #[inline]
+    pub fn signal(&self) -> Signal {
+        self.signal
+    }
+
+    /// Returns the raw signal code.
+    #[inline]
+    pub fn code(&self) -> u32 {
+        self.signal.code()
+    }
+
+    /// Returns the kind of the propagated signal.
+    #[inline]
+    pub fn kind(&self) -> SignalKind {
+        self.signal.kind()
+    }
+
+    /// Returns the attached message, if any.
+    #[inline]
+    pub fn message(&self) -> Option<&'static str> {
+        self.message
+    }
+
+    /// Returns the location where this error was created.
+    #[inline]
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
+
+impl fmt::Display for SignalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "signal {} ({}) at {}",
+            self.signal.code(),
+            self.signal.name().unwrap_or(self.kind().as_str()),
+            self.location
+        )?;
+        if let Some(message) = self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for SignalError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.signal)
+    }
+}
+
+/// Serializes as a flat record:
+/// `{"code":1,"kind":"terminate","message":null,"file":"src/lib.rs","line":10,"column":5}`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for SignalError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("SignalError", 6)?;
+        state.serialize_field("code", &self.code())?;
+        state.serialize_field("kind", &self.kind())?;
+        state.serialize_field("message", &self.message)?;
+        state.serialize_field("file", self.location.file())?;
+        state.serialize_field("line", &self.location.line())?;
+        state.serialize_field("column", &self.location.column())?;
+        state.end()
+    }
+}
+
+impl From<Signal> for SignalError {
+    #[track_caller]
+    #[inline]
+    fn from(signal: Signal) -> SignalError {
+        SignalError::new(signal)
+    }
+}
+
+impl From<SignalError> for Signal {
+    #[inline]
+    fn from(error: SignalError) -> Signal {
+        error.signal
+    }
+}
+
+// ============================================================================
+// Panic Payload
+// ============================================================================
+
+/// The panic payload used by [`check_signal`] when the `std` feature is enabled.
+///
+/// `check_signal` panics via [`std::panic::panic_any`] with this type, so
+/// `catch_unwind` callers can recover the signal by downcasting instead of
+/// parsing the panic message. Without `std`, `check_signal` panics with a
+/// formatted message instead.
+///
+/// Panic hooks that only print string payloads show this one as
+/// `Box<dyn Any>`; [`panic_hook`] and [`wrap_panic_hook`] log its
+/// [`message`](SignalPanic::message).
+///
+/// # Example
+///
+/// ```rust
+/// use std::panic::{catch_unwind, AssertUnwindSafe};
+/// use wasm_signal_handler::{check_signal, set_signal, SignalPanic};
+///
+/// set_signal(42);
+/// let payload = catch_unwind(AssertUnwindSafe(check_signal)).unwrap_err();
+/// assert_eq!(SignalPanic::downcast(&*payload).map(|s| s.code()), Some(42));
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SignalPanic {
+    signal: Signal,
+    message: std::string::String,
+}
+
+#[cfg(feature = "std")]
+impl SignalPanic {
+    /// Returns the signal that caused the panic.
+    #[inline]
+    pub fn signal(&self) -> Signal {
+        self.signal
+    }
+
+    /// Returns the panic message, as produced by the registered
+    /// [`PanicFormatter`] (or the default message).
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Extracts the signal from a panic payload produced by [`check_signal`].
+    ///
+    /// Returns `None` if the payload is not a `SignalPanic`.
+    pub fn downcast(payload: &(dyn core::any::Any + Send)) -> Option<Signal> {
+        payload
+            .downcast_ref::<SignalPanic>()
+            .map(SignalPanic::signal)
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for SignalPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// ============================================================================
+// Panic Message Formatter
+// ============================================================================
+
+/// A panic message formatter.
+///
+/// The formatter receives the propagated signal and writes the message that
+/// [`check_signal`] panics with. This is the text that ends up in host logs,
+/// so applications can include request IDs or localized descriptions.
+///
+/// With the `std` feature the message is carried in the [`SignalPanic`]
+/// payload, which hooks that only print string payloads (the standard hook
+/// and `console_error_panic_hook` among them) show as `Box<dyn Any>`.
+/// Install `set_panic_hook`, or keep such a hook with `wrap_panic_hook`,
+/// to log it. Without `std` the message is written into a fixed-size buffer
+/// of [`PANIC_MESSAGE_CAPACITY`] bytes and truncated beyond that. The
+/// formatter is not used when the `trap` feature makes wasm32 builds trap.
+pub type PanicFormatter = fn(Signal, &mut dyn fmt::Write);
+
+/// Capacity of the panic message buffer used without the `std` feature.
+pub const PANIC_MESSAGE_CAPACITY: usize = 256;
+
+/// Storage for the registered panic formatter.
+///
+/// Stored as a raw pointer for the same reasons as `HANDLER`.
+static PANIC_FORMATTER: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Registers a panic message formatter used by [`check_signal`].
+///
+/// # Returns
+///
+/// Returns the previously registered formatter, if any.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::set_panic_formatter;
+///
+/// set_panic_formatter(|signal, out| {
+///     let _ = write!(out, "request abc123 cancelled by host ({})", signal);
+/// });
+/// ```
+pub fn set_panic_formatter(formatter: PanicFormatter) -> Option<PanicFormatter> {
+    let old_ptr = PANIC_FORMATTER.swap(formatter as *mut (), ACQ_REL);
+
+    if old_ptr.is_null() {
+        None
+    } else {
+        // SAFETY: We only store valid PanicFormatter function pointers in PANIC_FORMATTER
+        Some(unsafe { core::mem::transmute::<*mut (), PanicFormatter>(old_ptr) })
+    }
+}
+
+/// Clears the registered panic formatter, restoring the default message.
+///
+/// # Returns
+///
+/// Returns the previously registered formatter, if any.
+pub fn clear_panic_formatter() -> Option<PanicFormatter> {
+    let old_ptr = PANIC_FORMATTER.swap(null_mut(), ACQ_REL);
+
+    if old_ptr.is_null() {
+        None
+    } else {
+        // SAFETY: We only store valid PanicFormatter function pointers in PANIC_FORMATTER
+        Some(unsafe { core::mem::transmute::<*mut (), PanicFormatter>(old_ptr) })
+    }
+}
+
+/// Gets the currently registered panic formatter, if any.
+pub fn get_panic_formatter() -> Option<PanicFormatter> {
+    let ptr = PANIC_FORMATTER.load(ACQUIRE);
+
+    if ptr.is_null() {
+        None
+    } else {
+        // SAFETY: We only store valid PanicFormatter function pointers in PANIC_FORMATTER
+        Some(unsafe { core::mem::transmute::<*mut (), PanicFormatter>(ptr) })
+    }
+}
+
+/// Writes the panic message for `signal` using the registered formatter,
+/// or the default `"signal received: Signal(N, name)"` message.
+#[cfg(not(all(feature = "trap", target_arch = "wasm32")))]
+fn write_panic_message(signal: Signal, out: &mut dyn fmt::Write) {
+    match get_panic_formatter() {
+        Some(formatter) => formatter(signal, out),
+        None => {
+            let _ = write!(out, "signal received: {}", signal);
+        }
+    }
+}
+
+/// A fixed-size panic message buffer for `no_std` builds.
+///
+/// Writes beyond the capacity are truncated at a character boundary.
+#[cfg(not(any(feature = "std", all(feature = "trap", target_arch = "wasm32"))))]
+struct MessageBuffer {
+    buf: [u8; PANIC_MESSAGE_CAPACITY],
+    len: usize,
+}
+
+#[cfg(not(any(feature = "std", all(feature = "trap", target_arch = "wasm32"))))]
+impl MessageBuffer {
+    fn new() -> MessageBuffer {
+        MessageBuffer {
+            buf: [0; PANIC_MESSAGE_CAPACITY],
+            len: 0,
+        }
+    }
+
+    fn as_str(&self) -> &str {
+        // Only whole characters are ever copied into the buffer
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+#[cfg(not(any(feature = "std", all(feature = "trap", target_arch = "wasm32"))))]
+impl fmt::Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let remaining = PANIC_MESSAGE_CAPACITY - self.len;
+        let mut take = s.len().min(remaining);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Memory Orderings
+// ============================================================================
+//
+// The signal word is written by the host and read by the guest; handler and
+// formatter slots are written and read by the guest. Orderings are chosen so
+// that each read synchronizes with the write it observes:
+//
+// - The fast path in `try_check_signal` is two `Relaxed` loads, of the
+//   signal word and of `ARMED`, and one branch on both. It only needs to
+//   eventually observe a non-zero value; no other data is read based on it,
+//   and the slow path reloads `ARMED` with `Acquire`.
+// - Clearing the signal (the compare-exchange in the slow path and the swap
+//   in `clear_signal`) is `AcqRel`: it acquires anything the writer published
+//   before raising the signal, and releases the cleared state. A failed
+//   compare-exchange is `Acquire`, as it observes a newer signal.
+// - Raising a signal from the guest (`set_signal`) is a `Release` store,
+//   pairing with the acquiring clear above.
+// - Handler and formatter slots are `AcqRel` swaps and `Acquire` loads, so a
+//   dispatched function pointer is never observed before its registration.
+//
+// With the `relaxed-fast` feature every ordering is `Relaxed`. This is only
+// sound for single-threaded builds (no shared memory and no guest threads),
+// where the host writes between guest instructions and no fences are needed.
+
+/// Ordering for loads that must observe data published with [`RELEASE`].
+const ACQUIRE: Ordering = if cfg!(feature = "relaxed-fast") {
+    Ordering::Relaxed
+} else {
+    Ordering::Acquire
+};
+
+/// Ordering for stores that publish data to [`ACQUIRE`] loads.
+const RELEASE: Ordering = if cfg!(feature = "relaxed-fast") {
+    Ordering::Relaxed
+} else {
+    Ordering::Release
+};
+
+/// Ordering for read-modify-write operations that both observe and publish.
+const ACQ_REL: Ordering = if cfg!(feature = "relaxed-fast") {
+    Ordering::Relaxed
+} else {
+    Ordering::AcqRel
+};
+
 // ============================================================================
 // Signal Variable
 // ============================================================================
@@ -75,18 +969,131 @@ impl fmt::Display for Signal {
 /// - `0` means no signal (clear state)
 /// - Any non-zero value represents an active signal
 ///
-/// This is an `AtomicU32` to ensure proper memory semantics and prevent
-/// compiler optimizations from eliding reads.
-static SIGNAL: AtomicU32 = AtomicU32::new(0);
+/// The word is an `AtomicU32` to ensure proper memory semantics and prevent
+/// compiler optimizations from eliding reads. It sits between a magic value
+/// and a check word, which hosts that write to a stale address cannot
+/// reproduce (see `storage::SignalBlock`).
+///
+/// With the `global-storage` feature on wasm32, the signal lives in the
+/// exported mutable global `WASM_SIGNAL_GLOBAL` instead (see `storage`).
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+static SIGNAL: storage::SignalBlock = storage::SignalBlock::new();
 
 /// The exported address of the signal variable.
 ///
 /// This is exported as a WebAssembly global named `WASM_SIGNAL_ADDR`.
 /// External tools can read this global to obtain the memory address
 /// of the signal variable, then write to that address to set a signal.
-#[no_mangle]
+///
+/// The export can be renamed, and aliases added, with the
+/// `WASM_SIGNAL_EXPORT_NAME` and `WASM_SIGNAL_EXPORT_ALIASES` environment
+/// variables at build time (see `build.rs`).
+///
+/// Not available with the `global-storage` feature on wasm32, where hosts
+/// write the `WASM_SIGNAL_GLOBAL` export instead.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+#[export_name = env!("WASM_SIGNAL_EXPORT_NAME")]
+#[used]
+pub static WASM_SIGNAL_ADDR: &core::sync::atomic::AtomicU32 = &SIGNAL.word;
+
+// Alias exports of `WASM_SIGNAL_ADDR`, generated by `build.rs`
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+include!(concat!(env!("OUT_DIR"), "/aliases.rs"));
+
+/// The ABI metadata record (see `abi`).
+///
+/// It lives in the crate root rather than in `abi` because the linker only
+/// keeps objects with referenced symbols, and this module's always are.
+#[cfg(target_arch = "wasm32")]
+#[link_section = "wasm_signal_abi"]
 #[used]
-pub static WASM_SIGNAL_ADDR: &AtomicU32 = &SIGNAL;
+static ABI: [u8; 8] = abi::record();
+
+/// Bit flags for guest-side work that must run on every check.
+///
+/// Checked by the fast path alongside the signal word, so features that are
+/// not in use (such as deadlines) cost nothing beyond one extra load.
+static ARMED: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(ARMED_INITIAL);
+
+/// A deadline is set (see `deadline`).
+const ARMED_DEADLINE: u32 = 1 << 0;
+
+/// A grace window is open (see `grace`).
+const ARMED_GRACE: u32 = 1 << 1;
+
+/// Signals are masked (see `mask`).
+const ARMED_MASK: u32 = 1 << 2;
+
+/// A termination request is latched (see `latch`).
+const ARMED_LATCH: u32 = 1 << 3;
+
+/// A test injection is pending (see `testing`).
+#[cfg(feature = "testing")]
+const ARMED_INJECT: u32 = 1 << 4;
+
+/// Fault injection is enabled (see `testing`).
+#[cfg(feature = "testing")]
+const ARMED_FAULT: u32 = 1 << 5;
+
+/// A timeline is being recorded (see `testing`).
+#[cfg(feature = "testing")]
+const ARMED_RECORD: u32 = 1 << 6;
+
+/// A signal mask other than [`SignalMask::ALL`] is set (see `filter`).
+const ARMED_FILTER: u32 = 1 << 7;
+
+/// Signals may be queued behind the pending one (see `policy`).
+const ARMED_QUEUE: u32 = 1 << 8;
+
+/// Checks are being counted (see `status`).
+const ARMED_COUNT: u32 = 1 << 9;
+
+/// The host is polled for signals (see `poll`).
+const ARMED_POLL: u32 = 1 << 10;
+
+/// A signal validator or permitted code table is set (see `validator`).
+const ARMED_VALIDATE: u32 = 1 << 11;
+
+/// Checks are recorded by call site (see `advisor`).
+const ARMED_ADVISE: u32 = 1 << 12;
+
+/// Gaps between checks are measured (see `gap`).
+const ARMED_GAP: u32 = 1 << 13;
+
+/// Checks are counted by call site (see `heatmap`).
+#[cfg(feature = "std")]
+const ARMED_HEATMAP: u32 = 1 << 14;
+
+/// An epoch deadline is set (see `epoch`).
+const ARMED_EPOCH: u32 = 1 << 15;
+
+/// The initial [`ARMED`] flags, set up by Cargo features.
+const ARMED_INITIAL: u32 = if cfg!(feature = "policy-enqueue") {
+    ARMED_QUEUE
+} else {
+    0
+} | if cfg!(all(feature = "host-poll", target_arch = "wasm32")) {
+    ARMED_POLL
+} else {
+    0
+};
+
+/// Sets `flags` in [`ARMED`].
+fn arm(flags: u32) {
+    ARMED.fetch_or(flags, ACQ_REL);
+}
+
+/// Clears `flags` in [`ARMED`].
+fn disarm(flags: u32) {
+    ARMED.fetch_and(!flags, ACQ_REL);
+}
+
+/// Wakes everything waiting for a signal, after guest code wrote one.
+fn notify_waiters() {
+    storage::notify();
+    #[cfg(feature = "futures-channel")]
+    channel::wake_all();
+}
 
 // ============================================================================
 // Signal Handler
@@ -113,6 +1120,12 @@ static HANDLER: AtomicPtr<()> = AtomicPtr::new(null_mut());
 /// The handler will be called when `check_signal` or `try_check_signal`
 /// detects an active signal. Only one handler can be registered at a time;
 /// calling this function replaces any previously registered handler.
+/// Libraries that should not replace the application's handler register
+/// under a namespace with [`register_namespaced_handler`] instead.
+///
+/// In threaded builds, checks on other threads may still be running the
+/// previous handler when this returns; use [`synchronize_signal_handler`]
+/// to wait for them.
 ///
 /// # Returns
 ///
@@ -135,7 +1148,9 @@ static HANDLER: AtomicPtr<()> = AtomicPtr::new(null_mut());
 /// ```
 pub fn set_signal_handler(handler: SignalHandler) -> Option<SignalHandler> {
     let new_ptr = handler as *mut ();
-    let old_ptr = HANDLER.swap(new_ptr, Ordering::SeqCst);
+    let old_ptr = HANDLER.swap(new_ptr, ACQ_REL);
+    generation::bump();
+    disposition::refresh();
 
     if old_ptr.is_null() {
         None
@@ -155,7 +1170,9 @@ pub fn set_signal_handler(handler: SignalHandler) -> Option<SignalHandler> {
 ///
 /// Returns the previously registered handler, if any.
 pub fn clear_signal_handler() -> Option<SignalHandler> {
-    let old_ptr = HANDLER.swap(null_mut(), Ordering::SeqCst);
+    let old_ptr = HANDLER.swap(null_mut(), ACQ_REL);
+    generation::bump();
+    disposition::refresh();
 
     if old_ptr.is_null() {
         None
@@ -167,7 +1184,7 @@ pub fn clear_signal_handler() -> Option<SignalHandler> {
 
 /// Gets the currently registered signal handler, if any.
 pub fn get_signal_handler() -> Option<SignalHandler> {
-    let ptr = HANDLER.load(Ordering::SeqCst);
+    let ptr = HANDLER.load(ACQUIRE);
 
     if ptr.is_null() {
         None
@@ -181,31 +1198,209 @@ pub fn get_signal_handler() -> Option<SignalHandler> {
 // Check Functions
 // ============================================================================
 
+/// The configuration a check acts on: read as it is needed by
+/// [`try_check_signal`], or taken from the snapshot of a [`SignalContext`].
+pub(crate) trait CheckConfig: Copy {
+    /// The [`ARMED`] flags.
+    fn armed(self) -> u32;
+    /// The signal handler.
+    fn handler(self) -> Option<SignalHandler>;
+    /// The [signal mask](set_signal_mask).
+    fn signal_mask(self) -> SignalMask;
+    /// Whether [latch mode](set_latch_mode) is enabled.
+    fn latch_mode(self) -> bool;
+}
+
+/// The current configuration, loaded when a check needs it.
+#[derive(Clone, Copy)]
+struct Live;
+
+impl CheckConfig for Live {
+    #[inline(always)]
+    fn armed(self) -> u32 {
+        ARMED.load(ACQUIRE)
+    }
+
+    #[inline(always)]
+    fn handler(self) -> Option<SignalHandler> {
+        get_signal_handler()
+    }
+
+    #[inline(always)]
+    fn signal_mask(self) -> SignalMask {
+        signal_mask()
+    }
+
+    #[inline(always)]
+    fn latch_mode(self) -> bool {
+        latch_mode()
+    }
+}
+
 /// Handles a detected signal by calling the registered handler.
 ///
 /// This function:
-/// 1. Atomically swaps the signal to 0 (clearing it)
-/// 2. Calls the registered handler (if any)
-/// 3. Returns the handler's result, or `Err(Signal)` if no handler
+/// 1. Clears the signal with a compare-exchange on the observed value, so a
+///    newer signal written since the load is not lost
+/// 2. Propagates [fatal](Signal::is_fatal) signals without consulting the
+///    handler
+/// 3. Snapshots and continues for [`Signal::CHECKPOINT`], if a snapshotter is
+///    registered
+/// 4. Runs the command handler and continues for [`Signal::COMMAND`], if one
+///    is registered for the waiting command
+/// 5. Calls the registered handler (if any)
+/// 6. Returns the handler's result, or `Err(Signal)` if no handler, unless a
+///    signal is latched (see [`set_latch_mode`])
+///
+/// If a newer signal replaced the observed one and the observed one was
+/// handled, the newer one is dispatched in the same call. If the observed
+/// signal propagates, the newer one stays pending for the next check.
+///
+/// After a handled signal, the word is re-checked up to the configured
+/// [recheck depth](set_recheck_depth) times, so signals raised while the
+/// handler ran are handled in the same call.
+fn handle_signal(signal: Signal) -> Result<(), Signal> {
+    handle_signal_with(signal, Live)
+}
+
+/// [`handle_signal`], acting on the configuration `config` holds rather than
+/// the current one.
 #[inline]
-fn handle_signal(signal_value: u32) -> Result<(), Signal> {
-    // Atomically clear the signal and get the value
-    // (We already read the value, but swap ensures we clear it)
-    SIGNAL.swap(0, Ordering::SeqCst);
+fn handle_signal_with(signal: Signal, config: impl CheckConfig) -> Result<(), Signal> {
+    let mut signal = signal;
+    let mut rechecks = RECHECK_DEPTH.load(ACQUIRE);
+    loop {
+        let newer = match storage::compare_exchange(signal.code(), 0, ACQ_REL, ACQUIRE) {
+            Ok(_) => {
+                if config.armed() & ARMED_QUEUE != 0 {
+                    // Queued signals wait for the next check, like newer ones
+                    policy::refill();
+                }
+                None
+            }
+            Err(current) => Signal::new(current),
+        };
+        let delivered = deliver(signal, config);
+        observed(signal, delivered);
+        delivered?;
+        if let Some(next) = newer {
+            if !admit_armed(config.armed(), config, next) {
+                return Ok(());
+            }
+            signal = next;
+            continue;
+        }
+        if rechecks == 0 {
+            return Ok(());
+        }
+        rechecks -= 1;
+        match Signal::new(storage::load(ACQUIRE)) {
+            Some(next) if admit_armed(config.armed(), config, next) => signal = next,
+            _ => return Ok(()),
+        }
+    }
+}
 
-    let signal = Signal(signal_value);
+/// Reports the outcome of an observed signal to the telemetry integrations.
+#[inline(always)]
+fn observed(signal: Signal, result: Result<(), Signal>) {
+    #[cfg(feature = "otel")]
+    otel::record(signal, result);
+    #[cfg(feature = "sentry")]
+    sentry::record(signal, result);
+    let _ = (signal, result);
+}
 
-    // Check if a handler is registered
-    let handler_ptr = HANDLER.load(Ordering::SeqCst);
+/// Returns `true` if `signal` passes [validation](set_signal_validator) and
+/// the [signal mask](set_signal_mask).
+#[inline]
+fn admit(signal: Signal) -> bool {
+    admit_armed(ARMED.load(ACQUIRE), Live, signal)
+}
 
-    if handler_ptr.is_null() {
-        // No handler: return error
-        Err(signal)
-    } else {
-        // SAFETY: We only store valid SignalHandler function pointers in HANDLER
-        let handler: SignalHandler =
-            unsafe { core::mem::transmute::<*mut (), SignalHandler>(handler_ptr) };
-        handler(signal)
+/// [`admit`], with the [`ARMED`] flags already loaded and the signal mask
+/// `config` holds.
+#[inline(always)]
+fn admit_armed(armed: u32, config: impl CheckConfig, signal: Signal) -> bool {
+    (armed & ARMED_VALIDATE == 0 || validator::validate(signal))
+        && (armed & ARMED_FILTER == 0 || filter::admit(config.signal_mask(), signal))
+}
+
+/// Delivers a signal that has been cleared from the signal word to the
+/// handler `config` holds.
+fn deliver(signal: Signal, config: impl CheckConfig) -> Result<(), Signal> {
+    status::acknowledge(signal);
+    source::observe(signal);
+    grace::start(signal);
+    latch::observe(signal, config.latch_mode());
+    listeners::notify(signal);
+    if signal.is_fatal() {
+        return Err(signal);
+    }
+    if signal == Signal::CHECKPOINT && checkpoint::take_snapshot() {
+        return Ok(());
+    }
+    if signal == Signal::COMMAND && mailbox::dispatch() {
+        return Ok(());
+    }
+    let result = match (signal.severity(), dispatch(signal, || config.handler())) {
+        // Advisory signals never propagate
+        (Some(Severity::Info | Severity::Warn), _) => Ok(()),
+        // The handler runs, but cannot swallow a kill
+        (Some(Severity::Kill), _) => Err(signal),
+        (_, result) => result,
+    };
+    match latch::latched_signal() {
+        // The handler cannot swallow a latched signal
+        Some(latched) => Err(latched),
+        None => result,
+    }
+}
+
+/// Maximum number of post-handler rechecks per check (see [`set_recheck_depth`]).
+static RECHECK_DEPTH: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+
+/// Sets how many times a check re-reads the signal word after a handler
+/// returns `Ok(())`.
+///
+/// With a depth of `0` (the default), a signal raised while a handler runs
+/// waits for the next check. With a depth of `n`, up to `n` such signals are
+/// handled in the same call. The bound keeps a handler that raises signals
+/// from livelocking the check.
+///
+/// # Returns
+///
+/// Returns the previous depth.
+pub fn set_recheck_depth(depth: u32) -> u32 {
+    RECHECK_DEPTH.swap(depth, ACQ_REL)
+}
+
+/// Gets the post-handler recheck depth.
+pub fn recheck_depth() -> u32 {
+    RECHECK_DEPTH.load(ACQUIRE)
+}
+
+/// Passes `signal` to the [namespaced handlers](register_namespaced_handler)
+/// and the handler `handler` returns (normally [`get_signal_handler`]), or
+/// returns it as an error if there are none.
+fn dispatch(signal: Signal, handler: impl FnOnce() -> Option<SignalHandler>) -> Result<(), Signal> {
+    // Held until the handler returns, so synchronize_signal_handler can
+    // wait for it
+    let _reader = generation::enter();
+    let namespaced = namespaced::any().then(|| namespaced::dispatch(signal));
+
+    let result = match handler() {
+        Some(handler) => handler(signal),
+        // No handler: return error, unless namespaced handlers ran
+        None => match namespaced {
+            Some(result) => return result,
+            None => Err(signal),
+        },
+    };
+    // The first signal propagated wins
+    match namespaced {
+        Some(Err(propagated)) => Err(propagated),
+        _ => result,
     }
 }
 
@@ -219,6 +1414,11 @@ fn handle_signal(signal_value: u32) -> Result<(), Signal> {
 /// - `Ok(())` if no signal is active, or if the handler cleared the signal
 /// - `Err(Signal)` if a signal is active and the handler propagated it
 ///
+/// # Panics
+///
+/// Takes the fatal path (like [`check_signal`]) if a grace window opened by
+/// a soft cancel has run out; see [`set_grace_period`].
+///
 /// # Example
 ///
 /// ```rust
@@ -232,14 +1432,179 @@ fn handle_signal(signal_value: u32) -> Result<(), Signal> {
 ///     Ok(())
 /// }
 /// ```
-#[inline]
+#[inline(always)]
+#[track_caller]
 pub fn try_check_signal() -> Result<(), Signal> {
-    let sig = SIGNAL.load(Ordering::Relaxed);
+    // Fast path: two relaxed loads and a branch predicted not taken
+    let code = storage::load(Ordering::Relaxed);
+    if code == 0 && ARMED.load(Ordering::Relaxed) == 0 {
+        return Ok(());
+    }
+    core::hint::cold_path();
+    slow_check(code)
+}
+
+/// Like [`try_check_signal`], converting a propagated signal into the
+/// caller's error type.
+///
+/// This saves `.map_err(Into::into)` where `?` does not apply, such as in
+/// tail position or when passing the result on. With `?`, name the type,
+/// since `?` converts again and leaves `E` ambiguous. The conversion is
+/// tracked to the caller, so a [`SignalError`] records the call-site of
+/// this function.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{try_check_signal_as, Signal};
+///
+/// #[derive(Debug)]
+/// enum AppError {
+///     Interrupted(Signal),
+/// }
+///
+/// impl From<Signal> for AppError {
+///     fn from(signal: Signal) -> AppError {
+///         AppError::Interrupted(signal)
+///     }
+/// }
+///
+/// fn step() -> Result<(), AppError> {
+///     // do_work()?;
+///     try_check_signal_as()
+/// }
+///
+/// fn steps() -> Result<(), AppError> {
+///     try_check_signal_as::<AppError>()?;
+///     step()
+/// }
+/// ```
+#[inline(always)]
+#[track_caller]
+pub fn try_check_signal_as<E: From<Signal>>() -> Result<(), E> {
+    match try_check_signal() {
+        Ok(()) => Ok(()),
+        // Called directly rather than through `map_err`, to keep the location
+        Err(signal) => Err(E::from(signal)),
+    }
+}
+
+/// Slow path of [`try_check_signal`] when a signal is pending or guest-side
+/// work is armed.
+///
+/// It is kept out of line and marked cold so that every inlined check only
+/// costs two loads and a branch.
+///
+/// A pending host signal takes precedence over a synthesized one; a passed
+/// deadline is reported by the next check instead.
+#[cold]
+#[inline(never)]
+#[track_caller]
+fn slow_check(code: u32) -> Result<(), Signal> {
+    if code != 0 {
+        storage::validate(code);
+    }
+    propagate(check_armed(code, Live))
+}
 
-    if sig != 0 {
-        handle_signal(sig)
+/// Moves the [lifecycle state](current_state) to cancelling and cancels the
+/// futures, tokens and channels tied to the signal word if `result` is a
+/// propagated signal.
+#[inline(always)]
+fn propagate(result: Result<(), Signal>) -> Result<(), Signal> {
+    if let Err(signal) = result {
+        lifecycle::advance(LifecycleState::Cancelling);
+        #[cfg(feature = "futures-util")]
+        abort::abort_all();
+        #[cfg(feature = "tokio-util")]
+        token::cancel_all();
+        #[cfg(feature = "futures-channel")]
+        channel::propagated(signal);
+        let _ = signal;
+    }
+    result
+}
+
+/// The checks of [`slow_check`] with the configuration `config` holds,
+/// before it acts on a propagated signal.
+#[inline(always)]
+#[track_caller]
+fn check_armed(code: u32, config: impl CheckConfig) -> Result<(), Signal> {
+    let armed = config.armed();
+    if armed & ARMED_ADVISE != 0 {
+        advisor::record(core::panic::Location::caller());
+    }
+    if armed & ARMED_GAP != 0 {
+        gap::on_check(core::panic::Location::caller());
+    }
+    #[cfg(feature = "std")]
+    if armed & ARMED_HEATMAP != 0 {
+        heatmap::record(core::panic::Location::caller());
+    }
+    if armed & ARMED_COUNT != 0 {
+        status::count_check();
+    }
+    #[cfg(feature = "host-poll")]
+    let code = if armed & ARMED_POLL != 0 {
+        poll::on_check(code)
     } else {
-        Ok(())
+        code
+    };
+    // Non-short-circuiting, so both injectors see every check
+    #[cfg(feature = "testing")]
+    let code = if (armed & ARMED_INJECT != 0 && testing::on_check())
+        | (armed & ARMED_FAULT != 0 && testing::fault::on_check())
+    {
+        storage::load(ACQUIRE)
+    } else {
+        code
+    };
+    let code = if armed & ARMED_QUEUE != 0 && code == 0 {
+        policy::refill()
+    } else {
+        code
+    };
+    #[cfg(feature = "testing")]
+    if armed & ARMED_RECORD != 0 {
+        testing::timeline::on_check(code);
+    }
+    if armed & ARMED_MASK != 0 {
+        match Signal::new(code) {
+            Some(signal) if signal.is_fatal() => return handle_signal_with(signal, config),
+            // Leave everything else pending until the mask is released
+            _ => return Ok(()),
+        }
+    }
+    if armed & ARMED_LATCH != 0 {
+        if let Some(signal) = latch::latched_signal() {
+            return Err(signal);
+        }
+    }
+    if armed & ARMED_GRACE != 0 && grace::expired() {
+        fatal(Signal::TERMINATE);
+    }
+    if let Some(signal) = Signal::new(code) {
+        if admit_armed(armed, config, signal) {
+            return handle_signal_with(signal, config);
+        }
+    }
+    let deadline = if armed & ARMED_EPOCH != 0 {
+        epoch::poll()
+    } else {
+        None
+    };
+    let deadline = match deadline {
+        None if armed & ARMED_DEADLINE != 0 => deadline::poll(),
+        deadline => deadline,
+    };
+    match deadline {
+        Some(signal) => {
+            listeners::notify(signal);
+            let result = dispatch(signal, || config.handler());
+            observed(signal, result);
+            result
+        }
+        None => Ok(()),
     }
 }
 
@@ -255,6 +1620,16 @@ pub fn try_check_signal() -> Result<(), Signal> {
 /// - No handler is registered, or
 /// - The registered handler returns `Err(Signal)`
 ///
+/// Before panicking, the registered shutdown hooks are run (once).
+/// With the `std` feature the panic payload is a [`SignalPanic`]; otherwise
+/// it is a formatted message. With the `trap` feature, wasm32 builds trap
+/// with `unreachable` instead of panicking, and with the `throw` feature
+/// they throw a `WASM_SIGNAL_TAG` wasm exception.
+///
+/// With the `asyncify` feature on wasm32, the guest first suspends to the
+/// host, which decides whether to resume (returning normally) or to let the
+/// panic go ahead; see `js/asyncify.js`.
+///
 /// # Example
 ///
 /// ```rust
@@ -268,9 +1643,84 @@ pub fn try_check_signal() -> Result<(), Signal> {
 /// }
 /// ```
 #[inline]
+#[track_caller]
 pub fn check_signal() {
     if let Err(signal) = try_check_signal() {
-        panic!("signal received: {}", signal);
+        #[cfg(all(feature = "asyncify", target_arch = "wasm32"))]
+        if asyncify::suspend(signal) {
+            return;
+        }
+        fatal(signal);
+    }
+}
+
+/// [`check_signal`], exported for calls inserted into a compiled module by
+/// the `wasm-signal-instrument` tool.
+///
+/// The panic unwinds through the instrumented frames, so it is only caught
+/// by `catch_unwind` when they were compiled with unwinding support.
+#[no_mangle]
+pub extern "C-unwind" fn __wasm_signal_check() {
+    check_signal();
+}
+
+/// Terminates execution for a signal propagated out of [`check_signal`].
+///
+/// Runs the registered shutdown hooks first. Then, with the `throw` feature
+/// and wasm exception handling, throws the signal as a wasm exception (see
+/// `throw`). With the `trap` feature on wasm32, it executes `unreachable`,
+/// trapping immediately without going through the panic machinery. Otherwise
+/// it panics (see [`SignalPanic`] and [`PanicFormatter`]).
+#[cold]
+#[inline(never)]
+#[track_caller]
+// The throw makes the trap or panic below unreachable
+#[cfg_attr(
+    all(
+        feature = "throw",
+        target_arch = "wasm32",
+        target_feature = "exception-handling"
+    ),
+    allow(unreachable_code)
+)]
+fn fatal(signal: Signal) -> ! {
+    end_grace();
+    latch::observe(signal, latch_mode());
+    run_shutdown_hooks();
+
+    #[cfg(all(
+        feature = "throw",
+        target_arch = "wasm32",
+        target_feature = "exception-handling"
+    ))]
+    throw::throw(signal.code());
+
+    #[cfg(all(feature = "trap", target_arch = "wasm32"))]
+    {
+        let _ = signal;
+        core::arch::wasm32::unreachable()
+    }
+
+    #[cfg(not(all(feature = "trap", target_arch = "wasm32")))]
+    signal_panic(signal)
+}
+
+/// Panics with the payload for a propagated signal.
+#[track_caller]
+#[cfg(not(all(feature = "trap", target_arch = "wasm32")))]
+fn signal_panic(signal: Signal) -> ! {
+    #[cfg(feature = "std")]
+    {
+        let mut message = std::string::String::new();
+        write_panic_message(signal, &mut message);
+        std::panic::panic_any(SignalPanic { signal, message });
+    }
+
+    #[cfg(not(feature = "std"))]
+    {
+        let mut message = MessageBuffer::new();
+        write_panic_message(signal, &mut message);
+        panic!("{}", message.as_str());
     }
 }
 
@@ -288,28 +1738,23 @@ pub fn check_signal() {
 /// - `Some(Signal)` if a signal is active
 #[inline]
 pub fn peek_signal() -> Option<Signal> {
-    let sig = SIGNAL.load(Ordering::Relaxed);
-    if sig != 0 {
-        Some(Signal(sig))
-    } else {
-        None
-    }
+    Signal::new(storage::load(ACQUIRE))
 }
 
 /// Manually clears the signal without invoking the handler.
 ///
+/// With `futures-channel`, this also clears a signal already propagated to
+/// [`SignalReceiver`]s, so they stop reporting it.
+///
 /// # Returns
 ///
 /// - `None` if no signal was active
 /// - `Some(Signal)` with the cleared signal value
 #[inline]
 pub fn clear_signal() -> Option<Signal> {
-    let sig = SIGNAL.swap(0, Ordering::SeqCst);
-    if sig != 0 {
-        Some(Signal(sig))
-    } else {
-        None
-    }
+    #[cfg(feature = "futures-channel")]
+    channel::forget();
+    Signal::new(storage::swap(0, ACQ_REL))
 }
 
 /// Manually sets a signal value.
@@ -321,32 +1766,140 @@ pub fn clear_signal() -> Option<Signal> {
 /// * `signal` - The signal value to set (0 to clear, non-zero to set)
 #[inline]
 pub fn set_signal(signal: u32) {
-    SIGNAL.store(signal, Ordering::SeqCst);
+    storage::store(signal, RELEASE);
+    #[cfg(feature = "otel")]
+    if signal != 0 {
+        otel::raised();
+    }
+    notify_waiters();
+}
+
+/// Raises a signal from guest code.
+///
+/// Unlike [`set_signal`], this is meant for production code that wants to
+/// trigger its own cancellation path, such as an internal watchdog that
+/// detects a broken invariant. The signal is sent exactly as a host would,
+/// following the [overwrite policy](set_overwrite_policy) (see
+/// [`send_signal`]). With `immediate`, pending signals are then
+/// dispatched right away through a regular check; otherwise it is left for
+/// the next check.
+///
+/// A code of `0` raises nothing.
+///
+/// # Returns
+///
+/// With `immediate`, the result of the check (see [`try_check_signal`]);
+/// otherwise `Ok(())`.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{raise_signal, Signal};
+///
+/// fn apply(balance: i64) -> Result<(), Signal> {
+///     if balance < 0 {
+///         // Unwind through the same path as a host cancellation
+///         raise_signal(Signal::CANCEL.code(), true)?;
+///     }
+///     Ok(())
+/// }
+/// ```
+#[track_caller]
+pub fn raise_signal(code: u32, immediate: bool) -> Result<(), Signal> {
+    if code == 0 {
+        return Ok(());
+    }
+    send_signal(code);
+    if immediate {
+        try_check_signal()
+    } else {
+        Ok(())
+    }
+}
+
+/// Sets a signal only if no signal is pending.
+///
+/// Cooperating components use this to avoid overwriting a pending signal,
+/// mirroring `Atomics.compareExchange(view, index, 0, code)` on the host.
+///
+/// # Errors
+///
+/// Returns the pending signal if one was already set.
+#[inline]
+pub fn set_signal_if_clear(code: u32) -> Result<(), Signal> {
+    match compare_exchange_signal(0, code) {
+        Ok(_) => Ok(()),
+        Err(pending) => Err(Signal::new(pending).expect("compare-exchange failed on 0")),
+    }
+}
+
+/// Replaces the signal word with `new` if it currently holds `expected`.
+///
+/// This is the building block for policies such as "don't overwrite a more
+/// severe pending signal": read the word, decide, and compare-exchange,
+/// retrying if it changed in between.
+///
+/// # Returns
+///
+/// The previous value of the signal word, as `Ok` if it was replaced and
+/// `Err` otherwise.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{compare_exchange_signal, Signal};
+///
+/// // Escalate a pending cancel to terminate, but leave anything else alone
+/// let _ = compare_exchange_signal(Signal::CANCEL.code(), Signal::TERMINATE.code());
+/// ```
+#[inline]
+pub fn compare_exchange_signal(expected: u32, new: u32) -> Result<u32, u32> {
+    let result = storage::compare_exchange(expected, new, ACQ_REL, ACQUIRE);
+    if result.is_ok() {
+        #[cfg(feature = "otel")]
+        if new != 0 {
+            otel::raised();
+        }
+        notify_waiters();
+    }
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    extern crate std;
+
+    /// Serializes tests that touch the global signal and handler state.
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    pub(crate) fn lock() -> std::sync::MutexGuard<'static, ()> {
+        LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     #[test]
     fn test_no_signal() {
+        let _guard = lock();
         clear_signal();
         assert!(try_check_signal().is_ok());
     }
 
     #[test]
     fn test_signal_detected() {
+        let _guard = lock();
         clear_signal_handler();
         set_signal(42);
         let result = try_check_signal();
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err().0, 42);
+        assert_eq!(result.unwrap_err().code(), 42);
         // Signal should be cleared after check
         assert!(try_check_signal().is_ok());
     }
 
     #[test]
     fn test_handler_clears_signal() {
+        let _guard = lock();
         set_signal_handler(|_signal| Ok(()));
         set_signal(1);
         assert!(try_check_signal().is_ok());
@@ -355,23 +1908,139 @@ mod tests {
 
     #[test]
     fn test_handler_propagates_signal() {
-        set_signal_handler(|signal| Err(Signal(signal.0 * 2)));
+        let _guard = lock();
+        set_signal_handler(|signal| Err(Signal::new(signal.code() * 2).unwrap()));
         set_signal(21);
         let result = try_check_signal();
         assert!(result.is_err());
-        assert_eq!(result.unwrap_err().0, 42);
+        assert_eq!(result.unwrap_err().code(), 42);
+        clear_signal_handler();
+    }
+
+    #[test]
+    fn test_newer_signal_survives_clear() {
+        let _guard = lock();
+        set_signal_handler(|signal| match signal {
+            Signal::CANCEL => Ok(()),
+            _ => Err(signal),
+        });
+
+        // A terminate lands after the cancel was loaded: it is dispatched too
+        set_signal(Signal::TERMINATE.code());
+        assert_eq!(handle_signal(Signal::CANCEL), Err(Signal::TERMINATE));
+        assert!(peek_signal().is_none());
+
+        // If the observed signal propagates, the newer one stays pending
+        clear_signal_handler();
+        set_signal(Signal::TERMINATE.code());
+        assert_eq!(handle_signal(Signal::CANCEL), Err(Signal::CANCEL));
+        assert_eq!(peek_signal(), Some(Signal::TERMINATE));
+        clear_signal();
+    }
+
+    #[test]
+    fn test_recheck_depth() {
+        let _guard = lock();
+        clear_signal();
+        static RAISES: core::sync::atomic::AtomicU32 = core::sync::atomic::AtomicU32::new(0);
+        set_signal_handler(|signal| {
+            // Each handled signal raises another one, twice
+            if RAISES.fetch_add(1, Ordering::SeqCst) < 2 {
+                set_signal(signal.code() + 1);
+            }
+            Ok(())
+        });
+
+        set_signal(10);
+        assert!(try_check_signal().is_ok());
+        assert_eq!(peek_signal(), Signal::new(11));
+
+        clear_signal();
+        RAISES.store(0, Ordering::SeqCst);
+        assert_eq!(set_recheck_depth(1), 0);
+        set_signal(10);
+        assert!(try_check_signal().is_ok());
+        // Handled 10 and 11; 12 exceeded the depth
+        assert_eq!(peek_signal(), Signal::new(12));
+
+        set_recheck_depth(0);
+        clear_signal_handler();
+        clear_signal();
+    }
+
+    #[test]
+    fn test_fatal_signals_bypass_handler() {
+        let _guard = lock();
+        clear_signal();
+        set_signal_handler(|_| Ok(()));
+
+        let fatal = Signal::new(Signal::FATAL_BASE | 7).unwrap();
+        assert!(fatal.is_fatal());
+        assert!(!Signal::new(Signal::FATAL_BASE - 1).unwrap().is_fatal());
+        assert_eq!(fatal.kind(), SignalKind::Fatal);
+
+        set_signal(fatal.code());
+        assert_eq!(try_check_signal(), Err(fatal));
+        assert!(peek_signal().is_none());
+
+        // Masking defers ordinary signals, but not fatal ones
+        let mask = SignalMaskGuard::new();
+        set_signal(fatal.code());
+        assert_eq!(try_check_signal(), Err(fatal));
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(mask.unmask(), Ok(()));
+
+        clear_signal_handler();
+    }
+
+    #[test]
+    fn test_raise_signal() {
+        let _guard = lock();
+        clear_signal();
+        set_signal_handler(|signal| match signal {
+            Signal::CANCEL => Ok(()),
+            _ => Err(signal),
+        });
+
+        assert_eq!(raise_signal(Signal::TERMINATE.code(), false), Ok(()));
+        assert_eq!(peek_signal(), Some(Signal::TERMINATE));
+        clear_signal();
+
+        assert_eq!(raise_signal(Signal::CANCEL.code(), true), Ok(()));
+        assert_eq!(
+            raise_signal(Signal::TERMINATE.code(), true),
+            Err(Signal::TERMINATE)
+        );
+        assert!(peek_signal().is_none());
+        assert_eq!(raise_signal(0, true), Ok(()));
+
         clear_signal_handler();
     }
 
+    #[test]
+    fn test_conditional_set() {
+        let _guard = lock();
+        clear_signal();
+
+        assert_eq!(set_signal_if_clear(2), Ok(()));
+        assert_eq!(set_signal_if_clear(1), Err(Signal::CANCEL));
+        assert_eq!(compare_exchange_signal(1, 5), Err(2));
+        assert_eq!(compare_exchange_signal(2, 1), Ok(2));
+        assert_eq!(peek_signal(), Some(Signal::TERMINATE));
+        clear_signal();
+    }
+
     #[test]
     fn test_set_handler_returns_previous() {
+        let _guard = lock();
         clear_signal_handler();
 
         fn handler1(_: Signal) -> Result<(), Signal> {
             Ok(())
         }
-        fn handler2(_: Signal) -> Result<(), Signal> {
-            Err(Signal(0))
+        fn handler2(signal: Signal) -> Result<(), Signal> {
+            Err(signal)
         }
 
         assert!(set_signal_handler(handler1).is_none());
@@ -383,22 +2052,169 @@ mod tests {
 
     #[test]
     fn test_peek_signal() {
+        let _guard = lock();
         clear_signal();
         assert!(peek_signal().is_none());
 
         set_signal(123);
-        assert_eq!(peek_signal(), Some(Signal(123)));
+        assert_eq!(peek_signal(), Signal::new(123));
 
         // Peek doesn't clear
-        assert_eq!(peek_signal(), Some(Signal(123)));
+        assert_eq!(peek_signal(), Signal::new(123));
+
+        clear_signal();
+    }
+
+    #[test]
+    fn test_signal_non_zero() {
+        assert!(Signal::new(0).is_none());
+        assert_eq!(Signal::new(1), Some(Signal::TERMINATE));
+        assert_eq!(Signal::CANCEL.code(), 2);
+        assert_eq!(u32::from(Signal::new(u32::MAX).unwrap()), u32::MAX);
+
+        // Niche optimization: no extra space for the "no signal" case
+        assert_eq!(core::mem::size_of::<Option<Signal>>(), 4);
+        assert_eq!(core::mem::size_of::<Result<(), Signal>>(), 4);
+    }
+
+    #[test]
+    fn test_signal_kind() {
+        assert_eq!(Signal::TERMINATE.kind(), SignalKind::Terminate);
+        assert_eq!(Signal::CANCEL.kind(), SignalKind::Cancel);
+        assert_eq!(Signal::DEADLINE.kind(), SignalKind::Deadline);
+        assert_eq!(Signal::CHECKPOINT.kind(), SignalKind::Checkpoint);
+        assert_eq!(Signal::COMMAND.kind(), SignalKind::Command);
+        assert_eq!(Signal::new(42).unwrap().kind(), SignalKind::Custom);
+    }
+
+    #[test]
+    fn test_signal_error_records_call_site() {
+        fn propagate() -> Result<(), SignalError> {
+            Err(Signal::new(7).unwrap())?;
+            Ok(())
+        }
+
+        let err = propagate().unwrap_err();
+        assert_eq!(err.code(), 7);
+        assert_eq!(err.kind(), SignalKind::Custom);
+        assert_eq!(err.location().file(), file!());
+        assert!(err.message().is_none());
+
+        let err = err.with_message("parsing body");
+        assert_eq!(err.message(), Some("parsing body"));
+        assert_eq!(Signal::from(err).code(), 7);
+    }
 
+    #[test]
+    fn test_try_check_signal_as() {
+        let _guard = lock();
         clear_signal();
+        clear_signal_handler();
+
+        assert!(try_check_signal_as::<SignalError>().is_ok());
+        set_signal(Signal::CANCEL.code());
+        let line = line!() + 1;
+        let err = try_check_signal_as::<SignalError>().unwrap_err();
+        assert_eq!(err.signal(), Signal::CANCEL);
+        assert_eq!(err.location().line(), line);
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let json = serde_json::to_string(&Signal::new(42).unwrap()).unwrap();
+        assert_eq!(json, "42");
+        assert_eq!(serde_json::from_str::<Signal>(&json).ok(), Signal::new(42));
+        assert!(serde_json::from_str::<Signal>("0").is_err());
+
+        let json = serde_json::to_string(&SignalKind::Terminate).unwrap();
+        assert_eq!(json, "\"terminate\"");
+        assert_eq!(
+            serde_json::from_str::<SignalKind>(&json).unwrap(),
+            SignalKind::Terminate
+        );
+
+        let err = SignalError::new(Signal::CANCEL).with_message("shutdown");
+        let value = serde_json::to_value(err).unwrap();
+        assert_eq!(value["code"], 2);
+        assert_eq!(value["kind"], "cancel");
+        assert_eq!(value["message"], "shutdown");
+        assert_eq!(value["file"], file!());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_check_signal_panic_payload() {
+        let _guard = lock();
+        clear_signal_handler();
+        set_signal(9);
+
+        let payload = std::panic::catch_unwind(check_signal).unwrap_err();
+        assert_eq!(SignalPanic::downcast(&*payload), Signal::new(9));
+        assert!(peek_signal().is_none());
+    }
+
+    #[cfg(not(all(feature = "trap", target_arch = "wasm32")))]
+    #[test]
+    fn test_panic_formatter() {
+        let _guard = lock();
+        clear_signal_handler();
+
+        fn formatter(signal: Signal, out: &mut dyn fmt::Write) {
+            let _ = write!(out, "request r-1 cancelled: code {}", signal.code());
+        }
+
+        assert!(set_panic_formatter(formatter).is_none());
+        set_signal(5);
+        let payload = std::panic::catch_unwind(check_signal).unwrap_err();
+        clear_panic_formatter();
+
+        #[cfg(feature = "std")]
+        let message = payload.downcast_ref::<SignalPanic>().unwrap().message();
+        #[cfg(not(feature = "std"))]
+        let message = payload.downcast_ref::<std::string::String>().unwrap();
+        assert_eq!(message, "request r-1 cancelled: code 5");
+    }
+
+    #[cfg(not(any(feature = "std", all(feature = "trap", target_arch = "wasm32"))))]
+    #[test]
+    fn test_message_buffer_truncates() {
+        use core::fmt::Write;
+
+        let mut buf = MessageBuffer::new();
+        for _ in 0..PANIC_MESSAGE_CAPACITY {
+            let _ = buf.write_str("é");
+        }
+        assert_eq!(buf.as_str().len(), PANIC_MESSAGE_CAPACITY);
+        assert!(buf.as_str().chars().all(|c| c == 'é'));
+    }
+
+    #[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
     #[test]
     fn test_signal_addr_export() {
         // Verify the exported address points to our signal
+        use core::sync::atomic::AtomicU32;
+
         let addr: *const AtomicU32 = WASM_SIGNAL_ADDR;
-        assert_eq!(addr, &SIGNAL as *const AtomicU32);
+        assert_eq!(addr, &SIGNAL.word as *const AtomicU32);
+    }
+
+    #[cfg(feature = "validate-signal")]
+    #[test]
+    fn test_unsealed_write_panics() {
+        let _guard = lock();
+        clear_signal_handler();
+        // Sealed by the guest's own write
+        set_signal(7);
+        assert_eq!(try_check_signal(), Err(Signal::new(7).unwrap()));
+
+        // A write that skips the check word, as through a stale address
+        SIGNAL.word.store(8, RELEASE);
+        let payload = std::panic::catch_unwind(|| try_check_signal()).unwrap_err();
+        assert!(payload
+            .downcast_ref::<std::string::String>()
+            .unwrap()
+            .starts_with("signal block corrupted"));
+        clear_signal();
     }
 }
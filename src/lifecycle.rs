@@ -0,0 +1,194 @@
+//! The guest's lifecycle state, published for the host.
+//!
+//! A host deciding whether to wait a little longer or terminate the instance
+//! is guessing unless it knows what the guest is doing: a guest that has not
+//! looked at the signal yet, one that is unwinding after a cancel and one
+//! running its shutdown hooks all look the same from outside. The state
+//! word tells them apart. It is exported, like the status words, as
+//! `WASM_SIGNAL_STATE_ADDR`, and holds a [`LifecycleState`].
+//!
+//! The crate drives it, so guests only have to enter their requests through
+//! a [`RequestScope`](crate::RequestScope):
+//!
+//! - a request scope moves it to [`Running`](LifecycleState::Running) on
+//!   entry, and back to [`Idle`](LifecycleState::Idle) on exit, as does
+//!   [`reset_all`](crate::reset_all);
+//! - a signal propagated out of a check moves it to
+//!   [`Cancelling`](LifecycleState::Cancelling);
+//! - [`run_shutdown_hooks`](crate::run_shutdown_hooks), which the fatal path
+//!   of `check_signal` calls, moves it to
+//!   [`CleaningUp`](LifecycleState::CleaningUp) while the hooks run and to
+//!   [`Terminated`](LifecycleState::Terminated) once they have.
+//!
+//! Within a request the state only moves forward, so a signal propagated
+//! while the hooks run does not step it back. Threaded guests wake host
+//! waiters on the word whenever it changes.
+
+use core::fmt;
+use core::sync::atomic::AtomicU32;
+
+use crate::{ACQUIRE, ACQ_REL};
+
+/// What the guest is doing, from the host's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[repr(u32)]
+pub enum LifecycleState {
+    /// No request is running.
+    Idle = 0,
+    /// A request is running and has not propagated a signal.
+    Running = 1,
+    /// A check propagated a signal, and the request is winding down.
+    Cancelling = 2,
+    /// The shutdown hooks are running.
+    CleaningUp = 3,
+    /// The shutdown hooks have run, and execution is ending.
+    Terminated = 4,
+}
+
+impl LifecycleState {
+    /// Returns the state stored in `word`, if any.
+    pub const fn from_word(word: u32) -> Option<LifecycleState> {
+        match word {
+            0 => Some(LifecycleState::Idle),
+            1 => Some(LifecycleState::Running),
+            2 => Some(LifecycleState::Cancelling),
+            3 => Some(LifecycleState::CleaningUp),
+            4 => Some(LifecycleState::Terminated),
+            _ => None,
+        }
+    }
+
+    /// Returns a short, stable name for this state.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleState::Idle => "idle",
+            LifecycleState::Running => "running",
+            LifecycleState::Cancelling => "cancelling",
+            LifecycleState::CleaningUp => "cleaning-up",
+            LifecycleState::Terminated => "terminated",
+        }
+    }
+}
+
+impl fmt::Display for LifecycleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+static STATE: AtomicU32 = AtomicU32::new(LifecycleState::Idle as u32);
+
+/// The exported address of the state word.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_STATE_ADDR: &AtomicU32 = &STATE;
+
+/// Returns the lifecycle state currently published to the host.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{current_state, LifecycleState, RequestScope};
+///
+/// {
+///     let _scope = RequestScope::new();
+///     assert_eq!(current_state(), LifecycleState::Running);
+/// }
+/// assert_eq!(current_state(), LifecycleState::Idle);
+/// ```
+pub fn current_state() -> LifecycleState {
+    // Only this module stores to the word
+    LifecycleState::from_word(STATE.load(ACQUIRE)).unwrap_or(LifecycleState::Idle)
+}
+
+/// Moves to `state`, wherever the state is.
+pub(crate) fn enter(state: LifecycleState) {
+    if STATE.swap(state as u32, ACQ_REL) != state as u32 {
+        notify();
+    }
+}
+
+/// Moves forward to `state`, unless the state is already past it.
+pub(crate) fn advance(state: LifecycleState) {
+    if STATE.fetch_max(state as u32, ACQ_REL) < state as u32 {
+        notify();
+    }
+}
+
+/// Wakes hosts waiting for a state change with `Atomics.waitAsync`.
+#[inline(always)]
+fn notify() {
+    #[cfg(all(target_arch = "wasm32", target_feature = "atomics"))]
+    // SAFETY: STATE is a valid, aligned 32-bit atomic for the program's lifetime
+    unsafe {
+        core::arch::wasm32::memory_atomic_notify(STATE.as_ptr() as *mut i32, u32::MAX);
+    }
+}
+
+/// The state word, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(u32);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(STATE.load(ACQUIRE))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    STATE.store(saved.0, crate::RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_shutdown_hooks, clear_signal_handler, rearm_shutdown_hooks, register_shutdown_hook,
+        reset_all, run_shutdown_hooks, set_signal, try_check_signal, RequestScope, Signal, RELEASE,
+    };
+
+    static DURING_HOOK: AtomicU32 = AtomicU32::new(0);
+
+    fn hook() {
+        DURING_HOOK.store(STATE.load(ACQUIRE), RELEASE);
+    }
+
+    #[test]
+    fn test_lifecycle_transitions() {
+        let _guard = lock();
+        clear_signal_handler();
+        clear_shutdown_hooks();
+        // Other tests leave signals propagated outside of a request
+        reset_all();
+        assert_eq!(current_state(), LifecycleState::Idle);
+
+        {
+            let _scope = RequestScope::new();
+            assert_eq!(current_state(), LifecycleState::Running);
+            assert!(try_check_signal().is_ok());
+            assert_eq!(current_state(), LifecycleState::Running);
+
+            set_signal(Signal::CANCEL.code());
+            assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+            assert_eq!(current_state(), LifecycleState::Cancelling);
+
+            register_shutdown_hook(hook).unwrap();
+            assert!(run_shutdown_hooks());
+            assert_eq!(DURING_HOOK.load(ACQUIRE), LifecycleState::CleaningUp as u32);
+            assert_eq!(current_state(), LifecycleState::Terminated);
+
+            // Never steps back within a request
+            set_signal(Signal::CANCEL.code());
+            assert!(try_check_signal().is_err());
+            assert_eq!(current_state(), LifecycleState::Terminated);
+        }
+
+        assert_eq!(current_state(), LifecycleState::Idle);
+        clear_shutdown_hooks();
+        rearm_shutdown_hooks();
+    }
+}
@@ -0,0 +1,166 @@
+//! Passive observers notified of every observed signal.
+//!
+//! The signal handler decides what happens to a signal, so there can only be
+//! one. Metrics, logging and tracing just want to know that a signal arrived;
+//! they register as listeners with [`add_signal_listener`] instead. Listeners
+//! run before the handler, cannot change the outcome, and also see signals
+//! that bypass the handler (checkpoints taken by a snapshotter and
+//! [fatal](crate::Signal::is_fatal) signals).
+
+use core::ptr::null_mut;
+use core::sync::atomic::AtomicPtr;
+
+use crate::{Signal, ACQUIRE, ACQ_REL, RELEASE};
+
+/// A passive signal observer.
+///
+/// Listeners run inside a signal check, so they must not check signals
+/// themselves.
+pub type SignalListener = fn(Signal);
+
+/// Maximum number of registered listeners.
+pub const MAX_SIGNAL_LISTENERS: usize = 8;
+
+/// Listener slots; null slots are free.
+///
+/// Stored as raw pointers for the same reasons as the handler slot.
+static LISTENERS: [AtomicPtr<()>; MAX_SIGNAL_LISTENERS] =
+    [const { AtomicPtr::new(null_mut()) }; MAX_SIGNAL_LISTENERS];
+
+/// Registers a listener.
+///
+/// A listener registered twice is notified twice.
+///
+/// # Returns
+///
+/// Returns `false` if [`MAX_SIGNAL_LISTENERS`] listeners are already
+/// registered.
+///
+/// # Example
+///
+/// ```rust
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use wasm_signal_handler::add_signal_listener;
+///
+/// static SIGNALS_SEEN: AtomicU32 = AtomicU32::new(0);
+///
+/// add_signal_listener(|_signal| {
+///     SIGNALS_SEEN.fetch_add(1, Ordering::Relaxed);
+/// });
+/// ```
+pub fn add_signal_listener(listener: SignalListener) -> bool {
+    LISTENERS.iter().any(|slot| {
+        slot.compare_exchange(null_mut(), listener as *mut (), ACQ_REL, ACQUIRE)
+            .is_ok()
+    })
+}
+
+/// Removes one registration of `listener`.
+///
+/// # Returns
+///
+/// Returns `true` if the listener was registered.
+pub fn remove_signal_listener(listener: SignalListener) -> bool {
+    LISTENERS.iter().any(|slot| {
+        slot.compare_exchange(listener as *mut (), null_mut(), ACQ_REL, ACQUIRE)
+            .is_ok()
+    })
+}
+
+/// Removes all listeners.
+pub fn clear_signal_listeners() {
+    for slot in &LISTENERS {
+        slot.store(null_mut(), RELEASE);
+    }
+}
+
+/// Returns `true` if `listener` is registered.
+#[cfg(feature = "js-listener")]
+pub(crate) fn contains(listener: SignalListener) -> bool {
+    LISTENERS
+        .iter()
+        .any(|slot| slot.load(ACQUIRE) == listener as *mut ())
+}
+
+/// Returns the number of registered listeners.
+pub(crate) fn count() -> usize {
+    LISTENERS
+        .iter()
+        .filter(|slot| !slot.load(ACQUIRE).is_null())
+        .count()
+}
+
+/// Notifies every registered listener of `signal`.
+pub(crate) fn notify(signal: Signal) {
+    for slot in &LISTENERS {
+        let ptr = slot.load(ACQUIRE);
+        if !ptr.is_null() {
+            // SAFETY: We only store valid SignalListener function pointers in LISTENERS
+            let listener = unsafe { core::mem::transmute::<*mut (), SignalListener>(ptr) };
+            listener(signal);
+        }
+    }
+}
+
+/// The listener slots, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved([usize; MAX_SIGNAL_LISTENERS]);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(core::array::from_fn(|i| {
+        LISTENERS[i].load(ACQUIRE) as usize
+    }))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    for (slot, &ptr) in LISTENERS.iter().zip(&saved.0) {
+        slot.store(ptr as *mut (), RELEASE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, set_signal, set_signal_handler, try_check_signal,
+    };
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    static SEEN: AtomicU32 = AtomicU32::new(0);
+
+    fn count(signal: Signal) {
+        SEEN.fetch_add(signal.code(), Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_listeners_observe_without_swallowing() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_listeners();
+        SEEN.store(0, Ordering::SeqCst);
+        set_signal_handler(Err);
+
+        assert!(add_signal_listener(count));
+        assert!(add_signal_listener(count));
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert_eq!(SEEN.load(Ordering::SeqCst), 4);
+
+        assert!(remove_signal_listener(count));
+        set_signal(Signal::TERMINATE.code());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        assert_eq!(SEEN.load(Ordering::SeqCst), 5);
+
+        for _ in 1..MAX_SIGNAL_LISTENERS {
+            assert!(add_signal_listener(count));
+        }
+        assert!(!add_signal_listener(count));
+
+        clear_signal_listeners();
+        clear_signal_handler();
+    }
+}
@@ -0,0 +1,213 @@
+//! Bridging Lunatic process signals.
+//!
+//! A Lunatic process can only learn about the rest of the system through
+//! its mailbox, which a computational loop never reads. With the `lunatic`
+//! feature, checks read it for it through the [host
+//! poll](crate::set_poll_stride) machinery (the feature implies
+//! `host-poll`), in place of the `env.wasm_signal_poll` import. Each poll
+//! takes at most one message tagged [`LUNATIC_SIGNAL_TAG`], without waiting:
+//!
+//! - A data message holding a code, as four little-endian bytes, is sent as
+//!   that signal. [`send_lunatic_signal`] sends one to another process, so
+//!   signals travel between processes as well as from the host.
+//! - The death of a process linked with [`link_lunatic_process`] is sent as
+//!   [`Signal::TERMINATE`](crate::Signal::TERMINATE), so a supervisor that
+//!   gives up on a worker, or dies, stops its loops at their next check
+//!   instead of leaving them running or killing them mid-write.
+//!
+//! Messages with other tags stay in the mailbox for the process to receive
+//! as usual.
+
+use crate::Signal;
+
+/// The tag of signal messages, and of links made by
+/// [`link_lunatic_process`].
+pub const LUNATIC_SIGNAL_TAG: i64 = 0x5349_474E;
+
+/// `receive` result for a data message.
+const RECEIVED_DATA: u32 = 0;
+
+/// `receive` result for the death of a linked process.
+const RECEIVED_LINK_DIED: u32 = 1;
+
+#[cfg(target_arch = "wasm32")]
+mod kernel {
+    use super::LUNATIC_SIGNAL_TAG;
+
+    #[link(wasm_import_module = "lunatic::message")]
+    extern "C" {
+        fn create_data(tag: i64, capacity: u64);
+        fn write_data(data: *const u8, data_len: usize) -> usize;
+        fn read_data(data: *mut u8, data_len: usize) -> usize;
+        fn data_size() -> u64;
+        fn send(process_id: u64) -> u32;
+        fn receive(tags: *const i64, tags_len: usize, timeout_ms: u64) -> u32;
+    }
+
+    #[link(wasm_import_module = "lunatic::process")]
+    extern "C" {
+        fn die_when_link_dies(trap: u32);
+        fn link(tag: i64, process_id: u64);
+    }
+
+    /// Takes the next signal-tagged message without waiting.
+    ///
+    /// # Returns
+    ///
+    /// The `receive` result, and the message's code if it is a four-byte
+    /// data message.
+    pub(super) fn receive_signal() -> (u32, u32) {
+        let tags = [LUNATIC_SIGNAL_TAG];
+        // SAFETY: The tag list and code buffer outlive the calls, and reads
+        // are bounded by the buffer length
+        unsafe {
+            let received = receive(tags.as_ptr(), tags.len(), 0);
+            let mut bytes = [0; 4];
+            if received == super::RECEIVED_DATA && data_size() == 4 {
+                read_data(bytes.as_mut_ptr(), bytes.len());
+            }
+            (received, u32::from_le_bytes(bytes))
+        }
+    }
+
+    pub(super) fn send_signal(process_id: u64, code: u32) {
+        let bytes = code.to_le_bytes();
+        // SAFETY: The buffer outlives the calls, and the message is created
+        // before it is written
+        unsafe {
+            create_data(LUNATIC_SIGNAL_TAG, bytes.len() as u64);
+            write_data(bytes.as_ptr(), bytes.len());
+            send(process_id);
+        }
+    }
+
+    pub(super) fn link_process(process_id: u64) {
+        // SAFETY: Neither call has preconditions
+        unsafe {
+            die_when_link_dies(0);
+            link(LUNATIC_SIGNAL_TAG, process_id);
+        }
+    }
+}
+
+/// Outside wasm32 there is no Lunatic runtime; tests use a stand-in for one
+/// pending message and the last one sent.
+#[cfg(not(target_arch = "wasm32"))]
+mod kernel {
+    pub(super) fn receive_signal() -> (u32, u32) {
+        #[cfg(test)]
+        {
+            if host::LINK_DIED.swap(false, crate::ACQ_REL) {
+                return (super::RECEIVED_LINK_DIED, 0);
+            }
+            match host::MESSAGE.swap(0, crate::ACQ_REL) {
+                0 => (u32::MAX, 0),
+                code => (super::RECEIVED_DATA, code),
+            }
+        }
+        #[cfg(not(test))]
+        (u32::MAX, 0)
+    }
+
+    pub(super) fn send_signal(process_id: u64, code: u32) {
+        #[cfg(test)]
+        host::SENT.store(process_id << 32 | u64::from(code), crate::RELEASE);
+        let _ = (process_id, code);
+    }
+
+    pub(super) fn link_process(process_id: u64) {
+        #[cfg(test)]
+        host::LINKED.store(process_id, crate::RELEASE);
+        let _ = process_id;
+    }
+
+    #[cfg(test)]
+    pub(super) mod host {
+        use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64};
+
+        pub static MESSAGE: AtomicU32 = AtomicU32::new(0);
+        pub static LINK_DIED: AtomicBool = AtomicBool::new(false);
+        pub static SENT: AtomicU64 = AtomicU64::new(0);
+        pub static LINKED: AtomicU64 = AtomicU64::new(0);
+    }
+}
+
+/// Sends `code` as a signal to the Lunatic process `process_id`, whose
+/// checks raise it at their next poll.
+pub fn send_lunatic_signal(process_id: u64, code: Signal) {
+    kernel::send_signal(process_id, code.code());
+}
+
+/// Links the current process to `process_id`, typically its supervisor, so
+/// that its death raises [`Signal::TERMINATE`] here instead of killing the
+/// process outright.
+///
+/// The link is made with [`LUNATIC_SIGNAL_TAG`]. From then on, no linked
+/// process's death kills this one: deaths of links made with other tags
+/// wait in the mailbox for the process to receive.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{link_lunatic_process, try_check_signal, Signal};
+///
+/// fn worker(supervisor: u64, jobs: &[u64]) -> Result<u64, Signal> {
+///     link_lunatic_process(supervisor);
+///     let mut total = 0;
+///     for job in jobs {
+///         try_check_signal()?;
+///         total += job;
+///     }
+///     Ok(total)
+/// }
+/// ```
+pub fn link_lunatic_process(process_id: u64) {
+    kernel::link_process(process_id);
+}
+
+/// Takes the next signal message from the mailbox, without waiting.
+///
+/// # Returns
+///
+/// The code to raise, or `0` for none.
+pub(crate) fn take_signal() -> u32 {
+    match kernel::receive_signal() {
+        (RECEIVED_DATA, code) => code,
+        (RECEIVED_LINK_DIED, _) => Signal::TERMINATE.code(),
+        _ => 0,
+    }
+}
+
+// The stand-in is only available outside wasm32
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, poll_host, try_check_signal, ACQUIRE, RELEASE,
+    };
+
+    #[test]
+    fn test_lunatic_mailbox() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        kernel::host::MESSAGE.store(Signal::CANCEL.code(), RELEASE);
+        assert!(poll_host());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert!(!poll_host());
+
+        link_lunatic_process(7);
+        assert_eq!(kernel::host::LINKED.load(ACQUIRE), 7);
+        kernel::host::LINK_DIED.store(true, RELEASE);
+        assert!(poll_host());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+
+        send_lunatic_signal(9, Signal::DEADLINE);
+        assert_eq!(
+            kernel::host::SENT.load(ACQUIRE),
+            9 << 32 | u64::from(Signal::DEADLINE.code())
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Host-to-guest commands.
+//!
+//! A signal code says "stop" (or "checkpoint"), but hosts also want to ask
+//! for things like "reduce memory usage" or "reload config", with arguments.
+//! The guest exports a small mailbox (at `WASM_SIGNAL_MAILBOX_ADDR`) holding
+//! one command code and [`MAILBOX_ARGS`] argument words. The host fills it,
+//! then rings the doorbell by writing [`Signal::COMMAND`] to the signal word.
+//!
+//! A check that observes the doorbell takes the command and runs the handler
+//! registered for its code with [`set_command_handler`], then continues (the
+//! check returns `Ok(())`). Commands without a handler stay in the mailbox
+//! for [`poll_command`], and the doorbell goes to the signal handler like any
+//! other signal, as it does when the mailbox is empty.
+//!
+//! The command word reads `0` once the guest has taken the command; hosts
+//! wait for that before posting the next one.
+//!
+//! [`Signal::COMMAND`]: crate::Signal::COMMAND
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU32};
+
+use crate::{ACQUIRE, RELEASE};
+
+/// Number of argument words in the mailbox.
+pub const MAILBOX_ARGS: usize = 4;
+
+/// Maximum number of registered command handlers.
+pub const MAX_COMMAND_HANDLERS: usize = 16;
+
+/// The mailbox shared with the host.
+///
+/// The command word (`0` when empty) followed by [`MAILBOX_ARGS`] argument
+/// words. The host writes the arguments before the command word. The layout
+/// is read by the host helpers and must not change.
+#[repr(C)]
+pub struct Mailbox {
+    command: AtomicU32,
+    args: [AtomicU32; MAILBOX_ARGS],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_ARG: AtomicU32 = AtomicU32::new(0);
+
+static MAILBOX: Mailbox = Mailbox {
+    command: AtomicU32::new(0),
+    args: [EMPTY_ARG; MAILBOX_ARGS],
+};
+
+/// The exported address of the mailbox.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_MAILBOX_ADDR: &Mailbox = &MAILBOX;
+
+/// A command taken from the mailbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Command {
+    /// The command code; its meaning is agreed between host and guest.
+    pub code: u32,
+    /// The argument words. Unused arguments are whatever the host left there,
+    /// usually `0`.
+    pub args: [u32; MAILBOX_ARGS],
+}
+
+/// Runs a command taken from the mailbox.
+///
+/// Handlers run from inside a signal check, so they must not check signals
+/// themselves.
+pub type CommandHandler = fn(Command);
+
+type Table = [(u32, Option<CommandHandler>); MAX_COMMAND_HANDLERS];
+
+/// The registered handlers, guarded by [`LOCK`]. Unused entries have code `0`.
+struct Handlers(UnsafeCell<Table>);
+
+// SAFETY: The table is only accessed under LOCK
+unsafe impl Sync for Handlers {}
+
+static HANDLERS: Handlers = Handlers(UnsafeCell::new([(0, None); MAX_COMMAND_HANDLERS]));
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+fn with_handlers<R>(f: impl FnOnce(&mut Table) -> R) -> R {
+    while LOCK
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // SAFETY: LOCK grants exclusive access to the table
+    let result = f(unsafe { &mut *HANDLERS.0.get() });
+    LOCK.store(false, RELEASE);
+    result
+}
+
+/// Registers `handler` for commands with `code`, replacing any handler
+/// registered before.
+///
+/// # Returns
+///
+/// Returns `false` if `code` is `0` or [`MAX_COMMAND_HANDLERS`] codes already
+/// have handlers.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{set_command_handler, Command};
+///
+/// const RELOAD_CONFIG: u32 = 1;
+///
+/// fn reload_config(command: Command) {
+///     let _version = command.args[0];
+///     // reload
+/// }
+///
+/// assert!(set_command_handler(RELOAD_CONFIG, reload_config));
+/// ```
+pub fn set_command_handler(code: u32, handler: CommandHandler) -> bool {
+    if code == 0 {
+        return false;
+    }
+    with_handlers(|handlers| {
+        let entry = match handlers.iter().position(|&(c, _)| c == code) {
+            Some(index) => index,
+            None => match handlers.iter().position(|&(c, _)| c == 0) {
+                Some(index) => index,
+                None => return false,
+            },
+        };
+        handlers[entry] = (code, Some(handler));
+        true
+    })
+}
+
+/// Removes the handler registered for `code`.
+///
+/// # Returns
+///
+/// Returns the removed handler, if any.
+pub fn clear_command_handler(code: u32) -> Option<CommandHandler> {
+    if code == 0 {
+        return None;
+    }
+    with_handlers(|handlers| {
+        let entry = handlers.iter_mut().find(|(c, _)| *c == code)?;
+        let handler = entry.1;
+        *entry = (0, None);
+        handler
+    })
+}
+
+fn handler_for(code: u32) -> Option<CommandHandler> {
+    with_handlers(|handlers| {
+        handlers
+            .iter()
+            .find(|&&(c, _)| c == code)
+            .and_then(|&(_, handler)| handler)
+    })
+}
+
+/// Takes the command waiting in the mailbox, if any, emptying it.
+///
+/// Use this for commands without a registered handler, for example from the
+/// signal handler when it receives [`Signal::COMMAND`](crate::Signal::COMMAND),
+/// or at a point where the application polls for work.
+pub fn poll_command() -> Option<Command> {
+    let code = MAILBOX.command.load(ACQUIRE);
+    if code == 0 {
+        return None;
+    }
+    let args = core::array::from_fn(|i| MAILBOX.args[i].load(ACQUIRE));
+    // The host does not write again until the command word reads 0
+    MAILBOX.command.store(0, RELEASE);
+    Some(Command { code, args })
+}
+
+/// Runs the handler for the waiting command, after the doorbell rang.
+///
+/// # Returns
+///
+/// Returns `false` if the mailbox is empty or the command has no handler,
+/// so the doorbell should go to the signal handler.
+pub(crate) fn dispatch() -> bool {
+    let code = MAILBOX.command.load(ACQUIRE);
+    if code == 0 {
+        return false;
+    }
+    let Some(handler) = handler_for(code) else {
+        return false;
+    };
+    if let Some(command) = poll_command() {
+        handler(command);
+    }
+    true
+}
+
+/// Empties the mailbox.
+pub(crate) fn reset() {
+    MAILBOX.command.store(0, RELEASE);
+}
+
+/// The mailbox and the registered handlers, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+pub(crate) struct Saved {
+    command: u32,
+    args: [u32; MAILBOX_ARGS],
+    handlers: Table,
+}
+
+#[cfg(feature = "testing")]
+impl Default for Saved {
+    fn default() -> Saved {
+        Saved {
+            command: 0,
+            args: [0; MAILBOX_ARGS],
+            handlers: [(0, None); MAX_COMMAND_HANDLERS],
+        }
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        command: MAILBOX.command.load(ACQUIRE),
+        args: core::array::from_fn(|i| MAILBOX.args[i].load(ACQUIRE)),
+        handlers: with_handlers(|handlers| *handlers),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    for (arg, &value) in MAILBOX.args.iter().zip(&saved.args) {
+        arg.store(value, RELEASE);
+    }
+    MAILBOX.command.store(saved.command, RELEASE);
+    with_handlers(|handlers| *handlers = saved.handlers);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, try_check_signal, Signal};
+
+    static LAST: AtomicU32 = AtomicU32::new(0);
+
+    fn record(command: Command) {
+        LAST.store(command.code * 100 + command.args[0], RELEASE);
+    }
+
+    /// Posts a command and rings the doorbell, as a host does.
+    fn post(code: u32, arg: u32) {
+        MAILBOX.args[0].store(arg, RELEASE);
+        MAILBOX.command.store(code, RELEASE);
+        set_signal(Signal::COMMAND.code());
+    }
+
+    #[test]
+    fn test_commands_run_handlers_and_continue() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        reset();
+
+        assert!(set_command_handler(7, record));
+        assert!(!set_command_handler(0, record));
+        post(7, 3);
+        assert!(try_check_signal().is_ok());
+        assert_eq!(LAST.load(ACQUIRE), 703);
+        assert_eq!(poll_command(), None);
+
+        // Without a handler the command waits, and the doorbell propagates
+        post(8, 1);
+        assert_eq!(try_check_signal(), Err(Signal::COMMAND));
+        assert_eq!(
+            poll_command(),
+            Some(Command {
+                code: 8,
+                args: [1, 0, 0, 0]
+            })
+        );
+
+        assert!(clear_command_handler(7).is_some());
+        assert!(clear_command_handler(7).is_none());
+    }
+}
@@ -0,0 +1,229 @@
+//! Critical-section signal masking with deferred delivery.
+//!
+//! While a [`SignalMaskGuard`] is alive, checks report no signal: a pending
+//! host signal stays in the signal word, and deadlines and grace windows are
+//! not evaluated. When the last guard is released, anything that arrived in
+//! the meantime is delivered immediately. This keeps short critical sections
+//! (such as writing a log record) from being interrupted part-way.
+//! [Fatal](Signal::is_fatal) signals are the exception and are delivered
+//! even while masked.
+//!
+//! The mask is global rather than per-thread, matching the signal word.
+
+use core::sync::atomic::AtomicU32;
+
+#[cfg(feature = "std")]
+use crate::check_signal;
+use crate::{arm, disarm, try_check_signal, Signal, ACQUIRE, ACQ_REL, ARMED_MASK, RELEASE};
+
+/// Number of live guards.
+static DEPTH: AtomicU32 = AtomicU32::new(0);
+
+/// Defers signal handling while alive.
+///
+/// Guards nest; signals are delivered when the outermost guard is released.
+/// Release a guard with [`unmask`](SignalMaskGuard::unmask) to receive a
+/// deferred signal as an `Err`.
+///
+/// With the `std` feature, dropping a guard instead delivers it like
+/// `check_signal`, taking the fatal path if the signal is propagated, unless
+/// the thread is already panicking. Without `std` there is no way to tell
+/// whether the drop is part of an unwind, and panicking there would abort,
+/// so dropping a guard only unmasks and the signal stays pending for the
+/// next check.
+#[must_use = "signals are unmasked as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct SignalMaskGuard {
+    _private: (),
+}
+
+impl SignalMaskGuard {
+    /// Masks signals until the returned guard is released.
+    pub fn new() -> SignalMaskGuard {
+        if DEPTH.fetch_add(1, ACQ_REL) == 0 {
+            arm(ARMED_MASK);
+        }
+        SignalMaskGuard { _private: () }
+    }
+
+    /// Releases the guard, delivering any deferred signal as with
+    /// [`try_check_signal`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(Signal)` if this was the outermost guard and a deferred
+    /// signal was propagated by the handler.
+    #[track_caller]
+    pub fn unmask(self) -> Result<(), Signal> {
+        let outermost = release();
+        core::mem::forget(self);
+        if outermost {
+            try_check_signal()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for SignalMaskGuard {
+    fn default() -> SignalMaskGuard {
+        SignalMaskGuard::new()
+    }
+}
+
+impl Drop for SignalMaskGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        if release() && !std::thread::panicking() {
+            check_signal();
+        }
+        #[cfg(not(feature = "std"))]
+        release();
+    }
+}
+
+/// Drops one level of masking, returning `true` if signals are now unmasked.
+///
+/// Guards that outlive a [`reset`] find the depth already at zero and do
+/// nothing.
+fn release() -> bool {
+    match DEPTH.try_update(ACQ_REL, ACQUIRE, |depth| depth.checked_sub(1)) {
+        Ok(1) => {
+            disarm(ARMED_MASK);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Unmasks signals regardless of live guards, without delivering anything.
+pub(crate) fn reset() {
+    DEPTH.store(0, RELEASE);
+    disarm(ARMED_MASK);
+}
+
+/// Returns `true` if signals are currently masked.
+pub fn signals_masked() -> bool {
+    DEPTH.load(ACQUIRE) != 0
+}
+
+/// Runs `f` with signals masked, then delivers any deferred signal.
+///
+/// # Errors
+///
+/// Returns `Err(Signal)` if a signal arrived while `f` ran and the handler
+/// propagated it. `f` always runs to completion; its result is discarded in
+/// that case.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{without_signals, Signal};
+///
+/// fn append_record(record: &[u8]) -> Result<(), Signal> {
+///     without_signals(|| {
+///         // write_header(record); write_body(record); write_checksum(record);
+///     })
+/// }
+/// ```
+#[track_caller]
+pub fn without_signals<R>(f: impl FnOnce() -> R) -> Result<R, Signal> {
+    let guard = SignalMaskGuard::new();
+    let result = f();
+    guard.unmask().map(|()| result)
+}
+
+/// Releases one level of masking taken by a forgotten guard, for callers
+/// that cannot hold one across the critical section (the C API).
+///
+/// Without a level to release, it does nothing.
+#[cfg(feature = "capi")]
+pub(crate) fn unmask_forgotten() -> Result<(), Signal> {
+    SignalMaskGuard { _private: () }.unmask()
+}
+
+/// The mask depth, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(u32);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(DEPTH.load(ACQUIRE))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    DEPTH.store(saved.0, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, peek_signal, set_signal};
+
+    extern crate std;
+
+    #[test]
+    fn test_signal_deferred_until_unmask() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        let outer = SignalMaskGuard::new();
+        let inner = SignalMaskGuard::new();
+        set_signal(Signal::TERMINATE.code());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(peek_signal(), Some(Signal::TERMINATE));
+
+        assert!(inner.unmask().is_ok());
+        assert!(signals_masked());
+        assert_eq!(outer.unmask(), Err(Signal::TERMINATE));
+        assert!(!signals_masked());
+    }
+
+    #[test]
+    fn test_without_signals() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        assert_eq!(without_signals(|| 1), Ok(1));
+        let result = without_signals(|| {
+            set_signal(Signal::CANCEL.code());
+            try_check_signal()
+        });
+        assert_eq!(result, Err(Signal::CANCEL));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_drop_delivers_signal() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        let result = std::panic::catch_unwind(|| {
+            let _mask = SignalMaskGuard::new();
+            set_signal(Signal::TERMINATE.code());
+        });
+        assert!(result.is_err());
+        assert!(!signals_masked());
+        crate::rearm_shutdown_hooks();
+    }
+
+    #[test]
+    #[cfg(not(feature = "std"))]
+    fn test_drop_leaves_signal_pending() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        let mask = SignalMaskGuard::new();
+        set_signal(Signal::TERMINATE.code());
+        drop(mask);
+        assert!(!signals_masked());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+    }
+}
@@ -0,0 +1,154 @@
+//! Human-readable names for signal codes.
+//!
+//! Hosts and applications often agree on custom codes beyond the well-known
+//! ones. Registering a name with [`register_signal_name`] makes it show up in
+//! [`Signal::name`], `Display` output, [`SignalError`](crate::SignalError)
+//! messages and the default panic message, so logs say `quota-exceeded`
+//! rather than `42`.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::AtomicBool;
+
+use crate::{ACQUIRE, RELEASE};
+
+/// Maximum number of registered names.
+pub const MAX_SIGNAL_NAMES: usize = 32;
+
+type Table = [(u32, &'static str); MAX_SIGNAL_NAMES];
+
+/// The registered names, guarded by [`LOCK`]. Unused entries have code `0`.
+struct Names(UnsafeCell<Table>);
+
+// SAFETY: The table is only accessed under LOCK
+unsafe impl Sync for Names {}
+
+static NAMES: Names = Names(UnsafeCell::new([(0, ""); MAX_SIGNAL_NAMES]));
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+fn with_names<R>(f: impl FnOnce(&mut Table) -> R) -> R {
+    while LOCK
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // SAFETY: LOCK grants exclusive access to the table
+    let result = f(unsafe { &mut *NAMES.0.get() });
+    LOCK.store(false, RELEASE);
+    result
+}
+
+/// Registers `name` for `code`, replacing any name registered before.
+///
+/// Registered names take precedence over the names of well-known codes.
+///
+/// # Returns
+///
+/// Returns `false` if `code` is `0` or [`MAX_SIGNAL_NAMES`] codes already
+/// have names.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{register_signal_name, Signal};
+///
+/// register_signal_name(42, "quota-exceeded");
+/// assert_eq!(Signal::new(42).unwrap().name(), Some("quota-exceeded"));
+/// ```
+pub fn register_signal_name(code: u32, name: &'static str) -> bool {
+    if code == 0 {
+        return false;
+    }
+    with_names(|names| {
+        let entry = match names.iter().position(|&(c, _)| c == code) {
+            Some(index) => index,
+            None => match names.iter().position(|&(c, _)| c == 0) {
+                Some(index) => index,
+                None => return false,
+            },
+        };
+        names[entry] = (code, name);
+        true
+    })
+}
+
+/// Removes the name registered for `code`.
+///
+/// # Returns
+///
+/// Returns the removed name, if any.
+pub fn unregister_signal_name(code: u32) -> Option<&'static str> {
+    if code == 0 {
+        return None;
+    }
+    with_names(|names| {
+        let entry = names.iter_mut().find(|(c, _)| *c == code)?;
+        let name = entry.1;
+        *entry = (0, "");
+        Some(name)
+    })
+}
+
+/// Returns the name registered for `code`, if any.
+pub(crate) fn lookup(code: u32) -> Option<&'static str> {
+    with_names(|names| {
+        names
+            .iter()
+            .find(|&&(c, _)| c == code)
+            .map(|&(_, name)| name)
+    })
+}
+
+/// The registered names, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+pub(crate) struct Saved(Table);
+
+#[cfg(feature = "testing")]
+impl Default for Saved {
+    fn default() -> Saved {
+        Saved([(0, ""); MAX_SIGNAL_NAMES])
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(with_names(|names| *names))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    with_names(|names| *names = saved.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{encode_signal, Severity, Signal};
+
+    extern crate std;
+    use std::string::ToString;
+
+    #[test]
+    fn test_registered_names() {
+        let _guard = lock();
+        let quota = Signal::new(42).unwrap();
+
+        assert_eq!(Signal::CANCEL.name(), Some("cancel"));
+        assert_eq!(quota.name(), None);
+        assert_eq!(quota.to_string(), "Signal(42)");
+
+        assert!(register_signal_name(42, "quota-exceeded"));
+        assert!(!register_signal_name(0, "none"));
+        assert_eq!(quota.name(), Some("quota-exceeded"));
+        assert_eq!(quota.to_string(), "Signal(42, quota-exceeded)");
+        assert_eq!(Signal::CANCEL.to_string(), "Signal(2, cancel)");
+
+        // Encoded severities fall back to the base code's name
+        let warn = Signal::new(encode_signal(42, Severity::Warn)).unwrap();
+        assert_eq!(warn.name(), Some("quota-exceeded"));
+
+        assert_eq!(unregister_signal_name(42), Some("quota-exceeded"));
+        assert_eq!(quota.name(), None);
+    }
+}
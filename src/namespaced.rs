@@ -0,0 +1,284 @@
+//! Signal handlers registered per namespace.
+//!
+//! There is one global handler slot, so two libraries in the same module
+//! that each call [`set_signal_handler`](crate::set_signal_handler) silently
+//! replace one another. Libraries register under their own namespace (their
+//! crate name, say) with [`register_namespaced_handler`] instead, and each
+//! keeps its handler whatever the others do.
+//!
+//! A dispatch calls every namespaced handler, in namespace order, then the
+//! global handler. Each sees the observed signal, and the signal is handled
+//! only if all of them return `Ok(())`; otherwise the first signal returned
+//! propagates. The order does not depend on registration order, so it is the
+//! same whichever library initializes first.
+//!
+//! Like the global handler, namespaced handlers are per-request state, which
+//! [`reset_all`](crate::reset_all) removes.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU32};
+
+use crate::{disposition, generation, Signal, SignalHandler, ACQUIRE, RELEASE};
+
+/// Maximum number of namespaces with a handler.
+pub const MAX_NAMESPACED_HANDLERS: usize = 8;
+
+type Table = [(&'static str, Option<SignalHandler>); MAX_NAMESPACED_HANDLERS];
+
+/// The registered handlers, sorted by namespace with the free entries last,
+/// guarded by [`LOCK`].
+struct Handlers(UnsafeCell<Table>);
+
+// SAFETY: The table is only accessed under LOCK
+unsafe impl Sync for Handlers {}
+
+static HANDLERS: Handlers = Handlers(UnsafeCell::new([("", None); MAX_NAMESPACED_HANDLERS]));
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+/// The number of registered handlers, so dispatch can skip the lock when
+/// there are none.
+static COUNT: AtomicU32 = AtomicU32::new(0);
+
+fn with_handlers<R>(f: impl FnOnce(&mut Table) -> R) -> R {
+    while LOCK
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // SAFETY: LOCK grants exclusive access to the table
+    let table = unsafe { &mut *HANDLERS.0.get() };
+    let result = f(table);
+    let count = table
+        .iter()
+        .filter(|(_, handler)| handler.is_some())
+        .count();
+    COUNT.store(count as u32, RELEASE);
+    LOCK.store(false, RELEASE);
+    result
+}
+
+/// Error returned when all [`MAX_NAMESPACED_HANDLERS`] namespaces have a
+/// handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NamespacedHandlersFull;
+
+impl fmt::Display for NamespacedHandlersFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "all {} namespaced handler slots are in use",
+            MAX_NAMESPACED_HANDLERS
+        )
+    }
+}
+
+impl core::error::Error for NamespacedHandlersFull {}
+
+/// Registers `handler` for `namespace`, replacing the handler registered
+/// for it before.
+///
+/// As with [`set_signal_handler`](crate::set_signal_handler), checks on
+/// other threads may still be running a replaced handler when this returns;
+/// use [`synchronize_signal_handler`](crate::synchronize_signal_handler) to
+/// wait for them.
+///
+/// # Returns
+///
+/// Returns the handler previously registered for `namespace`, if any.
+///
+/// # Errors
+///
+/// Returns [`NamespacedHandlersFull`] if `namespace` is new and
+/// [`MAX_NAMESPACED_HANDLERS`] namespaces already have a handler.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{register_namespaced_handler, Signal};
+///
+/// fn flush_and_propagate(signal: Signal) -> Result<(), Signal> {
+///     // Flush this library's buffers, then let the signal through
+///     Err(signal)
+/// }
+///
+/// register_namespaced_handler(env!("CARGO_PKG_NAME"), flush_and_propagate).unwrap();
+/// # wasm_signal_handler::unregister_namespaced_handler(env!("CARGO_PKG_NAME"));
+/// ```
+pub fn register_namespaced_handler(
+    namespace: &'static str,
+    handler: SignalHandler,
+) -> Result<Option<SignalHandler>, NamespacedHandlersFull> {
+    let previous = with_handlers(|handlers| {
+        let used = handlers.iter().take_while(|(_, h)| h.is_some()).count();
+        match handlers[..used].binary_search_by(|&(other, _)| other.cmp(namespace)) {
+            Ok(index) => Ok(handlers[index].1.replace(handler)),
+            Err(_) if used == MAX_NAMESPACED_HANDLERS => Err(NamespacedHandlersFull),
+            Err(index) => {
+                handlers[index..=used].rotate_right(1);
+                handlers[index] = (namespace, Some(handler));
+                Ok(None)
+            }
+        }
+    })?;
+    generation::bump();
+    disposition::refresh();
+    Ok(previous)
+}
+
+/// Removes the handler registered for `namespace`.
+///
+/// # Returns
+///
+/// Returns the removed handler, if any.
+pub fn unregister_namespaced_handler(namespace: &str) -> Option<SignalHandler> {
+    let removed = with_handlers(|handlers| {
+        let index = handlers
+            .iter()
+            .position(|&(other, handler)| handler.is_some() && other == namespace)?;
+        let removed = handlers[index].1;
+        handlers[index..].rotate_left(1);
+        handlers[MAX_NAMESPACED_HANDLERS - 1] = ("", None);
+        removed
+    })?;
+    generation::bump();
+    disposition::refresh();
+    Some(removed)
+}
+
+/// Returns the handler registered for `namespace`, if any.
+pub fn namespaced_handler(namespace: &str) -> Option<SignalHandler> {
+    with_handlers(|handlers| {
+        handlers
+            .iter()
+            .find(|&&(other, handler)| handler.is_some() && other == namespace)
+            .and_then(|&(_, handler)| handler)
+    })
+}
+
+/// Returns `true` if any namespace has a handler.
+pub(crate) fn any() -> bool {
+    COUNT.load(ACQUIRE) != 0
+}
+
+/// Calls every namespaced handler with `signal`, in namespace order.
+///
+/// Returns the first signal a handler propagated, after calling the rest.
+pub(crate) fn dispatch(signal: Signal) -> Result<(), Signal> {
+    // Copied out, so handlers can register and unregister
+    let handlers = with_handlers(|handlers| *handlers);
+    let mut result = Ok(());
+    for handler in handlers.iter().map_while(|&(_, handler)| handler) {
+        if let Err(propagated) = handler(signal) {
+            result = result.and(Err(propagated));
+        }
+    }
+    result
+}
+
+/// Removes every namespaced handler, for `reset_all`.
+pub(crate) fn reset() {
+    if !any() {
+        return;
+    }
+    with_handlers(|handlers| *handlers = [("", None); MAX_NAMESPACED_HANDLERS]);
+    generation::bump();
+    disposition::refresh();
+}
+
+/// The registered handlers, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+pub(crate) struct Saved(Table);
+
+#[cfg(feature = "testing")]
+impl Default for Saved {
+    fn default() -> Saved {
+        Saved([("", None); MAX_NAMESPACED_HANDLERS])
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(with_handlers(|handlers| *handlers))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    with_handlers(|handlers| *handlers = saved.0);
+    generation::bump();
+    disposition::refresh();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, set_signal, set_signal_handler, try_check_signal,
+    };
+    use core::sync::atomic::Ordering::SeqCst;
+
+    /// The order the handlers ran in, one decimal digit per handler.
+    static ORDER: AtomicU32 = AtomicU32::new(0);
+
+    fn log(digit: u32) {
+        let _ = ORDER.try_update(SeqCst, SeqCst, |order| Some(order * 10 + digit));
+    }
+
+    fn first(_signal: Signal) -> Result<(), Signal> {
+        log(1);
+        Ok(())
+    }
+
+    fn second(signal: Signal) -> Result<(), Signal> {
+        log(2);
+        Err(signal)
+    }
+
+    fn global(_signal: Signal) -> Result<(), Signal> {
+        log(3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_namespaced_handlers() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        ORDER.store(0, SeqCst);
+
+        // Registered out of order, dispatched by namespace
+        assert_eq!(register_namespaced_handler("zeta", second), Ok(None));
+        assert_eq!(register_namespaced_handler("alpha", first), Ok(None));
+        set_signal_handler(global);
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert_eq!(ORDER.load(SeqCst), 123);
+
+        // Replacing the global handler leaves the namespaced ones alone
+        clear_signal_handler();
+        assert!(register_namespaced_handler("zeta", first)
+            .unwrap()
+            .is_some());
+        ORDER.store(0, SeqCst);
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Ok(()));
+        assert_eq!(ORDER.load(SeqCst), 11);
+
+        assert!(unregister_namespaced_handler("alpha").is_some());
+        assert!(namespaced_handler("alpha").is_none());
+        assert!(namespaced_handler("zeta").is_some());
+        for namespace in ["b", "c", "d", "e", "f", "g", "h"] {
+            assert_eq!(register_namespaced_handler(namespace, first), Ok(None));
+        }
+        assert_eq!(
+            register_namespaced_handler("i", first),
+            Err(NamespacedHandlersFull)
+        );
+        for namespace in ["b", "c", "d", "e", "f", "g", "h", "zeta"] {
+            assert!(unregister_namespaced_handler(namespace).is_some());
+        }
+        assert!(!any());
+    }
+}
@@ -0,0 +1,230 @@
+//! Recording cancellation on OpenTelemetry spans.
+//!
+//! With the `otel` feature, every signal a check observes is recorded as a
+//! `wasm_signal` event on the active span (see
+//! `opentelemetry::trace::get_active_span`), so a trace of an edge request
+//! shows where in the request work was cancelled and why. The event carries:
+//!
+//! - `signal.code`: the code observed
+//! - `signal.name`: its [name](crate::Signal::name), if it has one
+//! - `signal.outcome`: `"handled"` if the handler cleared it, otherwise
+//!   `"propagated"`
+//! - `signal.latency_ms`: the time from raising the signal to the check
+//!   that observed it, when known
+//!
+//! The latency is only known for signals raised in the guest (with
+//! [`send_signal`](crate::send_signal) and friends) while a
+//! [clock](crate::now_ms) is available; host writes to the signal word carry
+//! no timestamp. Nothing is recorded when no span is recording.
+
+use core::sync::atomic::AtomicU64;
+use std::vec::Vec;
+
+use opentelemetry::trace::get_active_span;
+use opentelemetry::KeyValue;
+
+use crate::{now_ms, Signal, ACQUIRE, RELEASE};
+
+/// The name of the span events recorded.
+pub const OTEL_EVENT_NAME: &str = "wasm_signal";
+
+/// Clock time the pending signal was raised in the guest, plus one, or `0`
+/// if unknown.
+static RAISED_AT: AtomicU64 = AtomicU64::new(0);
+
+/// Notes the time a signal was raised in the guest.
+pub(crate) fn raised() {
+    if let Some(now) = now_ms() {
+        RAISED_AT.store(now.saturating_add(1), RELEASE);
+    }
+}
+
+/// Records the outcome of an observed signal on the active span.
+pub(crate) fn record(signal: Signal, result: Result<(), Signal>) {
+    let raised_at = RAISED_AT.swap(0, ACQUIRE);
+    get_active_span(|span| {
+        if !span.is_recording() {
+            return;
+        }
+        let mut attributes = Vec::with_capacity(4);
+        attributes.push(KeyValue::new("signal.code", i64::from(signal.code())));
+        if let Some(name) = signal.name() {
+            attributes.push(KeyValue::new("signal.name", name));
+        }
+        let outcome = if result.is_ok() {
+            "handled"
+        } else {
+            "propagated"
+        };
+        attributes.push(KeyValue::new("signal.outcome", outcome));
+        if let (Some(raised_at), Some(now)) = (raised_at.checked_sub(1), now_ms()) {
+            let latency = now.saturating_sub(raised_at);
+            attributes.push(KeyValue::new(
+                "signal.latency_ms",
+                i64::try_from(latency).unwrap_or(i64::MAX),
+            ));
+        }
+        span.add_event(OTEL_EVENT_NAME, attributes);
+    });
+}
+
+/// Forgets the time of the last raise.
+pub(crate) fn reset() {
+    RAISED_AT.store(0, RELEASE);
+}
+
+/// The time of the last raise, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(u64);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(RAISED_AT.load(ACQUIRE))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    RAISED_AT.store(saved.0, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_clock, clear_signal, clear_signal_handler, send_signal, set_clock,
+        set_signal_handler, try_check_signal,
+    };
+    use core::sync::atomic::Ordering::SeqCst;
+    use opentelemetry::trace::{
+        Span, SpanContext, Status, TraceContextExt, TraceFlags, TraceState,
+    };
+    use opentelemetry::{Context, Value};
+    use std::borrow::Cow;
+    use std::string::String;
+    use std::sync::{Arc, Mutex};
+    use std::time::SystemTime;
+
+    type Events = Arc<Mutex<Vec<(String, Vec<KeyValue>)>>>;
+
+    /// A recording span that keeps its events.
+    struct TestSpan(SpanContext, Events);
+
+    impl Span for TestSpan {
+        fn add_event_with_timestamp<T>(
+            &mut self,
+            name: T,
+            _timestamp: SystemTime,
+            attributes: Vec<KeyValue>,
+        ) where
+            T: Into<Cow<'static, str>>,
+        {
+            self.1
+                .lock()
+                .unwrap()
+                .push((name.into().into_owned(), attributes));
+        }
+
+        fn span_context(&self) -> &SpanContext {
+            &self.0
+        }
+
+        fn is_recording(&self) -> bool {
+            true
+        }
+
+        fn set_attribute(&mut self, _attribute: KeyValue) {}
+
+        fn set_status(&mut self, _status: Status) {}
+
+        fn update_name<T>(&mut self, _new_name: T)
+        where
+            T: Into<Cow<'static, str>>,
+        {
+        }
+
+        fn add_link(&mut self, _span_context: SpanContext, _attributes: Vec<KeyValue>) {}
+
+        fn end_with_timestamp(&mut self, _timestamp: SystemTime) {}
+    }
+
+    static NOW: AtomicU64 = AtomicU64::new(1_000);
+
+    fn fake_clock() -> u64 {
+        NOW.load(SeqCst)
+    }
+
+    fn ok(_signal: Signal) -> Result<(), Signal> {
+        Ok(())
+    }
+
+    fn attribute(attributes: &[KeyValue], key: &str) -> Option<Value> {
+        attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.clone())
+    }
+
+    #[test]
+    fn test_records_span_events() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        reset();
+        let events = Events::default();
+        let span = TestSpan(
+            SpanContext::new(
+                1u128.into(),
+                1u64.into(),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::NONE,
+            ),
+            events.clone(),
+        );
+        let _active = Context::current_with_span(span).attach();
+        let previous = set_clock(fake_clock);
+
+        // Raised in the guest, observed 25ms later and propagated
+        send_signal(Signal::CANCEL.code());
+        NOW.fetch_add(25, SeqCst);
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+
+        // Written without a timestamp, and handled
+        set_signal_handler(ok);
+        crate::storage::store(300, RELEASE);
+        assert_eq!(try_check_signal(), Ok(()));
+        clear_signal_handler();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        let (name, attributes) = &events[0];
+        assert_eq!(name, OTEL_EVENT_NAME);
+        assert_eq!(attribute(attributes, "signal.code"), Some(Value::I64(2)));
+        assert_eq!(
+            attribute(attributes, "signal.name"),
+            Some(Value::from("cancel"))
+        );
+        assert_eq!(
+            attribute(attributes, "signal.outcome"),
+            Some(Value::from("propagated"))
+        );
+        assert_eq!(
+            attribute(attributes, "signal.latency_ms"),
+            Some(Value::I64(25))
+        );
+        let (_, attributes) = &events[1];
+        assert_eq!(
+            attribute(attributes, "signal.outcome"),
+            Some(Value::from("handled"))
+        );
+        assert_eq!(attribute(attributes, "signal.name"), None);
+        assert_eq!(attribute(attributes, "signal.latency_ms"), None);
+
+        match previous {
+            Some(clock) => set_clock(clock),
+            None => clear_clock(),
+        };
+    }
+}
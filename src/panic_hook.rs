@@ -0,0 +1,314 @@
+//! A panic hook that reports signal state.
+//!
+//! A panic in a Workers log says where the guest stopped but not whether a
+//! signal made it stop. [`panic_hook`] is a drop-in replacement for
+//! `console_error_panic_hook::hook` that logs the panic message and stack
+//! with `console.error`, followed by the pending signal, the last
+//! acknowledged signals (see [`signal_history`]) and, while the [check
+//! advisor](crate::set_check_advisor) is enabled, the call sites that checked
+//! last. Outside JavaScript hosts, as on WASI, the report goes to stderr.
+//!
+//! Applications with their own hook can keep it and add the report with
+//! [`wrap_panic_hook`], or append it to their own output with
+//! [`write_signal_report`].
+//!
+//! [`signal_history`]: crate::signal_history
+
+use core::fmt::{self, Write};
+use std::boxed::Box;
+use std::panic::{self, PanicHookInfo};
+use std::string::String;
+use std::sync::Once;
+
+use crate::{
+    advisor, get_signal_handler, last_ack, latched_signal, peek_signal, signal_history,
+    signals_masked, Signal, SignalPanic,
+};
+
+/// The number of call sites a report lists.
+const REPORT_SITES: usize = 4;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod console {
+    use std::string::String;
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = console)]
+        pub(super) fn error(message: String);
+
+        pub(super) type Error;
+
+        #[wasm_bindgen(constructor)]
+        pub(super) fn new() -> Error;
+
+        #[wasm_bindgen(structural, method, getter)]
+        pub(super) fn stack(error: &Error) -> String;
+    }
+}
+
+/// Appends the signal state to `out`, as the hooks in this module do.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::write_signal_report;
+///
+/// let mut report = String::from("request failed\n");
+/// write_signal_report(&mut report).unwrap();
+/// assert!(report.contains("pending signal"));
+/// ```
+pub fn write_signal_report(out: &mut dyn Write) -> fmt::Result {
+    fn signal(out: &mut dyn Write, signal: Option<Signal>) -> fmt::Result {
+        match signal {
+            Some(signal) => write!(out, "{signal}"),
+            None => out.write_str("none"),
+        }
+    }
+
+    out.write_str("Signal state:\n  pending signal: ")?;
+    signal(out, peek_signal())?;
+    out.write_str("\n  last acknowledged: ")?;
+    signal(out, last_ack())?;
+    out.write_str("\n  history:")?;
+    let mut history = signal_history().peekable();
+    if history.peek().is_none() {
+        out.write_str(" none")?;
+    }
+    for past in history {
+        write!(out, " {past}")?;
+    }
+    out.write_str("\n  latched: ")?;
+    signal(out, latched_signal())?;
+    write!(
+        out,
+        "\n  handler installed: {}\n  masked: {}\n  last check sites:",
+        get_signal_handler().is_some(),
+        signals_masked()
+    )?;
+    let sites = advisor::last_check_sites::<REPORT_SITES>();
+    if sites[0].is_none() {
+        out.write_str(" not recorded (enable set_check_advisor)")?;
+    }
+    for site in sites.into_iter().flatten() {
+        write!(out, "\n    {site}")?;
+    }
+    out.write_char('\n')
+}
+
+/// Writes the message of a [`SignalPanic`] payload, which hooks that only
+/// print string payloads show as `Box<dyn Any>`.
+fn write_signal_panic(out: &mut String, info: &PanicHookInfo<'_>) {
+    if let Some(panic) = info.payload().downcast_ref::<SignalPanic>() {
+        let _ = writeln!(out, "{} ({})", panic.message(), panic.signal());
+    }
+}
+
+fn report(info: &PanicHookInfo<'_>) -> String {
+    let mut message = String::new();
+    if info.payload().is::<SignalPanic>() {
+        if let Some(location) = info.location() {
+            let _ = writeln!(message, "panicked at {location}:");
+        }
+        write_signal_panic(&mut message, info);
+    } else {
+        let _ = writeln!(message, "{info}");
+    }
+    let _ = message.write_char('\n');
+    let _ = write_signal_report(&mut message);
+    message
+}
+
+/// Output captured by tests instead of being logged.
+#[cfg(test)]
+static CAPTURED: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+fn log(mut message: String) {
+    #[cfg(test)]
+    if let Some(captured) = CAPTURED
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .as_mut()
+    {
+        captured.push_str(&message);
+        return;
+    }
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    {
+        // As console_error_panic_hook does, so the JS stack shows the
+        // frames leading into the guest
+        message.push_str("\nStack:\n\n");
+        message.push_str(&console::Error::new().stack());
+        message.push_str("\n\n");
+        console::error(message);
+    }
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    {
+        message.push('\n');
+        std::eprint!("{message}");
+    }
+}
+
+/// A panic hook that logs the panic with the signal state appended.
+///
+/// Install it with [`set_panic_hook`], or directly with
+/// `std::panic::set_hook(Box::new(panic_hook))`.
+pub fn panic_hook(info: &PanicHookInfo<'_>) {
+    log(report(info));
+}
+
+/// Installs [`panic_hook`], replacing the current hook. Only the first call
+/// has an effect, so it is safe to call at the start of every entry point.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// // In place of console_error_panic_hook::set_once()
+/// wasm_signal_handler::set_panic_hook();
+/// ```
+pub fn set_panic_hook() {
+    static SET: Once = Once::new();
+    SET.call_once(|| panic::set_hook(Box::new(panic_hook)));
+}
+
+/// Keeps the current panic hook, such as `console_error_panic_hook`'s, and
+/// logs the signal state after it runs.
+///
+/// Hooks that only print string payloads, the standard one included, print
+/// a [`SignalPanic`] as `Box<dyn Any>`, so for those panics the
+/// [formatted message](crate::set_panic_formatter) is logged first.
+///
+/// Each call wraps the hook again, so call it once.
+pub fn wrap_panic_hook() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous(info);
+        let mut message = String::new();
+        write_signal_panic(&mut message, info);
+        let _ = write_signal_report(&mut message);
+        log(message);
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, reset_check_advice, set_check_advisor, set_signal,
+        try_check_signal,
+    };
+
+    #[test]
+    fn test_signal_report() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        reset_check_advice();
+        for code in [300, 301] {
+            set_signal(code);
+            assert!(try_check_signal().is_err());
+        }
+        set_signal(Signal::CANCEL.code());
+
+        let mut report = String::new();
+        write_signal_report(&mut report).unwrap();
+        assert!(
+            report.contains("pending signal: Signal(2, cancel)"),
+            "{report}"
+        );
+        assert!(
+            report.contains("last acknowledged: Signal(301)"),
+            "{report}"
+        );
+        assert!(
+            report.contains("history: Signal(301) Signal(300)"),
+            "{report}"
+        );
+        assert!(report.contains("not recorded"), "{report}");
+
+        set_check_advisor(true);
+        let _ = try_check_signal();
+        set_check_advisor(false);
+        let mut report = String::new();
+        write_signal_report(&mut report).unwrap();
+        assert!(report.contains(file!()), "{report}");
+        assert!(
+            report.contains("history: Signal(2, cancel) Signal(301) Signal(300)"),
+            "{report}"
+        );
+        reset_check_advice();
+        clear_signal();
+
+        fn formatter(signal: Signal, out: &mut dyn Write) {
+            let _ = write!(out, "request r-1 cancelled: code {}", signal.code());
+        }
+
+        crate::set_panic_formatter(formatter);
+        let logged = capture(
+            || panic::set_hook(Box::new(panic_hook)),
+            || {
+                set_signal(Signal::CANCEL.code());
+                assert!(panic::catch_unwind(crate::check_signal).is_err());
+            },
+        );
+        crate::clear_panic_formatter();
+        assert!(logged.contains("panicked at "), "{logged}");
+        assert!(
+            logged.contains("request r-1 cancelled: code 2 (Signal(2, cancel))"),
+            "{logged}"
+        );
+        assert!(!logged.contains("Box<dyn Any>"), "{logged}");
+        assert!(logged.contains("Signal state:"), "{logged}");
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_scoped_state_restores_history() {
+        let _guard = lock();
+        clear_signal_handler();
+        set_signal(302);
+        assert!(try_check_signal().is_err());
+
+        {
+            let _state = crate::testing::ScopedSignalState::new();
+            assert_eq!(signal_history().count(), 0);
+            set_signal(303);
+            assert!(try_check_signal().is_err());
+        }
+
+        assert_eq!(signal_history().next(), Signal::new(302));
+    }
+
+    /// Runs `f` with `hook` installed, returning what it logged.
+    fn capture(hook: impl FnOnce(), f: impl FnOnce()) -> String {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        hook();
+        *CAPTURED.lock().unwrap() = Some(String::new());
+        f();
+        let captured = CAPTURED.lock().unwrap().take().unwrap();
+        panic::set_hook(previous);
+        captured
+    }
+
+    #[test]
+    fn test_wrapped_hook_logs_formatted_message() {
+        let _guard = lock();
+        clear_signal_handler();
+
+        fn formatter(signal: Signal, out: &mut dyn Write) {
+            let _ = write!(out, "request r-2 cancelled: code {}", signal.code());
+        }
+
+        crate::set_panic_formatter(formatter);
+        let logged = capture(wrap_panic_hook, || {
+            set_signal(Signal::CANCEL.code());
+            assert!(panic::catch_unwind(crate::check_signal).is_err());
+        });
+        crate::clear_panic_formatter();
+        assert!(logged.contains("request r-2 cancelled: code 2"), "{logged}");
+        assert!(logged.contains("Signal state:"), "{logged}");
+    }
+}
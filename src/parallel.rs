@@ -0,0 +1,144 @@
+//! Interrupting rayon parallel iterators on cancellation.
+//!
+//! Under a multi-threaded pool, such as one set up by wasm-bindgen-rayon,
+//! every worker reads the same signal word. The adapters here check it at
+//! the start of each work chunk a worker picks up. The first worker to
+//! observe a signal shares it with the others, so the whole computation
+//! winds down instead of only the worker that saw it.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use rayon::iter::plumbing::UnindexedConsumer;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{try_check_signal, Signal};
+
+/// Signal-aware adapters for rayon [`ParallelIterator`]s.
+pub trait SignalParallelIteratorExt: ParallelIterator {
+    /// Checks for a signal at the start of every work chunk.
+    ///
+    /// Items are wrapped in `Ok`. Once any worker's check propagates a
+    /// signal, every item produced afterwards, on any worker, is that signal
+    /// as `Err`. Consume the iterator with a short-circuiting method, such as
+    /// `collect::<Result<_, Signal>>()` or `try_for_each`, to stop the whole
+    /// computation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use rayon::prelude::*;
+    /// use wasm_signal_handler::{Signal, SignalParallelIteratorExt};
+    ///
+    /// fn squares(n: u64) -> Result<Vec<u64>, Signal> {
+    ///     (0..n)
+    ///         .into_par_iter()
+    ///         .interruptible()
+    ///         .map(|item| item.map(|i| i * i))
+    ///         .collect()
+    /// }
+    /// ```
+    fn interruptible(self) -> ParInterruptible<Self> {
+        self.check_signals_every(u32::MAX)
+    }
+
+    /// Like [`interruptible`](SignalParallelIteratorExt::interruptible), but
+    /// also checks before every `n`th item within a chunk, for chunks that
+    /// take long to process. An `n` of `0` is treated as `1`.
+    fn check_signals_every(self, n: u32) -> ParInterruptible<Self> {
+        ParInterruptible {
+            base: self,
+            every: n.max(1),
+        }
+    }
+}
+
+impl<I: ParallelIterator> SignalParallelIteratorExt for I {}
+
+/// Shorthand for `par_iter().interruptible()`.
+pub trait SignalParallelRefExt<'data> {
+    /// The parallel iterator over references.
+    type Iter: ParallelIterator;
+
+    /// Iterates over references in parallel, checking for a signal at the
+    /// start of every work chunk; see
+    /// [`interruptible`](SignalParallelIteratorExt::interruptible).
+    fn par_iter_interruptible(&'data self) -> ParInterruptible<Self::Iter>;
+}
+
+impl<'data, T: IntoParallelRefIterator<'data> + ?Sized> SignalParallelRefExt<'data> for T {
+    type Iter = T::Iter;
+
+    fn par_iter_interruptible(&'data self) -> ParInterruptible<T::Iter> {
+        self.par_iter().interruptible()
+    }
+}
+
+/// A parallel iterator that yields `Err(Signal)` on cancellation; see
+/// [`SignalParallelIteratorExt`].
+#[derive(Debug, Clone)]
+#[must_use = "iterators do nothing unless consumed"]
+pub struct ParInterruptible<I> {
+    base: I,
+    every: u32,
+}
+
+impl<I: ParallelIterator> ParallelIterator for ParInterruptible<I> {
+    type Item = Result<I::Item, Signal>;
+
+    fn drive_unindexed<C: UnindexedConsumer<Self::Item>>(self, consumer: C) -> C::Result {
+        // The signal a worker observed, or `0`. Checks clear the signal word,
+        // so other workers would not see the signal otherwise.
+        let observed = AtomicU32::new(0);
+        let every = self.every;
+        self.base
+            .map_init(
+                // Rayon calls `init` once per work chunk
+                || 0u32,
+                |count, item| {
+                    if let Some(signal) = Signal::new(observed.load(Ordering::Relaxed)) {
+                        return Err(signal);
+                    }
+                    if *count == 0 {
+                        if let Err(signal) = try_check_signal() {
+                            observed.store(signal.code(), Ordering::Relaxed);
+                            return Err(signal);
+                        }
+                    }
+                    *count = (*count + 1) % every;
+                    Ok(item)
+                },
+            )
+            .drive_unindexed(consumer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, peek_signal, set_signal};
+    use rayon::iter::IntoParallelIterator;
+
+    #[test]
+    fn test_interruptible_par_iter() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        let items: std::vec::Vec<u32> = (0..1_000).collect();
+        let sum: Result<u32, Signal> = items
+            .par_iter_interruptible()
+            .map(|item| item.copied())
+            .sum();
+        assert_eq!(sum, Ok(499_500));
+
+        // One check consumes the signal; every other chunk still stops
+        set_signal(Signal::CANCEL.code());
+        let result: Result<std::vec::Vec<u32>, Signal> = (0..1_000u32)
+            .into_par_iter()
+            .check_signals_every(10)
+            .collect();
+        assert_eq!(result, Err(Signal::CANCEL));
+        assert_eq!(peek_signal(), None);
+    }
+}
@@ -0,0 +1,142 @@
+//! The shape shared by platform signal codes.
+//!
+//! Each platform module ([Workers](crate::WorkersLimit),
+//! [Shopify](crate::ShopifyLimit), Fastly and wasmCloud) names a block of
+//! codes, each sent with an [encoded severity](crate::Severity) and standing
+//! for a generic [`SignalKind`](crate::SignalKind). [`platform_signals!`]
+//! declares the enum and its conversions from the codes and names alone, so
+//! the platforms only differ where they actually do.
+
+/// Declares an enum of platform signal codes.
+///
+/// Each variant lists its base code, the [`Severity`](crate::Severity) it is
+/// sent with, the [`SignalKind`](crate::SignalKind) a guest should treat it
+/// as and its name. The enum gets `ALL`, `base_code`, `severity`, `code`,
+/// `signal`, `from_code` and `as_str`, conversions to and from [`Signal`]
+/// and to `SignalKind`, and a function registering the names. `$noun` and
+/// `$nouns` name a variant in the generated docs, and the attributes before
+/// `kind` document the `SignalKind` conversion.
+///
+/// [`Signal`]: crate::Signal
+macro_rules! platform_signals {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident ($noun:literal, $nouns:literal) {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = $code:literal, $severity:ident, $kind:ident, $str:literal;
+            )+
+        }
+
+        $(#[$kind_meta:meta])*
+        kind;
+
+        pub fn $register:ident;
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+        #[non_exhaustive]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )+
+        }
+
+        impl $name {
+            #[doc = concat!("Every ", $noun, ", in code order.")]
+            pub const ALL: [$name; [$($name::$variant),+].len()] = [$($name::$variant),+];
+
+            /// Returns the code, without its severity.
+            pub const fn base_code(self) -> u32 {
+                match self {
+                    $($name::$variant => $code,)+
+                }
+            }
+
+            #[doc = concat!("Returns the severity the ", $noun, " is sent with.")]
+            pub const fn severity(self) -> $crate::Severity {
+                match self {
+                    $($name::$variant => $crate::Severity::$severity,)+
+                }
+            }
+
+            #[doc = concat!("Returns the signal word sent for the ", $noun, ".")]
+            pub const fn code(self) -> u32 {
+                $crate::encode_signal(self.base_code(), self.severity())
+            }
+
+            #[doc = concat!("Returns the ", $noun, " as a signal.")]
+            pub const fn signal(self) -> $crate::Signal {
+                match $crate::Signal::new(self.code()) {
+                    Some(signal) => signal,
+                    None => unreachable!(),
+                }
+            }
+
+            #[doc = concat!(
+                "Returns the ", $noun, " a signal word stands for, whatever severity it was\n",
+                "sent with."
+            )]
+            pub const fn from_code(code: u32) -> Option<$name> {
+                match $crate::decode_signal(code).0 {
+                    $($code => Some($name::$variant),)+
+                    _ => None,
+                }
+            }
+
+            #[doc = concat!("Returns a short, stable name for the ", $noun, ".")]
+            pub const fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $str,)+
+                }
+            }
+        }
+
+        impl From<$name> for $crate::Signal {
+            fn from(value: $name) -> $crate::Signal {
+                value.signal()
+            }
+        }
+
+        $(#[$kind_meta])*
+        impl From<$name> for $crate::SignalKind {
+            fn from(value: $name) -> $crate::SignalKind {
+                match value {
+                    $($name::$variant => $crate::SignalKind::$kind,)+
+                }
+            }
+        }
+
+        impl TryFrom<$crate::Signal> for $name {
+            type Error = $crate::Signal;
+
+            fn try_from(signal: $crate::Signal) -> Result<$name, $crate::Signal> {
+                $name::from_code(signal.code()).ok_or(signal)
+            }
+        }
+
+        #[doc = concat!(
+            "Registers the ", $nouns, "' names with\n",
+            "[`register_signal_name`](crate::register_signal_name), so they show up in\n",
+            "panic messages and diagnostics.\n",
+            "\n",
+            "Every name is attempted, so a full name table still gets the names of\n",
+            "codes it already holds.\n",
+            "\n",
+            "# Returns\n",
+            "\n",
+            "Returns `false` if the name table filled up before every name was\n",
+            "registered. The ", $nouns, " left out show without a name."
+        )]
+        pub fn $register() -> bool {
+            $name::ALL.iter().fold(true, |registered, value| {
+                $crate::register_signal_name(value.base_code(), value.as_str()) && registered
+            })
+        }
+    };
+}
+
+pub(crate) use platform_signals;
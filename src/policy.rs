@@ -0,0 +1,399 @@
+//! What happens when a signal arrives while another is pending.
+//!
+//! The signal word holds a single code, so a sender that finds it occupied
+//! must pick one of the two signals. The [`OverwritePolicy`] makes that
+//! choice explicit:
+//!
+//! - [`Overwrite`](OverwritePolicy::Overwrite): the newer signal replaces the
+//!   pending one (the behavior of a plain store).
+//! - [`KeepHighest`](OverwritePolicy::KeepHighest): the newer signal replaces
+//!   the pending one only if it is at least as [severe](crate::Severity).
+//! - [`Enqueue`](OverwritePolicy::Enqueue): the newer signal waits in a
+//!   bounded queue and moves into the signal word once the pending one has
+//!   been handled.
+//!
+//! The policy lives in an exported word (`WASM_SIGNAL_POLICY_ADDR`) so that
+//! host senders (`sendSignal` in `js/wasm-signal-handler.js`) and guest
+//! senders ([`send_signal`], [`raise_signal`](crate::raise_signal)) agree. The
+//! initial policy is chosen at compile time with the `policy-keep-highest` or
+//! `policy-enqueue` feature, and can be changed with [`set_overwrite_policy`].
+//!
+//! Policies only bind senders that go through these helpers; a raw store to
+//! the signal word always overwrites. With the `global-storage` feature on
+//! wasm32 the host cannot compare-exchange the signal global, so host writes
+//! always overwrite there.
+
+use core::sync::atomic::{AtomicBool, AtomicU32};
+
+use crate::{
+    arm, disarm, notify_waiters, severity, storage, ACQUIRE, ACQ_REL, ARMED_QUEUE, RELEASE,
+};
+
+/// How a sender treats a pending signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[repr(u32)]
+pub enum OverwritePolicy {
+    /// The newer signal replaces the pending one.
+    Overwrite = 0,
+    /// The more severe signal wins; on a tie the newer one does.
+    KeepHighest = 1,
+    /// The newer signal is queued behind the pending one.
+    Enqueue = 2,
+}
+
+impl OverwritePolicy {
+    /// The policy selected by Cargo features.
+    pub const DEFAULT: OverwritePolicy = if cfg!(feature = "policy-enqueue") {
+        OverwritePolicy::Enqueue
+    } else if cfg!(feature = "policy-keep-highest") {
+        OverwritePolicy::KeepHighest
+    } else {
+        OverwritePolicy::Overwrite
+    };
+
+    const fn from_word(word: u32) -> OverwritePolicy {
+        match word {
+            1 => OverwritePolicy::KeepHighest,
+            2 => OverwritePolicy::Enqueue,
+            _ => OverwritePolicy::Overwrite,
+        }
+    }
+}
+
+impl Default for OverwritePolicy {
+    fn default() -> OverwritePolicy {
+        OverwritePolicy::DEFAULT
+    }
+}
+
+/// Number of signals that can wait behind a pending one.
+pub const QUEUE_CAPACITY: usize = 16;
+
+/// A bounded multi-producer, single-consumer queue shared with the host.
+///
+/// Producers reserve a slot by advancing `tail`, then publish the code in
+/// it. The guest consumes under [`CONSUMER`], clearing a slot before
+/// advancing `head` past it, so a slot is always free by the time a producer
+/// can reserve it again. A slot still holding `0` has been reserved but not
+/// yet published.
+///
+/// The layout is read by the host helpers and must not change. Guest code
+/// interacts with the queue through [`send_signal`] and [`queued`] only.
+#[repr(C)]
+pub struct SignalQueue {
+    capacity: u32,
+    head: AtomicU32,
+    tail: AtomicU32,
+    slots: [AtomicU32; QUEUE_CAPACITY],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: AtomicU32 = AtomicU32::new(0);
+
+static QUEUE: SignalQueue = SignalQueue {
+    capacity: QUEUE_CAPACITY as u32,
+    head: AtomicU32::new(0),
+    tail: AtomicU32::new(0),
+    slots: [EMPTY_SLOT; QUEUE_CAPACITY],
+};
+
+/// Serializes consumers of [`QUEUE`].
+static CONSUMER: AtomicBool = AtomicBool::new(false);
+
+static POLICY: AtomicU32 = AtomicU32::new(OverwritePolicy::DEFAULT as u32);
+
+/// The exported address of the policy word.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_POLICY_ADDR: &AtomicU32 = &POLICY;
+
+/// The exported address of the signal queue.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_QUEUE_ADDR: &SignalQueue = &QUEUE;
+
+/// Sets the overwrite policy shared with host senders.
+///
+/// Switching away from [`OverwritePolicy::Enqueue`] keeps already queued
+/// signals; they are still delivered in order.
+///
+/// # Returns
+///
+/// Returns the previous policy.
+pub fn set_overwrite_policy(policy: OverwritePolicy) -> OverwritePolicy {
+    let previous = OverwritePolicy::from_word(POLICY.swap(policy as u32, ACQ_REL));
+    if policy == OverwritePolicy::Enqueue {
+        arm(ARMED_QUEUE);
+    } else if queued() == 0 {
+        disarm(ARMED_QUEUE);
+    }
+    previous
+}
+
+/// Gets the overwrite policy.
+pub fn overwrite_policy() -> OverwritePolicy {
+    OverwritePolicy::from_word(POLICY.load(ACQUIRE))
+}
+
+/// Returns the number of signals waiting in the queue.
+pub fn queued() -> usize {
+    let head = QUEUE.head.load(ACQUIRE);
+    QUEUE.tail.load(ACQUIRE).wrapping_sub(head) as usize
+}
+
+/// Sends a signal according to the [overwrite policy](set_overwrite_policy).
+///
+/// A code of `0` sends nothing.
+///
+/// # Returns
+///
+/// Returns `false` if the signal was dropped: under
+/// [`KeepHighest`](OverwritePolicy::KeepHighest) because a more severe signal
+/// is pending, or under [`Enqueue`](OverwritePolicy::Enqueue) because the
+/// queue is full.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{send_signal, set_overwrite_policy, OverwritePolicy, Signal};
+///
+/// set_overwrite_policy(OverwritePolicy::KeepHighest);
+/// send_signal(Signal::TERMINATE.code());
+/// // A cancel does not downgrade the pending terminate
+/// assert!(!send_signal(Signal::CANCEL.code()));
+/// # wasm_signal_handler::clear_signal();
+/// ```
+pub fn send_signal(code: u32) -> bool {
+    if code == 0 {
+        return true;
+    }
+    let sent = match overwrite_policy() {
+        OverwritePolicy::Overwrite => {
+            storage::store(code, RELEASE);
+            true
+        }
+        OverwritePolicy::KeepHighest => {
+            let mut pending = storage::load(ACQUIRE);
+            loop {
+                if pending != 0 && severity::rank(pending) > severity::rank(code) {
+                    return false;
+                }
+                match storage::compare_exchange(pending, code, ACQ_REL, ACQUIRE) {
+                    Ok(_) => return true,
+                    Err(current) => pending = current,
+                }
+            }
+        }
+        OverwritePolicy::Enqueue => {
+            storage::compare_exchange(0, code, ACQ_REL, ACQUIRE).is_ok() || push(code)
+        }
+    };
+    if sent {
+        #[cfg(feature = "otel")]
+        crate::otel::raised();
+        notify_waiters();
+    }
+    sent
+}
+
+/// Appends `code` to the queue.
+fn push(code: u32) -> bool {
+    let mut tail = QUEUE.tail.load(ACQUIRE);
+    loop {
+        if tail.wrapping_sub(QUEUE.head.load(ACQUIRE)) as usize >= QUEUE_CAPACITY {
+            return false;
+        }
+        match QUEUE
+            .tail
+            .compare_exchange_weak(tail, tail.wrapping_add(1), ACQ_REL, ACQUIRE)
+        {
+            Ok(_) => break,
+            Err(current) => tail = current,
+        }
+    }
+    QUEUE.slots[tail as usize % QUEUE_CAPACITY].store(code, RELEASE);
+    // Make sure a consumer that just saw an empty queue does not leave it
+    arm(ARMED_QUEUE);
+    true
+}
+
+fn with_consumer<R>(f: impl FnOnce() -> R) -> R {
+    while CONSUMER
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    let result = f();
+    CONSUMER.store(false, RELEASE);
+    result
+}
+
+/// Removes the oldest published code from the queue.
+fn pop() -> Option<u32> {
+    with_consumer(|| {
+        let head = QUEUE.head.load(ACQUIRE);
+        if head == QUEUE.tail.load(ACQUIRE) {
+            return None;
+        }
+        let code = QUEUE.slots[head as usize % QUEUE_CAPACITY].swap(0, ACQ_REL);
+        if code == 0 {
+            // Reserved but not yet published
+            return None;
+        }
+        QUEUE.head.store(head.wrapping_add(1), RELEASE);
+        Some(code)
+    })
+}
+
+/// Moves the oldest queued signal into the signal word if it is clear.
+///
+/// # Returns
+///
+/// Returns the signal word afterwards.
+pub(crate) fn refill() -> u32 {
+    let pending = storage::load(ACQUIRE);
+    if pending != 0 {
+        return pending;
+    }
+    let Some(code) = pop() else {
+        if overwrite_policy() != OverwritePolicy::Enqueue {
+            disarm(ARMED_QUEUE);
+        }
+        return 0;
+    };
+    match storage::compare_exchange(0, code, ACQ_REL, ACQUIRE) {
+        Ok(_) => {
+            notify_waiters();
+            code
+        }
+        Err(current) => {
+            // A sender got there first; requeue behind it
+            push(code);
+            current
+        }
+    }
+}
+
+/// Empties the queue.
+pub(crate) fn clear_queue() {
+    with_consumer(|| {
+        for slot in &QUEUE.slots {
+            slot.store(0, RELEASE);
+        }
+        QUEUE.head.store(QUEUE.tail.load(ACQUIRE), RELEASE);
+    });
+    if overwrite_policy() != OverwritePolicy::Enqueue {
+        disarm(ARMED_QUEUE);
+    }
+}
+
+/// The policy and queued codes, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+pub(crate) struct Saved {
+    policy: u32,
+    queued: [u32; QUEUE_CAPACITY],
+}
+
+#[cfg(feature = "testing")]
+impl Default for Saved {
+    fn default() -> Saved {
+        Saved {
+            policy: OverwritePolicy::DEFAULT as u32,
+            queued: [0; QUEUE_CAPACITY],
+        }
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    let mut queued = [0; QUEUE_CAPACITY];
+    with_consumer(|| {
+        let head = QUEUE.head.load(ACQUIRE);
+        for (i, code) in queued.iter_mut().enumerate() {
+            *code =
+                QUEUE.slots[head.wrapping_add(i as u32) as usize % QUEUE_CAPACITY].load(ACQUIRE);
+        }
+    });
+    Saved {
+        policy: POLICY.load(ACQUIRE),
+        queued,
+    }
+}
+
+/// Restores the policy and queue. `ARMED` is restored separately by the
+/// caller.
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    POLICY.store(saved.policy, RELEASE);
+    with_consumer(|| {
+        let len = saved.queued.iter().take_while(|&&code| code != 0).count();
+        for (slot, &code) in QUEUE.slots.iter().zip(&saved.queued) {
+            slot.store(code, RELEASE);
+        }
+        QUEUE.head.store(0, RELEASE);
+        QUEUE.tail.store(len as u32, RELEASE);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, encode_signal, peek_signal, set_signal_handler,
+        try_check_signal, Severity, Signal,
+    };
+
+    #[test]
+    fn test_keep_highest() {
+        let _guard = lock();
+        clear_signal();
+        let previous = set_overwrite_policy(OverwritePolicy::KeepHighest);
+
+        assert!(send_signal(Signal::CANCEL.code()));
+        assert!(send_signal(Signal::TERMINATE.code()));
+        assert!(!send_signal(Signal::CANCEL.code()));
+        assert!(!send_signal(encode_signal(9, Severity::Warn)));
+        assert_eq!(peek_signal(), Some(Signal::TERMINATE));
+
+        clear_signal();
+        set_overwrite_policy(previous);
+    }
+
+    #[test]
+    fn test_enqueue_delivers_in_order() {
+        let _guard = lock();
+        clear_signal();
+        let previous = set_overwrite_policy(OverwritePolicy::Enqueue);
+        set_signal_handler(|signal| match signal {
+            Signal::CHECKPOINT => Ok(()),
+            _ => Err(signal),
+        });
+
+        assert!(send_signal(Signal::CHECKPOINT.code()));
+        assert!(send_signal(Signal::CANCEL.code()));
+        assert!(send_signal(Signal::TERMINATE.code()));
+        assert_eq!(queued(), 2);
+
+        assert!(try_check_signal().is_ok());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        assert!(try_check_signal().is_ok());
+        assert_eq!(queued(), 0);
+
+        // A full queue drops the signal
+        send_signal(Signal::CANCEL.code());
+        for _ in 0..QUEUE_CAPACITY {
+            assert!(send_signal(Signal::CANCEL.code()));
+        }
+        assert!(!send_signal(Signal::CANCEL.code()));
+        clear_queue();
+        clear_signal();
+
+        clear_signal_handler();
+        set_overwrite_policy(previous);
+    }
+}
@@ -0,0 +1,247 @@
+//! Delivering signals through an imported host function.
+//!
+//! Some sandboxes do not let the host write guest memory, but do let it
+//! provide imports. With the `host-poll` feature on wasm32, the module
+//! imports `env.wasm_signal_poll() -> u32`, and every
+//! [`poll_stride`]th check calls it. A non-zero result is sent as a signal
+//! exactly as if the host had written it (see [`send_signal`]), so checks,
+//! handlers and waiters work the same with either mechanism, and a host may
+//! use both.
+//!
+//! The import is required once the feature is enabled: hosts that have no
+//! signal to deliver provide a function returning `0`. With the `extism`
+//! feature, polls read an Extism var instead (see `extism`), with the
+//! `lunatic` feature the process mailbox (see `lunatic`), with the
+//! `wasi-http` feature they probe the request's connection (see
+//! `wasi_http`), and with the `wasmcloud` feature they call a registered
+//! WIT binding (see `wasmcloud`).
+//!
+//! [`send_signal`]: crate::send_signal
+
+use core::sync::atomic::AtomicU32;
+
+use crate::{policy, storage, ACQUIRE, RELEASE};
+
+/// The default number of checks per host poll.
+pub const DEFAULT_POLL_STRIDE: u32 = 64;
+
+static STRIDE: AtomicU32 = AtomicU32::new(DEFAULT_POLL_STRIDE);
+
+/// Checks left until the next poll.
+static COUNTDOWN: AtomicU32 = AtomicU32::new(0);
+
+#[cfg(all(
+    target_arch = "wasm32",
+    not(any(
+        feature = "extism",
+        feature = "lunatic",
+        feature = "wasi-http",
+        feature = "wasmcloud"
+    ))
+))]
+#[link(wasm_import_module = "env")]
+extern "C" {
+    /// Returns the signal the host wants to deliver, or `0` for none.
+    fn wasm_signal_poll() -> u32;
+}
+
+#[cfg(all(
+    target_arch = "wasm32",
+    not(any(
+        feature = "extism",
+        feature = "lunatic",
+        feature = "wasi-http",
+        feature = "wasmcloud"
+    ))
+))]
+fn host_poll() -> u32 {
+    // SAFETY: The import takes no arguments and has no preconditions
+    unsafe { wasm_signal_poll() }
+}
+
+#[cfg(any(
+    feature = "extism",
+    feature = "lunatic",
+    feature = "wasi-http",
+    feature = "wasmcloud"
+))]
+fn host_poll() -> u32 {
+    #[cfg(feature = "extism")]
+    let code = crate::extism::take_signal();
+    #[cfg(not(feature = "extism"))]
+    let code = 0;
+    #[cfg(feature = "lunatic")]
+    let code = if code == 0 {
+        crate::lunatic::take_signal()
+    } else {
+        code
+    };
+    #[cfg(feature = "wasi-http")]
+    let code = if code == 0 {
+        crate::wasi_http::poll()
+    } else {
+        code
+    };
+    #[cfg(feature = "wasmcloud")]
+    let code = if code == 0 {
+        crate::wasmcloud::poll()
+    } else {
+        code
+    };
+    code
+}
+
+/// What the stand-in host returns from its next poll.
+#[cfg(all(
+    test,
+    not(target_arch = "wasm32"),
+    not(any(
+        feature = "extism",
+        feature = "lunatic",
+        feature = "wasi-http",
+        feature = "wasmcloud"
+    ))
+))]
+pub(crate) static TEST_HOST: AtomicU32 = AtomicU32::new(0);
+
+/// Outside wasm32 there is no host to poll.
+#[cfg(all(
+    not(target_arch = "wasm32"),
+    not(any(
+        feature = "extism",
+        feature = "lunatic",
+        feature = "wasi-http",
+        feature = "wasmcloud"
+    ))
+))]
+fn host_poll() -> u32 {
+    #[cfg(test)]
+    return TEST_HOST.swap(0, crate::ACQ_REL);
+    #[cfg(not(test))]
+    0
+}
+
+/// Sets how many checks pass between host polls, returning the previous
+/// stride.
+///
+/// A stride of `1` polls on every check; larger strides trade latency for
+/// fewer calls out of the guest. `0` is treated as `1`. The next check polls.
+pub fn set_poll_stride(stride: u32) -> u32 {
+    let previous = STRIDE.swap(stride.max(1), RELEASE);
+    COUNTDOWN.store(0, RELEASE);
+    previous
+}
+
+/// Returns the number of checks between host polls.
+pub fn poll_stride() -> u32 {
+    STRIDE.load(ACQUIRE)
+}
+
+/// Polls the host right away, sending any signal it returns.
+///
+/// Useful before blocking work, or where checks are too far apart for the
+/// stride to give acceptable latency.
+///
+/// # Returns
+///
+/// Returns `true` if the host delivered a signal and it was sent.
+pub fn poll_host() -> bool {
+    let code = host_poll();
+    code != 0 && policy::send_signal(code)
+}
+
+/// Polls the host if this check completes a stride.
+///
+/// # Returns
+///
+/// The pending signal code after the poll, or `code` if no poll was due.
+pub(crate) fn on_check(code: u32) -> u32 {
+    let due = COUNTDOWN
+        .try_update(RELEASE, ACQUIRE, |left| left.checked_sub(1))
+        .is_err();
+    if !due {
+        return code;
+    }
+    COUNTDOWN.store(STRIDE.load(ACQUIRE) - 1, RELEASE);
+    if poll_host() {
+        storage::load(ACQUIRE)
+    } else {
+        code
+    }
+}
+
+/// The poll stride and countdown, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+pub(crate) struct Saved {
+    stride: u32,
+    countdown: u32,
+}
+
+#[cfg(feature = "testing")]
+impl Default for Saved {
+    fn default() -> Saved {
+        Saved {
+            stride: DEFAULT_POLL_STRIDE,
+            countdown: 0,
+        }
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        stride: STRIDE.load(ACQUIRE),
+        countdown: COUNTDOWN.load(ACQUIRE),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    STRIDE.store(saved.stride, RELEASE);
+    COUNTDOWN.store(saved.countdown, RELEASE);
+}
+
+// The other sources are tested in `extism`, `lunatic`, `wasi_http` and
+// `wasmcloud`
+#[cfg(all(
+    test,
+    not(any(
+        feature = "extism",
+        feature = "lunatic",
+        feature = "wasi-http",
+        feature = "wasmcloud"
+    ))
+))]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::ARMED_POLL;
+    use crate::{arm, clear_signal, clear_signal_handler, disarm, try_check_signal, Signal};
+
+    #[test]
+    fn test_strided_delivery() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        arm(ARMED_POLL);
+        let previous = set_poll_stride(3);
+
+        // The first check polls, then every third
+        TEST_HOST.store(Signal::CANCEL.code(), RELEASE);
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        TEST_HOST.store(Signal::CANCEL.code(), RELEASE);
+        assert!(try_check_signal().is_ok());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+
+        assert_eq!(set_poll_stride(0), 3);
+        assert_eq!(poll_stride(), 1);
+        TEST_HOST.store(Signal::TERMINATE.code(), RELEASE);
+        assert!(poll_host());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        assert!(!poll_host());
+
+        set_poll_stride(previous);
+        disarm(ARMED_POLL);
+    }
+}
@@ -0,0 +1,99 @@
+//! Guest-to-host progress reporting.
+//!
+//! The signal word carries requests from the host; the progress slot carries
+//! the guest's answer to "how far along are you?". A host that can see a job
+//! is 95% done may choose to wait instead of cancelling it, and a stage code
+//! tells it whether the guest is somewhere safe to interrupt.
+//!
+//! The slot's address is exported as `WASM_SIGNAL_PROGRESS_ADDR`, with the
+//! same double indirection as the other exported words.
+
+use core::sync::atomic::AtomicU32;
+
+use crate::{events, ACQUIRE, ACQ_REL, RELEASE};
+
+/// The progress slot shared with the host.
+///
+/// Three words: the units done, the total units (`0` if unknown) and an
+/// application-defined stage code (`0` if none). The layout is read by the
+/// host helpers and must not change.
+#[repr(C)]
+pub struct ProgressSlot {
+    done: AtomicU32,
+    total: AtomicU32,
+    stage: AtomicU32,
+}
+
+static PROGRESS: ProgressSlot = ProgressSlot {
+    done: AtomicU32::new(0),
+    total: AtomicU32::new(0),
+    stage: AtomicU32::new(0),
+};
+
+/// The exported address of the progress slot.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_PROGRESS_ADDR: &ProgressSlot = &PROGRESS;
+
+/// Publishes that `done` out of `total` units of work are complete.
+///
+/// Pass a `total` of `0` if it is not known. The two words are not written
+/// atomically together; `total` is written first, so a host reading while
+/// the total changes may briefly pair the new total with the old count.
+#[inline]
+pub fn set_progress(done: u32, total: u32) {
+    PROGRESS.total.store(total, RELEASE);
+    PROGRESS.done.store(done, RELEASE);
+}
+
+/// Publishes an application-defined stage code, such as `1` for parsing and
+/// `2` for rendering. `0` means no stage.
+///
+/// A change of stage is also posted to the host as an
+/// [`EVENT_STAGE_CHANGED`](crate::EVENT_STAGE_CHANGED) event.
+pub fn set_stage(code: u32) {
+    if PROGRESS.stage.swap(code, ACQ_REL) != code {
+        events::post_event(events::EVENT_STAGE_CHANGED, code);
+    }
+}
+
+/// Returns the published `(done, total)` progress.
+pub fn progress() -> (u32, u32) {
+    (PROGRESS.done.load(ACQUIRE), PROGRESS.total.load(ACQUIRE))
+}
+
+/// Returns the published stage code.
+pub fn stage() -> u32 {
+    PROGRESS.stage.load(ACQUIRE)
+}
+
+/// Clears the progress slot.
+pub(crate) fn reset() {
+    set_progress(0, 0);
+    PROGRESS.stage.store(0, RELEASE);
+}
+
+/// The progress slot, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    done: u32,
+    total: u32,
+    stage: u32,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    let (done, total) = progress();
+    Saved {
+        done,
+        total,
+        stage: stage(),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    set_progress(saved.done, saved.total);
+    PROGRESS.stage.store(saved.stage, RELEASE);
+}
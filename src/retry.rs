@@ -0,0 +1,252 @@
+//! Retrying fallible operations without outliving cancellation.
+//!
+//! Retry loops around outbound calls are where a cancelled Worker burns the
+//! rest of its budget: each attempt fails, the loop backs off and tries
+//! again, and nothing in it looks at the signal word. [`retry_interruptible`]
+//! checks for a signal before every attempt and throughout every backoff, so
+//! the loop stops as soon as a check propagates a signal. With the
+//! `wasm-bindgen-futures` feature, [`retry_interruptible_async`] does the
+//! same for async operations, backing off on a JavaScript timer.
+
+use core::fmt;
+
+use crate::{now_ms, try_check_signal, Signal};
+
+/// How often and how long [`retry_interruptible`] backs off.
+///
+/// The backoff after the first failed attempt is `initial_backoff_ms`, and
+/// each later one is `multiplier` times the last, up to `max_backoff_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryPolicy {
+    /// Attempts to make in total, including the first. `0` is treated as
+    /// `1`. Defaults to 3.
+    pub max_attempts: u32,
+    /// Backoff after the first failed attempt, in milliseconds. Defaults to
+    /// 50.
+    pub initial_backoff_ms: u32,
+    /// Upper bound on any backoff, in milliseconds. Defaults to 1000.
+    pub max_backoff_ms: u32,
+    /// Factor each backoff grows by. Defaults to 2.
+    pub multiplier: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff_ms: 50,
+            max_backoff_ms: 1000,
+            multiplier: 2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The backoff after failed attempt `attempt`, counting from 1.
+    fn backoff_ms(&self, attempt: u32) -> u32 {
+        let growth = self.multiplier.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff_ms
+            .saturating_mul(growth)
+            .min(self.max_backoff_ms)
+    }
+}
+
+/// Error returned by [`retry_interruptible`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryError<E> {
+    /// A check propagated a signal before the operation succeeded.
+    Signal(Signal),
+    /// Every attempt failed; holds the error of the last one.
+    Exhausted(E),
+}
+
+impl<E: fmt::Display> fmt::Display for RetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryError::Signal(signal) => write!(f, "retry interrupted by {signal}"),
+            RetryError::Exhausted(error) => write!(f, "all attempts failed: {error}"),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> core::error::Error for RetryError<E> {}
+
+impl<E> From<Signal> for RetryError<E> {
+    fn from(signal: Signal) -> RetryError<E> {
+        RetryError::Signal(signal)
+    }
+}
+
+/// Calls `op` until it succeeds or `policy` runs out of attempts, backing
+/// off between attempts.
+///
+/// `op` is passed the attempt number, counting from 1. A signal is checked
+/// before every attempt and continually while backing off, which
+/// busy-waits on the [clock](crate::now_ms). Without a clock, attempts
+/// follow one another without a backoff.
+///
+/// # Errors
+///
+/// Returns [`RetryError::Signal`] as soon as a check propagates a signal,
+/// and [`RetryError::Exhausted`] with the last error if every attempt fails.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{retry_interruptible, RetryPolicy};
+///
+/// fn fetch(attempt: u32) -> Result<&'static str, &'static str> {
+///     if attempt < 2 { Err("unavailable") } else { Ok("body") }
+/// }
+///
+/// let policy = RetryPolicy { initial_backoff_ms: 1, ..RetryPolicy::default() };
+/// assert_eq!(retry_interruptible(policy, fetch), Ok("body"));
+/// ```
+pub fn retry_interruptible<T, E, F>(policy: RetryPolicy, mut op: F) -> Result<T, RetryError<E>>
+where
+    F: FnMut(u32) -> Result<T, E>,
+{
+    let mut attempt = 1;
+    loop {
+        try_check_signal()?;
+        let error = match op(attempt) {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        if attempt >= policy.max_attempts {
+            return Err(RetryError::Exhausted(error));
+        }
+        busy_backoff(policy.backoff_ms(attempt))?;
+        attempt += 1;
+    }
+}
+
+/// Spins for `ms` milliseconds, checking for a signal on every turn.
+fn busy_backoff(ms: u32) -> Result<(), Signal> {
+    let Some(start) = now_ms() else {
+        return Ok(());
+    };
+    let until = start.saturating_add(u64::from(ms));
+    loop {
+        try_check_signal()?;
+        if now_ms().is_none_or(|now| now >= until) {
+            return Ok(());
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Awaits `op` until it succeeds or `policy` runs out of attempts, backing
+/// off between attempts on a JavaScript timer.
+///
+/// As [`retry_interruptible`], but the backoff yields to the host instead
+/// of spinning. A signal is checked before every attempt and whenever the
+/// backoff is polled. A host signal that arrives mid-backoff is noticed when
+/// the timer fires, or sooner if something wakes the task (with the
+/// `futures-channel` feature, `wakeTasks` does).
+///
+/// # Errors
+///
+/// As [`retry_interruptible`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{retry_interruptible_async, RetryError, RetryPolicy};
+///
+/// async fn fetch(attempt: u32) -> Result<String, String> {
+///     let _ = attempt;
+///     Ok(String::from("body"))
+/// }
+///
+/// async fn handle() -> Result<String, RetryError<String>> {
+///     retry_interruptible_async(RetryPolicy::default(), fetch).await
+/// }
+/// ```
+#[cfg(feature = "wasm-bindgen-futures")]
+pub async fn retry_interruptible_async<T, E, F, Fut>(
+    policy: RetryPolicy,
+    mut op: F,
+) -> Result<T, RetryError<E>>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: core::future::Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        try_check_signal()?;
+        let error = match op(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+        if attempt >= policy.max_attempts {
+            return Err(RetryError::Exhausted(error));
+        }
+        crate::timeout::sleep_interruptible(policy.backoff_ms(attempt)).await?;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_clock, clear_signal, clear_signal_handler, set_clock, set_signal};
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    static NOW: AtomicU64 = AtomicU64::new(0);
+
+    /// Advances a millisecond per read, so backoffs end.
+    fn ticking_clock() -> u64 {
+        NOW.fetch_add(1, Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_retry_interruptible() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        let previous = set_clock(ticking_clock);
+
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            initial_backoff_ms: 10,
+            max_backoff_ms: 25,
+            multiplier: 2,
+        };
+        assert_eq!(
+            [1, 2, 3, 4].map(|attempt| policy.backoff_ms(attempt)),
+            [10, 20, 25, 25]
+        );
+
+        // Succeeds on the third attempt, after backing off 10ms then 20ms
+        let start = NOW.load(Ordering::SeqCst);
+        let result = retry_interruptible(policy, |attempt| {
+            if attempt < 3 {
+                Err(attempt)
+            } else {
+                Ok(attempt)
+            }
+        });
+        assert_eq!(result, Ok(3));
+        assert!(NOW.load(Ordering::SeqCst) - start >= 30);
+
+        let result: Result<(), _> = retry_interruptible(policy, Err);
+        assert_eq!(result, Err(RetryError::Exhausted(4)));
+
+        // A signal raised by an attempt stops the backoff before the next
+        let mut attempts = 0;
+        let result: Result<(), _> = retry_interruptible(policy, |_| {
+            attempts += 1;
+            set_signal(Signal::CANCEL.code());
+            Err(())
+        });
+        assert_eq!(result, Err(RetryError::Signal(Signal::CANCEL)));
+        assert_eq!(attempts, 1);
+
+        match previous {
+            Some(clock) => set_clock(clock),
+            None => clear_clock(),
+        };
+    }
+}
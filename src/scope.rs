@@ -0,0 +1,184 @@
+//! Resetting per-request state.
+//!
+//! Instances are commonly reused across requests (Cloudflare Workers reuses
+//! isolates), so state left behind by a cancelled request — a pending signal,
+//! an open grace window, a latched termination — would otherwise bleed into
+//! the next one. [`reset_all`] clears it, and [`RequestScope`] does so on
+//! entry and exit of a request.
+
+use crate::{
+    clear_deadline, clear_epoch_deadline, clear_signal, clear_signal_handler, end_grace, lifecycle,
+    mailbox, mask, namespaced, policy, progress, rearm_shutdown_hooks, source, status, unlatch,
+    validator, LifecycleState,
+};
+
+/// Resets all per-request state.
+///
+/// This clears:
+/// - the pending signal, its source tag and any queued signals
+/// - the last code rejected by validation, so it is reported again
+/// - a command waiting in the mailbox
+/// - the registered signal handler and any
+///   [namespaced handlers](crate::register_namespaced_handler)
+/// - signal masks (live [`SignalMaskGuard`](crate::SignalMaskGuard)s become
+///   inert)
+/// - the deadline, the epoch deadline and any open grace window
+/// - a latched signal
+/// - the published progress and stage
+/// - the ack word, the heartbeat, the [check count](crate::set_count_checks)
+///   and the [signal history](crate::signal_history)
+/// - live [`abort_registration`](crate::abort_registration)s, which are
+///   dropped without being aborted
+/// - tokens bridged with [`bridge_to_token`](crate::bridge_to_token), which
+///   are dropped without being cancelled
+/// - a signal reported to channel receivers (see
+///   [`SignalReceiver`](crate::SignalReceiver))
+/// - the time of the last check, so the [check gap](crate::set_check_gap_warning)
+///   is measured afresh
+/// - the time the pending signal was raised, reported to OpenTelemetry
+/// - the last observed signal, reported to Sentry
+/// - the [lifecycle state](crate::current_state), back to idle
+///
+/// and re-arms the shutdown hooks. Configuration (shutdown hooks, the grace
+/// period, latch mode, the overwrite policy, check counting, the clock, the
+/// panic formatter, the checkpointer and the signal validator) is kept.
+///
+/// The individual resets are not a single atomic step, so this should be
+/// called between requests rather than while checks may run concurrently.
+pub fn reset_all() {
+    // Unmask last, so a concurrent check cannot act on half-reset state
+    unlatch();
+    end_grace();
+    clear_deadline();
+    clear_epoch_deadline();
+    clear_signal_handler();
+    namespaced::reset();
+    policy::clear_queue();
+    clear_signal();
+    source::reset();
+    validator::reset();
+    mailbox::reset();
+    progress::reset();
+    status::reset();
+    #[cfg(feature = "futures-util")]
+    crate::abort::reset();
+    #[cfg(feature = "tokio-util")]
+    crate::token::reset();
+    #[cfg(feature = "futures-channel")]
+    crate::channel::reset();
+    crate::restart_check_gap();
+    #[cfg(feature = "otel")]
+    crate::otel::reset();
+    #[cfg(feature = "sentry")]
+    crate::sentry::reset();
+    rearm_shutdown_hooks();
+    lifecycle::enter(LifecycleState::Idle);
+    mask::reset();
+}
+
+/// Resets all per-request state on creation and again on drop.
+///
+/// While the scope is alive, the [lifecycle state](crate::current_state)
+/// published to the host is running, until a signal propagates.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{try_check_signal, RequestScope, Signal};
+///
+/// fn handle_request() -> Result<(), Signal> {
+///     let _scope = RequestScope::new();
+///     // Register per-request handlers, deadlines etc. here
+///     try_check_signal()?;
+///     Ok(())
+/// }
+/// ```
+#[must_use = "state is reset again as soon as the scope is dropped"]
+#[derive(Debug)]
+pub struct RequestScope {
+    _private: (),
+}
+
+impl RequestScope {
+    /// Resets all per-request state and enters a new scope.
+    pub fn new() -> RequestScope {
+        reset_all();
+        lifecycle::enter(LifecycleState::Running);
+        RequestScope { _private: () }
+    }
+}
+
+impl Default for RequestScope {
+    fn default() -> RequestScope {
+        RequestScope::new()
+    }
+}
+
+impl Drop for RequestScope {
+    fn drop(&mut self) {
+        reset_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        deadline_remaining, latched_signal, set_deadline, set_latch_mode, set_signal,
+        set_signal_handler, signals_masked, try_check_signal, Signal, SignalMaskGuard,
+    };
+    use core::time::Duration;
+
+    #[test]
+    fn test_scope_resets_stale_state() {
+        let _guard = lock();
+        crate::set_clock(|| 1);
+        set_latch_mode(true);
+
+        {
+            let _scope = RequestScope::new();
+            set_signal_handler(|_| Ok(()));
+            set_deadline(Duration::from_secs(1)).unwrap();
+            set_signal(Signal::TERMINATE.code());
+            assert!(try_check_signal().is_err());
+            core::mem::forget(SignalMaskGuard::new());
+        }
+
+        assert!(latched_signal().is_none());
+        assert!(deadline_remaining().is_none());
+        assert!(!signals_masked());
+        assert!(crate::get_signal_handler().is_none());
+        assert!(try_check_signal().is_ok());
+
+        set_latch_mode(false);
+        crate::clear_clock();
+    }
+
+    #[test]
+    fn test_scope_resets_metrics_between_requests() {
+        let _guard = lock();
+        crate::set_count_checks(true);
+
+        {
+            let _scope = RequestScope::new();
+            crate::register_namespaced_handler("scope", |_| Ok(())).unwrap();
+            crate::heartbeat();
+            set_signal(Signal::CANCEL.code());
+            assert!(try_check_signal().is_ok());
+            assert!(try_check_signal().is_ok());
+        }
+
+        // The next request starts from nothing
+        {
+            let _scope = RequestScope::new();
+            assert!(crate::namespaced_handler("scope").is_none());
+            assert!(crate::last_ack().is_none());
+            assert_eq!(crate::heartbeat_count(), 0);
+            assert_eq!(crate::checks_since_last_signal(), Some(0));
+            assert_eq!(crate::signal_history().count(), 0);
+        }
+
+        crate::set_count_checks(false);
+    }
+}
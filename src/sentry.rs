@@ -0,0 +1,233 @@
+//! Signal context for Sentry events.
+//!
+//! A panic from [`check_signal`](crate::check_signal) is a host asking the
+//! guest to stop, not a bug, but crash aggregation sees a panic like any
+//! other. With the `sentry` feature, [`wrap_sentry_panic_hook`] wraps the
+//! hook installed by `sentry::init` so every panic event carries a
+//! `wasm_signal` context (see [`sentry_signal_context`]), and panics caused
+//! by a signal are also tagged and fingerprinted by code. Sentry then groups
+//! host-cancellation aborts apart from genuine bugs, one issue per code.
+
+use core::any::Any;
+use core::sync::atomic::{AtomicBool, AtomicU32};
+use std::boxed::Box;
+use std::panic::{set_hook, take_hook};
+use std::string::ToString;
+use std::vec::Vec;
+
+use sentry_core::protocol::{Context, Map, Value};
+
+use crate::{peek_signal, signal_history, Signal, SignalPanic, ACQUIRE, RELEASE};
+
+/// The code of the last signal a check observed, or `0`.
+static LAST_CODE: AtomicU32 = AtomicU32::new(0);
+
+/// Whether the last observed signal propagated.
+static LAST_PROPAGATED: AtomicBool = AtomicBool::new(false);
+
+/// Records the outcome of an observed signal for the context.
+pub(crate) fn record(signal: Signal, result: Result<(), Signal>) {
+    LAST_PROPAGATED.store(result.is_err(), RELEASE);
+    LAST_CODE.store(signal.code(), RELEASE);
+}
+
+/// Forgets the last observed signal.
+pub(crate) fn reset() {
+    LAST_CODE.store(0, RELEASE);
+    LAST_PROPAGATED.store(false, RELEASE);
+}
+
+/// The last observed signal, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    code: u32,
+    propagated: bool,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        code: LAST_CODE.load(ACQUIRE),
+        propagated: LAST_PROPAGATED.load(ACQUIRE),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    LAST_PROPAGATED.store(saved.propagated, RELEASE);
+    LAST_CODE.store(saved.code, RELEASE);
+}
+
+fn signal_value(signal: Option<Signal>) -> Value {
+    signal.map_or(Value::Null, |signal| Value::from(signal.code()))
+}
+
+/// Returns the signal state as a Sentry context.
+///
+/// The context holds the last signal a check observed (`code`, `name` and
+/// `outcome`, which is `"handled"` if the handler cleared it and
+/// `"propagated"` otherwise), the `pending` code and the `history` of
+/// acknowledged codes, most recent first. Attach it to events captured by
+/// hand with `scope.set_context("wasm_signal", sentry_signal_context())`.
+pub fn sentry_signal_context() -> Context {
+    let last = Signal::new(LAST_CODE.load(ACQUIRE));
+    let mut context = Map::new();
+    context.insert("code".into(), signal_value(last));
+    context.insert(
+        "name".into(),
+        last.and_then(|signal| signal.name())
+            .map_or(Value::Null, Value::from),
+    );
+    let outcome = match (last, LAST_PROPAGATED.load(ACQUIRE)) {
+        (None, _) => Value::Null,
+        (Some(_), false) => Value::from("handled"),
+        (Some(_), true) => Value::from("propagated"),
+    };
+    context.insert("outcome".into(), outcome);
+    context.insert("pending".into(), signal_value(peek_signal()));
+    let history: Vec<Value> = signal_history()
+        .map(|signal| Value::from(signal.code()))
+        .collect();
+    context.insert("history".into(), Value::Array(history));
+    Context::Other(context)
+}
+
+/// Runs `report` with the signal context, and a signal panic's tag and
+/// fingerprint, on the current scope.
+fn with_signal_scope<R>(payload: &(dyn Any + Send), report: impl FnOnce() -> R) -> R {
+    sentry_core::with_scope(
+        |scope| {
+            scope.set_context("wasm_signal", sentry_signal_context());
+            if let Some(signal) = SignalPanic::downcast(payload) {
+                let code = signal.code().to_string();
+                scope.set_tag("wasm_signal.cancelled", "true");
+                scope.set_tag("wasm_signal.code", &code);
+                scope.set_fingerprint(Some(&["wasm-signal", &code]));
+                if let Some(name) = signal.name() {
+                    scope.set_tag("wasm_signal.name", name);
+                }
+            }
+        },
+        report,
+    )
+}
+
+/// Wraps the current panic hook, normally the one `sentry::init` installs,
+/// so the events it captures carry the signal context.
+///
+/// Panics carrying a [`SignalPanic`] are also tagged `wasm_signal.cancelled`
+/// and `wasm_signal.code`, and fingerprinted as `["wasm-signal", code]`.
+/// Call it once, after initializing Sentry.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// // After `let _sentry = sentry::init(...)`
+/// wasm_signal_handler::wrap_sentry_panic_hook();
+/// ```
+pub fn wrap_sentry_panic_hook() {
+    let previous = take_hook();
+    set_hook(Box::new(move |info| {
+        with_signal_scope(info.payload(), || previous(info));
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, try_check_signal};
+    use sentry_core::protocol::Event;
+    use sentry_core::{Client, ClientOptions, Envelope, Hub, Transport};
+    use std::string::String;
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps the events sent, instead of sending them.
+    #[derive(Default)]
+    struct Captured(Mutex<Vec<Event<'static>>>);
+
+    impl Transport for Captured {
+        fn send_envelope(&self, envelope: Envelope) {
+            if let Some(event) = envelope.event() {
+                self.0.lock().unwrap().push(event.clone());
+            }
+        }
+    }
+
+    fn capture(payload: &(dyn Any + Send)) -> Event<'static> {
+        let captured = Arc::new(Captured::default());
+        let client = Client::from(ClientOptions {
+            dsn: "https://public@sentry.invalid/1".parse().ok(),
+            transport: Some(Arc::new(captured.clone())),
+            ..ClientOptions::default()
+        });
+        let hub = Arc::new(Hub::new_from_top(Hub::current()));
+        hub.bind_client(Some(Arc::new(client)));
+        Hub::run(hub, || {
+            with_signal_scope(payload, || {
+                sentry_core::capture_message("panic", sentry_core::Level::Fatal)
+            });
+        });
+        let event = captured.0.lock().unwrap().pop();
+        event.unwrap()
+    }
+
+    fn fingerprint<'a>(event: &'a Event<'_>) -> Vec<&'a str> {
+        event.fingerprint.iter().map(|part| &**part).collect()
+    }
+
+    #[test]
+    fn test_signal_context() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        reset();
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+
+        let payload = SignalPanic {
+            signal: Signal::CANCEL,
+            message: String::from("cancelled"),
+        };
+        let event = capture(&payload);
+        let Some(Context::Other(context)) = event.contexts.get("wasm_signal") else {
+            panic!("no signal context: {:?}", event.contexts);
+        };
+        assert_eq!(context["code"], Value::from(2));
+        assert_eq!(context["name"], Value::from("cancel"));
+        assert_eq!(context["outcome"], Value::from("propagated"));
+        assert_eq!(context["pending"], Value::Null);
+        assert_eq!(context["history"][0], Value::from(2));
+        assert_eq!(
+            event.tags.get("wasm_signal.code").map(String::as_str),
+            Some("2")
+        );
+        assert_eq!(fingerprint(&event), ["wasm-signal", "2"]);
+
+        // Other panics keep the context, but not the grouping
+        let event = capture(&"index out of bounds");
+        assert!(event.contexts.contains_key("wasm_signal"));
+        assert!(!event.tags.contains_key("wasm_signal.code"));
+        assert_eq!(fingerprint(&event), ["{{ default }}"]);
+        reset();
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn test_scoped_state_restores_last_signal() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        record(Signal::CANCEL, Err(Signal::CANCEL));
+
+        {
+            let _state = crate::testing::ScopedSignalState::new();
+            assert_eq!(LAST_CODE.load(ACQUIRE), 0);
+            record(Signal::TERMINATE, Ok(()));
+        }
+        assert_eq!(LAST_CODE.load(ACQUIRE), Signal::CANCEL.code());
+        assert!(LAST_PROPAGATED.load(ACQUIRE));
+        reset();
+    }
+}
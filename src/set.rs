@@ -0,0 +1,306 @@
+//! Sets of signal codes.
+//!
+//! Code that works with groups of signals — the codes a component
+//! understands, the codes a drain dispatched — would otherwise pass raw
+//! bitmasks around. [`SignalSet`] is a small copyable set with the usual set
+//! operations, convertible to and from the [`SignalMask`] that checks
+//! filter by.
+
+use core::fmt;
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Sub, SubAssign};
+
+use crate::{Signal, SignalMask};
+
+/// A set of signal codes.
+///
+/// As with [`SignalMask`], codes `1` to `62` are tracked individually and
+/// all higher codes share a single "other" entry, and signals are matched by
+/// their [base code](Signal::base_code). Unlike a mask, a set has no
+/// special case for fatal codes: they are members only if added.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{Signal, SignalSet};
+///
+/// let stop = SignalSet::from_iter([Signal::TERMINATE, Signal::CANCEL]);
+/// let seen = SignalSet::EMPTY.with(Signal::CANCEL).with(Signal::new(42).unwrap());
+///
+/// assert_eq!(stop & seen, SignalSet::EMPTY.with(Signal::CANCEL));
+/// assert_eq!((seen - stop).iter().map(|s| s.code()).collect::<Vec<_>>(), [42]);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SignalSet(u64);
+
+impl SignalSet {
+    /// The empty set.
+    pub const EMPTY: SignalSet = SignalSet(0);
+
+    /// Every code, including the codes above `62`.
+    pub const ALL: SignalSet = SignalSet(!1);
+
+    /// The entry shared by codes above `62`.
+    const OTHER: u64 = 1 << 63;
+
+    const fn bit(code: u32) -> u64 {
+        if code < 63 {
+            1 << code
+        } else {
+            SignalSet::OTHER
+        }
+    }
+
+    /// Returns the set with `signal` added.
+    pub const fn with(self, signal: Signal) -> SignalSet {
+        SignalSet(self.0 | SignalSet::bit(signal.base_code()))
+    }
+
+    /// Returns the set with `signal` removed.
+    pub const fn without(self, signal: Signal) -> SignalSet {
+        SignalSet(self.0 & !SignalSet::bit(signal.base_code()))
+    }
+
+    /// Returns the set with every code above `62` added.
+    pub const fn with_others(self) -> SignalSet {
+        SignalSet(self.0 | SignalSet::OTHER)
+    }
+
+    /// Adds `signal`, returning `true` if it was not already a member.
+    pub fn insert(&mut self, signal: Signal) -> bool {
+        let added = !self.contains(signal);
+        *self = self.with(signal);
+        added
+    }
+
+    /// Removes `signal`, returning `true` if it was a member.
+    pub fn remove(&mut self, signal: Signal) -> bool {
+        let removed = self.contains(signal);
+        *self = self.without(signal);
+        removed
+    }
+
+    /// Returns `true` if `signal` is a member.
+    pub const fn contains(self, signal: Signal) -> bool {
+        self.0 & SignalSet::bit(signal.base_code()) != 0
+    }
+
+    /// Returns `true` if the codes above `62` are members.
+    pub const fn contains_others(self) -> bool {
+        self.0 & SignalSet::OTHER != 0
+    }
+
+    /// Returns `true` if the set has no members.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the number of entries, counting the codes above `62` as one.
+    pub const fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// Returns the codes in either set.
+    pub const fn union(self, other: SignalSet) -> SignalSet {
+        SignalSet(self.0 | other.0)
+    }
+
+    /// Returns the codes in both sets.
+    pub const fn intersection(self, other: SignalSet) -> SignalSet {
+        SignalSet(self.0 & other.0)
+    }
+
+    /// Returns the codes in `self` but not in `other`.
+    pub const fn difference(self, other: SignalSet) -> SignalSet {
+        SignalSet(self.0 & !other.0)
+    }
+
+    /// Returns the codes not in the set.
+    pub const fn complement(self) -> SignalSet {
+        SignalSet(!self.0 & SignalSet::ALL.0)
+    }
+
+    /// Returns `true` if every member of `self` is in `other`.
+    pub const fn is_subset(self, other: SignalSet) -> bool {
+        self.0 & !other.0 == 0
+    }
+
+    /// Returns a mask letting through the set's codes, and the fatal codes.
+    /// The same as `SignalMask::from`, for use in constants.
+    pub const fn to_mask(self) -> SignalMask {
+        SignalMask::from_bits(self.0)
+    }
+
+    /// Returns the individually tracked members, in ascending order of code.
+    /// The shared entry for codes above `62` is not included; see
+    /// [`contains_others`](SignalSet::contains_others).
+    pub fn iter(self) -> SignalSetIter {
+        SignalSetIter(self.0 & !SignalSet::OTHER)
+    }
+}
+
+impl fmt::Debug for SignalSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut set = f.debug_set();
+        set.entries(self.iter().map(|signal| signal.code()));
+        if self.contains_others() {
+            set.entry(&format_args!("63.."));
+        }
+        set.finish()
+    }
+}
+
+/// Iterator over the members of a [`SignalSet`], from
+/// [`SignalSet::iter`].
+#[derive(Debug, Clone)]
+pub struct SignalSetIter(u64);
+
+impl Iterator for SignalSetIter {
+    type Item = Signal;
+
+    fn next(&mut self) -> Option<Signal> {
+        let code = self.0.trailing_zeros();
+        // Clears the lowest set bit
+        self.0 &= self.0.checked_sub(1)?;
+        Signal::new(code)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SignalSetIter {}
+
+impl IntoIterator for SignalSet {
+    type Item = Signal;
+    type IntoIter = SignalSetIter;
+
+    fn into_iter(self) -> SignalSetIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<Signal> for SignalSet {
+    fn from_iter<I: IntoIterator<Item = Signal>>(iter: I) -> SignalSet {
+        let mut set = SignalSet::EMPTY;
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Signal> for SignalSet {
+    fn extend<I: IntoIterator<Item = Signal>>(&mut self, iter: I) {
+        for signal in iter {
+            *self = self.with(signal);
+        }
+    }
+}
+
+impl From<Signal> for SignalSet {
+    fn from(signal: Signal) -> SignalSet {
+        SignalSet::EMPTY.with(signal)
+    }
+}
+
+/// The codes the mask lets through, leaving out the fatal codes it admits
+/// implicitly.
+impl From<SignalMask> for SignalSet {
+    fn from(mask: SignalMask) -> SignalSet {
+        SignalSet(mask.bits() & SignalSet::ALL.0)
+    }
+}
+
+/// A mask letting through the set's codes, and the fatal codes.
+impl From<SignalSet> for SignalMask {
+    fn from(set: SignalSet) -> SignalMask {
+        set.to_mask()
+    }
+}
+
+impl BitOr for SignalSet {
+    type Output = SignalSet;
+
+    fn bitor(self, other: SignalSet) -> SignalSet {
+        self.union(other)
+    }
+}
+
+impl BitOrAssign for SignalSet {
+    fn bitor_assign(&mut self, other: SignalSet) {
+        *self = self.union(other);
+    }
+}
+
+impl BitAnd for SignalSet {
+    type Output = SignalSet;
+
+    fn bitand(self, other: SignalSet) -> SignalSet {
+        self.intersection(other)
+    }
+}
+
+impl BitAndAssign for SignalSet {
+    fn bitand_assign(&mut self, other: SignalSet) {
+        *self = self.intersection(other);
+    }
+}
+
+impl Sub for SignalSet {
+    type Output = SignalSet;
+
+    fn sub(self, other: SignalSet) -> SignalSet {
+        self.difference(other)
+    }
+}
+
+impl SubAssign for SignalSet {
+    fn sub_assign(&mut self, other: SignalSet) {
+        *self = self.difference(other);
+    }
+}
+
+impl Not for SignalSet {
+    type Output = SignalSet;
+
+    fn not(self) -> SignalSet {
+        self.complement()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_set() {
+        let custom = Signal::new(42).unwrap();
+        let high = Signal::new(1000).unwrap();
+        let mut set = SignalSet::from_iter([Signal::CANCEL, custom]);
+        assert_eq!(set.len(), 2);
+        assert!(set.insert(high));
+        assert!(!set.insert(Signal::new(2000).unwrap()));
+        assert!(set.contains_others());
+        assert!(set.iter().eq([Signal::CANCEL, custom]));
+
+        let stop = SignalSet::from(Signal::CANCEL) | Signal::TERMINATE.into();
+        assert_eq!(set & stop, SignalSet::from(Signal::CANCEL));
+        assert_eq!((set - stop).with_others(), set.without(Signal::CANCEL));
+        assert!((set & stop).is_subset(stop));
+        assert!(!(!stop).contains(Signal::CANCEL));
+        assert!((!stop).contains(custom));
+        assert_eq!(!SignalSet::EMPTY, SignalSet::ALL);
+        assert!(set.remove(custom));
+        assert!(!set.remove(custom));
+
+        // Masks admit fatal codes whether or not they are in the set
+        let fatal = Signal::new(Signal::FATAL_BASE).unwrap();
+        let mask = SignalMask::from(stop);
+        assert!(mask.contains(Signal::TERMINATE) && mask.contains(fatal));
+        assert!(!mask.contains(custom));
+        assert_eq!(SignalSet::from(mask), stop);
+        assert_eq!(SignalSet::from(SignalMask::ALL), SignalSet::ALL);
+    }
+}
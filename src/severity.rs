@@ -0,0 +1,173 @@
+//! Severity levels encoded in the signal word.
+//!
+//! The top four bits of the signal word may carry a [`Severity`], with the
+//! code in the remaining 28 bits:
+//!
+//! ```text
+//!  31    28 27                         0
+//! +--------+----------------------------+
+//! |severity|            code            |
+//! +--------+----------------------------+
+//! ```
+//!
+//! A severity of `0` means none was encoded, and the word is a plain code as
+//! before. Severity `0xF` is [`Severity::Fatal`], which makes the
+//! [fatal range](crate::Signal::FATAL_BASE) part of the same encoding.
+//!
+//! Checks key their behavior on an encoded severity:
+//!
+//! - [`Info`](Severity::Info) and [`Warn`](Severity::Warn) are advisory. The
+//!   handler sees them, but they never propagate, so they never panic.
+//! - [`Cancel`](Severity::Cancel) behaves like an unencoded code: the handler
+//!   decides.
+//! - [`Kill`](Severity::Kill) runs the handler but always propagates, and is
+//!   latched like [`Signal::TERMINATE`](crate::Signal::TERMINATE).
+//! - [`Fatal`](Severity::Fatal) bypasses the handler entirely.
+//!
+//! Host-side senders use the matching `encodeSignal`/`decodeSignal` helpers
+//! in `js/wasm-signal-handler.js`.
+
+use core::fmt;
+
+/// The number of bits the severity is shifted by.
+pub const SEVERITY_SHIFT: u32 = 28;
+
+/// The bits of the signal word holding the code.
+pub const CODE_MASK: u32 = (1 << SEVERITY_SHIFT) - 1;
+
+/// How urgently a signal must be acted on, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[repr(u8)]
+pub enum Severity {
+    /// Informational; never propagates.
+    Info = 1,
+    /// A warning; never propagates.
+    Warn = 2,
+    /// A cancellation request the handler may decline.
+    Cancel = 3,
+    /// A termination request the handler sees but cannot swallow.
+    Kill = 4,
+    /// Bypasses the handler (see [`Signal::FATAL_BASE`](crate::Signal::FATAL_BASE)).
+    Fatal = 0xF,
+}
+
+impl Severity {
+    /// Returns the severity encoded in the top bits of `word`, if any.
+    ///
+    /// Unassigned severity values decode as `None`.
+    pub const fn from_word(word: u32) -> Option<Severity> {
+        match word >> SEVERITY_SHIFT {
+            1 => Some(Severity::Info),
+            2 => Some(Severity::Warn),
+            3 => Some(Severity::Cancel),
+            4 => Some(Severity::Kill),
+            0xF => Some(Severity::Fatal),
+            _ => None,
+        }
+    }
+
+    /// Returns a short, stable name for this severity.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warn => "warn",
+            Severity::Cancel => "cancel",
+            Severity::Kill => "kill",
+            Severity::Fatal => "fatal",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Encodes `code` with `severity` into a signal word.
+///
+/// Bits of `code` above [`CODE_MASK`] are discarded.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{decode_signal, encode_signal, Severity};
+///
+/// let word = encode_signal(42, Severity::Warn);
+/// assert_eq!(word, 0x2000_002A);
+/// assert_eq!(decode_signal(word), (42, Some(Severity::Warn)));
+/// ```
+pub const fn encode_signal(code: u32, severity: Severity) -> u32 {
+    ((severity as u32) << SEVERITY_SHIFT) | (code & CODE_MASK)
+}
+
+/// Splits a signal word into its code and encoded severity.
+///
+/// Words without an encoded severity decode as `(word, None)`, so plain
+/// codes round-trip unchanged.
+pub const fn decode_signal(word: u32) -> (u32, Option<Severity>) {
+    match Severity::from_word(word) {
+        Some(severity) => (word & CODE_MASK, Some(severity)),
+        None => (word, None),
+    }
+}
+
+/// Orders signal words by severity, for keeping the more severe of two.
+///
+/// Words without an encoded severity rank as [`Severity::Cancel`], except
+/// [`Signal::TERMINATE`](crate::Signal::TERMINATE), which ranks as
+/// [`Severity::Kill`]. Must match `rankSignal` in the host helpers.
+pub(crate) const fn rank(word: u32) -> u32 {
+    match Severity::from_word(word) {
+        Some(severity) => severity as u32,
+        None if word == 1 => Severity::Kill as u32,
+        None => Severity::Cancel as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        check_signal, clear_signal, clear_signal_handler, set_signal, set_signal_handler,
+        try_check_signal, Signal, SignalKind,
+    };
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(decode_signal(2), (2, None));
+        let word = encode_signal(Signal::CANCEL.code(), Severity::Kill);
+        let signal = Signal::new(word).unwrap();
+        assert_eq!(signal.severity(), Some(Severity::Kill));
+        assert_eq!(signal.base_code(), 2);
+        assert_eq!(signal.kind(), SignalKind::Cancel);
+        assert_eq!(
+            Signal::new(encode_signal(7, Severity::Fatal)).map(Signal::is_fatal),
+            Some(true)
+        );
+        assert!(Severity::Info < Severity::Kill);
+    }
+
+    #[test]
+    fn test_dispatch_by_severity() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        // Advisory signals never propagate, so never panic
+        set_signal(encode_signal(9, Severity::Info));
+        check_signal();
+        set_signal(encode_signal(9, Severity::Warn));
+        assert!(try_check_signal().is_ok());
+
+        let kill = Signal::new(encode_signal(9, Severity::Kill)).unwrap();
+        set_signal_handler(|_| Ok(()));
+        set_signal(kill.code());
+        assert_eq!(try_check_signal(), Err(kill));
+
+        clear_signal_handler();
+    }
+}
@@ -0,0 +1,170 @@
+//! Signal conventions for Shopify Functions.
+//!
+//! A Shopify Function runs synchronously to completion under fixed limits on
+//! executed instructions and on the size of its output, with no host that
+//! writes signals while it runs. The guest enforces the limits itself
+//! instead: the [`ShopifyLimit`] codes, in the `0x5300` block, are raised
+//! with [`raise_signal`] so the handler and checks treat them like any other
+//! signal. [`ShopifyOutputBudget`] raises the output size codes, and
+//! [`SHOPIFY_CHECK_STRIDE`] is a starting point for thinning checks, which
+//! count against the instruction limit too.
+//!
+//! The limits are Shopify's published defaults at the time of writing;
+//! pass the current values where they may differ.
+
+use crate::platform::platform_signals;
+use crate::{raise_signal, Signal};
+
+/// Shopify's limit on instructions executed by a Function.
+pub const SHOPIFY_INSTRUCTION_LIMIT: u64 = 11_000_000;
+
+/// Shopify's limit on the size of a Function's output, in bytes.
+pub const SHOPIFY_OUTPUT_LIMIT: usize = 20 * 1024;
+
+/// The recommended stride for [`check_signal_here!`](crate::check_signal_here)
+/// in per-item loops of a Function.
+///
+/// A check costs a handful of instructions, so with a few hundred
+/// instructions of work per item this keeps checks well under one percent
+/// of the instruction budget.
+pub const SHOPIFY_CHECK_STRIDE: u32 = 64;
+
+/// The share of the output limit, in percent, at which
+/// [`ShopifyLimit::OutputSizePressure`] is raised.
+pub const SHOPIFY_OUTPUT_PRESSURE_PERCENT: usize = 80;
+
+platform_signals! {
+    /// A Shopify Functions limit the guest signals itself about.
+    pub enum ShopifyLimit ("limit", "limits") {
+        /// The Function's own estimate says the instruction limit is close.
+        InstructionLimitApproaching = 0x5301, Cancel, Cancel, "instruction-limit-approaching";
+        /// The output is close to its size limit.
+        OutputSizePressure = 0x5302, Warn, Custom, "output-size-pressure";
+        /// The output would exceed its size limit.
+        OutputSizeExceeded = 0x5303, Kill, Terminate, "output-size-exceeded";
+    }
+
+    /// The generic kind a guest should treat the limit as: an approaching
+    /// instruction limit is a cancel, exceeded output a termination, and output
+    /// pressure has no generic equivalent.
+    kind;
+
+    pub fn register_shopify_names;
+}
+
+/// Tracks the size of a Function's output against its limit.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{ShopifyOutputBudget, Signal};
+///
+/// fn emit(operations: &[&str]) -> Result<String, Signal> {
+///     let mut budget = ShopifyOutputBudget::default();
+///     let mut output = String::new();
+///     for operation in operations {
+///         budget.add(operation.len())?;
+///         output.push_str(operation);
+///     }
+///     Ok(output)
+/// }
+/// # assert!(emit(&["{}"]).is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShopifyOutputBudget {
+    limit: usize,
+    written: usize,
+    warned: bool,
+}
+
+impl ShopifyOutputBudget {
+    /// Creates a budget of `limit` bytes.
+    pub const fn new(limit: usize) -> ShopifyOutputBudget {
+        ShopifyOutputBudget {
+            limit,
+            written: 0,
+            warned: false,
+        }
+    }
+
+    /// Counts `bytes` more of output.
+    ///
+    /// The first time the output passes [`SHOPIFY_OUTPUT_PRESSURE_PERCENT`]
+    /// of the limit, [`ShopifyLimit::OutputSizePressure`] is raised and
+    /// dispatched, so the handler can start trimming. If the output would
+    /// pass the limit, the bytes are not counted and
+    /// [`ShopifyLimit::OutputSizeExceeded`] is raised and dispatched.
+    ///
+    /// # Returns
+    ///
+    /// The result of dispatching, as from [`raise_signal`], or `Ok(())`.
+    #[track_caller]
+    pub fn add(&mut self, bytes: usize) -> Result<(), Signal> {
+        let written = self.written.saturating_add(bytes);
+        if written > self.limit {
+            return raise_signal(ShopifyLimit::OutputSizeExceeded.code(), true);
+        }
+        self.written = written;
+        let pressure = self.limit.saturating_mul(SHOPIFY_OUTPUT_PRESSURE_PERCENT) / 100;
+        if !self.warned && written >= pressure {
+            self.warned = true;
+            return raise_signal(ShopifyLimit::OutputSizePressure.code(), true);
+        }
+        Ok(())
+    }
+
+    /// Returns the bytes counted so far.
+    pub const fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Returns the bytes left before the limit.
+    pub const fn remaining(&self) -> usize {
+        self.limit.saturating_sub(self.written)
+    }
+}
+
+impl Default for ShopifyOutputBudget {
+    /// A budget of [`SHOPIFY_OUTPUT_LIMIT`] bytes.
+    fn default() -> ShopifyOutputBudget {
+        ShopifyOutputBudget::new(SHOPIFY_OUTPUT_LIMIT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, last_ack};
+
+    #[test]
+    fn test_shopify_output_budget() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        for limit in ShopifyLimit::ALL {
+            assert_eq!(ShopifyLimit::try_from(limit.signal()), Ok(limit));
+        }
+
+        let mut budget = ShopifyOutputBudget::new(1000);
+        assert_eq!(budget.add(700), Ok(()));
+        // Pressure is advisory, so it is handled even without a handler
+        assert_eq!(budget.add(100), Ok(()));
+        assert_eq!(last_ack(), Some(ShopifyLimit::OutputSizePressure.signal()));
+        assert_eq!(
+            budget.add(201),
+            Err(ShopifyLimit::OutputSizeExceeded.signal())
+        );
+        assert_eq!(budget.written(), 800);
+        assert_eq!(budget.remaining(), 200);
+        assert_eq!(budget.add(200), Ok(()));
+
+        // Small limits are not rounded down to a threshold of zero
+        crate::status::reset();
+        let mut budget = ShopifyOutputBudget::new(10);
+        assert_eq!(budget.add(7), Ok(()));
+        assert_eq!(last_ack(), None);
+        assert_eq!(budget.add(1), Ok(()));
+        assert_eq!(last_ack(), Some(ShopifyLimit::OutputSizePressure.signal()));
+    }
+}
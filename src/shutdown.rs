@@ -0,0 +1,223 @@
+//! Graceful shutdown hooks.
+//!
+//! Hooks registered with [`register_shutdown_hook`] run exactly once, in
+//! registration order, when a signal is propagated out of
+//! [`check_signal`](crate::check_signal) — right before it panics, traps or throws.
+//! Applications use them to flush buffered logs, close transactions, or
+//! persist partial progress on cancellation.
+
+use core::fmt;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicBool, AtomicPtr};
+
+use crate::{lifecycle, LifecycleState, ACQUIRE, ACQ_REL, RELEASE};
+
+/// A shutdown hook function.
+///
+/// Hooks run on the fatal path, possibly while the application is in an
+/// inconsistent state, and must not rely on being able to check signals.
+pub type ShutdownHook = fn();
+
+/// Maximum number of shutdown hooks that can be registered.
+pub const MAX_SHUTDOWN_HOOKS: usize = 16;
+
+/// Storage for registered hooks, filled in registration order.
+///
+/// Stored as raw pointers for the same reasons as the signal handler.
+static HOOKS: [AtomicPtr<()>; MAX_SHUTDOWN_HOOKS] =
+    [const { AtomicPtr::new(null_mut()) }; MAX_SHUTDOWN_HOOKS];
+
+/// Set once the hooks have started running.
+static RAN: AtomicBool = AtomicBool::new(false);
+
+/// Error returned when all [`MAX_SHUTDOWN_HOOKS`] slots are in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShutdownHooksFull;
+
+impl fmt::Display for ShutdownHooksFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "all {} shutdown hook slots are in use",
+            MAX_SHUTDOWN_HOOKS
+        )
+    }
+}
+
+impl core::error::Error for ShutdownHooksFull {}
+
+/// Registers a hook to run when a fatal signal is propagated.
+///
+/// # Errors
+///
+/// Returns [`ShutdownHooksFull`] if [`MAX_SHUTDOWN_HOOKS`] hooks are
+/// already registered.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::register_shutdown_hook;
+///
+/// fn flush_logs() {
+///     // Write out buffered log lines
+/// }
+///
+/// register_shutdown_hook(flush_logs).unwrap();
+/// ```
+pub fn register_shutdown_hook(hook: ShutdownHook) -> Result<(), ShutdownHooksFull> {
+    let ptr = hook as *mut ();
+    for slot in &HOOKS {
+        if slot
+            .compare_exchange(null_mut(), ptr, ACQ_REL, ACQUIRE)
+            .is_ok()
+        {
+            return Ok(());
+        }
+    }
+    Err(ShutdownHooksFull)
+}
+
+/// Removes all registered shutdown hooks.
+pub fn clear_shutdown_hooks() {
+    for slot in &HOOKS {
+        slot.store(null_mut(), RELEASE);
+    }
+}
+
+/// Runs the registered shutdown hooks, unless they have already run.
+///
+/// The [lifecycle state](crate::current_state) is cleaning up while they
+/// run, and terminated once they have.
+///
+/// This is called automatically on the fatal path of
+/// [`check_signal`](crate::check_signal), and can be called directly by
+/// applications that terminate on an `Err(Signal)` from
+/// [`try_check_signal`](crate::try_check_signal).
+///
+/// # Returns
+///
+/// Returns `true` if this call ran the hooks, or `false` if they had already
+/// run.
+pub fn run_shutdown_hooks() -> bool {
+    if RAN.swap(true, ACQ_REL) {
+        return false;
+    }
+
+    lifecycle::advance(LifecycleState::CleaningUp);
+    for slot in &HOOKS {
+        let ptr = slot.load(ACQUIRE);
+        if ptr.is_null() {
+            // Slots are filled in order, but a concurrent clear may leave gaps
+            continue;
+        }
+        // SAFETY: We only store valid ShutdownHook function pointers in HOOKS
+        let hook = unsafe { core::mem::transmute::<*mut (), ShutdownHook>(ptr) };
+        hook();
+    }
+    lifecycle::advance(LifecycleState::Terminated);
+    true
+}
+
+/// Returns `true` if the shutdown hooks have run.
+pub fn shutdown_hooks_ran() -> bool {
+    RAN.load(ACQUIRE)
+}
+
+/// Re-arms the hooks so they run again on the next fatal signal.
+///
+/// Useful when an instance survives a fatal signal (e.g. the panic was caught
+/// at a request boundary) and is reused for further work.
+pub fn rearm_shutdown_hooks() {
+    RAN.store(false, RELEASE);
+}
+
+/// Registered hooks and whether they ran, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    hooks: [Option<ShutdownHook>; MAX_SHUTDOWN_HOOKS],
+    ran: bool,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    let mut saved = Saved {
+        ran: RAN.load(ACQUIRE),
+        ..Saved::default()
+    };
+    for (slot, hook) in HOOKS.iter().zip(&mut saved.hooks) {
+        let ptr = slot.load(ACQUIRE);
+        // SAFETY: We only store valid ShutdownHook function pointers in HOOKS
+        *hook =
+            (!ptr.is_null()).then(|| unsafe { core::mem::transmute::<*mut (), ShutdownHook>(ptr) });
+    }
+    saved
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    for (slot, hook) in HOOKS.iter().zip(&saved.hooks) {
+        slot.store(hook.map_or(null_mut(), |hook| hook as *mut ()), RELEASE);
+    }
+    RAN.store(saved.ran, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{check_signal, clear_signal_handler, set_signal};
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    extern crate std;
+
+    static FIRST: AtomicU32 = AtomicU32::new(0);
+    static SECOND: AtomicU32 = AtomicU32::new(0);
+
+    fn first() {
+        // Hooks run in registration order
+        assert_eq!(SECOND.load(Ordering::SeqCst), FIRST.load(Ordering::SeqCst));
+        FIRST.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn second() {
+        SECOND.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_hooks_run_once_on_fatal_signal() {
+        let _guard = lock();
+        clear_signal_handler();
+        clear_shutdown_hooks();
+        rearm_shutdown_hooks();
+
+        register_shutdown_hook(first).unwrap();
+        register_shutdown_hook(second).unwrap();
+
+        for _ in 0..2 {
+            set_signal(1);
+            assert!(std::panic::catch_unwind(check_signal).is_err());
+        }
+
+        assert!(shutdown_hooks_ran());
+        assert_eq!(FIRST.load(Ordering::SeqCst), 1);
+        assert_eq!(SECOND.load(Ordering::SeqCst), 1);
+        assert!(!run_shutdown_hooks());
+
+        clear_shutdown_hooks();
+        rearm_shutdown_hooks();
+    }
+
+    #[test]
+    fn test_registry_full() {
+        let _guard = lock();
+        clear_shutdown_hooks();
+
+        for _ in 0..MAX_SHUTDOWN_HOOKS {
+            register_shutdown_hook(second).unwrap();
+        }
+        assert_eq!(register_shutdown_hook(second), Err(ShutdownHooksFull));
+
+        clear_shutdown_hooks();
+    }
+}
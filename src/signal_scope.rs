@@ -0,0 +1,234 @@
+//! Structured concurrency on top of the signal word.
+//!
+//! A [`SignalScope`] owns the child futures spawned into it and drives them
+//! together from [`join`](SignalScope::join). They are all cancelled, by
+//! being dropped, when the scope is dropped or when a check in the scope
+//! propagates a signal, so no child outlives the request that started it.
+//!
+//! The scope does not need an executor: it is polled by whatever task awaits
+//! `join`, and polls its children in turn. A signal is noticed whenever the
+//! scope is polled, that is, whenever a child is woken. With the
+//! `futures-channel` feature the scope is also woken like a
+//! [`SignalReceiver`](crate::SignalReceiver), so a signal raised while every
+//! child waits still cancels them.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::{try_check_signal, Signal};
+
+type Child<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// A set of child futures cancelled together; see the [module
+/// docs](self).
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{Signal, SignalScope};
+///
+/// async fn fetch_all(urls: &[&str]) -> Result<(), Signal> {
+///     let mut scope = SignalScope::new();
+///     for url in urls {
+///         scope.spawn(async move {
+///             let _ = url; // fetch it
+///         });
+///     }
+///     // Synchronous work between awaits checks through the scope
+///     scope.check()?;
+///     scope.join().await
+/// }
+/// ```
+#[derive(Default)]
+#[must_use = "a scope does nothing unless joined"]
+pub struct SignalScope<'a> {
+    /// Children still running. Finished children are removed.
+    children: Vec<Child<'a>>,
+    /// The signal that cancelled the scope.
+    cancelled: Option<Signal>,
+}
+
+impl<'a> SignalScope<'a> {
+    /// Creates an empty scope.
+    pub fn new() -> SignalScope<'a> {
+        SignalScope {
+            children: Vec::new(),
+            cancelled: None,
+        }
+    }
+
+    /// Adds a child. It first runs when the scope is next joined.
+    ///
+    /// A child spawned into a cancelled scope is dropped right away.
+    pub fn spawn(&mut self, child: impl Future<Output = ()> + 'a) {
+        if self.cancelled.is_none() {
+            self.children.push(Box::pin(child));
+        }
+    }
+
+    /// Returns the number of children still running.
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Returns `true` if no children are running.
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Returns the signal that cancelled the scope, if any.
+    pub fn cancelled(&self) -> Option<Signal> {
+        self.cancelled
+    }
+
+    /// Checks for a signal, for synchronous sections of the scope's work.
+    ///
+    /// # Errors
+    ///
+    /// If a check propagates a signal, or the scope was already cancelled,
+    /// cancels the children and returns the signal.
+    pub fn check(&mut self) -> Result<(), Signal> {
+        if let Some(signal) = self.cancelled {
+            return Err(signal);
+        }
+        try_check_signal().inspect_err(|&signal| self.cancel_with(signal))
+    }
+
+    /// Cancels the scope with `signal`, dropping every child.
+    pub fn cancel_with(&mut self, signal: Signal) {
+        self.cancelled.get_or_insert(signal);
+        self.children.clear();
+    }
+
+    /// Runs the children until all have finished.
+    ///
+    /// # Errors
+    ///
+    /// Returns the signal if the scope is cancelled first; the children that
+    /// had not finished are dropped.
+    pub fn join(&mut self) -> ScopeJoin<'_, 'a> {
+        ScopeJoin { scope: self }
+    }
+
+    fn poll_children(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.children
+            .retain_mut(|child| child.as_mut().poll(cx).is_pending());
+        if self.children.is_empty() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl fmt::Debug for SignalScope<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignalScope")
+            .field("children", &self.children.len())
+            .field("cancelled", &self.cancelled)
+            .finish()
+    }
+}
+
+/// The future returned by [`SignalScope::join`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct ScopeJoin<'s, 'a> {
+    scope: &'s mut SignalScope<'a>,
+}
+
+impl Future for ScopeJoin<'_, '_> {
+    type Output = Result<(), Signal>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Signal>> {
+        let scope = &mut *self.get_mut().scope;
+        if let Some(signal) = scope.cancelled {
+            return Poll::Ready(Err(signal));
+        }
+        #[cfg(feature = "futures-channel")]
+        let result = crate::channel::poll_signal_aware(cx, |cx| scope.poll_children(cx));
+        #[cfg(not(feature = "futures-channel"))]
+        let result = match try_check_signal() {
+            Ok(()) => scope.poll_children(cx).map(Ok),
+            Err(signal) => Poll::Ready(Err(signal)),
+        };
+        match result {
+            Poll::Ready(Err(signal)) => {
+                scope.cancel_with(signal);
+                Poll::Ready(Err(signal))
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal};
+    use core::cell::Cell;
+    use core::future::{pending, ready};
+    use core::pin::pin;
+    use core::task::Waker;
+
+    /// Drops when its child is cancelled.
+    struct DropFlag<'a>(&'a Cell<u32>);
+
+    impl Drop for DropFlag<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_signal_scope() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        let mut cx = Context::from_waker(Waker::noop());
+        let dropped = Cell::new(0);
+
+        let mut scope = SignalScope::new();
+        scope.spawn(ready(()));
+        for _ in 0..2 {
+            let flag = DropFlag(&dropped);
+            scope.spawn(async move {
+                let _flag = flag;
+                pending::<()>().await;
+            });
+        }
+        assert_eq!(pin!(scope.join()).poll(&mut cx), Poll::Pending);
+        assert_eq!(scope.len(), 2);
+        assert_eq!(dropped.get(), 0);
+
+        // A signal cancels every child, and the scope stays cancelled
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(
+            pin!(scope.join()).poll(&mut cx),
+            Poll::Ready(Err(Signal::CANCEL))
+        );
+        assert_eq!(dropped.get(), 2);
+        assert!(scope.is_empty());
+        assert_eq!(scope.check(), Err(Signal::CANCEL));
+        scope.spawn(ready(()));
+        assert!(scope.is_empty());
+
+        // Dropping a scope cancels its children too
+        let mut scope = SignalScope::new();
+        let flag = DropFlag(&dropped);
+        scope.spawn(async move {
+            let _flag = flag;
+            pending::<()>().await;
+        });
+        assert_eq!(scope.check(), Ok(()));
+        drop(scope);
+        assert_eq!(dropped.get(), 3);
+        #[cfg(feature = "futures-channel")]
+        crate::channel::reset();
+    }
+}
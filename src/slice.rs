@@ -0,0 +1,118 @@
+//! Processing large slices in interruptible chunks.
+//!
+//! Checking for a signal on every element of a large buffer costs more than
+//! the work in tight loops, and checking only once leaves the loop
+//! uncancellable. The methods of [`SignalSliceExt`] split the slice into
+//! chunks and check before each one.
+
+use crate::{try_check_signal, Signal};
+
+/// Chunked, signal-aware processing of slices.
+pub trait SignalSliceExt<T> {
+    /// Calls `f` on consecutive chunks of `chunk_size` elements (the last
+    /// may be shorter), checking for a signal before each chunk. A
+    /// `chunk_size` of `0` is treated as `1`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the signal if a check propagates one; the remaining chunks
+    /// are not processed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wasm_signal_handler::{Signal, SignalSliceExt};
+    ///
+    /// fn checksum(data: &[u8]) -> Result<u32, Signal> {
+    ///     let mut sum = 0u32;
+    ///     data.process_chunks_interruptible(64 * 1024, |chunk| {
+    ///         sum = chunk.iter().fold(sum, |sum, &b| sum.wrapping_add(u32::from(b)));
+    ///     })?;
+    ///     Ok(sum)
+    /// }
+    /// ```
+    fn process_chunks_interruptible<F>(&self, chunk_size: usize, f: F) -> Result<(), Signal>
+    where
+        F: FnMut(&[T]);
+
+    /// Folds consecutive chunks of `chunk_size` elements into an
+    /// accumulator, checking for a signal before each chunk. A `chunk_size`
+    /// of `0` is treated as `1`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the signal if a check propagates one; the partial
+    /// accumulator is dropped.
+    fn fold_chunks_interruptible<B, F>(
+        &self,
+        chunk_size: usize,
+        init: B,
+        f: F,
+    ) -> Result<B, Signal>
+    where
+        F: FnMut(B, &[T]) -> B;
+}
+
+impl<T> SignalSliceExt<T> for [T] {
+    fn process_chunks_interruptible<F>(&self, chunk_size: usize, mut f: F) -> Result<(), Signal>
+    where
+        F: FnMut(&[T]),
+    {
+        for chunk in self.chunks(chunk_size.max(1)) {
+            try_check_signal()?;
+            f(chunk);
+        }
+        Ok(())
+    }
+
+    fn fold_chunks_interruptible<B, F>(
+        &self,
+        chunk_size: usize,
+        init: B,
+        mut f: F,
+    ) -> Result<B, Signal>
+    where
+        F: FnMut(B, &[T]) -> B,
+    {
+        let mut acc = init;
+        for chunk in self.chunks(chunk_size.max(1)) {
+            try_check_signal()?;
+            acc = f(acc, chunk);
+        }
+        Ok(acc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal};
+
+    #[test]
+    fn test_chunks_interruptible() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        let data = [1u32, 2, 3, 4, 5, 6, 7];
+
+        let sum =
+            data.fold_chunks_interruptible(3, 0, |acc, chunk| acc + chunk.iter().sum::<u32>());
+        assert_eq!(sum, Ok(28));
+        let mut chunks = 0;
+        assert!(data
+            .process_chunks_interruptible(0, |_| chunks += 1)
+            .is_ok());
+        assert_eq!(chunks, 7);
+
+        // A signal raised by one chunk stops the next
+        let mut seen = 0;
+        let result = data.process_chunks_interruptible(2, |chunk| {
+            seen += chunk.len();
+            set_signal(Signal::CANCEL.code());
+        });
+        assert_eq!(result, Err(Signal::CANCEL));
+        assert_eq!(seen, 2);
+        clear_signal();
+    }
+}
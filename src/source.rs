@@ -0,0 +1,116 @@
+//! Tagging signals with the subsystem that raised them.
+//!
+//! Next to the signal word, the guest exports a **source** word (at
+//! `WASM_SIGNAL_SOURCE_ADDR`) where hosts may write an identifier for the
+//! sender — say, `1` for the runtime's CPU limiter and `2` for an operator —
+//! *before* writing the signal word. When a check observes the signal it
+//! consumes the source word, and handlers read the tag back with
+//! [`Signal::source`](crate::Signal::source) to treat operator-initiated
+//! cancellation differently from automatic limits.
+//!
+//! The meaning of source IDs is up to the host; `0` means untagged. The two
+//! words are not written atomically together, so if a second signal replaces
+//! the first before the guest observes it, the last written source wins.
+
+use core::sync::atomic::{AtomicU32, AtomicU64};
+
+use crate::{Signal, ACQUIRE, ACQ_REL, RELEASE};
+
+static SOURCE: AtomicU32 = AtomicU32::new(0);
+
+/// The last observed signal code (high half) and its source (low half).
+static OBSERVED: AtomicU64 = AtomicU64::new(0);
+
+/// The exported address of the source word.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_SOURCE_ADDR: &AtomicU32 = &SOURCE;
+
+/// Tags the next signal with `source`, as a host does before raising it.
+///
+/// Guest components that raise signals themselves (see
+/// [`raise_signal`](crate::raise_signal)) use this to identify themselves.
+pub fn set_signal_source(source: u32) {
+    SOURCE.store(source, RELEASE);
+}
+
+/// Returns the source recorded when `signal` was last observed.
+pub(crate) fn source_of(signal: Signal) -> Option<u32> {
+    let observed = OBSERVED.load(ACQUIRE);
+    let source = observed as u32;
+    if (observed >> 32) as u32 == signal.code() && source != 0 {
+        Some(source)
+    } else {
+        None
+    }
+}
+
+/// Consumes the source word on behalf of `signal`.
+pub(crate) fn observe(signal: Signal) {
+    let source = SOURCE.swap(0, ACQ_REL);
+    OBSERVED.store(((signal.code() as u64) << 32) | source as u64, RELEASE);
+}
+
+/// Clears the source word and the recorded source.
+pub(crate) fn reset() {
+    SOURCE.store(0, RELEASE);
+    OBSERVED.store(0, RELEASE);
+}
+
+/// The source words, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    source: u32,
+    observed: u64,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        source: SOURCE.load(ACQUIRE),
+        observed: OBSERVED.load(ACQUIRE),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    SOURCE.store(saved.source, RELEASE);
+    OBSERVED.store(saved.observed, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{
+        clear_signal, clear_signal_handler, set_signal, set_signal_handler, try_check_signal,
+    };
+
+    const OPERATOR: u32 = 2;
+
+    #[test]
+    fn test_handler_sees_source() {
+        let _guard = lock();
+        clear_signal();
+        reset();
+        set_signal_handler(|signal| match signal.source() {
+            // Operators may cancel; automatic limits are retried
+            Some(OPERATOR) => Err(signal),
+            _ => Ok(()),
+        });
+
+        set_signal_source(OPERATOR);
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert_eq!(Signal::CANCEL.source(), Some(OPERATOR));
+        assert_eq!(Signal::TERMINATE.source(), None);
+
+        // The tag is consumed with the signal it was written for
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(Signal::CANCEL.source(), None);
+
+        clear_signal_handler();
+    }
+}
@@ -0,0 +1,172 @@
+//! Guest-to-host status words.
+//!
+//! Alongside the signal word, the guest publishes two words that hosts can
+//! read to implement escalation policies:
+//!
+//! - The **ack** word holds the code of the last signal observed by a check,
+//!   so the host knows its request reached the guest. Hosts write `0` to it
+//!   before raising a signal. Threaded guests also wake host waiters on it.
+//! - The **heartbeat** word is a counter the guest bumps with [`heartbeat`] at
+//!   progress points, so the host can tell a guest that is winding down from
+//!   one that is stuck.
+//!
+//! Their addresses are exported like the signal word's, as
+//! `WASM_SIGNAL_ACK_ADDR` and `WASM_SIGNAL_HEARTBEAT_ADDR`.
+//!
+//! Optionally, checks are also counted (see [`set_count_checks`]), so
+//! diagnostics can tell how long ago the guest last saw a signal. The last
+//! few acknowledged signals are kept for diagnostics too, in
+//! [`signal_history`].
+
+use core::sync::atomic::AtomicU32;
+
+use crate::{arm, disarm, events, Signal, ACQUIRE, ACQ_REL, ARMED, ARMED_COUNT, RELEASE};
+
+static ACK: AtomicU32 = AtomicU32::new(0);
+static HEARTBEAT: AtomicU32 = AtomicU32::new(0);
+
+/// Checks since the last acknowledged signal, while counting is enabled.
+static CHECKS: AtomicU32 = AtomicU32::new(0);
+
+/// The number of acknowledged signals [`signal_history`] keeps.
+pub const SIGNAL_HISTORY_LEN: usize = 8;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_SIGNAL: AtomicU32 = AtomicU32::new(0);
+
+/// The last acknowledged codes, as a ring indexed by `HISTORY_NEXT`.
+static HISTORY: [AtomicU32; SIGNAL_HISTORY_LEN] = [NO_SIGNAL; SIGNAL_HISTORY_LEN];
+static HISTORY_NEXT: AtomicU32 = AtomicU32::new(0);
+
+/// The exported address of the ack word.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_ACK_ADDR: &AtomicU32 = &ACK;
+
+/// The exported address of the heartbeat word.
+#[no_mangle]
+#[used]
+pub static WASM_SIGNAL_HEARTBEAT_ADDR: &AtomicU32 = &HEARTBEAT;
+
+/// Bumps the heartbeat counter, signalling progress to the host.
+#[inline]
+pub fn heartbeat() {
+    HEARTBEAT.fetch_add(1, RELEASE);
+}
+
+/// Returns the current heartbeat counter. It wraps on overflow.
+pub fn heartbeat_count() -> u32 {
+    HEARTBEAT.load(ACQUIRE)
+}
+
+/// Returns the last signal acknowledged by a check, if any.
+pub fn last_ack() -> Option<Signal> {
+    Signal::new(ACK.load(ACQUIRE))
+}
+
+/// Records that a check observed `signal`.
+pub(crate) fn acknowledge(signal: Signal) {
+    ACK.store(signal.code(), RELEASE);
+    CHECKS.store(0, RELEASE);
+    let slot = HISTORY_NEXT.fetch_add(1, ACQ_REL) as usize % SIGNAL_HISTORY_LEN;
+    HISTORY[slot].store(signal.code(), RELEASE);
+    events::post_event(events::EVENT_SIGNAL_ACKNOWLEDGED, signal.code());
+    // Wakes hosts waiting for the ack with `Atomics.waitAsync`
+    #[cfg(all(target_arch = "wasm32", target_feature = "atomics"))]
+    // SAFETY: ACK is a valid, aligned 32-bit atomic for the program's lifetime
+    unsafe {
+        core::arch::wasm32::memory_atomic_notify(ACK.as_ptr() as *mut i32, u32::MAX);
+    }
+}
+
+/// Returns the last [`SIGNAL_HISTORY_LEN`] signals acknowledged by checks,
+/// most recent first.
+///
+/// Unlike the events the host drains, the history is never consumed, so
+/// panic reports and other diagnostics can read it at any time.
+pub fn signal_history() -> impl Iterator<Item = Signal> {
+    let next = HISTORY_NEXT.load(ACQUIRE) as usize;
+    (1..=SIGNAL_HISTORY_LEN)
+        .map(move |back| next.wrapping_sub(back) % SIGNAL_HISTORY_LEN)
+        .map_while(|slot| Signal::new(HISTORY[slot].load(ACQUIRE)))
+}
+
+/// Enables or disables counting checks.
+///
+/// Counting takes every check off the fast path, so it is off by default.
+/// Enabling it restarts the count.
+pub fn set_count_checks(enabled: bool) {
+    if enabled {
+        CHECKS.store(0, RELEASE);
+        arm(ARMED_COUNT);
+    } else {
+        disarm(ARMED_COUNT);
+    }
+}
+
+/// Returns the number of checks since the last acknowledged signal, or
+/// since counting was enabled. It saturates instead of wrapping.
+///
+/// Returns `None` unless counting is enabled with [`set_count_checks`].
+pub fn checks_since_last_signal() -> Option<u32> {
+    if ARMED.load(ACQUIRE) & ARMED_COUNT == 0 {
+        return None;
+    }
+    Some(CHECKS.load(ACQUIRE))
+}
+
+/// Counts a check.
+pub(crate) fn count_check() {
+    let _ = CHECKS.try_update(RELEASE, ACQUIRE, |checks| checks.checked_add(1));
+}
+
+/// Clears the ack word, the heartbeat, the check count and the signal
+/// history, for `reset_all`.
+pub(crate) fn reset() {
+    ACK.store(0, RELEASE);
+    HEARTBEAT.store(0, RELEASE);
+    CHECKS.store(0, RELEASE);
+    for slot in &HISTORY {
+        slot.store(0, RELEASE);
+    }
+    HISTORY_NEXT.store(0, RELEASE);
+}
+
+/// Clears the ack word, as a host does before raising a signal.
+#[cfg(feature = "testing")]
+pub(crate) fn clear_ack() {
+    ACK.store(0, RELEASE);
+}
+
+/// The status words and signal history, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    ack: u32,
+    heartbeat: u32,
+    checks: u32,
+    history: [u32; SIGNAL_HISTORY_LEN],
+    history_next: u32,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        ack: ACK.load(ACQUIRE),
+        heartbeat: HEARTBEAT.load(ACQUIRE),
+        checks: CHECKS.load(ACQUIRE),
+        history: core::array::from_fn(|slot| HISTORY[slot].load(ACQUIRE)),
+        history_next: HISTORY_NEXT.load(ACQUIRE),
+    }
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    ACK.store(saved.ack, RELEASE);
+    HEARTBEAT.store(saved.heartbeat, RELEASE);
+    CHECKS.store(saved.checks, RELEASE);
+    for (slot, &code) in HISTORY.iter().zip(&saved.history) {
+        slot.store(code, RELEASE);
+    }
+    HISTORY_NEXT.store(saved.history_next, RELEASE);
+}
@@ -0,0 +1,448 @@
+//! Backing storage for the signal word.
+//!
+//! By default the signal word is the `SIGNAL` atomic in linear memory, whose
+//! address is exported through `WASM_SIGNAL_ADDR`.
+//!
+//! With the `global-storage` feature on wasm32, the signal word is instead a
+//! mutable WebAssembly global exported as `WASM_SIGNAL_GLOBAL`, which hosts
+//! write through `instance.exports.WASM_SIGNAL_GLOBAL.value`. This suits
+//! embedders that sandbox linear memory access but allow global writes.
+//!
+//! All accesses to the signal word go through the functions in this module,
+//! so the check functions work identically with either backend.
+//!
+//! In linear memory, the word is the middle of a [`SignalBlock`]: it follows
+//! [`BLOCK_MAGIC`](crate::BLOCK_MAGIC) and is followed by a check word, which
+//! writers set to `code ^ BLOCK_MAGIC` before writing a non-zero code. With
+//! the `validate-signal` feature, checks verify both before acting on a
+//! code, so a host writing through an address from a different build of the
+//! module fails loudly instead of delivering whatever its write landed on.
+//!
+//! With the `control-memory` feature on wasm32, the block is instead read
+//! and written through the exported `__wasm_signal_control_load` and
+//! `__wasm_signal_control_store` functions, which take the byte offset of a
+//! word in the block. As compiled, they access the block in linear memory.
+//! The `wasm-signal-control-memory` tool moves it to a secondary memory, as
+//! allowed by the multi-memory proposal, by rewriting both functions (see
+//! the README). Hosts can then be granted write access to that memory
+//! alone, and only read access to the heap.
+
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::Ordering;
+
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+use crate::{BLOCK_MAGIC, SIGNAL};
+
+/// The signal word with its validation header and check word.
+///
+/// The exported address points at `word`, so hosts that only write the word
+/// keep working; the layout around it is read by the host helpers and must
+/// not change.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+#[repr(C)]
+pub(crate) struct SignalBlock {
+    magic: AtomicU32,
+    pub(crate) word: AtomicU32,
+    check: AtomicU32,
+}
+
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+impl SignalBlock {
+    pub(crate) const fn new() -> SignalBlock {
+        SignalBlock {
+            magic: AtomicU32::new(BLOCK_MAGIC),
+            word: AtomicU32::new(0),
+            check: AtomicU32::new(BLOCK_MAGIC),
+        }
+    }
+}
+
+/// Accesses to the words of the signal block, where they are.
+#[cfg(not(any(
+    all(feature = "global-storage", target_arch = "wasm32"),
+    all(feature = "control-memory", target_arch = "wasm32")
+)))]
+mod cell {
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    #[inline(always)]
+    pub(super) fn load(cell: &AtomicU32, order: Ordering) -> u32 {
+        cell.load(order)
+    }
+
+    #[inline(always)]
+    pub(super) fn store(cell: &AtomicU32, value: u32, order: Ordering) {
+        cell.store(value, order)
+    }
+
+    #[inline(always)]
+    pub(super) fn swap(cell: &AtomicU32, value: u32, order: Ordering) -> u32 {
+        cell.swap(value, order)
+    }
+
+    #[inline(always)]
+    pub(super) fn compare_exchange(
+        cell: &AtomicU32,
+        current: u32,
+        new: u32,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<u32, u32> {
+        cell.compare_exchange(current, new, success, failure)
+    }
+}
+
+// The control memory is not shared, so, as with the global backend, the
+// words need no atomic instructions, and read-modify-write operations
+// cannot be interleaved with host writes. The accessors are never inlined,
+// so the tool finds every access in their bodies, and their volatile
+// accesses keep calls from being merged or hoisted out of loops.
+
+/// Accesses to the words of the signal block, through the functions the
+/// `wasm-signal-control-memory` tool rewrites.
+#[cfg(all(feature = "control-memory", target_arch = "wasm32"))]
+mod cell {
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    use super::{__wasm_signal_control_load, __wasm_signal_control_store};
+    use crate::SIGNAL;
+
+    /// Returns the byte offset of `cell` in the block.
+    #[inline(always)]
+    fn offset(cell: &AtomicU32) -> u32 {
+        (cell as *const AtomicU32 as usize - &SIGNAL as *const _ as usize) as u32
+    }
+
+    #[inline(always)]
+    pub(super) fn load(cell: &AtomicU32, _order: Ordering) -> u32 {
+        __wasm_signal_control_load(offset(cell))
+    }
+
+    #[inline(always)]
+    pub(super) fn store(cell: &AtomicU32, value: u32, _order: Ordering) {
+        __wasm_signal_control_store(offset(cell), value)
+    }
+
+    #[inline(always)]
+    pub(super) fn swap(cell: &AtomicU32, value: u32, order: Ordering) -> u32 {
+        let previous = load(cell, order);
+        store(cell, value, order);
+        previous
+    }
+
+    #[inline(always)]
+    pub(super) fn compare_exchange(
+        cell: &AtomicU32,
+        current: u32,
+        new: u32,
+        success: Ordering,
+        _failure: Ordering,
+    ) -> Result<u32, u32> {
+        let previous = load(cell, success);
+        if previous == current {
+            store(cell, new, success);
+            Ok(previous)
+        } else {
+            Err(previous)
+        }
+    }
+}
+
+/// Reads the word at byte `offset` in the signal block.
+#[cfg(all(feature = "control-memory", target_arch = "wasm32"))]
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn __wasm_signal_control_load(offset: u32) -> u32 {
+    // SAFETY: Offsets are those of the block's words
+    unsafe { core::ptr::read_volatile(SIGNAL.magic.as_ptr().byte_add(offset as usize)) }
+}
+
+/// Writes the word at byte `offset` in the signal block.
+#[cfg(all(feature = "control-memory", target_arch = "wasm32"))]
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn __wasm_signal_control_store(offset: u32, value: u32) {
+    // SAFETY: Offsets are those of the block's words
+    unsafe { core::ptr::write_volatile(SIGNAL.magic.as_ptr().byte_add(offset as usize), value) }
+}
+
+/// Returns `true` if the magic value before the signal word is intact.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+pub(crate) fn block_intact() -> bool {
+    cell::load(&SIGNAL.magic, crate::ACQUIRE) == BLOCK_MAGIC
+}
+
+/// Sets the check word for a write of `code`, ahead of the write itself.
+#[cfg(all(
+    feature = "validate-signal",
+    not(all(feature = "global-storage", target_arch = "wasm32"))
+))]
+#[inline(always)]
+fn seal(code: u32) {
+    if code != 0 {
+        cell::store(&SIGNAL.check, code ^ BLOCK_MAGIC, crate::RELEASE);
+    }
+}
+
+#[cfg(not(any(
+    feature = "validate-signal",
+    all(feature = "global-storage", target_arch = "wasm32")
+)))]
+#[inline(always)]
+fn seal(_code: u32) {}
+
+/// How many times [`validate`] reads a mismatched check word before it
+/// reports corruption.
+#[cfg(all(
+    feature = "validate-signal",
+    not(all(feature = "global-storage", target_arch = "wasm32"))
+))]
+const VALIDATE_ATTEMPTS: u32 = 4;
+
+/// Checks that the block around a non-zero `code` read from the signal word
+/// is intact.
+///
+/// # Panics
+///
+/// Panics if the magic value was overwritten, or if the check word does not
+/// match `code` on any of [`VALIDATE_ATTEMPTS`] reads and the word has not
+/// changed since.
+#[cfg(all(
+    feature = "validate-signal",
+    not(all(feature = "global-storage", target_arch = "wasm32"))
+))]
+#[track_caller]
+pub(crate) fn validate(code: u32) {
+    use crate::ACQUIRE;
+
+    let magic = cell::load(&SIGNAL.magic, ACQUIRE);
+    if magic != BLOCK_MAGIC {
+        panic!(
+            "signal block corrupted: magic {magic:#010x} at {:p}; is the host using a stale address?",
+            &SIGNAL.magic
+        );
+    }
+    // Writers set the check word before the code, and a compare-exchange
+    // that loses a race puts it back after, so a mismatch may be a write in
+    // progress: re-read both a few times, and only fail if the code is still
+    // the one that was read and never matched
+    for _ in 0..VALIDATE_ATTEMPTS {
+        if cell::load(&SIGNAL.check, ACQUIRE) == code ^ BLOCK_MAGIC
+            || cell::load(&SIGNAL.word, ACQUIRE) != code
+        {
+            return;
+        }
+        core::hint::spin_loop();
+    }
+    panic!(
+        "signal block corrupted: code {code:#010x} without a matching check word at {:p}; \
+         is the host using a stale address or an outdated helper?",
+        &SIGNAL.word
+    );
+}
+
+/// Without the `validate-signal` feature, nothing is validated.
+#[cfg(not(all(
+    feature = "validate-signal",
+    not(all(feature = "global-storage", target_arch = "wasm32"))
+)))]
+#[inline(always)]
+pub(crate) fn validate(_code: u32) {}
+
+/// Loads the signal word.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+#[inline(always)]
+pub(crate) fn load(order: Ordering) -> u32 {
+    cell::load(&SIGNAL.word, order)
+}
+
+/// Stores `value` into the signal word.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+#[inline(always)]
+pub(crate) fn store(value: u32, order: Ordering) {
+    seal(value);
+    cell::store(&SIGNAL.word, value, order)
+}
+
+/// Replaces the signal word with `value`, returning the previous value.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+#[inline(always)]
+pub(crate) fn swap(value: u32, order: Ordering) -> u32 {
+    seal(value);
+    cell::swap(&SIGNAL.word, value, order)
+}
+
+/// Replaces the signal word with `new` if it holds `current`.
+///
+/// Returns the previous value, as `Ok` if it was replaced. The check word is
+/// only sealed for `new` once the word was seen holding `current`, so an
+/// exchange that fails outright leaves it alone.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+#[inline(always)]
+pub(crate) fn compare_exchange(
+    current: u32,
+    new: u32,
+    success: Ordering,
+    failure: Ordering,
+) -> Result<u32, u32> {
+    let previous = cell::load(&SIGNAL.word, failure);
+    if previous != current {
+        return Err(previous);
+    }
+    seal(new);
+    let result = cell::compare_exchange(&SIGNAL.word, current, new, success, failure);
+    if let Err(previous) = result {
+        // Restore the check word of the code that stayed
+        seal(previous);
+    }
+    result
+}
+
+/// Blocks until the signal word may no longer hold `expected`.
+///
+/// With shared memory on wasm32 this is an atomic wait; elsewhere it yields
+/// to other threads (or just spins without `std`), so callers must re-check.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+#[inline]
+pub(crate) fn wait(expected: u32) {
+    #[cfg(all(target_arch = "wasm32", target_feature = "atomics"))]
+    // SAFETY: SIGNAL is a valid, aligned 32-bit atomic for the program's lifetime
+    unsafe {
+        core::arch::wasm32::memory_atomic_wait32(
+            SIGNAL.word.as_ptr() as *mut i32,
+            expected as i32,
+            -1,
+        );
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "atomics")))]
+    {
+        let _ = expected;
+        #[cfg(feature = "std")]
+        std::thread::yield_now();
+        #[cfg(not(feature = "std"))]
+        core::hint::spin_loop();
+    }
+}
+
+/// Wakes threads blocked in [`wait`] after the signal word was written.
+#[cfg(not(all(feature = "global-storage", target_arch = "wasm32")))]
+#[inline]
+pub(crate) fn notify() {
+    #[cfg(all(target_arch = "wasm32", target_feature = "atomics"))]
+    // SAFETY: SIGNAL is a valid, aligned 32-bit atomic for the program's lifetime
+    unsafe {
+        core::arch::wasm32::memory_atomic_notify(SIGNAL.word.as_ptr() as *mut i32, u32::MAX);
+    }
+}
+
+// Globals are not shared between threads, so the global backend needs no
+// atomic instructions: each access is a single `global.get`/`global.set`,
+// and read-modify-write operations cannot be interleaved with host writes,
+// which only happen while the guest is not executing. The asm blocks are not
+// `pure`, so the compiler treats them as side effects and never caches or
+// hoists a read of the global out of a loop. Each block repeats the
+// `.globaltype` declaration because the functions are inlined into other
+// crates' objects, where the symbol would otherwise be typed as data.
+
+#[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+core::arch::global_asm!(
+    ".globaltype WASM_SIGNAL_GLOBAL, i32",
+    ".globl WASM_SIGNAL_GLOBAL",
+    ".export_name WASM_SIGNAL_GLOBAL, WASM_SIGNAL_GLOBAL",
+    "WASM_SIGNAL_GLOBAL:",
+);
+
+/// Loads the signal word.
+#[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+#[inline(always)]
+pub(crate) fn load(_order: Ordering) -> u32 {
+    let value: u32;
+    // SAFETY: Reads the i32 global declared above
+    unsafe {
+        core::arch::asm!(
+            ".globaltype WASM_SIGNAL_GLOBAL, i32",
+            "global.get WASM_SIGNAL_GLOBAL",
+            "local.set {}",
+            out(local) value,
+            options(nostack),
+        );
+    }
+    value
+}
+
+/// Stores `value` into the signal word.
+#[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+#[inline(always)]
+pub(crate) fn store(value: u32, _order: Ordering) {
+    // SAFETY: Writes the i32 global declared above
+    unsafe {
+        core::arch::asm!(
+            ".globaltype WASM_SIGNAL_GLOBAL, i32",
+            "local.get {}",
+            "global.set WASM_SIGNAL_GLOBAL",
+            in(local) value,
+            options(nostack),
+        );
+    }
+}
+
+/// Replaces the signal word with `value`, returning the previous value.
+#[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+#[inline(always)]
+pub(crate) fn swap(value: u32, order: Ordering) -> u32 {
+    let previous = load(order);
+    store(value, order);
+    previous
+}
+
+/// Nothing can wait on a global, so there is no one to wake.
+#[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+#[inline(always)]
+pub(crate) fn notify() {}
+
+/// Replaces the signal word with `new` if it holds `current`.
+///
+/// Returns the previous value, as `Ok` if it was replaced.
+#[cfg(all(feature = "global-storage", target_arch = "wasm32"))]
+#[inline(always)]
+pub(crate) fn compare_exchange(
+    current: u32,
+    new: u32,
+    success: Ordering,
+    _failure: Ordering,
+) -> Result<u32, u32> {
+    let previous = load(success);
+    if previous == current {
+        store(new, success);
+        Ok(previous)
+    } else {
+        Err(previous)
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "validate-signal",
+    not(all(feature = "global-storage", target_arch = "wasm32"))
+))]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, try_check_signal, Signal};
+    use crate::{ACQUIRE, ACQ_REL};
+
+    #[test]
+    fn test_failed_exchange_keeps_check_word() {
+        let _guard = lock();
+        clear_signal_handler();
+        set_signal(7);
+
+        // The word holds 7, so this exchange must not seal 9
+        assert_eq!(compare_exchange(0, 9, ACQ_REL, ACQUIRE), Err(7));
+        assert_eq!(SIGNAL.check.load(ACQUIRE), 7 ^ BLOCK_MAGIC);
+        assert_eq!(try_check_signal(), Err(Signal::new(7).unwrap()));
+        clear_signal();
+    }
+}
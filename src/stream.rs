@@ -0,0 +1,162 @@
+//! Interrupting `futures` streams on cancellation.
+//!
+//! Long-running stream pipelines, such as reading a request body or consuming
+//! a queue, only notice cancellation if something checks for it between
+//! items. [`SignalStreamExt`] adds adapters that do, ending the stream with
+//! `Err(Signal)` once a check propagates a signal.
+
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::{FusedStream, Stream};
+
+use crate::{try_check_signal, Signal};
+
+/// Signal-aware adapters for [`Stream`]s.
+pub trait SignalStreamExt: Stream + Sized {
+    /// Checks for a signal before every item.
+    ///
+    /// Items are wrapped in `Ok`. If a check propagates a signal, the stream
+    /// yields it as `Err` and then ends, without polling the inner stream
+    /// again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use futures_core::Stream;
+    /// use wasm_signal_handler::{Signal, SignalStreamExt};
+    ///
+    /// fn body_chunks(body: impl Stream<Item = Vec<u8>>) -> impl Stream<Item = Result<Vec<u8>, Signal>> {
+    ///     body.interruptible()
+    /// }
+    /// ```
+    fn interruptible(self) -> Interruptible<Self> {
+        self.check_signals_every(1)
+    }
+
+    /// Like [`interruptible`](SignalStreamExt::interruptible), but checks
+    /// only before every `n`th item, for streams of many small items.
+    ///
+    /// A check also runs on every poll until the next item arrives, so a
+    /// stream waiting on its source still notices a signal when woken. An `n`
+    /// of `0` is treated as `1`.
+    fn check_signals_every(self, n: u32) -> Interruptible<Self> {
+        Interruptible {
+            stream: self,
+            every: n.max(1),
+            count: 0,
+            done: false,
+        }
+    }
+}
+
+impl<S: Stream> SignalStreamExt for S {}
+
+/// A stream that ends with `Err(Signal)` on cancellation; see
+/// [`SignalStreamExt`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Interruptible<S> {
+    stream: S,
+    every: u32,
+    /// Items since the last check.
+    count: u32,
+    done: bool,
+}
+
+impl<S> Interruptible<S> {
+    /// Returns the inner stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Stream> Stream for Interruptible<S> {
+    type Item = Result<S::Item, Signal>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // SAFETY: `stream` is structurally pinned and never moved out while
+        // pinned; the other fields are plain data
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.done {
+            return Poll::Ready(None);
+        }
+        if this.count == 0 {
+            if let Err(signal) = try_check_signal() {
+                this.done = true;
+                return Poll::Ready(Some(Err(signal)));
+            }
+        }
+        // SAFETY: See above
+        let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        match stream.poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                this.count = (this.count + 1) % this.every;
+                Poll::Ready(Some(Ok(item)))
+            }
+            Poll::Ready(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        // A signal can end the stream early, or add an item
+        let (_, upper) = self.stream.size_hint();
+        (0, upper.and_then(|upper| upper.checked_add(1)))
+    }
+}
+
+impl<S: Stream> FusedStream for Interruptible<S> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal};
+    use core::task::Waker;
+
+    /// Yields 0, 1, 2, ... forever.
+    struct Counter(u32);
+
+    impl Stream for Counter {
+        type Item = u32;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<u32>> {
+            self.0 += 1;
+            Poll::Ready(Some(self.0 - 1))
+        }
+    }
+
+    fn next<S: Stream + Unpin>(stream: &mut S) -> Poll<Option<S::Item>> {
+        Pin::new(stream).poll_next(&mut Context::from_waker(Waker::noop()))
+    }
+
+    #[test]
+    fn test_interruptible_stream() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        let mut stream = Counter(0).check_signals_every(2);
+        assert_eq!(next(&mut stream), Poll::Ready(Some(Ok(0))));
+        // Only checked before every other item
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(next(&mut stream), Poll::Ready(Some(Ok(1))));
+        assert_eq!(next(&mut stream), Poll::Ready(Some(Err(Signal::CANCEL))));
+        assert_eq!(next(&mut stream), Poll::Ready(None));
+        assert!(stream.is_terminated());
+
+        let mut stream = Counter(0).interruptible();
+        assert_eq!(next(&mut stream), Poll::Ready(Some(Ok(0))));
+    }
+}
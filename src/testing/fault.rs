@@ -0,0 +1,110 @@
+//! Randomized fault injection.
+
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicU64, AtomicUsize};
+
+use crate::{arm, disarm, storage, ACQUIRE, ACQ_REL, ARMED_FAULT, RELEASE};
+
+/// Configuration for [`enable_fault_injection`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FaultConfig {
+    /// Seed for the generator. The same seed and sequence of checks always
+    /// produce the same faults.
+    pub seed: u64,
+    /// Each check raises a signal with probability `1 / one_in`. `0` and `1`
+    /// both fault on every check.
+    pub one_in: u32,
+    /// Codes to choose from, uniformly. Must not be empty or contain `0`.
+    pub codes: &'static [u32],
+}
+
+/// Splitmix64 state.
+static STATE: AtomicU64 = AtomicU64::new(0);
+static ONE_IN: AtomicU32 = AtomicU32::new(0);
+/// The code table; `CODES_LEN` is published last, like a scenario.
+static CODES_PTR: AtomicPtr<u32> = AtomicPtr::new(core::ptr::null_mut());
+static CODES_LEN: AtomicUsize = AtomicUsize::new(0);
+static INJECTED: AtomicU64 = AtomicU64::new(0);
+
+/// Makes every check raise a random signal from `config.codes` with
+/// probability `1 / config.one_in`, as if the host had written it.
+///
+/// Use this to fuzz cancellation safety: run a workload many times under
+/// different seeds and assert its invariants hold wherever it was
+/// interrupted. A failing seed reproduces the same interruptions.
+///
+/// # Panics
+///
+/// Panics if `config.codes` is empty or contains `0`.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::testing::{disable_fault_injection, enable_fault_injection, FaultConfig};
+/// use wasm_signal_handler::{try_check_signal, Signal};
+///
+/// for seed in 0..100 {
+///     enable_fault_injection(FaultConfig { seed, one_in: 50, codes: &[1, 2] });
+///     let mut steps = 0;
+///     let result: Result<(), Signal> = (|| {
+///         for _ in 0..1000 {
+///             try_check_signal()?;
+///             steps += 1;
+///         }
+///         Ok(())
+///     })();
+///     // Assert invariants hold for `steps` completed steps...
+///     let _ = result;
+/// }
+/// disable_fault_injection();
+/// ```
+pub fn enable_fault_injection(config: FaultConfig) {
+    assert!(
+        !config.codes.is_empty() && !config.codes.contains(&0),
+        "fault injection codes must be non-empty and non-zero"
+    );
+    CODES_LEN.store(0, RELEASE);
+    STATE.store(config.seed, RELEASE);
+    ONE_IN.store(config.one_in.max(1), RELEASE);
+    INJECTED.store(0, RELEASE);
+    CODES_PTR.store(config.codes.as_ptr() as *mut u32, RELEASE);
+    CODES_LEN.store(config.codes.len(), RELEASE);
+    arm(ARMED_FAULT);
+}
+
+/// Stops injecting faults. A signal that was already raised stays pending.
+pub fn disable_fault_injection() {
+    disarm(ARMED_FAULT);
+    CODES_LEN.store(0, RELEASE);
+}
+
+/// Returns the number of signals injected since fault injection was enabled.
+pub fn faults_injected() -> u64 {
+    INJECTED.load(ACQUIRE)
+}
+
+/// Returns the next pseudo-random number (splitmix64).
+fn next() -> u64 {
+    let mut z = STATE
+        .fetch_add(0x9E37_79B9_7F4A_7C15, ACQ_REL)
+        .wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Called from the check slow path while fault injection is enabled, before
+/// the signal word is read.
+///
+/// Returns `true` if a signal was raised.
+pub(crate) fn on_check() -> bool {
+    let len = CODES_LEN.load(ACQUIRE);
+    if len == 0 || !next().is_multiple_of(u64::from(ONE_IN.load(ACQUIRE))) {
+        return false;
+    }
+    // SAFETY: CODES_PTR and CODES_LEN come from a `&'static [u32]`
+    let codes = unsafe { core::slice::from_raw_parts(CODES_PTR.load(ACQUIRE), len) };
+    let code = codes[(next() % len as u64) as usize];
+    storage::store(code, RELEASE);
+    INJECTED.fetch_add(1, ACQ_REL);
+    true
+}
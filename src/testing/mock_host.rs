@@ -0,0 +1,193 @@
+//! An in-process host simulator.
+
+use crate::{clear_signal, heartbeat_count, last_ack, set_signal, status, Signal};
+
+/// How a [`MockHost`] escalates when the guest does not respond.
+///
+/// Times are measured in host polls, so tests are deterministic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EscalationPolicy {
+    /// Polls to wait for an ack before escalating.
+    pub ack_timeout: u32,
+    /// The signal written when escalating.
+    pub escalate_to: Signal,
+    /// Polls without a heartbeat after escalating before giving up on the
+    /// guest.
+    pub kill_after: u32,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> EscalationPolicy {
+        EscalationPolicy {
+            ack_timeout: 3,
+            escalate_to: Signal::TERMINATE,
+            kill_after: 3,
+        }
+    }
+}
+
+/// The outcome of a [`MockHost::poll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostAction {
+    /// No signal is outstanding.
+    Idle,
+    /// Waiting for the guest to acknowledge the outstanding signal.
+    Waiting,
+    /// The guest acknowledged the outstanding signal.
+    Acknowledged(Signal),
+    /// The guest did not acknowledge in time; this signal was written.
+    Escalated(Signal),
+    /// The guest stopped making progress after escalation; a real host
+    /// would kill the instance now.
+    Kill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    Raised {
+        signal: Signal,
+        polls: u32,
+    },
+    Escalated {
+        signal: Signal,
+        polls: u32,
+        heartbeat: u32,
+    },
+    Killed,
+}
+
+/// Drives the host side of the protocol against the in-process statics, so
+/// host/guest interaction can be tested natively without a wasm runtime.
+///
+/// The host raises a signal, then polls: it reads the ack word and, if the
+/// guest does not acknowledge within [`EscalationPolicy::ack_timeout`] polls,
+/// writes [`EscalationPolicy::escalate_to`]. After escalating it watches the
+/// heartbeat word and reports [`HostAction::Kill`] once the guest has made
+/// no progress for [`EscalationPolicy::kill_after`] polls.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::testing::{HostAction, MockHost};
+/// use wasm_signal_handler::{try_check_signal, Signal};
+///
+/// let mut host = MockHost::new();
+/// host.raise(Signal::CANCEL);
+/// assert_eq!(host.poll(), HostAction::Waiting);
+///
+/// assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+/// assert_eq!(host.poll(), HostAction::Acknowledged(Signal::CANCEL));
+/// ```
+#[derive(Debug)]
+pub struct MockHost {
+    policy: EscalationPolicy,
+    state: State,
+}
+
+impl MockHost {
+    /// Creates a host with the default policy.
+    pub fn new() -> MockHost {
+        MockHost::with_policy(EscalationPolicy::default())
+    }
+
+    /// Creates a host with the given policy.
+    pub fn with_policy(policy: EscalationPolicy) -> MockHost {
+        MockHost {
+            policy,
+            state: State::Idle,
+        }
+    }
+
+    /// Returns the escalation policy.
+    pub fn policy(&self) -> EscalationPolicy {
+        self.policy
+    }
+
+    /// Raises `signal`: clears the ack word and writes the signal word.
+    pub fn raise(&mut self, signal: Signal) {
+        status::clear_ack();
+        set_signal(signal.code());
+        self.state = State::Raised { signal, polls: 0 };
+    }
+
+    /// Returns the last signal acknowledged by the guest.
+    pub fn ack(&self) -> Option<Signal> {
+        last_ack()
+    }
+
+    /// Returns the guest's heartbeat counter.
+    pub fn heartbeat(&self) -> u32 {
+        heartbeat_count()
+    }
+
+    /// Advances the host by one poll, applying the escalation policy.
+    pub fn poll(&mut self) -> HostAction {
+        match self.state {
+            State::Idle => HostAction::Idle,
+            State::Killed => HostAction::Kill,
+            State::Raised { signal, .. } | State::Escalated { signal, .. }
+                if last_ack() == Some(signal) =>
+            {
+                self.state = State::Idle;
+                HostAction::Acknowledged(signal)
+            }
+            State::Raised { signal, polls } => {
+                if polls < self.policy.ack_timeout {
+                    self.state = State::Raised {
+                        signal,
+                        polls: polls + 1,
+                    };
+                    return HostAction::Waiting;
+                }
+                let escalated = self.policy.escalate_to;
+                status::clear_ack();
+                set_signal(escalated.code());
+                self.state = State::Escalated {
+                    signal: escalated,
+                    polls: 0,
+                    heartbeat: heartbeat_count(),
+                };
+                HostAction::Escalated(escalated)
+            }
+            State::Escalated {
+                signal,
+                polls,
+                heartbeat,
+            } => {
+                let current = heartbeat_count();
+                if current != heartbeat {
+                    self.state = State::Escalated {
+                        signal,
+                        polls: 0,
+                        heartbeat: current,
+                    };
+                    return HostAction::Waiting;
+                }
+                if polls < self.policy.kill_after {
+                    self.state = State::Escalated {
+                        signal,
+                        polls: polls + 1,
+                        heartbeat,
+                    };
+                    return HostAction::Waiting;
+                }
+                self.state = State::Killed;
+                HostAction::Kill
+            }
+        }
+    }
+
+    /// Withdraws any outstanding signal and returns to idle.
+    pub fn reset(&mut self) {
+        clear_signal();
+        status::clear_ack();
+        self.state = State::Idle;
+    }
+}
+
+impl Default for MockHost {
+    fn default() -> MockHost {
+        MockHost::new()
+    }
+}
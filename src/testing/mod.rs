@@ -0,0 +1,356 @@
+//! Deterministic signal injection for tests.
+//!
+//! Injections raise a signal exactly as if the host had written it, at a
+//! chosen point in the sequence of checks, so tests can assert that a loop
+//! stops within a bounded number of iterations after cancellation:
+//!
+//! ```rust
+//! use wasm_signal_handler::testing::{clear_injections, inject_signal_after_checks};
+//! use wasm_signal_handler::{try_check_signal, Signal};
+//!
+//! fn process(items: &mut u32) -> Result<(), Signal> {
+//!     loop {
+//!         try_check_signal()?;
+//!         *items += 1;
+//!     }
+//! }
+//!
+//! let mut items = 0;
+//! inject_signal_after_checks(10, Signal::CANCEL.code());
+//! assert_eq!(process(&mut items), Err(Signal::CANCEL));
+//! assert_eq!(items, 10);
+//! clear_injections();
+//! ```
+//!
+//! Only checks made while an injection is armed are counted.
+//!
+//! [`enable_fault_injection`] instead raises signals at random, seeded
+//! points for fuzzing cancellation safety.
+//!
+//! [`start_recording`] captures the timeline of signals observed by checks,
+//! and [`replay`] re-applies it, to reproduce a cancellation at an exact
+//! point.
+//!
+//! [`ScopedSignalState`] isolates tests from each other's global state.
+//!
+//! [`MockHost`] simulates the host side of the protocol, including
+//! escalation, against the same in-process state.
+//!
+//! Requires the `testing` feature.
+
+use core::sync::atomic::{AtomicPtr, AtomicU32, AtomicU64, AtomicUsize};
+
+use crate::{arm, disarm, storage, ACQUIRE, ACQ_REL, ARMED_INJECT, RELEASE};
+
+pub(crate) mod fault;
+mod mock_host;
+mod scoped;
+pub(crate) mod timeline;
+
+pub use fault::{disable_fault_injection, enable_fault_injection, faults_injected, FaultConfig};
+pub use mock_host::{EscalationPolicy, HostAction, MockHost};
+pub use scoped::ScopedSignalState;
+pub use timeline::{replay, start_recording, stop_recording, Recording, TIMELINE_CAPACITY};
+
+/// A scripted signal: `code` is raised once `after_checks` checks have
+/// completed, so the next check observes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Step {
+    /// Number of completed checks before the signal is raised.
+    pub after_checks: u64,
+    /// The signal code to raise. `0` clears the signal, like a host write.
+    pub code: u32,
+}
+
+impl Step {
+    /// Creates a step raising `code` after `after_checks` checks.
+    pub const fn new(after_checks: u64, code: u32) -> Step {
+        Step { after_checks, code }
+    }
+}
+
+/// Checks counted since injection was armed.
+static CHECKS: AtomicU64 = AtomicU64::new(0);
+
+/// A single pending injection; `ONE_SHOT_CODE` is `0` when none is pending.
+static ONE_SHOT_AFTER: AtomicU64 = AtomicU64::new(0);
+static ONE_SHOT_CODE: AtomicU32 = AtomicU32::new(0);
+
+/// The running scenario and the index of its next step. `SCENARIO_LEN` is
+/// published last, so a reader that observes it also observes the pointer.
+static SCENARIO_PTR: AtomicPtr<Step> = AtomicPtr::new(core::ptr::null_mut());
+static SCENARIO_LEN: AtomicUsize = AtomicUsize::new(0);
+static SCENARIO_NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// Raises `code` once `n` further checks have completed.
+///
+/// Replaces any pending single injection and restarts the check count.
+/// Scenarios are not affected.
+pub fn inject_signal_after_checks(n: u64, code: u32) {
+    CHECKS.store(0, RELEASE);
+    ONE_SHOT_AFTER.store(n, RELEASE);
+    ONE_SHOT_CODE.store(code, RELEASE);
+    arm(ARMED_INJECT);
+}
+
+/// Runs a scripted scenario of signals at given check counts.
+///
+/// `steps` must be sorted by [`Step::after_checks`]; steps sharing a count
+/// are applied in order, so the last one wins. Replaces any running scenario
+/// and restarts the check count.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::testing::{clear_injections, run_scenario, Step};
+/// use wasm_signal_handler::{set_signal_handler, try_check_signal, Signal};
+///
+/// // A soft cancel the application survives, then a hard kill
+/// static SCENARIO: [Step; 2] = [Step::new(3, 2), Step::new(5, 1)];
+///
+/// set_signal_handler(|signal| match signal {
+///     Signal::CANCEL => Ok(()),
+///     _ => Err(signal),
+/// });
+/// run_scenario(&SCENARIO);
+/// let mut completed = 0;
+/// while try_check_signal().is_ok() {
+///     completed += 1;
+/// }
+/// assert_eq!(completed, 5);
+/// clear_injections();
+/// # wasm_signal_handler::clear_signal_handler();
+/// ```
+pub fn run_scenario(steps: &'static [Step]) {
+    SCENARIO_LEN.store(0, RELEASE);
+    CHECKS.store(0, RELEASE);
+    SCENARIO_NEXT.store(0, RELEASE);
+    SCENARIO_PTR.store(steps.as_ptr() as *mut Step, RELEASE);
+    SCENARIO_LEN.store(steps.len(), RELEASE);
+    arm(ARMED_INJECT);
+}
+
+/// Cancels pending injections and the running scenario.
+///
+/// A signal that was already raised stays pending.
+pub fn clear_injections() {
+    disarm(ARMED_INJECT);
+    ONE_SHOT_CODE.store(0, RELEASE);
+    SCENARIO_LEN.store(0, RELEASE);
+}
+
+/// Returns the number of checks counted since the last injection was armed.
+pub fn checks_observed() -> u64 {
+    CHECKS.load(ACQUIRE)
+}
+
+/// Called from the check slow path while injection is armed, before the
+/// signal word is read.
+///
+/// Returns `true` if a signal was raised.
+pub(crate) fn on_check() -> bool {
+    let completed = CHECKS.fetch_add(1, ACQ_REL);
+    let mut raised = false;
+
+    let code = ONE_SHOT_CODE.load(ACQUIRE);
+    if code != 0 && ONE_SHOT_AFTER.load(ACQUIRE) == completed {
+        ONE_SHOT_CODE.store(0, RELEASE);
+        storage::store(code, RELEASE);
+        raised = true;
+    }
+
+    let len = SCENARIO_LEN.load(ACQUIRE);
+    if len != 0 {
+        // SAFETY: SCENARIO_PTR and SCENARIO_LEN come from a `&'static [Step]`
+        let steps = unsafe { core::slice::from_raw_parts(SCENARIO_PTR.load(ACQUIRE), len) };
+        let mut next = SCENARIO_NEXT.load(ACQUIRE);
+        while let Some(step) = steps.get(next) {
+            if step.after_checks != completed {
+                break;
+            }
+            storage::store(step.code, RELEASE);
+            raised = true;
+            next += 1;
+        }
+        SCENARIO_NEXT.store(next, RELEASE);
+    }
+
+    raised
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, try_check_signal, Signal};
+
+    #[test]
+    fn test_inject_after_checks() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        inject_signal_after_checks(2, Signal::TERMINATE.code());
+        assert!(try_check_signal().is_ok());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        assert!(try_check_signal().is_ok());
+        assert_eq!(checks_observed(), 4);
+
+        clear_injections();
+    }
+
+    #[test]
+    fn test_scenario_steps() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        static STEPS: [Step; 3] = [Step::new(0, 2), Step::new(2, 3), Step::new(2, 1)];
+        run_scenario(&STEPS);
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert!(try_check_signal().is_ok());
+        // Later steps at the same count overwrite earlier ones
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+
+        clear_injections();
+    }
+
+    #[test]
+    fn test_mock_host_escalates_then_kills() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        crate::set_signal_handler(|signal| match signal {
+            // Ignore soft cancels entirely
+            Signal::CANCEL => Ok(()),
+            _ => Err(signal),
+        });
+
+        let mut host = MockHost::with_policy(EscalationPolicy {
+            ack_timeout: 1,
+            escalate_to: Signal::TERMINATE,
+            kill_after: 1,
+        });
+        host.raise(Signal::CANCEL);
+        assert_eq!(host.poll(), HostAction::Waiting);
+        assert_eq!(host.poll(), HostAction::Escalated(Signal::TERMINATE));
+
+        // The guest is still making progress, so the host keeps waiting
+        crate::heartbeat();
+        assert_eq!(host.poll(), HostAction::Waiting);
+        assert_eq!(host.poll(), HostAction::Waiting);
+        assert_eq!(host.poll(), HostAction::Kill);
+
+        host.raise(Signal::TERMINATE);
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        assert_eq!(host.ack(), Some(Signal::TERMINATE));
+        assert_eq!(host.poll(), HostAction::Acknowledged(Signal::TERMINATE));
+        assert_eq!(host.poll(), HostAction::Idle);
+
+        host.reset();
+        clear_signal_handler();
+    }
+
+    #[test]
+    fn test_fault_injection_is_deterministic() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        let run = || {
+            enable_fault_injection(FaultConfig {
+                seed: 42,
+                one_in: 4,
+                codes: &[1, 2],
+            });
+            let mut trace = [0u32; 64];
+            for slot in &mut trace {
+                *slot = try_check_signal().err().map_or(0, Signal::code);
+            }
+            disable_fault_injection();
+            trace
+        };
+
+        let first = run();
+        assert!(faults_injected() > 0);
+        assert!(first.iter().all(|&code| code <= 2));
+        assert_eq!(run(), first);
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        start_recording();
+        assert!(try_check_signal().is_ok());
+        crate::set_signal(2);
+        // A masked signal is recorded once, when first observed
+        let mask = crate::SignalMaskGuard::new();
+        assert!(try_check_signal().is_ok());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(mask.unmask(), Err(Signal::CANCEL));
+        crate::set_signal(1);
+        assert!(try_check_signal().is_err());
+        let recording = stop_recording();
+        assert_eq!(recording.steps(), &[Step::new(1, 2), Step::new(4, 1)]);
+
+        static REPLAY: [Step; 2] = [Step::new(1, 2), Step::new(3, 1)];
+        replay(&REPLAY);
+        let mut observed = [0u32; 4];
+        for slot in &mut observed {
+            *slot = try_check_signal().err().map_or(0, Signal::code);
+        }
+        assert_eq!(observed, [0, 2, 0, 1]);
+        clear_injections();
+    }
+
+    #[test]
+    fn test_scoped_state_restores() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        crate::set_signal_handler(|_| Ok(()));
+        crate::set_latch_mode(true);
+        crate::set_signal(2);
+
+        {
+            let _state = ScopedSignalState::new();
+            assert!(crate::peek_signal().is_none());
+            assert!(crate::get_signal_handler().is_none());
+            assert!(!crate::latch_mode());
+
+            crate::set_signal(1);
+            let _mask = core::mem::ManuallyDrop::new(crate::SignalMaskGuard::new());
+            inject_signal_after_checks(0, 3);
+        }
+
+        assert_eq!(crate::peek_signal(), Some(Signal::CANCEL));
+        assert!(crate::get_signal_handler().is_some());
+        assert!(crate::latch_mode());
+        assert!(!crate::signals_masked());
+        assert!(try_check_signal().is_ok());
+
+        crate::set_latch_mode(false);
+        clear_signal_handler();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_recording_serializes_as_steps() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        start_recording();
+        crate::set_signal(2);
+        assert!(try_check_signal().is_err());
+        let json = serde_json::to_string(&stop_recording()).unwrap();
+        assert_eq!(json, r#"[{"after_checks":0,"code":2}]"#);
+        let steps: [Step; 1] = serde_json::from_str(&json).unwrap();
+        assert_eq!(steps, [Step::new(0, 2)]);
+    }
+}
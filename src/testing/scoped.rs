@@ -0,0 +1,248 @@
+//! Saving and restoring the complete signal state around a test.
+
+use core::sync::atomic::AtomicBool;
+
+use crate::{
+    advisor, checkpoint, clear_panic_formatter, clear_signal_handler, clock, deadline, epoch,
+    events, filter, gap, get_panic_formatter, get_signal_handler, grace, latch, lifecycle,
+    listeners, mailbox, mask, names, namespaced, policy, progress, recheck_depth,
+    set_panic_formatter, set_recheck_depth, set_signal_handler, shutdown, source, status, storage,
+    validator, PanicFormatter, SignalHandler, ACQUIRE, ARMED, ARMED_FAULT, ARMED_INITIAL,
+    ARMED_INJECT, ARMED_RECORD, RELEASE,
+};
+
+/// Serializes [`ScopedSignalState`]s, across threads.
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+/// Injection bits; injections are never carried across a scope boundary.
+const ARMED_TESTING: u32 = ARMED_INJECT | ARMED_FAULT | ARMED_RECORD;
+
+/// The complete signal state.
+#[derive(Default)]
+struct Saved {
+    signal: u32,
+    armed: u32,
+    handler: Option<SignalHandler>,
+    panic_formatter: Option<PanicFormatter>,
+    recheck_depth: u32,
+    advisor: advisor::Saved,
+    checkpoint: checkpoint::Saved,
+    clock: clock::Saved,
+    deadline: deadline::Saved,
+    epoch: epoch::Saved,
+    events: events::Saved,
+    filter: filter::Saved,
+    gap: gap::Saved,
+    grace: grace::Saved,
+    #[cfg(feature = "std")]
+    heatmap: crate::heatmap::Saved,
+    latch: latch::Saved,
+    lifecycle: lifecycle::Saved,
+    listeners: listeners::Saved,
+    mailbox: mailbox::Saved,
+    mask: mask::Saved,
+    names: names::Saved,
+    namespaced: namespaced::Saved,
+    policy: policy::Saved,
+    #[cfg(feature = "host-poll")]
+    poll: crate::poll::Saved,
+    #[cfg(feature = "js-listener")]
+    js_listener: crate::js_listener::Saved,
+    #[cfg(feature = "otel")]
+    otel: crate::otel::Saved,
+    #[cfg(feature = "sentry")]
+    sentry: crate::sentry::Saved,
+    progress: progress::Saved,
+    shutdown: shutdown::Saved,
+    source: source::Saved,
+    status: status::Saved,
+    validator: validator::Saved,
+    #[cfg(feature = "futures-util")]
+    abort: crate::abort::Saved,
+    #[cfg(feature = "futures-channel")]
+    channel: crate::channel::Saved,
+    #[cfg(feature = "tokio-util")]
+    token: crate::token::Saved,
+}
+
+impl Saved {
+    fn save() -> Saved {
+        Saved {
+            signal: storage::load(ACQUIRE),
+            armed: ARMED.load(ACQUIRE),
+            handler: get_signal_handler(),
+            panic_formatter: get_panic_formatter(),
+            recheck_depth: recheck_depth(),
+            advisor: advisor::save(),
+            checkpoint: checkpoint::save(),
+            clock: clock::save(),
+            deadline: deadline::save(),
+            epoch: epoch::save(),
+            events: events::save(),
+            filter: filter::save(),
+            gap: gap::save(),
+            grace: grace::save(),
+            #[cfg(feature = "std")]
+            heatmap: crate::heatmap::save(),
+            latch: latch::save(),
+            lifecycle: lifecycle::save(),
+            listeners: listeners::save(),
+            mailbox: mailbox::save(),
+            mask: mask::save(),
+            names: names::save(),
+            namespaced: namespaced::save(),
+            policy: policy::save(),
+            #[cfg(feature = "host-poll")]
+            poll: crate::poll::save(),
+            #[cfg(feature = "js-listener")]
+            js_listener: crate::js_listener::save(),
+            #[cfg(feature = "otel")]
+            otel: crate::otel::save(),
+            #[cfg(feature = "sentry")]
+            sentry: crate::sentry::save(),
+            progress: progress::save(),
+            shutdown: shutdown::save(),
+            source: source::save(),
+            status: status::save(),
+            validator: validator::save(),
+            #[cfg(feature = "futures-util")]
+            abort: crate::abort::save(),
+            #[cfg(feature = "futures-channel")]
+            channel: crate::channel::save(),
+            #[cfg(feature = "tokio-util")]
+            token: crate::token::save(),
+        }
+    }
+
+    fn restore(&self) {
+        // Disarm first, so no check acts on partially restored state
+        ARMED.store(0, RELEASE);
+        match self.handler {
+            Some(handler) => set_signal_handler(handler),
+            None => clear_signal_handler(),
+        };
+        match self.panic_formatter {
+            Some(formatter) => set_panic_formatter(formatter),
+            None => clear_panic_formatter(),
+        };
+        set_recheck_depth(self.recheck_depth);
+        advisor::restore(&self.advisor);
+        checkpoint::restore(&self.checkpoint);
+        clock::restore(&self.clock);
+        deadline::restore(&self.deadline);
+        epoch::restore(&self.epoch);
+        events::restore(&self.events);
+        filter::restore(&self.filter);
+        gap::restore(&self.gap);
+        grace::restore(&self.grace);
+        #[cfg(feature = "std")]
+        crate::heatmap::restore(&self.heatmap);
+        latch::restore(&self.latch);
+        lifecycle::restore(&self.lifecycle);
+        listeners::restore(&self.listeners);
+        mailbox::restore(&self.mailbox);
+        mask::restore(&self.mask);
+        names::restore(&self.names);
+        namespaced::restore(&self.namespaced);
+        policy::restore(&self.policy);
+        #[cfg(feature = "host-poll")]
+        crate::poll::restore(&self.poll);
+        #[cfg(feature = "js-listener")]
+        crate::js_listener::restore(&self.js_listener);
+        #[cfg(feature = "otel")]
+        crate::otel::restore(&self.otel);
+        #[cfg(feature = "sentry")]
+        crate::sentry::restore(&self.sentry);
+        progress::restore(&self.progress);
+        shutdown::restore(&self.shutdown);
+        source::restore(&self.source);
+        status::restore(&self.status);
+        validator::restore(&self.validator);
+        #[cfg(feature = "futures-util")]
+        crate::abort::restore(&self.abort);
+        #[cfg(feature = "futures-channel")]
+        crate::channel::restore(&self.channel);
+        #[cfg(feature = "tokio-util")]
+        crate::token::restore(&self.token);
+        storage::store(self.signal, RELEASE);
+        ARMED.store(self.armed & !ARMED_TESTING, RELEASE);
+    }
+}
+
+/// Isolates a test from the global signal state.
+///
+/// Creating a guard waits for any other guard to be dropped, saves the
+/// complete state (signal word, handler, namespaced handlers, panic
+/// formatter, recheck depth, listeners, shutdown hooks, clock, deadline,
+/// grace period, latch, masks, signal mask, signal validator and permitted
+/// codes, check gap warning, heat map, signal names, overwrite policy and
+/// queue, host poll stride, checkpointer, source and status words, signal
+/// history, abort registrations, bridged tokens, the signal reported to
+/// channel receivers, this thread's JavaScript listeners, the raise time
+/// kept for OpenTelemetry and the last signal kept for Sentry) and resets it
+/// to its initial values. Dropping the guard restores the saved state.
+/// Pending injections and recordings are cancelled on both entry and exit.
+///
+/// The per-call-site counters of [`check_signal_here!`](crate::check_signal_here)
+/// and [`try_check_signal_here!`](crate::try_check_signal_here) are private
+/// to each expansion and are not saved, so a thinned site may check on a
+/// different execution than it would in a fresh process.
+///
+/// Tests that all hold a guard can run concurrently under `cargo test`.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::testing::ScopedSignalState;
+/// use wasm_signal_handler::{set_signal, try_check_signal, Signal};
+///
+/// // In each test:
+/// let _state = ScopedSignalState::new();
+/// set_signal(Signal::CANCEL.code());
+/// assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+/// ```
+#[must_use = "the state is restored as soon as the guard is dropped"]
+pub struct ScopedSignalState {
+    saved: Saved,
+}
+
+impl ScopedSignalState {
+    /// Waits for exclusive access, then saves and resets the state.
+    pub fn new() -> ScopedSignalState {
+        while LOCK
+            .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+            .is_err()
+        {
+            #[cfg(feature = "std")]
+            std::thread::yield_now();
+            #[cfg(not(feature = "std"))]
+            core::hint::spin_loop();
+        }
+        let saved = Saved::save();
+        Saved {
+            armed: ARMED_INITIAL,
+            ..Saved::default()
+        }
+        .restore();
+        ScopedSignalState { saved }
+    }
+}
+
+impl Default for ScopedSignalState {
+    fn default() -> ScopedSignalState {
+        ScopedSignalState::new()
+    }
+}
+
+impl core::fmt::Debug for ScopedSignalState {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ScopedSignalState").finish_non_exhaustive()
+    }
+}
+
+impl Drop for ScopedSignalState {
+    fn drop(&mut self) {
+        self.saved.restore();
+        LOCK.store(false, RELEASE);
+    }
+}
@@ -0,0 +1,123 @@
+//! Signal timeline recording and replay.
+
+use core::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize};
+
+use super::{run_scenario, Step};
+use crate::{arm, disarm, ACQUIRE, ACQ_REL, ARMED_RECORD, RELEASE};
+
+/// Maximum number of steps in a [`Recording`].
+pub const TIMELINE_CAPACITY: usize = 256;
+
+static CHECKS: AtomicU64 = AtomicU64::new(0);
+static LAST_SEEN: AtomicU32 = AtomicU32::new(0);
+static LEN: AtomicUsize = AtomicUsize::new(0);
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+static AFTER: [AtomicU64; TIMELINE_CAPACITY] = [const { AtomicU64::new(0) }; TIMELINE_CAPACITY];
+static CODES: [AtomicU32; TIMELINE_CAPACITY] = [const { AtomicU32::new(0) }; TIMELINE_CAPACITY];
+
+/// A recorded timeline: each signal that appeared in the signal word, and the
+/// number of checks that had completed before the check that observed it.
+///
+/// With the `serde` feature, a recording serializes as a sequence of
+/// [`Step`]s, which deserializes back into a `Vec<Step>` for [`replay`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    steps: [Step; TIMELINE_CAPACITY],
+    len: usize,
+    dropped: usize,
+}
+
+impl Recording {
+    /// The recorded steps, in order.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps[..self.len]
+    }
+
+    /// Number of signals not recorded because the timeline was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Recording {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.steps())
+    }
+}
+
+/// Starts recording the signals observed by checks, discarding any previous
+/// recording.
+///
+/// Signals are recorded when a check first observes them, whether written by
+/// the host, [`set_signal`](crate::set_signal) or an injection. Only checks
+/// made while recording are counted.
+pub fn start_recording() {
+    disarm(ARMED_RECORD);
+    CHECKS.store(0, RELEASE);
+    LAST_SEEN.store(0, RELEASE);
+    LEN.store(0, RELEASE);
+    DROPPED.store(0, RELEASE);
+    arm(ARMED_RECORD);
+}
+
+/// Stops recording and returns the timeline.
+pub fn stop_recording() -> Recording {
+    disarm(ARMED_RECORD);
+    let len = LEN.load(ACQUIRE).min(TIMELINE_CAPACITY);
+    let mut steps = [Step::new(0, 0); TIMELINE_CAPACITY];
+    for (i, step) in steps.iter_mut().enumerate().take(len) {
+        *step = Step::new(AFTER[i].load(ACQUIRE), CODES[i].load(ACQUIRE));
+    }
+    Recording {
+        steps,
+        len,
+        dropped: DROPPED.load(ACQUIRE),
+    }
+}
+
+/// Replays a recorded timeline: each signal is raised after the same number
+/// of checks as when it was recorded.
+///
+/// This is [`run_scenario`] under another name, taking the steps of a
+/// [`Recording`]. Steps must be `'static`; leak them if needed.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::testing::{clear_injections, replay, start_recording, stop_recording};
+/// use wasm_signal_handler::{set_signal, try_check_signal};
+///
+/// start_recording();
+/// try_check_signal().ok();
+/// set_signal(2); // e.g. the host cancels between steps 1 and 2
+/// try_check_signal().ok();
+/// let recording = stop_recording();
+///
+/// let steps: &'static [_] = Box::leak(recording.steps().to_vec().into_boxed_slice());
+/// replay(steps);
+/// assert!(try_check_signal().is_ok());
+/// assert_eq!(try_check_signal().unwrap_err().code(), 2);
+/// clear_injections();
+/// ```
+pub fn replay(steps: &'static [Step]) {
+    run_scenario(steps);
+}
+
+/// Called from the check slow path while recording, with the code the check
+/// is about to act on.
+pub(crate) fn on_check(code: u32) {
+    let completed = CHECKS.fetch_add(1, ACQ_REL);
+    if LAST_SEEN.swap(code, ACQ_REL) == code || code == 0 {
+        // Still pending (e.g. masked) or nothing to record
+        return;
+    }
+    let index = LEN.fetch_add(1, ACQ_REL);
+    if index < TIMELINE_CAPACITY {
+        AFTER[index].store(completed, RELEASE);
+        CODES[index].store(code, RELEASE);
+    } else {
+        LEN.store(TIMELINE_CAPACITY, RELEASE);
+        DROPPED.fetch_add(1, ACQ_REL);
+    }
+}
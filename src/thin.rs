@@ -0,0 +1,126 @@
+//! Thinning checks per call site.
+//!
+//! A check is two atomic loads, but in the hottest loops even that shows up,
+//! and different loops want different strides. [`check_signal_here!`] and
+//! [`try_check_signal_here!`] keep a counter in a static at each place they
+//! are expanded, and only check on every `stride`th execution of that site.
+//! Nothing needs to be passed around, so each module thins its own hot paths
+//! independently.
+
+use core::sync::atomic::{AtomicU32, Ordering::Relaxed};
+
+/// Counts an execution of a call site, returning `true` if it should check.
+///
+/// The first execution checks, then every `stride`th after it. A `stride`
+/// of `0` is treated as `1`.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __check_due(counter: &AtomicU32, stride: u32) -> bool {
+    // A plain load and store rather than a read-modify-write, which costs
+    // a locked instruction on native targets. A lost update under
+    // contention only moves the next check by an execution.
+    let count = counter.load(Relaxed);
+    let next = count + 1;
+    counter.store(if next >= stride { 0 } else { next }, Relaxed);
+    count == 0
+}
+
+/// [`check_signal`](crate::check_signal), on every `stride`th execution of
+/// this call site.
+///
+/// Expands to a static counter private to the call site, so each site
+/// keeps its own stride. Threads running the same site share its counter.
+/// A `stride` of `0` is treated as `1`.
+///
+/// # Panics
+///
+/// As [`check_signal`](crate::check_signal), on the executions that check.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::check_signal_here;
+///
+/// fn blur(pixels: &mut [u8]) {
+///     for i in 1..pixels.len() {
+///         check_signal_here!(4096);
+///         pixels[i] = pixels[i - 1] / 2 + pixels[i] / 2;
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! check_signal_here {
+    ($stride:expr) => {{
+        static COUNTER: ::core::sync::atomic::AtomicU32 = ::core::sync::atomic::AtomicU32::new(0);
+        if $crate::__check_due(&COUNTER, $stride) {
+            $crate::check_signal();
+        }
+    }};
+}
+
+/// [`try_check_signal`](crate::try_check_signal), on every `stride`th
+/// execution of this call site.
+///
+/// Evaluates to `Ok(())` on the executions that skip the check. A `stride`
+/// of `0` is treated as `1`.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{try_check_signal_here, Signal};
+///
+/// fn sum(values: &[u64]) -> Result<u64, Signal> {
+///     let mut total = 0;
+///     for value in values {
+///         try_check_signal_here!(1024)?;
+///         total += value;
+///     }
+///     Ok(total)
+/// }
+/// ```
+#[macro_export]
+macro_rules! try_check_signal_here {
+    ($stride:expr) => {{
+        static COUNTER: ::core::sync::atomic::AtomicU32 = ::core::sync::atomic::AtomicU32::new(0);
+        if $crate::__check_due(&COUNTER, $stride) {
+            $crate::try_check_signal()
+        } else {
+            ::core::result::Result::<(), $crate::Signal>::Ok(())
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, Signal};
+
+    fn step() -> Result<(), Signal> {
+        try_check_signal_here!(3)
+    }
+
+    #[test]
+    fn test_check_signal_here() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        let counter = AtomicU32::new(0);
+        let due: [bool; 5] = core::array::from_fn(|_| __check_due(&counter, 2));
+        assert_eq!(due, [true, false, true, false, true]);
+        assert!(__check_due(&AtomicU32::new(0), 0));
+
+        // The first execution checks, the next two skip
+        assert_eq!(step(), Ok(()));
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(step(), Ok(()));
+        assert_eq!(step(), Ok(()));
+        assert_eq!(step(), Err(Signal::CANCEL));
+
+        // Another call site keeps its own count
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal_here!(100), Err(Signal::CANCEL));
+        clear_signal();
+    }
+}
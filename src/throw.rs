@@ -0,0 +1,38 @@
+//! Throwing propagated signals as wasm exceptions.
+//!
+//! With the `throw` feature, on wasm32 builds with the exception-handling
+//! proposal enabled, a signal propagated out of `check_signal` throws an
+//! exception with the exported `WASM_SIGNAL_TAG` tag instead of panicking.
+//! The tag has one `i32` parameter, the signal code. JavaScript hosts catch
+//! it with `WebAssembly.Exception.prototype.is`, and non-Rust guest code
+//! with a `catch` for the tag, without knowing anything about Rust panics.
+//!
+//! To Rust the exception is a foreign one. With `panic=unwind`, destructors
+//! run as it passes through Rust frames, but a `catch_unwind` on the way
+//! aborts, including the one wasm-bindgen wraps its exports in. It suits
+//! `panic=abort` builds, or exports called without wasm-bindgen.
+
+// Same as `WASM_SIGNAL_GLOBAL` in `storage`: the throw repeats the
+// `.tagtype` declaration for the objects of other crates it is inlined into.
+core::arch::global_asm!(
+    ".tagtype WASM_SIGNAL_TAG i32",
+    ".globl WASM_SIGNAL_TAG",
+    ".export_name WASM_SIGNAL_TAG, WASM_SIGNAL_TAG",
+    "WASM_SIGNAL_TAG:",
+);
+
+/// Throws `code` with the signal tag.
+#[inline(always)]
+pub(crate) fn throw(code: u32) -> ! {
+    // SAFETY: Throws with the tag declared above. Nothing after the throw
+    // runs, matching the `!` return type.
+    unsafe {
+        core::arch::asm!(
+            ".tagtype WASM_SIGNAL_TAG i32",
+            "local.get {}",
+            "throw WASM_SIGNAL_TAG",
+            in(local) code,
+            options(noreturn, nostack),
+        );
+    }
+}
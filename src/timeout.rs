@@ -0,0 +1,142 @@
+//! Time limits and timed checks for futures.
+//!
+//! Edge functions usually care about two limits at once: their own time
+//! budget and the host asking them to stop. [`with_timeout`] enforces both
+//! on a future, reporting a blown budget as [`Signal::DEADLINE`] like
+//! [`set_deadline`](crate::set_deadline) does for synchronous code.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+#[cfg(feature = "futures-channel")]
+use crate::channel::poll_signal_aware;
+use crate::{listeners, try_check_signal, Signal};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &js_sys::Function, ms: u32) -> JsValue;
+
+    #[wasm_bindgen(js_name = clearTimeout)]
+    fn clear_timeout(id: &JsValue);
+}
+
+/// Runs `future` with a time limit of `ms` milliseconds.
+///
+/// Resolves to `Ok` with the future's output if it completes in time. If the
+/// time limit passes first, the future is dropped, [listeners] are notified of
+/// a [`Signal::DEADLINE`], and this resolves to `Err(Signal::DEADLINE)`. A
+/// signal check runs on every poll, so host cancellation ends the future
+/// early with the propagated signal.
+///
+/// The time limit is a JavaScript timer, so it fires even while the future
+/// waits. The check for host signals only runs when the task is polled.
+///
+/// [listeners]: crate::add_signal_listener
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{with_timeout, Signal};
+///
+/// async fn lookup() -> u32 {
+///     42
+/// }
+///
+/// async fn handle() -> Result<u32, Signal> {
+///     with_timeout(lookup(), 50).await
+/// }
+/// ```
+pub fn with_timeout<F: Future>(future: F, ms: u32) -> WithTimeout<F> {
+    WithTimeout {
+        future,
+        timer: Timer::new(ms),
+    }
+}
+
+/// The future returned by [`with_timeout`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct WithTimeout<F> {
+    future: F,
+    timer: Timer,
+}
+
+impl<F: Future> Future for WithTimeout<F> {
+    type Output = Result<F::Output, Signal>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: `future` is structurally pinned and never moved out while
+        // pinned; `timer` is `Unpin`
+        let this = unsafe { self.get_unchecked_mut() };
+        try_check_signal()?;
+        // SAFETY: See above
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        if let Poll::Ready(output) = future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        if Pin::new(&mut this.timer).poll(cx).is_ready() {
+            listeners::notify(Signal::DEADLINE);
+            return Poll::Ready(Err(Signal::DEADLINE));
+        }
+        Poll::Pending
+    }
+}
+
+/// Sleeps for `ms` milliseconds, checking for a signal whenever polled.
+pub(crate) async fn sleep_interruptible(ms: u32) -> Result<(), Signal> {
+    let mut timer = Timer::new(ms);
+    core::future::poll_fn(|cx| poll_signal_aware(cx, |cx| Pin::new(&mut timer).poll(cx))).await
+}
+
+/// Polls `inner` unless a check propagates a signal.
+#[cfg(not(feature = "futures-channel"))]
+fn poll_signal_aware<T>(
+    cx: &mut Context<'_>,
+    inner: impl FnOnce(&mut Context<'_>) -> Poll<T>,
+) -> Poll<Result<T, Signal>> {
+    try_check_signal()?;
+    inner(cx).map(Ok)
+}
+
+/// A `setTimeout` timer as a future, cleared when dropped.
+#[derive(Debug)]
+struct Timer {
+    promise: JsFuture,
+    id: JsValue,
+}
+
+impl Timer {
+    fn new(ms: u32) -> Timer {
+        let mut id = JsValue::UNDEFINED;
+        // The executor runs synchronously, before `id` is read
+        let mut slot = core::panic::AssertUnwindSafe(&mut id);
+        let promise = js_sys::Promise::new(&mut move |resolve, _reject| {
+            **slot = set_timeout(&resolve, ms);
+        });
+        Timer {
+            promise: JsFuture::from(promise),
+            id,
+        }
+    }
+}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // A timer promise cannot reject
+        Pin::new(&mut self.promise).poll(cx).map(|_| ())
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        // Don't keep the event loop alive for a timer nobody awaits
+        clear_timeout(&self.id);
+    }
+}
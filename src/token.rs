@@ -0,0 +1,124 @@
+//! Cancelling tokio-util `CancellationToken`s on cancellation.
+//!
+//! Async codebases running tokio (on WASI, say) usually propagate
+//! cancellation through a tree of
+//! [`CancellationToken`](tokio_util::sync::CancellationToken)s. Bridging the
+//! root of that tree with [`bridge_to_token`] plugs it into host
+//! cancellation: the token is cancelled as soon as a check propagates a
+//! signal, and its children with it.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::AtomicBool;
+use std::vec::Vec;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::{ACQUIRE, RELEASE};
+
+/// Tokens of live bridges, guarded by [`LOCK`].
+struct Tokens(UnsafeCell<Vec<CancellationToken>>);
+
+// SAFETY: The tokens are only accessed under LOCK, and are `Send + Sync`
+unsafe impl Sync for Tokens {}
+
+static TOKENS: Tokens = Tokens(UnsafeCell::new(Vec::new()));
+static LOCK: AtomicBool = AtomicBool::new(false);
+
+fn with_tokens<R>(f: impl FnOnce(&mut Vec<CancellationToken>) -> R) -> R {
+    while LOCK
+        .compare_exchange_weak(false, true, ACQUIRE, core::sync::atomic::Ordering::Relaxed)
+        .is_err()
+    {
+        core::hint::spin_loop();
+    }
+    // SAFETY: LOCK grants exclusive access to the tokens
+    let result = f(unsafe { &mut *TOKENS.0.get() });
+    LOCK.store(false, RELEASE);
+    result
+}
+
+/// Cancels `token` when a check propagates a signal.
+///
+/// Each bridge fires at most once: after a propagated signal, bridge new
+/// tokens for new work. Signals the handler clears do not cancel anything,
+/// and a token cancelled by other means is simply dropped from the bridge.
+/// [`reset_all`](crate::reset_all) drops live bridges without cancelling
+/// them.
+///
+/// # Example
+///
+/// ```rust
+/// use tokio_util::sync::CancellationToken;
+/// use wasm_signal_handler::bridge_to_token;
+///
+/// let shutdown = CancellationToken::new();
+/// bridge_to_token(shutdown.clone());
+///
+/// // Existing code keeps selecting on child tokens
+/// let request = shutdown.child_token();
+/// # let _ = request;
+/// ```
+pub fn bridge_to_token(token: CancellationToken) {
+    with_tokens(|tokens| {
+        tokens.retain(|token| !token.is_cancelled());
+        tokens.push(token);
+    });
+}
+
+/// Cancels every bridged token, after a check propagated a signal.
+pub(crate) fn cancel_all() {
+    let tokens = with_tokens(core::mem::take);
+    // Cancel outside the lock, since cancelling wakes tasks
+    for token in tokens {
+        token.cancel();
+    }
+}
+
+/// Drops live bridges without cancelling them.
+pub(crate) fn reset() {
+    drop(with_tokens(core::mem::take));
+}
+
+/// The live bridges, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved(Vec<CancellationToken>);
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved(with_tokens(|tokens| tokens.clone()))
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    let old = with_tokens(|tokens| core::mem::replace(tokens, saved.0.clone()));
+    drop(old);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, set_signal, try_check_signal, Signal};
+
+    #[test]
+    fn test_cancel_on_propagated_signal() {
+        let _guard = lock();
+        clear_signal();
+        let root = CancellationToken::new();
+        let child = root.child_token();
+        bridge_to_token(root.clone());
+
+        // A handled signal does not cancel
+        crate::set_signal_handler(|_| Ok(()));
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_ok());
+        assert!(!child.is_cancelled());
+
+        clear_signal_handler();
+        set_signal(Signal::CANCEL.code());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert!(root.is_cancelled());
+        assert!(child.is_cancelled());
+    }
+}
@@ -0,0 +1,218 @@
+//! Cancelling computations when the view that started them goes away.
+//!
+//! A component that starts a heavy computation, such as filtering a large
+//! table or rendering a chart, should stop it when the component unmounts
+//! or the route changes, rather than finish work nobody will see. A
+//! [`ViewTask`] ties the two together: computations run inside
+//! [`ViewTask::enter`], and [`ViewTask::cancel`], called from the
+//! framework's cleanup, raises the task's signal if one is still running.
+//! [`route_changed`] does the same for every task started before a
+//! navigation.
+//!
+//! The helpers only need a cleanup callback, so they fit any framework. In
+//! Leptos, pass [`ViewTask::canceller`] to `on_cleanup`; in Yew, return it
+//! from a `use_effect_with` closure. [`use_cancellable_task`] wraps the
+//! first pattern.
+//!
+//! The signal word is shared by the whole module, so other computations
+//! running at the time of a cancellation see the signal too. Views whose
+//! tasks must not cancel each other run them in separate workers.
+
+use alloc::{boxed::Box, sync::Arc};
+use core::sync::atomic::AtomicU32;
+
+use crate::{send_signal, Signal, ACQUIRE, ACQ_REL};
+
+/// Set in a task's state once it is cancelled; the low bits count runs in
+/// progress.
+const CANCELLED: u32 = 1 << 31;
+
+/// Bumped by [`route_changed`]; tasks created before the bump are stale.
+static ROUTE: AtomicU32 = AtomicU32::new(0);
+
+/// Runs in progress across every task.
+static RUNNING: AtomicU32 = AtomicU32::new(0);
+
+/// A computation started by a view, cancelled when the view goes away.
+///
+/// Clones share the same state, so the view can keep one and hand another to
+/// its cleanup.
+#[derive(Debug, Clone)]
+pub struct ViewTask {
+    state: Arc<AtomicU32>,
+    route: u32,
+    code: Signal,
+}
+
+impl ViewTask {
+    /// Creates a task that raises `code` when cancelled mid-run.
+    pub fn new(code: Signal) -> ViewTask {
+        ViewTask {
+            state: Arc::new(AtomicU32::new(0)),
+            route: ROUTE.load(ACQUIRE),
+            code,
+        }
+    }
+
+    /// Returns the signal the task raises when cancelled.
+    pub fn code(&self) -> Signal {
+        self.code
+    }
+
+    /// Returns `true` once the task is cancelled, or a route change has made
+    /// it stale.
+    pub fn is_cancelled(&self) -> bool {
+        self.state.load(ACQUIRE) & CANCELLED != 0 || self.route != ROUTE.load(ACQUIRE)
+    }
+
+    /// Marks a run of the task as in progress until the guard is dropped.
+    ///
+    /// Hold the guard across the computation, including across awaits.
+    ///
+    /// # Errors
+    ///
+    /// Returns the task's signal if it is already cancelled.
+    pub fn enter(&self) -> Result<ViewTaskGuard, Signal> {
+        let state = self.state.fetch_add(1, ACQ_REL);
+        RUNNING.fetch_add(1, ACQ_REL);
+        let guard = ViewTaskGuard {
+            state: self.state.clone(),
+        };
+        if state & CANCELLED != 0 || self.route != ROUTE.load(ACQUIRE) {
+            return Err(self.code);
+        }
+        Ok(guard)
+    }
+
+    /// Runs `f` as a run of the task.
+    ///
+    /// # Errors
+    ///
+    /// Returns the task's signal if it is already cancelled, or what `f`
+    /// returns.
+    pub fn run<T>(&self, f: impl FnOnce() -> Result<T, Signal>) -> Result<T, Signal> {
+        let _guard = self.enter()?;
+        f()
+    }
+
+    /// Cancels the task, raising its signal if a run is in progress.
+    ///
+    /// Later calls to [`enter`](ViewTask::enter) fail.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the signal was raised.
+    pub fn cancel(&self) -> bool {
+        let state = self.state.fetch_or(CANCELLED, ACQ_REL);
+        state & CANCELLED == 0 && state != 0 && send_signal(self.code.code())
+    }
+
+    /// Returns a callback that cancels the task, for the framework's cleanup.
+    pub fn canceller(&self) -> impl FnOnce() + Send + Sync + 'static {
+        let task = self.clone();
+        move || {
+            task.cancel();
+        }
+    }
+}
+
+impl Default for ViewTask {
+    /// A task raising [`Signal::CANCEL`].
+    fn default() -> ViewTask {
+        ViewTask::new(Signal::CANCEL)
+    }
+}
+
+/// A run of a [`ViewTask`] in progress, from [`ViewTask::enter`].
+#[derive(Debug)]
+#[must_use = "the run ends when the guard is dropped"]
+pub struct ViewTaskGuard {
+    state: Arc<AtomicU32>,
+}
+
+impl Drop for ViewTaskGuard {
+    fn drop(&mut self) {
+        self.state.fetch_sub(1, ACQ_REL);
+        RUNNING.fetch_sub(1, ACQ_REL);
+    }
+}
+
+/// Creates a [`ViewTask`] raising [`Signal::CANCEL`], and registers its
+/// cancellation with `on_cleanup`.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{try_check_signal, use_cancellable_task};
+///
+/// # fn on_cleanup(_: impl FnOnce() + Send + Sync + 'static) {}
+/// // In a Leptos component, with `leptos::on_cleanup`
+/// let task = use_cancellable_task(on_cleanup);
+/// let rows = task.run(|| {
+///     let mut rows = 0;
+///     for _ in 0..1000 {
+///         try_check_signal()?;
+///         rows += 1;
+///     }
+///     Ok(rows)
+/// });
+/// # assert_eq!(rows, Ok(1000));
+/// ```
+pub fn use_cancellable_task(on_cleanup: impl FnOnce(Box<dyn FnOnce() + Send + Sync>)) -> ViewTask {
+    let task = ViewTask::default();
+    on_cleanup(Box::new(task.canceller()));
+    task
+}
+
+/// Cancels every task created before the call, for the router to call on
+/// navigation.
+///
+/// # Returns
+///
+/// Returns `true` if a run was in progress and [`Signal::CANCEL`] was
+/// raised.
+pub fn route_changed() -> bool {
+    ROUTE.fetch_add(1, ACQ_REL);
+    RUNNING.load(ACQUIRE) != 0 && send_signal(Signal::CANCEL.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, try_check_signal};
+
+    #[test]
+    fn test_view_task() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+
+        // Unmounting while idle only stops later runs
+        let task = ViewTask::default();
+        assert_eq!(task.run(|| Ok(1)), Ok(1));
+        task.canceller()();
+        assert!(task.is_cancelled());
+        assert_eq!(task.run(|| Ok(2)), Err(Signal::CANCEL));
+        assert_eq!(try_check_signal(), Ok(()));
+
+        // Unmounting mid-run raises the signal
+        let task = ViewTask::new(Signal::TERMINATE);
+        let result = task.run(|| {
+            assert!(task.cancel());
+            assert!(!task.cancel());
+            try_check_signal()
+        });
+        assert_eq!(result, Err(Signal::TERMINATE));
+
+        // A route change cancels tasks created before it
+        let task = ViewTask::default();
+        let running = task.enter().unwrap();
+        assert!(route_changed());
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        drop(running);
+        assert!(task.is_cancelled());
+        assert!(!route_changed());
+        assert!(!ViewTask::default().is_cancelled());
+    }
+}
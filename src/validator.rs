@@ -0,0 +1,247 @@
+//! Validating codes before they are dispatched.
+//!
+//! A misconfigured host can write codes the application never agreed on.
+//! Rather than let a check consume such a code and hand the handler (or the
+//! caller) a signal it cannot interpret, applications can register a
+//! [validator function](set_signal_validator) and/or a table of
+//! [permitted codes](set_permitted_codes). A code either one rejects is left
+//! pending in the signal word, where a host that notices can clear or
+//! replace it, and is reported once as an
+//! [`EVENT_SIGNAL_REJECTED`](crate::EVENT_SIGNAL_REJECTED) event.
+//!
+//! Validation applies to every code read from the signal word, fatal ones
+//! included, so permitted tables must list the codes the host may send, such
+//! as [`Signal::TERMINATE`] and [`Signal::CANCEL`]. Deadlines are raised by
+//! the guest itself and never validated.
+
+use core::ops::RangeInclusive;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicPtr, AtomicU32};
+
+use crate::{arm, disarm, events, Signal, ACQUIRE, ACQ_REL, ARMED_VALIDATE, RELEASE};
+
+/// A function deciding whether a raw signal code is valid.
+pub type SignalValidator = fn(u32) -> bool;
+
+/// A table of permitted signal codes, as inclusive ranges of raw codes.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{set_permitted_codes, PermittedCodes, Signal};
+///
+/// // Terminate, cancel and the application's own 100..=199
+/// static CODES: PermittedCodes = PermittedCodes::new(&[1..=2, 100..=199]);
+///
+/// assert!(CODES.contains(Signal::CANCEL.code()));
+/// assert!(!CODES.contains(42));
+/// set_permitted_codes(&CODES);
+/// # wasm_signal_handler::clear_permitted_codes();
+/// ```
+#[derive(Debug)]
+pub struct PermittedCodes(&'static [RangeInclusive<u32>]);
+
+impl PermittedCodes {
+    /// Creates a table permitting the codes in `ranges`.
+    pub const fn new(ranges: &'static [RangeInclusive<u32>]) -> PermittedCodes {
+        PermittedCodes(ranges)
+    }
+
+    /// Returns `true` if `code` is in one of the ranges.
+    pub fn contains(&self, code: u32) -> bool {
+        self.0.iter().any(|range| range.contains(&code))
+    }
+}
+
+/// Stored as a raw pointer, like the signal handler.
+static VALIDATOR: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+static PERMITTED: AtomicPtr<PermittedCodes> = AtomicPtr::new(null_mut());
+
+/// The last code rejected, so each rejection is only reported once.
+static LAST_REJECTED: AtomicU32 = AtomicU32::new(0);
+
+/// Arms validation if a validator or table is set, and disarms it otherwise.
+fn rearm() {
+    if VALIDATOR.load(ACQUIRE).is_null() && PERMITTED.load(ACQUIRE).is_null() {
+        disarm(ARMED_VALIDATE);
+    } else {
+        arm(ARMED_VALIDATE);
+    }
+}
+
+fn to_validator(ptr: *mut ()) -> Option<SignalValidator> {
+    if ptr.is_null() {
+        None
+    } else {
+        // SAFETY: We only store valid SignalValidator function pointers in
+        // VALIDATOR
+        Some(unsafe { core::mem::transmute::<*mut (), SignalValidator>(ptr) })
+    }
+}
+
+fn to_permitted(ptr: *mut PermittedCodes) -> Option<&'static PermittedCodes> {
+    // SAFETY: We only store pointers from `&'static PermittedCodes` in
+    // PERMITTED
+    unsafe { ptr.as_ref() }
+}
+
+/// Registers a function that every code must pass before it is dispatched.
+///
+/// Codes the validator rejects are left pending and reported once as an
+/// [`EVENT_SIGNAL_REJECTED`](crate::EVENT_SIGNAL_REJECTED) event. Keep it
+/// cheap and side-effect free, as it runs on every check while such a code
+/// is pending.
+///
+/// # Returns
+///
+/// Returns the previously registered validator, if any.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{set_signal, set_signal_validator, try_check_signal};
+///
+/// // This host only ever sends codes below 16
+/// set_signal_validator(|code| code < 16);
+///
+/// set_signal(0xdead_beef);
+/// assert!(try_check_signal().is_ok()); // Rejected, and still pending
+/// # wasm_signal_handler::clear_signal_validator();
+/// # wasm_signal_handler::clear_signal();
+/// ```
+pub fn set_signal_validator(validator: SignalValidator) -> Option<SignalValidator> {
+    let previous = to_validator(VALIDATOR.swap(validator as *mut (), ACQ_REL));
+    rearm();
+    previous
+}
+
+/// Clears the registered validator, returning it if one was set.
+pub fn clear_signal_validator() -> Option<SignalValidator> {
+    let previous = to_validator(VALIDATOR.swap(null_mut(), ACQ_REL));
+    rearm();
+    previous
+}
+
+/// Gets the registered validator, if any.
+pub fn get_signal_validator() -> Option<SignalValidator> {
+    to_validator(VALIDATOR.load(ACQUIRE))
+}
+
+/// Sets the table of permitted codes. Codes outside it are rejected as by
+/// [`set_signal_validator`]; with both set, a code must pass both.
+///
+/// # Returns
+///
+/// Returns the previous table, if any.
+pub fn set_permitted_codes(codes: &'static PermittedCodes) -> Option<&'static PermittedCodes> {
+    let ptr = codes as *const PermittedCodes as *mut PermittedCodes;
+    let previous = to_permitted(PERMITTED.swap(ptr, ACQ_REL));
+    rearm();
+    previous
+}
+
+/// Clears the table of permitted codes, returning it if one was set.
+pub fn clear_permitted_codes() -> Option<&'static PermittedCodes> {
+    let previous = to_permitted(PERMITTED.swap(null_mut(), ACQ_REL));
+    rearm();
+    previous
+}
+
+/// Returns the last code that failed validation, if any.
+pub fn last_rejected() -> Option<Signal> {
+    Signal::new(LAST_REJECTED.load(ACQUIRE))
+}
+
+/// Returns `true` if `signal` passes the validator and the permitted table.
+pub(crate) fn validate(signal: Signal) -> bool {
+    let code = signal.code();
+    let valid = to_validator(VALIDATOR.load(ACQUIRE)).is_none_or(|validator| validator(code))
+        && to_permitted(PERMITTED.load(ACQUIRE)).is_none_or(|codes| codes.contains(code));
+    if !valid && LAST_REJECTED.swap(code, ACQ_REL) != code {
+        events::post_event(events::EVENT_SIGNAL_REJECTED, code);
+    }
+    valid
+}
+
+/// Forgets the last rejected code, so that it is reported again.
+pub(crate) fn reset() {
+    LAST_REJECTED.store(0, RELEASE);
+}
+
+/// The validator, table and last rejection, saved by `ScopedSignalState`.
+#[cfg(feature = "testing")]
+#[derive(Default)]
+pub(crate) struct Saved {
+    validator: Option<SignalValidator>,
+    permitted: Option<&'static PermittedCodes>,
+    last_rejected: u32,
+}
+
+#[cfg(feature = "testing")]
+pub(crate) fn save() -> Saved {
+    Saved {
+        validator: get_signal_validator(),
+        permitted: to_permitted(PERMITTED.load(ACQUIRE)),
+        last_rejected: LAST_REJECTED.load(ACQUIRE),
+    }
+}
+
+/// Restores the validator and table. `ARMED` is restored separately by the
+/// caller.
+#[cfg(feature = "testing")]
+pub(crate) fn restore(saved: &Saved) {
+    let validator = saved
+        .validator
+        .map_or(null_mut(), |validator| validator as *mut ());
+    let permitted = saved.permitted.map_or(null_mut(), |codes| {
+        codes as *const PermittedCodes as *mut PermittedCodes
+    });
+    VALIDATOR.store(validator, RELEASE);
+    PERMITTED.store(permitted, RELEASE);
+    LAST_REJECTED.store(saved.last_rejected, RELEASE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::tests::drain;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, peek_signal, set_signal, try_check_signal};
+
+    static CODES: PermittedCodes = PermittedCodes::new(&[1..=2, 100..=199]);
+
+    #[test]
+    fn test_rejected_codes_stay_pending() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        reset();
+        while drain().is_some() {}
+        assert!(set_signal_validator(|code| code != 150).is_none());
+        assert!(set_permitted_codes(&CODES).is_none());
+
+        set_signal(42);
+        assert!(try_check_signal().is_ok());
+        assert!(try_check_signal().is_ok());
+        assert_eq!(peek_signal(), Signal::new(42));
+        assert_eq!(last_rejected(), Signal::new(42));
+
+        set_signal(150);
+        assert!(try_check_signal().is_ok());
+        assert_eq!(last_rejected(), Signal::new(150));
+        // Each rejection is reported once
+        assert_eq!(drain(), Some((events::EVENT_SIGNAL_REJECTED, 42)));
+        assert_eq!(drain(), Some((events::EVENT_SIGNAL_REJECTED, 150)));
+        assert_eq!(drain(), None);
+
+        set_signal(120);
+        assert_eq!(try_check_signal(), Err(Signal::new(120).unwrap()));
+
+        assert!(clear_signal_validator().is_some());
+        assert!(clear_permitted_codes().is_some());
+        set_signal(42);
+        assert_eq!(try_check_signal(), Err(Signal::new(42).unwrap()));
+        reset();
+    }
+}
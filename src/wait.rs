@@ -0,0 +1,87 @@
+//! Blocking until the host raises a signal.
+//!
+//! Worker-style guests often dedicate a thread to waiting for instructions
+//! from the host. [`wait_for_signal`] parks that thread instead of having it
+//! poll the signal word.
+
+use crate::{
+    admit, listeners, policy, source, status, storage, Signal, ACQUIRE, ACQ_REL, ARMED, ARMED_QUEUE,
+};
+
+/// Blocks until a signal is raised, then takes it.
+///
+/// The signal is cleared from the signal word, acknowledged and passed to the
+/// [listeners](crate::add_signal_listener), but not to the signal handler:
+/// the caller decides what to do with it. Codes excluded by the
+/// [signal mask](crate::set_signal_mask) are not taken.
+///
+/// With shared memory on wasm32 (the `atomics` target feature), the thread
+/// sleeps in `memory.atomic.wait32` until a sender writes the signal word;
+/// the host helpers and guest senders such as
+/// [`send_signal`](crate::send_signal) wake it. Native threads yield while
+/// waiting. Without shared memory the host cannot write while the guest
+/// runs, so this only returns once a signal is already pending.
+///
+/// Not available with the `global-storage` feature on wasm32, for the same
+/// reason.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{wait_for_signal, Signal};
+///
+/// // On a dedicated control thread
+/// loop {
+///     match wait_for_signal() {
+///         Signal::TERMINATE => break,
+///         signal => { /* act on the instruction */ }
+///     }
+/// }
+/// ```
+pub fn wait_for_signal() -> Signal {
+    loop {
+        let mut code = storage::load(ACQUIRE);
+        if code == 0 && ARMED.load(ACQUIRE) & ARMED_QUEUE != 0 {
+            code = policy::refill();
+        }
+        if let Some(signal) = Signal::new(code) {
+            if admit(signal) {
+                if storage::compare_exchange(code, 0, ACQ_REL, ACQUIRE).is_err() {
+                    // Replaced meanwhile; look again
+                    continue;
+                }
+                status::acknowledge(signal);
+                source::observe(signal);
+                listeners::notify(signal);
+                return signal;
+            }
+            // Dropped, or ignored and left pending; wait for a change either way
+            code = storage::load(ACQUIRE);
+        }
+        storage::wait(code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, send_signal};
+
+    extern crate std;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_wait_for_signal() {
+        let _guard = lock();
+        clear_signal();
+
+        let waiter = thread::spawn(wait_for_signal);
+        thread::sleep(Duration::from_millis(20));
+        send_signal(Signal::CANCEL.code());
+        assert_eq!(waiter.join().unwrap(), Signal::CANCEL);
+        assert!(crate::peek_signal().is_none());
+        assert_eq!(crate::last_ack(), Some(Signal::CANCEL));
+    }
+}
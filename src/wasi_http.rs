@@ -0,0 +1,137 @@
+//! Cancelling `wasi:http` handlers when the client goes away.
+//!
+//! A `wasi:http` host has no way to interrupt a component that is still
+//! computing a response: when the client disconnects, or the host drops the
+//! response, all the guest can see is that the streams it shares with the
+//! host are closed. With the `wasi-http` feature, [`handle_wasi_http`] wraps
+//! an `incoming-handler` export, taking a probe that looks for that, usually
+//! by asking the outgoing body's stream whether it can still be written.
+//! Checks call the probe through the [host poll](crate::set_poll_stride)
+//! machinery (the feature implies `host-poll`), in place of the
+//! `env.wasm_signal_poll` import that components cannot satisfy, and raise
+//! [`Signal::CANCEL`] the first time it reports a disconnect.
+
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicBool, AtomicPtr};
+
+use crate::{RequestScope, Signal, ACQUIRE, ACQ_REL, RELEASE};
+
+/// The probe of the request being handled.
+type Probe<'a> = &'a (dyn Fn() -> bool + Sync);
+
+/// Points at the [`Probe`] of the request being handled, which lives on the
+/// stack of [`handle_wasi_http`] until it returns.
+static PROBE: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Set once the probe has reported a disconnect, so it is raised once.
+static DISCONNECTED: AtomicBool = AtomicBool::new(false);
+
+/// Uninstalls a probe on drop, including when the handler unwinds.
+struct Installed {
+    previous: *mut (),
+    disconnected: bool,
+}
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        PROBE.store(self.previous, RELEASE);
+        DISCONNECTED.store(self.disconnected, RELEASE);
+    }
+}
+
+/// Runs an `incoming-handler` export, raising [`Signal::CANCEL`] into it
+/// once `disconnected` reports that the client has gone away.
+///
+/// The request runs in a [`RequestScope`], so it starts with clean signal
+/// state even where the instance is reused. `disconnected` is called on
+/// every [poll stride](crate::set_poll_stride) of checks until it returns
+/// `true`, so it should be cheap and must not block.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::{handle_wasi_http, try_check_signal, Signal};
+///
+/// # struct Stream;
+/// # impl Stream {
+/// #     fn check_write(&self) -> Result<u64, ()> { Ok(4096) }
+/// # }
+/// # let stream = Stream;
+/// // With `wasi::http` bindings, `stream` is the outgoing body's
+/// // `output-stream`, which fails with `closed` once the client is gone
+/// let result = handle_wasi_http(
+///     || stream.check_write().is_err(),
+///     || -> Result<u64, Signal> {
+///         let mut sum = 0;
+///         for i in 0..1000 {
+///             try_check_signal()?;
+///             sum += i;
+///         }
+///         Ok(sum)
+///     },
+/// );
+/// # assert_eq!(result, Ok(499_500));
+/// ```
+pub fn handle_wasi_http<R>(
+    disconnected: impl Fn() -> bool + Sync,
+    handler: impl FnOnce() -> R,
+) -> R {
+    let _scope = RequestScope::new();
+    let probe: Probe = &disconnected;
+    let _installed = Installed {
+        previous: PROBE.swap(&probe as *const Probe as *mut (), ACQ_REL),
+        disconnected: DISCONNECTED.swap(false, ACQ_REL),
+    };
+    handler()
+}
+
+/// Calls the probe of the request being handled.
+///
+/// # Returns
+///
+/// [`Signal::CANCEL`]'s code the first time the probe reports a disconnect,
+/// or `0`.
+pub(crate) fn poll() -> u32 {
+    let probe = PROBE.load(ACQUIRE);
+    if probe.is_null() || DISCONNECTED.load(ACQUIRE) {
+        return 0;
+    }
+    // SAFETY: PROBE only holds pointers to the probe of a running
+    // `handle_wasi_http`, which clears it before the probe goes out of scope
+    let probe = unsafe { *(probe as *const Probe) };
+    if probe() && !DISCONNECTED.swap(true, ACQ_REL) {
+        Signal::CANCEL.code()
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal_handler, poll_host, try_check_signal};
+
+    #[test]
+    fn test_wasi_http_disconnect() {
+        let _guard = lock();
+        clear_signal_handler();
+        let closed = AtomicBool::new(false);
+
+        let result = handle_wasi_http(
+            || closed.load(ACQUIRE),
+            || {
+                assert!(!poll_host());
+                try_check_signal()?;
+                closed.store(true, RELEASE);
+                assert!(poll_host());
+                // Raised once per request
+                assert!(!poll_host());
+                try_check_signal()
+            },
+        );
+        assert_eq!(result, Err(Signal::CANCEL));
+        assert!(!poll_host());
+        assert_eq!(try_check_signal(), Ok(()));
+    }
+}
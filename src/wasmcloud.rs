@@ -0,0 +1,190 @@
+//! Signal delivery for wasmCloud components.
+//!
+//! A wasmCloud host cannot write a component's memory or call into it while
+//! an invocation runs, so the `wasmcloud` feature uses the
+//! `wasm-signal:control` WIT package in `wit/wasm-signal.wit` instead:
+//!
+//! - During an invocation, checks poll the imported `host.poll` function
+//!   through the [host poll](crate::set_poll_stride) machinery (the feature
+//!   implies `host-poll`). The component registers the generated binding
+//!   with [`set_wasmcloud_poll`]. Each poll reports the last acknowledged
+//!   code and the heartbeat counter to the host, and raises the code it
+//!   returns.
+//! - Between invocations, the host calls the exported `signal` interface,
+//!   whose functions forward to [`send_signal`], [`notify_lifecycle`] and
+//!   [`wasmcloud_status`].
+//!
+//! Lifecycle notifications are delivered as the [`WasmcloudLifecycle`]
+//! codes, in the `0xAC00` block, each with an [encoded severity](crate::Severity).
+
+use core::ptr::null_mut;
+use core::sync::atomic::AtomicPtr;
+
+use crate::platform::platform_signals;
+use crate::{heartbeat_count, last_ack, peek_signal, send_signal, ACQUIRE, ACQ_REL};
+
+platform_signals! {
+    /// A lifecycle notification from the wasmCloud host.
+    ///
+    /// The names match the WIT `lifecycle` enum.
+    pub enum WasmcloudLifecycle ("notification", "notifications") {
+        /// The host cancelled the in-flight invocation.
+        InvocationCancelled = 0xAC01, Cancel, Cancel, "invocation-cancelled";
+        /// The component is being stopped or scaled down.
+        Stopping = 0xAC02, Kill, Terminate, "stopping";
+        /// The host is shutting down.
+        HostShutdown = 0xAC03, Kill, Terminate, "host-shutdown";
+        /// The component's configuration changed.
+        ConfigChanged = 0xAC04, Info, Custom, "config-changed";
+    }
+
+    /// The generic kind a guest should treat the notification as: a cancelled
+    /// invocation is a cancel, stopping or shutdown a termination, and a
+    /// configuration change has no generic equivalent.
+    kind;
+
+    pub fn register_wasmcloud_names;
+}
+
+/// Delivers a lifecycle notification, as the exported `signal.notify`
+/// function should.
+///
+/// # Returns
+///
+/// Returns `false` if the [overwrite policy](crate::set_overwrite_policy)
+/// dropped it.
+pub fn notify_lifecycle(event: WasmcloudLifecycle) -> bool {
+    send_signal(event.code())
+}
+
+/// The signal state reported to the wasmCloud host, matching the WIT
+/// `status` record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WasmcloudStatus {
+    /// The pending signal code, or `0`.
+    pub pending: u32,
+    /// The last signal a check acknowledged, or `0`.
+    pub ack: u32,
+    /// The [heartbeat](crate::heartbeat) counter.
+    pub heartbeat: u32,
+}
+
+/// Returns the signal state, as the exported `signal.status` function
+/// should.
+pub fn wasmcloud_status() -> WasmcloudStatus {
+    WasmcloudStatus {
+        pending: peek_signal().map_or(0, |signal| signal.code()),
+        ack: last_ack().map_or(0, |signal| signal.code()),
+        heartbeat: heartbeat_count(),
+    }
+}
+
+/// The binding of the imported `host.poll` function: takes the last
+/// acknowledged code and the heartbeat counter, and returns a code to
+/// raise, or `0`.
+pub type WasmcloudPoll = fn(u32, u32) -> u32;
+
+/// Stored as a raw pointer for the same reasons as the signal handler.
+static POLL: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Registers the binding of the imported `host.poll` function, which checks
+/// then call on every [poll stride](crate::set_poll_stride).
+///
+/// # Returns
+///
+/// Returns the previously registered binding, if any.
+///
+/// # Example
+///
+/// ```rust
+/// use wasm_signal_handler::set_wasmcloud_poll;
+///
+/// // With bindings generated from `wit/wasm-signal.wit`, this is
+/// // `bindings::wasm_signal::control::host::poll`
+/// fn poll(_ack: u32, _heartbeat: u32) -> u32 {
+///     0
+/// }
+///
+/// set_wasmcloud_poll(poll);
+/// # wasm_signal_handler::clear_wasmcloud_poll();
+/// ```
+pub fn set_wasmcloud_poll(poll: WasmcloudPoll) -> Option<WasmcloudPoll> {
+    from_ptr(POLL.swap(poll as *mut (), ACQ_REL))
+}
+
+/// Clears the registered `host.poll` binding.
+pub fn clear_wasmcloud_poll() -> Option<WasmcloudPoll> {
+    from_ptr(POLL.swap(null_mut(), ACQ_REL))
+}
+
+fn from_ptr(ptr: *mut ()) -> Option<WasmcloudPoll> {
+    if ptr.is_null() {
+        None
+    } else {
+        // SAFETY: We only store valid WasmcloudPoll function pointers in POLL
+        Some(unsafe { core::mem::transmute::<*mut (), WasmcloudPoll>(ptr) })
+    }
+}
+
+/// Polls the host through the registered binding.
+///
+/// # Returns
+///
+/// The code the host returned, or `0` for none or without a binding.
+pub(crate) fn poll() -> u32 {
+    match from_ptr(POLL.load(ACQUIRE)) {
+        Some(poll) => {
+            let status = wasmcloud_status();
+            poll(status.ack, status.heartbeat)
+        }
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock;
+    use crate::{clear_signal, clear_signal_handler, heartbeat, poll_host, try_check_signal};
+    use core::sync::atomic::AtomicU32;
+    use core::sync::atomic::Ordering::SeqCst;
+
+    static HOST_PENDING: AtomicU32 = AtomicU32::new(0);
+    static REPORTED_ACK: AtomicU32 = AtomicU32::new(0);
+
+    fn host(ack: u32, _heartbeat: u32) -> u32 {
+        REPORTED_ACK.store(ack, SeqCst);
+        HOST_PENDING.swap(0, SeqCst)
+    }
+
+    #[test]
+    fn test_wasmcloud_delivery() {
+        let _guard = lock();
+        clear_signal();
+        clear_signal_handler();
+        for event in WasmcloudLifecycle::ALL {
+            assert_eq!(WasmcloudLifecycle::try_from(event.signal()), Ok(event));
+        }
+
+        // During an invocation, through the polled import
+        set_wasmcloud_poll(host);
+        let cancelled = WasmcloudLifecycle::InvocationCancelled.signal();
+        HOST_PENDING.store(cancelled.code(), SeqCst);
+        assert!(poll_host());
+        assert_eq!(try_check_signal(), Err(cancelled));
+        assert!(!poll_host());
+        assert_eq!(REPORTED_ACK.load(SeqCst), cancelled.code());
+        clear_wasmcloud_poll();
+
+        // Between invocations, through the exports
+        heartbeat();
+        let before = wasmcloud_status();
+        assert!(notify_lifecycle(WasmcloudLifecycle::Stopping));
+        let status = wasmcloud_status();
+        assert_eq!(status.pending, WasmcloudLifecycle::Stopping.code());
+        assert_eq!(status.ack, cancelled.code());
+        assert_eq!(status.heartbeat, before.heartbeat);
+        clear_signal();
+    }
+}
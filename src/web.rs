@@ -0,0 +1,165 @@
+//! Raising signals on browser page lifecycle events.
+//!
+//! A tab that is closing or moving to the background is a reason to stop
+//! long computations. [`bind_page_lifecycle`] listens for `beforeunload`
+//! and `pagehide` on the global object and for `visibilitychange` on
+//! `document`, and sends a configurable signal for each (see
+//! [`send_signal`]), so the same checks that handle host cancellation
+//! handle these too.
+//!
+//! Event listeners run on the thread that registered them, between tasks.
+//! Work on the main thread sees the signal once it next awaits; work in a
+//! worker sharing the module's memory sees it at its next check.
+//!
+//! [`send_signal`]: crate::send_signal
+
+use js_sys::{Function, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{send_signal, Signal};
+
+#[wasm_bindgen]
+extern "C" {
+    type EventTarget;
+
+    #[wasm_bindgen(method, js_name = addEventListener)]
+    fn add_event_listener(this: &EventTarget, kind: &str, listener: &Function);
+
+    #[wasm_bindgen(method, js_name = removeEventListener)]
+    fn remove_event_listener(this: &EventTarget, kind: &str, listener: &Function);
+}
+
+/// The signal codes [`bind_page_lifecycle`] sends. `None` leaves an event
+/// unbound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PageLifecycleCodes {
+    /// Sent on `beforeunload`, when the user starts closing or leaving the
+    /// page. Defaults to [`Signal::CANCEL`], since the user may stay.
+    pub before_unload: Option<Signal>,
+    /// Sent on `pagehide`, when the page is being unloaded or put in the
+    /// back/forward cache. Defaults to [`Signal::TERMINATE`].
+    pub page_hide: Option<Signal>,
+    /// Sent on `visibilitychange` when the page becomes hidden. Defaults to
+    /// [`Signal::CANCEL`].
+    pub hidden: Option<Signal>,
+}
+
+impl Default for PageLifecycleCodes {
+    fn default() -> PageLifecycleCodes {
+        PageLifecycleCodes {
+            before_unload: Some(Signal::CANCEL),
+            page_hide: Some(Signal::TERMINATE),
+            hidden: Some(Signal::CANCEL),
+        }
+    }
+}
+
+/// Page lifecycle listeners, from [`bind_page_lifecycle`]. Dropping it
+/// removes the listeners; use [`forget`](PageLifecycle::forget) to keep
+/// them for the life of the page.
+#[must_use = "dropping the binding removes the listeners"]
+pub struct PageLifecycle {
+    listeners: [Option<Listener>; 3],
+}
+
+struct Listener {
+    target: EventTarget,
+    kind: &'static str,
+    /// Taken when the listener is kept for good.
+    closure: Option<Closure<dyn FnMut()>>,
+}
+
+impl Listener {
+    fn bind(target: &JsValue, kind: &'static str, closure: Closure<dyn FnMut()>) -> Listener {
+        let target: EventTarget = target.clone().unchecked_into();
+        target.add_event_listener(kind, closure.as_ref().unchecked_ref());
+        Listener {
+            target,
+            kind,
+            closure: Some(closure),
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Some(closure) = &self.closure {
+            self.target
+                .remove_event_listener(self.kind, closure.as_ref().unchecked_ref());
+        }
+    }
+}
+
+impl PageLifecycle {
+    /// Keeps the listeners bound for the life of the page.
+    pub fn forget(mut self) {
+        for listener in self.listeners.iter_mut().flatten() {
+            if let Some(closure) = listener.closure.take() {
+                closure.forget();
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for PageLifecycle {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut list = f.debug_list();
+        for listener in self.listeners.iter().flatten() {
+            list.entry(&listener.kind);
+        }
+        list.finish()
+    }
+}
+
+/// Sends signals on page lifecycle events, as configured by `codes`.
+///
+/// Events without a global object or `document` to listen on, as in a
+/// worker, are left unbound.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{bind_page_lifecycle, PageLifecycleCodes};
+///
+/// bind_page_lifecycle(PageLifecycleCodes::default()).forget();
+/// ```
+pub fn bind_page_lifecycle(codes: PageLifecycleCodes) -> PageLifecycle {
+    let global = js_sys::global();
+    let document = Reflect::get(&global, &JsValue::from_str("document"))
+        .ok()
+        .filter(|document| document.is_object());
+    let has_listeners = |target: &JsValue| {
+        Reflect::get(target, &JsValue::from_str("addEventListener"))
+            .is_ok_and(|add| add.is_function())
+    };
+
+    let on_global = |kind, code: Option<Signal>| {
+        let code = code?;
+        has_listeners(&global).then(|| {
+            let closure = Closure::<dyn FnMut()>::new(move || {
+                send_signal(code.code());
+            });
+            Listener::bind(&global, kind, closure)
+        })
+    };
+    let before_unload = on_global("beforeunload", codes.before_unload);
+    let page_hide = on_global("pagehide", codes.page_hide);
+
+    let hidden = match (codes.hidden, document) {
+        (Some(code), Some(document)) if has_listeners(&document) => {
+            let state = document.clone();
+            let closure = Closure::<dyn FnMut()>::new(move || {
+                let visibility = Reflect::get(&state, &JsValue::from_str("visibilityState"));
+                if visibility.is_ok_and(|visibility| visibility == "hidden") {
+                    send_signal(code.code());
+                }
+            });
+            Some(Listener::bind(&document, "visibilitychange", closure))
+        }
+        _ => None,
+    };
+
+    PageLifecycle {
+        listeners: [before_unload, page_hide, hidden],
+    }
+}
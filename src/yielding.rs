@@ -0,0 +1,83 @@
+//! Yielding to JavaScript from long async sections.
+//!
+//! A CPU-bound section of an async function never gives JavaScript a chance
+//! to run, and never notices cancellation unless it checks. With the
+//! `wasm-bindgen-futures` feature, [`signal_aware_yield`] does both in one
+//! call, and [`for_each_yielding`] makes a whole synchronous workload
+//! cooperative.
+
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::{try_check_signal, Signal};
+
+/// Yields to the JavaScript microtask queue, then checks for a signal.
+///
+/// Microtasks queued before the call, such as promise continuations and
+/// `queueMicrotask` callbacks, run before this resolves. Timers and I/O
+/// callbacks are macrotasks and do not.
+///
+/// Only meaningful on wasm32 with a JavaScript host; elsewhere the
+/// underlying `js-sys` calls panic.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{signal_aware_yield, Signal};
+///
+/// async fn crunch(rows: &[u32]) -> Result<u64, Signal> {
+///     let mut sum = 0;
+///     for (i, row) in rows.iter().enumerate() {
+///         sum += u64::from(*row);
+///         if i % 10_000 == 0 {
+///             signal_aware_yield().await?;
+///         }
+///     }
+///     Ok(sum)
+/// }
+/// ```
+pub async fn signal_aware_yield() -> Result<(), Signal> {
+    let resolved = js_sys::Promise::resolve(&JsValue::UNDEFINED);
+    // A resolved promise cannot reject
+    let _ = JsFuture::from(resolved).await;
+    try_check_signal()
+}
+
+/// Calls `f` on every item of `iter`, in chunks of `chunk_size` items, with
+/// a [`signal_aware_yield`] between chunks.
+///
+/// Returns `Err(Signal)` as soon as a check between chunks propagates a
+/// signal; the remaining items are not processed. Pick a `chunk_size` that
+/// takes a few milliseconds to process: smaller chunks respond to
+/// cancellation sooner, larger ones spend less time yielding. A
+/// `chunk_size` of `0` is treated as `1`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use wasm_signal_handler::{for_each_yielding, Signal};
+///
+/// async fn reindex(docs: Vec<String>) -> Result<(), Signal> {
+///     for_each_yielding(docs, 500, |doc| {
+///         let _ = doc; // index(doc)
+///     })
+///     .await
+/// }
+/// ```
+pub async fn for_each_yielding<I, F>(iter: I, chunk_size: usize, mut f: F) -> Result<(), Signal>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item),
+{
+    let chunk_size = chunk_size.max(1);
+    let mut iter = iter.into_iter().peekable();
+    loop {
+        for item in iter.by_ref().take(chunk_size) {
+            f(item);
+        }
+        if iter.peek().is_none() {
+            return Ok(());
+        }
+        signal_aware_yield().await?;
+    }
+}
@@ -6,6 +6,8 @@
 //! Run with: cargo test --test abort --target wasm32-unknown-unknown \
 //!           --config 'target.wasm32-unknown-unknown.rustflags=["-Cpanic=abort"]'
 
+#![cfg(not(feature = "global-storage"))]
+
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_test::*;
 
@@ -67,7 +69,7 @@ fn test_signal_returns_err() {
     // Should return Err, not panic
     let result = try_check_signal();
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, 42);
+    assert_eq!(result.unwrap_err().code(), 42);
 }
 
 #[wasm_bindgen_test]
@@ -91,14 +93,14 @@ fn test_handler_propagates_error_no_panic() {
     clear_signal();
 
     // Handler that propagates the error
-    set_signal_handler(|signal| Err(Signal(signal.0 * 2)));
+    set_signal_handler(|signal| Err(Signal::new(signal.code() * 2).unwrap()));
 
     set_signal(21);
 
     // try_check_signal returns Err, doesn't panic
     let result = try_check_signal();
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, 42);
+    assert_eq!(result.unwrap_err().code(), 42);
 
     clear_signal_handler();
 }
@@ -117,7 +119,7 @@ fn test_js_write_signal_try_check() {
     // Use try_check (doesn't panic)
     let result = try_check_signal();
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, 999);
+    assert_eq!(result.unwrap_err().code(), 999);
 }
 
 #[wasm_bindgen_test]
@@ -143,14 +145,14 @@ fn test_peek_does_not_clear() {
     set_signal(100);
 
     // Peek multiple times
-    assert_eq!(peek_signal(), Some(Signal(100)));
-    assert_eq!(peek_signal(), Some(Signal(100)));
-    assert_eq!(peek_signal(), Some(Signal(100)));
+    assert_eq!(peek_signal(), Signal::new(100));
+    assert_eq!(peek_signal(), Signal::new(100));
+    assert_eq!(peek_signal(), Signal::new(100));
 
     // Still there
     let result = try_check_signal();
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, 100);
+    assert_eq!(result.unwrap_err().code(), 100);
 
     // Now cleared
     assert!(peek_signal().is_none());
@@ -162,7 +164,7 @@ fn test_clear_signal_returns_value() {
 
     set_signal(777);
     let cleared = clear_signal();
-    assert_eq!(cleared, Some(Signal(777)));
+    assert_eq!(cleared, Signal::new(777));
 
     // Already cleared
     let cleared_again = clear_signal();
@@ -184,7 +186,7 @@ fn test_multiple_signals_try_check() {
     for i in 1..=10 {
         set_signal(i);
         let result = try_check_signal();
-        assert_eq!(result.unwrap_err().0, i);
+        assert_eq!(result.unwrap_err().code(), i);
         assert!(try_check_signal().is_ok());
     }
 }
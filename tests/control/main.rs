@@ -0,0 +1,58 @@
+//! Tests for the `control-memory` accessors.
+//!
+//! These tests verify that, before `wasm-signal-control-memory` moves the
+//! signal block, the exported accessors read and write it in linear memory,
+//! where host writes through them are observed.
+//!
+//! Run with: cargo test --test control --target wasm32-unknown-unknown --features control-memory
+
+#![cfg(feature = "control-memory")]
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+
+use wasm_signal_handler::{
+    clear_signal, clear_signal_handler, peek_signal, set_signal, try_check_signal, Signal,
+    BLOCK_MAGIC,
+};
+
+wasm_bindgen_test_configure!(run_in_node_experimental);
+
+// ============================================================================
+// JavaScript helper functions
+// ============================================================================
+
+#[wasm_bindgen(inline_js = r#"
+export function controlLoad(exports, offset) {
+    return exports.__wasm_signal_control_load(offset) >>> 0;
+}
+
+export function controlStore(exports, offset, value) {
+    exports.__wasm_signal_control_store(offset, value | 0);
+}
+"#)]
+extern "C" {
+    fn controlLoad(exports: &JsValue, offset: u32) -> u32;
+    fn controlStore(exports: &JsValue, offset: u32, value: u32);
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[wasm_bindgen_test]
+fn test_accessors_reach_the_block() {
+    clear_signal();
+    clear_signal_handler();
+
+    let exports = wasm_bindgen::exports();
+    assert_eq!(controlLoad(&exports, 0), BLOCK_MAGIC);
+
+    set_signal(7);
+    assert_eq!(controlLoad(&exports, 4), 7);
+    assert_eq!(peek_signal(), Signal::new(7));
+
+    controlStore(&exports, 4, 42);
+    assert_eq!(try_check_signal(), Err(Signal::new(42).unwrap()));
+    assert_eq!(controlLoad(&exports, 4), 0);
+}
@@ -0,0 +1,95 @@
+//! Tests for the `global-storage` backend.
+//!
+//! These tests verify that the signal word lives in the exported mutable
+//! `WASM_SIGNAL_GLOBAL` global and that host writes to it are observed.
+//!
+//! Run with: cargo test --test global --target wasm32-unknown-unknown --features global-storage
+
+#![cfg(feature = "global-storage")]
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+
+use wasm_signal_handler::{
+    clear_signal, clear_signal_handler, peek_signal, set_signal, set_signal_handler,
+    try_check_signal, Signal,
+};
+
+wasm_bindgen_test_configure!(run_in_node_experimental);
+
+// ============================================================================
+// JavaScript helper functions
+// ============================================================================
+
+#[wasm_bindgen(inline_js = r#"
+export function readSignalGlobal(exports) {
+    return exports.WASM_SIGNAL_GLOBAL.value >>> 0;
+}
+
+export function writeSignalGlobal(exports, value) {
+    exports.WASM_SIGNAL_GLOBAL.value = value | 0;
+}
+"#)]
+extern "C" {
+    fn readSignalGlobal(exports: &JsValue) -> u32;
+    fn writeSignalGlobal(exports: &JsValue, value: u32);
+}
+
+fn get_wasm_exports() -> JsValue {
+    wasm_bindgen::exports()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[wasm_bindgen_test]
+fn test_global_write_detected() {
+    clear_signal();
+    clear_signal_handler();
+
+    let exports = get_wasm_exports();
+    writeSignalGlobal(&exports, 42);
+
+    assert_eq!(peek_signal(), Signal::new(42));
+    let result = try_check_signal();
+    assert_eq!(result.unwrap_err().code(), 42);
+
+    // The check clears the global
+    assert_eq!(readSignalGlobal(&exports), 0);
+    assert!(try_check_signal().is_ok());
+}
+
+#[wasm_bindgen_test]
+fn test_guest_write_visible_to_host() {
+    clear_signal();
+
+    let exports = get_wasm_exports();
+    set_signal(7);
+    assert_eq!(readSignalGlobal(&exports), 7);
+
+    assert_eq!(clear_signal(), Signal::new(7));
+    assert_eq!(readSignalGlobal(&exports), 0);
+}
+
+#[wasm_bindgen_test]
+fn test_global_high_codes() {
+    clear_signal();
+    clear_signal_handler();
+
+    // i32 globals hold the code's bit pattern
+    writeSignalGlobal(&get_wasm_exports(), u32::MAX);
+    assert_eq!(try_check_signal().unwrap_err().code(), u32::MAX);
+}
+
+#[wasm_bindgen_test]
+fn test_global_with_handler() {
+    clear_signal();
+
+    set_signal_handler(|_signal| Ok(()));
+    writeSignalGlobal(&get_wasm_exports(), 3);
+    assert!(try_check_signal().is_ok());
+    assert!(peek_signal().is_none());
+
+    clear_signal_handler();
+}
@@ -0,0 +1,80 @@
+//! Tests for the `throw` mode.
+//!
+//! These tests verify that a signal propagated out of `check_signal` throws
+//! the exported `WASM_SIGNAL_TAG` exception, which the host can catch and
+//! decode. They run separately from the main suite, whose panic tests
+//! expect `check_signal` to panic.
+//!
+//! Run with: cargo test --test throw --target wasm32-unknown-unknown --features throw
+
+#![cfg(feature = "throw")]
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+
+use wasm_signal_handler::{
+    clear_signal, clear_signal_handler, peek_signal, rearm_shutdown_hooks, register_shutdown_hook,
+    set_signal, shutdown_hooks_ran,
+};
+
+wasm_bindgen_test_configure!(run_in_node_experimental);
+
+// ============================================================================
+// JavaScript helper functions
+// ============================================================================
+
+#[wasm_bindgen(inline_js = r#"
+export function callCheck(exports) {
+    try {
+        exports.__wasm_signal_check();
+    } catch (err) {
+        return err;
+    }
+}
+"#)]
+extern "C" {
+    /// Calls the exported check, returning what it threw, if anything.
+    fn callCheck(exports: &JsValue) -> JsValue;
+}
+
+#[wasm_bindgen(module = "/js/wasm-signal-handler.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = signalFromException)]
+    fn host_signal_from_exception(exports: &JsValue, err: &JsValue) -> Option<u32>;
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[wasm_bindgen_test]
+fn test_signal_thrown_as_exception() {
+    clear_signal();
+    clear_signal_handler();
+    let exports = wasm_bindgen::exports();
+
+    assert!(callCheck(&exports).is_undefined());
+
+    set_signal(7);
+    let err = callCheck(&exports);
+    assert_eq!(host_signal_from_exception(&exports, &err), Some(7));
+    assert_eq!(peek_signal(), None);
+}
+
+#[wasm_bindgen_test]
+fn test_shutdown_hooks_run_before_throw() {
+    clear_signal();
+    clear_signal_handler();
+    rearm_shutdown_hooks();
+    fn hook() {}
+    register_shutdown_hook(hook).unwrap();
+    let exports = wasm_bindgen::exports();
+
+    set_signal(2);
+    let err = callCheck(&exports);
+    assert!(shutdown_hooks_ran());
+    assert_eq!(host_signal_from_exception(&exports, &err), Some(2));
+
+    // Other exceptions are not signals
+    assert_eq!(host_signal_from_exception(&exports, &JsValue::NULL), None);
+}
@@ -0,0 +1,73 @@
+//! Tests for `wasm32-wasip1-threads`.
+//!
+//! Every thread is its own instance of the module, sharing one linear
+//! memory. These tests verify that the signal word in that memory is seen by
+//! all of them, and that `wait_for_signal` sleeps and wakes across threads.
+//!
+//! Run with: cargo test --test wasi --target wasm32-wasip1-threads --features std
+//! (with a runner such as `wasmtime -W threads=y -S threads=y`)
+
+#![cfg(all(target_os = "wasi", target_feature = "atomics"))]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use wasm_signal_handler::{
+    clear_signal, clear_signal_handler, last_ack, peek_signal, send_signal, signal_addr,
+    try_check_signal, wait_for_signal, Signal, WASM_SIGNAL_ADDR,
+};
+
+/// Serializes the tests, which share the signal word.
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn lock() -> MutexGuard<'static, ()> {
+    let guard = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    clear_signal();
+    clear_signal_handler();
+    guard
+}
+
+/// Writes the signal word the way a host does, through its address.
+fn host_write(code: u32) {
+    // SAFETY: signal_addr() is the address of the signal word in shared memory
+    let word = unsafe { &*(signal_addr() as *const AtomicU32) };
+    word.store(code, Ordering::SeqCst);
+}
+
+#[test]
+fn test_export_points_into_shared_memory() {
+    let _guard = lock();
+    let addr = WASM_SIGNAL_ADDR as *const AtomicU32 as usize;
+    assert_eq!(addr, signal_addr());
+    // Spawned threads resolve the same address
+    let spawned = thread::spawn(signal_addr).join().unwrap();
+    assert_eq!(spawned, addr);
+}
+
+#[test]
+fn test_spawned_thread_sees_signal() {
+    let _guard = lock();
+    let worker = thread::spawn(|| loop {
+        if let Err(signal) = try_check_signal() {
+            return signal;
+        }
+        thread::yield_now();
+    });
+    thread::sleep(Duration::from_millis(20));
+    host_write(Signal::TERMINATE.code());
+    assert_eq!(worker.join().unwrap(), Signal::TERMINATE);
+    assert_eq!(peek_signal(), None);
+}
+
+#[test]
+fn test_wait_for_signal_across_threads() {
+    let _guard = lock();
+    let waiter = thread::spawn(wait_for_signal);
+    thread::sleep(Duration::from_millis(20));
+    // Sleeps in memory.atomic.wait32 until the sender wakes it
+    send_signal(Signal::CANCEL.code());
+    assert_eq!(waiter.join().unwrap(), Signal::CANCEL);
+    assert_eq!(last_ack(), Some(Signal::CANCEL));
+}
@@ -5,12 +5,19 @@
 //! 2. JavaScript can read the signal address and write to it
 //! 3. Rust correctly detects signals set from JavaScript
 
+// Signals thrown as wasm exceptions are not caught by the panic tests
+#![cfg(not(any(feature = "global-storage", feature = "throw")))]
+
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_test::*;
 
 use wasm_signal_handler::{
-    check_signal, clear_signal, clear_signal_handler, peek_signal, set_signal, set_signal_handler,
-    try_check_signal, Signal, WASM_SIGNAL_ADDR,
+    ack_addr, check_signal, clear_signal, clear_signal_handler, encode_signal, heartbeat,
+    heartbeat_addr, mailbox_addr, peek_signal, poll_command, post_event, queued,
+    set_overwrite_policy, set_progress, set_signal, set_signal_handler, set_stage, signal_addr,
+    signal_layout, try_check_signal, Command, LifecycleState, OverwritePolicy, RequestScope,
+    Severity, Signal, DISPOSITION_HANDLER, DISPOSITION_PANICS, EVENT_SIGNAL_ACKNOWLEDGED,
+    EVENT_USER_BASE,
 };
 
 wasm_bindgen_test_configure!(run_in_node_experimental);
@@ -36,11 +43,65 @@ export function writeSignalValue(wasmMemory, signalAddr, value) {
     const view = new DataView(wasmMemory.buffer);
     view.setUint32(signalAddr, value, true);
 }
+
+export function policyAddresses(policyAddr, queueAddr) {
+    return { policyAddr, queueAddr };
+}
+
+export function getField(object, name) {
+    return object[name];
+}
 "#)]
 extern "C" {
     fn getSignalAddress(memory: &JsValue, addr_ptr: u32) -> u32;
     fn readSignalValue(memory: &JsValue, signal_addr: u32) -> u32;
     fn writeSignalValue(memory: &JsValue, signal_addr: u32, value: u32);
+    fn policyAddresses(policy_addr: u32, queue_addr: u32) -> JsValue;
+    fn getField(object: &JsValue, name: &str) -> u32;
+}
+
+// The official host-side helpers shipped with the crate
+#[wasm_bindgen(module = "/js/wasm-signal-handler.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = writeSignal)]
+    fn host_write_signal(memory: &JsValue, signal_addr: u32, code: u32);
+    #[wasm_bindgen(js_name = readSignal)]
+    fn host_read_signal(memory: &JsValue, signal_addr: u32) -> u32;
+    #[wasm_bindgen(js_name = clearSignal)]
+    fn host_clear_signal(memory: &JsValue, signal_addr: u32);
+    #[wasm_bindgen(js_name = readAck)]
+    fn host_read_ack(memory: &JsValue, ack_addr: u32) -> u32;
+    #[wasm_bindgen(js_name = clearAck)]
+    fn host_clear_ack(memory: &JsValue, ack_addr: u32);
+    #[wasm_bindgen(js_name = readHeartbeat)]
+    fn host_read_heartbeat(memory: &JsValue, heartbeat_addr: u32) -> u32;
+    #[wasm_bindgen(js_name = readDisposition)]
+    fn host_read_disposition(memory: &JsValue, disposition_addr: u32) -> u32;
+    #[wasm_bindgen(js_name = readState)]
+    fn host_read_state(memory: &JsValue, state_addr: u32) -> u32;
+    #[wasm_bindgen(js_name = sendCommand)]
+    fn host_send_command(
+        memory: &JsValue,
+        signal_addr: u32,
+        mailbox_addr: u32,
+        code: u32,
+        args: Vec<u32>,
+    ) -> bool;
+    #[wasm_bindgen(js_name = drainEvents)]
+    fn host_drain_events(memory: &JsValue, events_addr: u32) -> Vec<JsValue>;
+    #[wasm_bindgen(js_name = readProgress)]
+    fn host_read_progress(memory: &JsValue, progress_addr: u32) -> JsValue;
+    #[wasm_bindgen(js_name = writeSource)]
+    fn host_write_source(memory: &JsValue, source_addr: u32, source: u32);
+    #[wasm_bindgen(js_name = sendSignal)]
+    fn host_send_signal(
+        memory: &JsValue,
+        signal_addr: u32,
+        policy_addrs: &JsValue,
+        code: u32,
+    ) -> bool;
+    #[wasm_bindgen(js_name = encodeSignal)]
+    fn host_encode_signal(code: u32, severity: u32) -> u32;
 }
 
 /// Get the WebAssembly memory object
@@ -48,10 +109,9 @@ fn get_wasm_memory() -> JsValue {
     wasm_bindgen::memory()
 }
 
-/// Get the address of SIGNAL by reading through WASM_SIGNAL_ADDR
+/// Get the address of SIGNAL
 fn get_signal_addr() -> u32 {
-    // WASM_SIGNAL_ADDR is a reference to SIGNAL, so its value IS the address
-    WASM_SIGNAL_ADDR as *const _ as u32
+    signal_addr() as u32
 }
 
 // ============================================================================
@@ -76,11 +136,11 @@ fn test_set_signal_from_rust() {
     clear_signal_handler();
 
     set_signal(42);
-    assert_eq!(peek_signal(), Some(Signal(42)));
+    assert_eq!(peek_signal(), Signal::new(42));
 
     let result = try_check_signal();
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, 42);
+    assert_eq!(result.unwrap_err().code(), 42);
 
     // Signal should be cleared after check
     assert!(peek_signal().is_none());
@@ -135,19 +195,248 @@ fn test_write_signal_from_js() {
     // Read from Rust
     assert_eq!(
         peek_signal(),
-        Some(Signal(456)),
+        Signal::new(456),
         "Rust should see signal set by JS"
     );
 
     // try_check_signal should detect it
     let result = try_check_signal();
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, 456);
+    assert_eq!(result.unwrap_err().code(), 456);
 
     // Signal should be cleared
     assert!(peek_signal().is_none());
 }
 
+#[wasm_bindgen_test]
+fn test_host_helpers_write_path() {
+    clear_signal();
+    clear_signal_handler();
+
+    let memory = get_wasm_memory();
+    let signal_addr = get_signal_addr();
+
+    // Write through the shipped Atomics-based helper
+    host_write_signal(&memory, signal_addr, 321);
+    assert_eq!(peek_signal(), Signal::new(321));
+    assert_eq!(host_read_signal(&memory, signal_addr), 321);
+
+    host_clear_signal(&memory, signal_addr);
+    assert!(peek_signal().is_none());
+
+    // Codes above i32::MAX survive the unsigned conversion
+    host_write_signal(&memory, signal_addr, u32::MAX);
+    assert_eq!(try_check_signal().unwrap_err().code(), u32::MAX);
+    assert_eq!(host_read_signal(&memory, signal_addr), 0);
+}
+
+#[wasm_bindgen_test]
+fn test_host_reads_status_words() {
+    clear_signal();
+    clear_signal_handler();
+
+    let memory = get_wasm_memory();
+    let signal_addr = get_signal_addr();
+    let ack_addr = ack_addr() as u32;
+    let heartbeat_addr = heartbeat_addr() as u32;
+
+    host_clear_ack(&memory, ack_addr);
+    assert_eq!(host_read_ack(&memory, ack_addr), 0);
+    host_write_signal(&memory, signal_addr, 2);
+    assert!(try_check_signal().is_err());
+    assert_eq!(host_read_ack(&memory, ack_addr), 2);
+
+    let before = host_read_heartbeat(&memory, heartbeat_addr);
+    heartbeat();
+    assert_eq!(
+        host_read_heartbeat(&memory, heartbeat_addr),
+        before.wrapping_add(1)
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_host_reads_disposition() {
+    clear_signal_handler();
+
+    let memory = get_wasm_memory();
+    let disposition_addr = signal_layout().disposition as u32;
+    assert_eq!(
+        host_read_disposition(&memory, disposition_addr),
+        DISPOSITION_PANICS
+    );
+
+    set_signal_handler(|_| Ok(()));
+    assert_eq!(
+        host_read_disposition(&memory, disposition_addr),
+        DISPOSITION_PANICS | DISPOSITION_HANDLER
+    );
+    clear_signal_handler();
+}
+
+#[wasm_bindgen_test]
+fn test_host_reads_lifecycle_state() {
+    clear_signal_handler();
+
+    let memory = get_wasm_memory();
+    let state_addr = signal_layout().state as u32;
+    {
+        let _scope = RequestScope::new();
+        assert_eq!(
+            host_read_state(&memory, state_addr),
+            LifecycleState::Running as u32
+        );
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_err());
+        assert_eq!(
+            host_read_state(&memory, state_addr),
+            LifecycleState::Cancelling as u32
+        );
+    }
+    assert_eq!(
+        host_read_state(&memory, state_addr),
+        LifecycleState::Idle as u32
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_host_sends_command() {
+    clear_signal();
+    clear_signal_handler();
+
+    let memory = get_wasm_memory();
+    let signal_addr = get_signal_addr();
+    let mailbox_addr = mailbox_addr() as u32;
+
+    assert!(host_send_command(
+        &memory,
+        signal_addr,
+        mailbox_addr,
+        9,
+        vec![1, 2]
+    ));
+    // Only one command at a time
+    assert!(!host_send_command(
+        &memory,
+        signal_addr,
+        mailbox_addr,
+        9,
+        vec![]
+    ));
+    assert_eq!(peek_signal(), Some(Signal::COMMAND));
+
+    // Without a command handler, the doorbell goes to the signal handler
+    assert_eq!(try_check_signal(), Err(Signal::COMMAND));
+    assert_eq!(
+        poll_command(),
+        Some(Command {
+            code: 9,
+            args: [1, 2, 0, 0]
+        })
+    );
+}
+
+#[wasm_bindgen_test]
+fn test_host_drains_events() {
+    clear_signal();
+    clear_signal_handler();
+
+    let memory = get_wasm_memory();
+    let events_addr = signal_layout().events as u32;
+    host_drain_events(&memory, events_addr);
+
+    set_signal(Signal::CANCEL.code());
+    assert!(try_check_signal().is_err());
+    assert!(post_event(EVENT_USER_BASE + 1, 42));
+    let events: Vec<(u32, u32)> = host_drain_events(&memory, events_addr)
+        .iter()
+        .map(|event| (getField(event, "kind"), getField(event, "value")))
+        .collect();
+    assert_eq!(
+        events,
+        [(EVENT_SIGNAL_ACKNOWLEDGED, 2), (EVENT_USER_BASE + 1, 42)]
+    );
+    assert!(host_drain_events(&memory, events_addr).is_empty());
+}
+
+#[wasm_bindgen_test]
+fn test_host_reads_progress() {
+    let memory = get_wasm_memory();
+    let progress_addr = signal_layout().progress as u32;
+
+    set_progress(3, 10);
+    set_stage(2);
+    let progress = host_read_progress(&memory, progress_addr);
+    assert_eq!(getField(&progress, "done"), 3);
+    assert_eq!(getField(&progress, "total"), 10);
+    assert_eq!(getField(&progress, "stage"), 2);
+    set_progress(0, 0);
+    set_stage(0);
+}
+
+#[wasm_bindgen_test]
+fn test_host_tags_source() {
+    clear_signal();
+    clear_signal_handler();
+
+    let memory = get_wasm_memory();
+    let signal_addr = get_signal_addr();
+    let source_addr = signal_layout().source as u32;
+
+    host_write_source(&memory, source_addr, 7);
+    host_write_signal(&memory, signal_addr, 2);
+    assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+    assert_eq!(Signal::CANCEL.source(), Some(7));
+}
+
+#[wasm_bindgen_test]
+fn test_host_follows_overwrite_policy() {
+    clear_signal();
+    clear_signal_handler();
+
+    let memory = get_wasm_memory();
+    let signal_addr = get_signal_addr();
+    let addrs = policyAddresses(signal_layout().policy as u32, signal_layout().queue as u32);
+
+    let previous = set_overwrite_policy(OverwritePolicy::KeepHighest);
+    assert!(host_send_signal(&memory, signal_addr, &addrs, 1));
+    assert!(!host_send_signal(&memory, signal_addr, &addrs, 2));
+    assert_eq!(peek_signal(), Some(Signal::TERMINATE));
+    clear_signal();
+
+    // Host and guest share the queue
+    set_overwrite_policy(OverwritePolicy::Enqueue);
+    assert!(host_send_signal(&memory, signal_addr, &addrs, 2));
+    assert!(host_send_signal(&memory, signal_addr, &addrs, 1));
+    assert_eq!(queued(), 1);
+    assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+    assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+
+    set_overwrite_policy(previous);
+}
+
+#[wasm_bindgen_test]
+fn test_host_encodes_severity() {
+    clear_signal();
+    clear_signal_handler();
+
+    for severity in [Severity::Info, Severity::Kill, Severity::Fatal] {
+        assert_eq!(
+            host_encode_signal(42, severity as u32),
+            encode_signal(42, severity)
+        );
+    }
+
+    // Advisory signals never propagate
+    let memory = get_wasm_memory();
+    let signal_addr = get_signal_addr();
+    host_write_signal(
+        &memory,
+        signal_addr,
+        host_encode_signal(2, Severity::Warn as u32),
+    );
+    assert!(try_check_signal().is_ok());
+}
+
 #[wasm_bindgen_test]
 fn test_js_signal_with_handler() {
     use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
@@ -164,7 +453,7 @@ fn test_js_signal_with_handler() {
 
     set_signal_handler(|signal| {
         HANDLER_CALLED.store(true, Ordering::SeqCst);
-        HANDLER_VALUE.store(signal.0, Ordering::SeqCst);
+        HANDLER_VALUE.store(signal.code(), Ordering::SeqCst);
         // Propagate the signal (don't clear it)
         Err(signal)
     });
@@ -189,7 +478,7 @@ fn test_js_signal_with_handler() {
     );
 
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, 789);
+    assert_eq!(result.unwrap_err().code(), 789);
 
     clear_signal_handler();
 }
@@ -233,17 +522,17 @@ fn test_multiple_signals_from_js() {
     // First signal
     writeSignalValue(&memory, signal_addr, 1);
     let result = try_check_signal();
-    assert_eq!(result.unwrap_err().0, 1);
+    assert_eq!(result.unwrap_err().code(), 1);
 
     // Second signal (different value)
     writeSignalValue(&memory, signal_addr, 2);
     let result = try_check_signal();
-    assert_eq!(result.unwrap_err().0, 2);
+    assert_eq!(result.unwrap_err().code(), 2);
 
     // Third signal
     writeSignalValue(&memory, signal_addr, 3);
     let result = try_check_signal();
-    assert_eq!(result.unwrap_err().0, 3);
+    assert_eq!(result.unwrap_err().code(), 3);
 
     // No more signals
     assert!(try_check_signal().is_ok());
@@ -332,7 +621,7 @@ fn test_max_signal_value() {
 
     let result = try_check_signal();
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, u32::MAX);
+    assert_eq!(result.unwrap_err().code(), u32::MAX);
 }
 
 #[wasm_bindgen_test]
@@ -348,7 +637,7 @@ fn test_signal_one() {
 
     let result = try_check_signal();
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().0, 1);
+    assert_eq!(result.unwrap_err().code(), 1);
 }
 
 #[wasm_bindgen_test]
@@ -362,7 +651,7 @@ fn test_rapid_signal_toggle() {
     for i in 1..=10 {
         writeSignalValue(&memory, signal_addr, i);
         let result = try_check_signal();
-        assert_eq!(result.unwrap_err().0, i);
+        assert_eq!(result.unwrap_err().code(), i);
 
         // Verify cleared
         assert!(try_check_signal().is_ok());
@@ -393,7 +682,18 @@ fn test_check_signal_panic_is_recoverable() {
 
     assert!(result.is_err(), "check_signal should have panicked");
 
-    // Verify the panic message contains the signal value
+    // With std, the panic payload carries the signal itself
+    #[cfg(feature = "std")]
+    if let Err(panic_payload) = &result {
+        assert_eq!(
+            wasm_signal_handler::SignalPanic::downcast(&**panic_payload),
+            Signal::new(42),
+            "Panic payload should be a SignalPanic"
+        );
+    }
+
+    // Without std, verify the panic message contains the signal value
+    #[cfg(not(feature = "std"))]
     if let Err(panic_payload) = result {
         if let Some(msg) = panic_payload.downcast_ref::<String>() {
             assert!(
@@ -436,7 +736,7 @@ fn test_handler_panic_is_recoverable() {
     // Set a handler that panics
     set_signal_handler(|signal| {
         PANIC_COUNT.fetch_add(1, Ordering::SeqCst);
-        panic!("Handler panic for signal {}", signal.0);
+        panic!("Handler panic for signal {}", signal.code());
     });
 
     let memory = get_wasm_memory();
@@ -479,7 +779,7 @@ fn test_handler_panic_with_check_signal_is_recoverable() {
 
     // Set a handler that panics
     set_signal_handler(|signal| {
-        panic!("Handler deliberate panic: {}", signal.0);
+        panic!("Handler deliberate panic: {}", signal.code());
     });
 
     let memory = get_wasm_memory();
@@ -564,7 +864,7 @@ fn test_handler_panic_clears_signal_first() {
     set_signal_handler(|_signal| {
         // Check signal state during handler execution
         let current = peek_signal();
-        SIGNAL_DURING_HANDLER.store(current.map(|s| s.0).unwrap_or(0), Ordering::SeqCst);
+        SIGNAL_DURING_HANDLER.store(current.map(|s| s.code()).unwrap_or(0), Ordering::SeqCst);
         panic!("intentional panic");
     });
 
@@ -587,6 +887,51 @@ fn test_handler_panic_clears_signal_first() {
     clear_signal_handler();
 }
 
+#[wasm_bindgen_test]
+fn test_shutdown_hooks_run_before_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use wasm_signal_handler::{
+        clear_shutdown_hooks, rearm_shutdown_hooks, register_shutdown_hook, shutdown_hooks_ran,
+    };
+
+    clear_signal();
+    clear_signal_handler();
+    clear_shutdown_hooks();
+    rearm_shutdown_hooks();
+
+    static HOOK_CALLS: AtomicU32 = AtomicU32::new(0);
+    HOOK_CALLS.store(0, Ordering::SeqCst);
+
+    register_shutdown_hook(|| {
+        // The signal has already been consumed when hooks run
+        assert!(peek_signal().is_none());
+        HOOK_CALLS.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    let memory = get_wasm_memory();
+    let signal_addr = get_signal_addr();
+
+    for _ in 0..2 {
+        writeSignalValue(&memory, signal_addr, 1);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            check_signal();
+        }));
+        assert!(result.is_err());
+    }
+
+    assert!(shutdown_hooks_ran());
+    assert_eq!(
+        HOOK_CALLS.load(Ordering::SeqCst),
+        1,
+        "Hooks should run exactly once"
+    );
+
+    clear_shutdown_hooks();
+    rearm_shutdown_hooks();
+}
+
 #[wasm_bindgen_test]
 fn test_nested_signal_during_handler() {
     use std::sync::atomic::{AtomicU32, Ordering};
@@ -598,13 +943,13 @@ fn test_nested_signal_during_handler() {
 
     // Handler that sets a nested signal when handling signal 1
     fn nested_handler(signal: Signal) -> Result<(), Signal> {
-        if signal.0 == 1 {
+        if signal.code() == 1 {
             // Set a new signal during handling
             set_signal(2);
 
             // Check for the nested signal
             if let Some(nested) = peek_signal() {
-                NESTED_SIGNAL_SEEN.store(nested.0, Ordering::SeqCst);
+                NESTED_SIGNAL_SEEN.store(nested.code(), Ordering::SeqCst);
             }
         }
         // Clear by returning Ok
@@ -633,7 +978,7 @@ fn test_nested_signal_during_handler() {
     // The nested signal should still be pending
     assert_eq!(
         peek_signal(),
-        Some(Signal(2)),
+        Signal::new(2),
         "Nested signal should still be pending"
     );
 
@@ -643,3 +988,458 @@ fn test_nested_signal_during_handler() {
 
     clear_signal_handler();
 }
+
+// ============================================================================
+// Tests: Async helpers
+// ============================================================================
+
+#[cfg(feature = "wasm-bindgen-futures")]
+mod yielding {
+    use super::*;
+    use wasm_signal_handler::{for_each_yielding, signal_aware_yield, with_timeout};
+
+    #[wasm_bindgen(inline_js = r#"
+    export function writeSignalSoon(memory, signalAddr, code) {
+        queueMicrotask(() => new DataView(memory.buffer).setUint32(signalAddr, code, true));
+    }
+    "#)]
+    extern "C" {
+        fn writeSignalSoon(memory: &JsValue, signal_addr: u32, code: u32);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_signal_aware_yield() {
+        clear_signal();
+        clear_signal_handler();
+        assert_eq!(signal_aware_yield().await, Ok(()));
+
+        // The microtask only runs if the yield actually yields
+        writeSignalSoon(&get_wasm_memory(), get_signal_addr(), 2);
+        assert_eq!(peek_signal(), None);
+        assert_eq!(signal_aware_yield().await, Err(Signal::CANCEL));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_with_timeout() {
+        clear_signal();
+        clear_signal_handler();
+
+        assert_eq!(with_timeout(async { 7 }, 1_000).await, Ok(7));
+        let never = core::future::pending::<()>();
+        assert_eq!(with_timeout(never, 5).await, Err(Signal::DEADLINE));
+
+        set_signal(Signal::TERMINATE.code());
+        assert_eq!(
+            with_timeout(async { 7 }, 1_000).await,
+            Err(Signal::TERMINATE)
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_for_each_yielding() {
+        clear_signal();
+        clear_signal_handler();
+
+        let mut seen = Vec::new();
+        let result = for_each_yielding(0..10, 3, |i| seen.push(i)).await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+
+        // A signal raised mid-chunk stops processing after that chunk
+        let mut seen = Vec::new();
+        let result = for_each_yielding(0..10, 4, |i| {
+            if i == 5 {
+                set_signal(Signal::CANCEL.code());
+            }
+            seen.push(i);
+        })
+        .await;
+        assert_eq!(result, Err(Signal::CANCEL));
+        assert_eq!(seen, (0..8).collect::<Vec<_>>());
+    }
+}
+
+#[cfg(any(feature = "wasm-bindgen-futures", feature = "gloo"))]
+mod host_timer {
+    use super::*;
+
+    #[wasm_bindgen(inline_js = r#"
+    export function writeSignalAfter(memory, signalAddr, code, ms) {
+        setTimeout(() => new DataView(memory.buffer).setUint32(signalAddr, code, true), ms);
+    }
+    "#)]
+    extern "C" {
+        pub(super) fn writeSignalAfter(memory: &JsValue, signal_addr: u32, code: u32, ms: u32);
+    }
+}
+
+#[cfg(feature = "gloo")]
+mod periodic {
+    use super::host_timer::writeSignalAfter;
+    use super::*;
+    use wasm_signal_handler::poll_signal_every;
+
+    #[wasm_bindgen_test]
+    async fn test_poll_signal_every() {
+        clear_signal();
+        clear_signal_handler();
+
+        // Nothing in the guest checks; only the interval sees the host write
+        writeSignalAfter(&get_wasm_memory(), get_signal_addr(), 1, 20);
+        assert_eq!(poll_signal_every(5).await, Signal::TERMINATE);
+    }
+}
+
+#[cfg(feature = "wasm-bindgen-futures")]
+mod retry {
+    use super::*;
+    use core::cell::Cell;
+    use wasm_signal_handler::{retry_interruptible_async, RetryError, RetryPolicy};
+
+    #[wasm_bindgen_test]
+    async fn test_retry_stops_during_backoff() {
+        clear_signal();
+        clear_signal_handler();
+        let attempts = Cell::new(0);
+        let policy = RetryPolicy {
+            max_attempts: 100,
+            initial_backoff_ms: 100,
+            ..RetryPolicy::default()
+        };
+
+        // The host cancels while the first backoff is in progress
+        host_timer::writeSignalAfter(&get_wasm_memory(), get_signal_addr(), 1, 10);
+        let result: Result<(), _> = retry_interruptible_async(policy, |_| {
+            attempts.set(attempts.get() + 1);
+            async { Err("unavailable") }
+        })
+        .await;
+        assert_eq!(result, Err(RetryError::Signal(Signal::TERMINATE)));
+        assert_eq!(attempts.get(), 1);
+    }
+}
+
+#[cfg(feature = "wasm-bindgen-futures")]
+mod ack {
+    use super::*;
+    use wasm_bindgen_futures::JsFuture;
+
+    #[wasm_bindgen(module = "/js/wasm-signal-handler.js")]
+    extern "C" {
+        #[wasm_bindgen(js_name = awaitAck)]
+        fn host_await_ack(memory: &JsValue, ack_addr: u32, timeout_ms: u32) -> js_sys::Promise;
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_host_awaits_ack() {
+        clear_signal();
+        clear_signal_handler();
+        let ack_addr = ack_addr() as u32;
+        host_clear_ack(&get_wasm_memory(), ack_addr);
+
+        // Acknowledged after the host started waiting
+        let ack = host_await_ack(&get_wasm_memory(), ack_addr, 1000);
+        set_signal(Signal::CANCEL.code());
+        assert!(try_check_signal().is_err());
+        let code = JsFuture::from(ack).await.unwrap();
+        assert_eq!(code.as_f64(), Some(f64::from(Signal::CANCEL.code())));
+
+        // Never acknowledged
+        host_clear_ack(&get_wasm_memory(), ack_addr);
+        let code = JsFuture::from(host_await_ack(&get_wasm_memory(), ack_addr, 20))
+            .await
+            .unwrap();
+        assert_eq!(code.as_f64(), Some(0.0));
+    }
+}
+
+#[cfg(feature = "wasm-bindgen-futures")]
+mod durable_object {
+    use super::*;
+    use wasm_bindgen_futures::JsFuture;
+
+    #[wasm_bindgen(inline_js = r#"
+    export function fakeState() {
+        const values = new Map();
+        let alarm = null;
+        return {
+            storage: {
+                async get(key) { return values.get(key); },
+                async put(key, value) { values.set(key, value); },
+                async delete(key) { return values.delete(key); },
+                async getAlarm() { return alarm; },
+                async setAlarm(at) { alarm = at; },
+                async deleteAlarm() { alarm = null; },
+            },
+        };
+    }
+    "#)]
+    extern "C" {
+        fn fakeState() -> JsValue;
+    }
+
+    #[wasm_bindgen(module = "/js/durable-object.js")]
+    extern "C" {
+        type SignalAlarm;
+
+        #[wasm_bindgen(constructor)]
+        fn new(state: &JsValue, exports: &JsValue) -> SignalAlarm;
+        #[wasm_bindgen(method)]
+        fn arm(this: &SignalAlarm, ms: u32) -> js_sys::Promise;
+        #[wasm_bindgen(method)]
+        fn disarm(this: &SignalAlarm) -> js_sys::Promise;
+        #[wasm_bindgen(method)]
+        fn alarm(this: &SignalAlarm) -> js_sys::Promise;
+    }
+
+    async fn fired(alarm: &SignalAlarm) -> bool {
+        JsFuture::from(alarm.alarm()).await.unwrap() == JsValue::TRUE
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_signal_alarm() {
+        clear_signal();
+        clear_signal_handler();
+        let alarm = SignalAlarm::new(&fakeState(), &wasm_bindgen::exports());
+
+        // Not armed: the alarm belongs to the object
+        assert!(!fired(&alarm).await);
+
+        JsFuture::from(alarm.arm(0)).await.unwrap();
+        assert!(fired(&alarm).await);
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        assert!(!fired(&alarm).await);
+
+        JsFuture::from(alarm.arm(0)).await.unwrap();
+        JsFuture::from(alarm.disarm()).await.unwrap();
+        assert!(!fired(&alarm).await);
+        assert_eq!(peek_signal(), None);
+    }
+}
+
+#[cfg(feature = "wasm-bindgen-futures")]
+mod workers {
+    use super::*;
+    use wasm_bindgen_futures::JsFuture;
+    use wasm_signal_handler::WorkersLimit;
+
+    #[wasm_bindgen(inline_js = r#"
+    export function sleep(ms) {
+        return new Promise((resolve) => setTimeout(resolve, ms));
+    }
+    export function budgetOptions(budgetMs, marginMs) {
+        return { budgetMs, marginMs };
+    }
+    "#)]
+    extern "C" {
+        fn sleep(ms: u32) -> js_sys::Promise;
+        fn budgetOptions(budget_ms: u32, margin_ms: u32) -> JsValue;
+    }
+
+    #[wasm_bindgen(module = "/js/workers.js")]
+    extern "C" {
+        type SignalBudget;
+
+        #[wasm_bindgen(constructor)]
+        fn new(exports: &JsValue, options: &JsValue) -> SignalBudget;
+        #[wasm_bindgen(method)]
+        fn start(this: &SignalBudget);
+        #[wasm_bindgen(method)]
+        fn stop(this: &SignalBudget);
+        #[wasm_bindgen(method, getter)]
+        fn fired(this: &SignalBudget) -> bool;
+
+        #[wasm_bindgen(thread_local_v2, js_name = WorkersLimit)]
+        static WORKERS_LIMIT: JsValue;
+    }
+
+    #[wasm_bindgen_test]
+    fn test_workers_limit_codes_match() {
+        let names = [
+            "CPU_LIMIT_APPROACHING",
+            "WALL_CLOCK_LIMIT",
+            "MEMORY_PRESSURE",
+            "EVICTION",
+        ];
+        for (name, limit) in names.iter().zip(WorkersLimit::ALL) {
+            let code = WORKERS_LIMIT.with(|codes| getField(codes, name));
+            assert_eq!(code, limit.code(), "{name}");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_signal_budget() {
+        clear_signal();
+        clear_signal_handler();
+        let budget = SignalBudget::new(&wasm_bindgen::exports(), &budgetOptions(20, 10));
+
+        budget.start();
+        JsFuture::from(sleep(30)).await.unwrap();
+        assert!(budget.fired());
+        assert_eq!(
+            try_check_signal(),
+            Err(WorkersLimit::CpuLimitApproaching.signal())
+        );
+
+        // Stopped before the margin: nothing is raised
+        budget.start();
+        budget.stop();
+        JsFuture::from(sleep(30)).await.unwrap();
+        assert!(!budget.fired());
+        assert_eq!(peek_signal(), None);
+    }
+}
+
+#[cfg(feature = "js-listener")]
+mod js_listener {
+    use super::*;
+    use wasm_signal_handler::{
+        add_js_signal_listener, clear_js_signal_listeners, remove_js_signal_listener,
+    };
+
+    #[wasm_bindgen(inline_js = r#"
+    export function recorder(seen) {
+        return (code) => seen.push(code);
+    }
+    export function thrower() {
+        return () => { throw new Error("callback failed"); };
+    }
+    "#)]
+    extern "C" {
+        fn recorder(seen: &js_sys::Array) -> js_sys::Function;
+        fn thrower() -> js_sys::Function;
+    }
+
+    #[wasm_bindgen_test]
+    fn test_js_signal_listener() {
+        clear_signal();
+        clear_signal_handler();
+        let seen = js_sys::Array::new();
+        let callback = recorder(&seen);
+        assert!(add_js_signal_listener(&callback));
+        assert!(add_js_signal_listener(&thrower()));
+
+        set_signal(7);
+        assert_eq!(try_check_signal(), Err(Signal::new(7).unwrap()));
+        assert!(remove_js_signal_listener(&callback));
+        assert!(!remove_js_signal_listener(&callback));
+        set_signal(8);
+        assert!(try_check_signal().is_err());
+        assert_eq!(seen.length(), 1);
+        assert_eq!(seen.get(0).as_f64(), Some(7.0));
+        clear_js_signal_listeners();
+    }
+}
+
+#[cfg(feature = "web")]
+mod web {
+    use super::*;
+    use wasm_signal_handler::{bind_page_lifecycle, PageLifecycleCodes};
+
+    // Node has no window or document, so stand in for both
+    #[wasm_bindgen(inline_js = r#"
+    export function installFakePage() {
+        const page = new EventTarget();
+        globalThis.addEventListener = page.addEventListener.bind(page);
+        globalThis.removeEventListener = page.removeEventListener.bind(page);
+        globalThis.document = Object.assign(new EventTarget(), { visibilityState: "visible" });
+        globalThis.__fakePage = page;
+    }
+
+    export function firePageEvent(kind) {
+        globalThis.__fakePage.dispatchEvent(new Event(kind));
+    }
+
+    export function setVisibility(state) {
+        document.visibilityState = state;
+        document.dispatchEvent(new Event("visibilitychange"));
+    }
+    "#)]
+    extern "C" {
+        fn installFakePage();
+        fn firePageEvent(kind: &str);
+        fn setVisibility(state: &str);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_page_lifecycle_signals() {
+        clear_signal();
+        clear_signal_handler();
+        installFakePage();
+        let binding = bind_page_lifecycle(PageLifecycleCodes {
+            before_unload: None,
+            ..PageLifecycleCodes::default()
+        });
+
+        firePageEvent("beforeunload");
+        assert_eq!(peek_signal(), None);
+        setVisibility("visible");
+        assert_eq!(peek_signal(), None);
+        setVisibility("hidden");
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+        firePageEvent("pagehide");
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+
+        // Dropping the binding removes the listeners
+        drop(binding);
+        firePageEvent("pagehide");
+        assert_eq!(peek_signal(), None);
+    }
+}
+
+#[cfg(feature = "edge")]
+mod edge {
+    use super::*;
+    use wasm_signal_handler::{bind_abort_signal, register_edge_instance, setup_edge};
+
+    #[wasm_bindgen(inline_js = r#"
+    export function makeRequest() {
+        const controller = new AbortController();
+        return { request: { signal: controller.signal }, controller };
+    }
+
+    export function registeredCount() {
+        return globalThis[Symbol.for("wasm-signal-handler")].length;
+    }
+    "#)]
+    extern "C" {
+        fn makeRequest() -> JsValue;
+        fn registeredCount() -> u32;
+    }
+
+    fn get(value: &JsValue, key: &str) -> JsValue {
+        js_sys::Reflect::get(value, &JsValue::from_str(key)).unwrap()
+    }
+
+    fn abort(pair: &JsValue) {
+        let controller = get(pair, "controller");
+        let abort: js_sys::Function = get(&controller, "abort").into();
+        abort.call0(&controller).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    fn test_edge_abort_signal() {
+        clear_signal();
+        clear_signal_handler();
+
+        let pair = makeRequest();
+        let binding = setup_edge(&get(&pair, "request"));
+        assert!(binding.is_bound());
+        assert!(!register_edge_instance());
+        assert_eq!(registeredCount(), 1);
+        abort(&pair);
+        assert_eq!(try_check_signal(), Err(Signal::CANCEL));
+
+        // Dropping the binding removes the listener
+        let pair = makeRequest();
+        drop(setup_edge(&get(&pair, "request")));
+        abort(&pair);
+        assert_eq!(peek_signal(), None);
+
+        // An already aborted signal is delivered right away
+        let binding = bind_abort_signal(&get(&get(&pair, "request"), "signal"), Signal::TERMINATE);
+        assert!(!binding.is_bound());
+        assert_eq!(try_check_signal(), Err(Signal::TERMINATE));
+        assert!(!setup_edge(&JsValue::UNDEFINED).is_bound());
+    }
+}